@@ -30,11 +30,27 @@
 //! let posit = db.create_posit(appearance_set, String::from("Alice"), time.clone());
 //! assert_eq!(posit.value(), &"Alice".to_string());
 //! ```
-use crate::datatype::{DataType, Time};
-use crate::persist::Persistor;
+//!
+//! # Transactions
+//! Constructs created between [`Database::begin`] and [`Database::commit`] are
+//! kept in memory right away, while their persistence is deferred to the commit.
+//! Should it fail, or [`Database::rollback`] be called instead, the keepers and
+//! lookups are returned to their state at `begin`.
+//! ```
+//! use bareclad::construct::{Database, PersistenceMode};
+//! let db = Database::new(PersistenceMode::InMemory).unwrap();
+//! let roles = db.role_keeper().lock().unwrap().len();
+//! db.begin().unwrap();
+//! db.create_role("person".to_string(), false);
+//! db.rollback().unwrap();
+//! assert_eq!(db.role_keeper().lock().unwrap().len(), roles);
+//! ```
+use crate::datatype::{Certainty, DataType, Decimal, Time};
+use crate::persist::{PendingWrite, Persistor};
 use tracing::{warn};
 use crate::error::BarecladError;
 use bimap::BiMap;
+use chrono::NaiveDateTime;
 use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
 use roaring::RoaringTreemap;
 use seahash::SeaHasher;
@@ -42,10 +58,12 @@ use std::any::{Any, TypeId};
 use std::cmp::Ordering;
 use std::collections::hash_map::{Entry, RandomState};
 use std::collections::hash_set::Iter;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
-use std::sync::{Arc, Mutex};
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
 
 /// Internal heterogeneous map keyed by `TypeId` used for storing per-value
 /// type bimap indices for posits.
@@ -79,6 +97,10 @@ impl TypeMap {
 pub type Thing = u64;
 /// Reserved identity constant representing the initial lower bound for the generator.
 pub const GENESIS: Thing = 0;
+/// The highest identity a thing can have, since identities are persisted as SQLite integers.
+pub const MAX_THING: Thing = i64::MAX as Thing;
+/// The highest identity a thing can be restored under, leaving at least as many to be generated.
+pub const MAX_RESTORED_THING: Thing = MAX_THING / 2;
 
 pub type ThingHasher = BuildHasherDefault<SeaHasher>;
 pub type OtherHasher = BuildHasherDefault<SeaHasher>;
@@ -88,6 +110,8 @@ pub struct ThingGenerator {
     lower_bound: Thing,
     retained: HashSet<Thing, ThingHasher>,
     released: Vec<Thing>,
+    /// Identities retained since the open checkpoint, if any, which restoring it forgets again.
+    taken: Option<Vec<Thing>>,
 }
 
 impl ThingGenerator {
@@ -97,13 +121,14 @@ impl ThingGenerator {
             lower_bound: GENESIS,
             retained: HashSet::<Thing, ThingHasher>::default(),
             released: Vec::new(),
+            taken: None,
         }
     }
     /// Things may be explicitly referenced (e.g. restored) but only implicitly
     /// created through the generator. Retaining teaches the generator about an
     /// externally observed identity (e.g. during persistence restore).
     pub fn retain(&mut self, t: Thing) {
-        self.retained.insert(t);
+        self.take(t);
         if t > self.lower_bound {
             self.lower_bound = t;
         }
@@ -119,17 +144,76 @@ impl ThingGenerator {
         }
     }
     /// Generates a new (or recycled) identity.
+    ///
+    /// # Panics
+    /// When no identity up to [`MAX_THING`] is left to generate.
     pub fn generate(&mut self) -> Thing {
-        self.released.pop().unwrap_or_else(|| {
-            self.lower_bound += 1;
-            self.retained.insert(self.lower_bound);
+        let thing = self.released.pop().unwrap_or_else(|| {
+            self.lower_bound = self.lower_bound.checked_add(1).filter(|t| *t <= MAX_THING).expect("thing identities exhausted");
             self.lower_bound
-        })
+        });
+        self.take(thing);
+        thing
+    }
+    fn take(&mut self, t: Thing) {
+        if self.retained.insert(t)
+            && let Some(taken) = &mut self.taken
+        {
+            taken.push(t);
+        }
+    }
+    /// Raises the lower bound to `t`, so that the identities up to it are never generated but left
+    /// free to be restored.
+    pub fn reserve(&mut self, t: Thing) {
+        if t > self.lower_bound {
+            self.lower_bound = t;
+        }
     }
     /// Iterates over currently retained identities (unordered).
     pub fn iter(&self) -> Iter<'_, Thing> {
         self.retained.iter()
     }
+    /// The state to return to with [`ThingGenerator::restore`]. Until the checkpoint is restored or
+    /// [kept](ThingGenerator::keep), the generator remembers the identities it retains.
+    pub fn checkpoint(&mut self) -> ThingCheckpoint {
+        self.taken = Some(Vec::new());
+        ThingCheckpoint {
+            lower_bound: self.lower_bound,
+            released: self.released.clone(),
+        }
+    }
+    /// Keeps the identities retained since the last checkpoint, which can then no longer be restored.
+    pub fn keep(&mut self) {
+        self.taken = None;
+    }
+    /// Forgets the identities retained since the checkpoint was taken, making them available again.
+    /// This takes time in proportion to the number of those identities, however far the lower bound
+    /// was raised.
+    /// ```
+    /// use bareclad::construct::ThingGenerator;
+    /// let mut generator = ThingGenerator::new();
+    /// let kept = generator.generate();
+    /// let checkpoint = generator.checkpoint();
+    /// generator.reserve(1 << 40);
+    /// let forgotten = generator.generate();
+    /// generator.restore(checkpoint);
+    /// assert_eq!((generator.check(kept), generator.check(forgotten)), (Some(kept), None));
+    /// assert_eq!(generator.generate(), kept + 1);
+    /// ```
+    pub fn restore(&mut self, checkpoint: ThingCheckpoint) {
+        for t in self.taken.take().unwrap_or_default() {
+            self.retained.remove(&t);
+        }
+        self.lower_bound = checkpoint.lower_bound;
+        self.released = checkpoint.released;
+    }
+}
+
+/// The state of a [`ThingGenerator`] at the start of a transaction.
+#[derive(Debug, Clone)]
+pub struct ThingCheckpoint {
+    lower_bound: Thing,
+    released: Vec<Thing>,
 }
 
 // ------------- Role -------------
@@ -215,12 +299,25 @@ impl RoleKeeper {
     pub fn get(&self, name: &str) -> Arc<Role> {
         Arc::clone(self.kept.get(name).unwrap())
     }
+    /// Like `get`, but returns `None` for unknown role names.
+    pub fn find(&self, name: &str) -> Option<Arc<Role>> {
+        self.kept.get(name).map(Arc::clone)
+    }
     pub fn lookup(&self, role: &Thing) -> Arc<Role> {
         Arc::clone(self.lookup.get(role).unwrap())
     }
+    pub fn remove(&mut self, role: &Role) {
+        if self.kept.remove(role.name()).is_some() {
+            self.lookup.remove(&role.role());
+        }
+    }
     pub fn len(&self) -> usize {
         self.kept.len()
     }
+    /// Iterates over the kept roles (unordered).
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Role>> {
+        self.kept.values()
+    }
 }
 
 // ------------- Appearance -------------
@@ -274,6 +371,9 @@ impl AppearanceKeeper {
             previously_kept,
         )
     }
+    pub fn remove(&mut self, appearance: &Arc<Appearance>) {
+        self.kept.remove(appearance);
+    }
     pub fn len(&self) -> usize {
         self.kept.len()
     }
@@ -334,6 +434,9 @@ impl AppearanceSetKeeper {
             previously_kept,
         )
     }
+    pub fn remove(&mut self, appearance_set: &Arc<AppearanceSet>) {
+        self.kept.remove(appearance_set);
+    }
     pub fn len(&self) -> usize {
         self.kept.len()
     }
@@ -459,6 +562,13 @@ impl PositKeeper {
         };
         map.get_by_right(&thing).map(Arc::clone)
     }
+    /// Removes a kept posit, as when the transaction keeping it rolls back.
+    pub fn remove<V: 'static + DataType>(&mut self, posit: &Arc<Posit<V>>) {
+        let map = self.kept.get_mut::<BiMap<Arc<Posit<V>>, Thing>>();
+        if map.is_some_and(|map| map.remove_by_left(posit).is_some()) {
+            self.length -= 1;
+        }
+    }
     pub fn len(&self) -> usize {
         self.length
     }
@@ -475,13 +585,26 @@ impl<K: Eq + Hash, V: Eq + Hash, H: BuildHasher + Default> Lookup<K, V, H> {
             index: HashMap::<K, HashSet<V>, H>::default(),
         }
     }
-    pub fn insert(&mut self, key: K, value: V) {
+    /// Returns false if the value was already kept under the key.
+    pub fn insert(&mut self, key: K, value: V) -> bool {
         let map = self.index.entry(key).or_insert(HashSet::<V>::new());
-        map.insert(value);
+        map.insert(value)
+    }
+    pub fn remove(&mut self, key: &K, value: &V) {
+        if let Some(set) = self.index.get_mut(key) {
+            set.remove(value);
+            if set.is_empty() {
+                self.index.remove(key);
+            }
+        }
     }
     pub fn lookup(&self, key: &K) -> &HashSet<V> {
         self.index.get(key).unwrap()
     }
+    /// Like `lookup`, but returns `None` for keys that were never inserted.
+    pub fn get(&self, key: &K) -> Option<&HashSet<V>> {
+        self.index.get(key)
+    }
 }
 
 /// Lookup mapping a key to a set of Thing IDs, backed by a RoaringTreemap.
@@ -507,6 +630,166 @@ impl<K: Eq + Hash, H: BuildHasher + Default> ThingLookup<K, H> {
     pub fn lookup(&self, key: &K) -> &RoaringTreemap {
         self.index.get(key).unwrap()
     }
+    /// Like `lookup`, but returns `None` for keys that were never inserted.
+    pub fn get(&self, key: &K) -> Option<&RoaringTreemap> {
+        self.index.get(key)
+    }
+}
+
+/// Ordered lookup mapping a key to a set of Thing IDs, for range scans over keys.
+#[derive(Debug)]
+pub struct OrderedLookup<K> {
+    index: BTreeMap<K, RoaringTreemap>,
+    // every thing in the index, whatever its key
+    all: RoaringTreemap,
+}
+impl<K: Ord> Default for OrderedLookup<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: Ord> OrderedLookup<K> {
+    pub fn new() -> Self {
+        Self {
+            index: BTreeMap::new(),
+            all: RoaringTreemap::new(),
+        }
+    }
+    pub fn insert(&mut self, key: K, thing: Thing) {
+        self.index.entry(key).or_default().insert(thing);
+        self.all.insert(thing);
+    }
+    pub fn remove(&mut self, key: &K, thing: Thing) {
+        if let Some(set) = self.index.get_mut(key) {
+            set.remove(thing);
+            if set.is_empty() {
+                self.index.remove(key);
+            }
+            // a thing may be kept under several keys, such as the words of a text
+            if !self.index.values().any(|things| things.contains(thing)) {
+                self.all.remove(thing);
+            }
+        }
+    }
+    /// The things whose keys lie in the range; an empty or inverted range yields none.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> RoaringTreemap {
+        // BTreeMap::range panics on inverted ranges rather than returning nothing.
+        if let (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) =
+            (range.start_bound(), range.end_bound())
+        {
+            let open = matches!(range.start_bound(), Bound::Excluded(_)) || matches!(range.end_bound(), Bound::Excluded(_));
+            if start > end || (start == end && open) {
+                return RoaringTreemap::new();
+            }
+        }
+        self.index.range(range).fold(RoaringTreemap::new(), |acc, (_, things)| acc | things)
+    }
+    /// Every thing in the lookup.
+    pub fn all(&self) -> &RoaringTreemap {
+        &self.all
+    }
+}
+
+impl OrderedLookup<String> {
+    /// The things kept under a key starting with the prefix.
+    pub fn prefixed(&self, prefix: &str) -> RoaringTreemap {
+        self.index
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .fold(RoaringTreemap::new(), |acc, (_, things)| acc | things)
+    }
+}
+
+/// Splits a text into the words the word index is keyed by: runs of letters and digits, in lowercase.
+/// ```
+/// use bareclad::construct::words;
+/// assert_eq!(words("Specter phase-shifted, 2x!"), vec!["specter", "phase", "shifted", "2x"]);
+/// ```
+pub fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// ------------- Transactions -------------
+/// A change to the keepers and lookups, undone should its transaction roll back.
+enum Change {
+    /// A restored identity, which may lie below the checkpoint of the transaction.
+    Thing(Thing),
+    Role(Arc<Role>),
+    Appearance(Arc<Appearance>),
+    AppearanceSet(Arc<AppearanceSet>),
+    /// Posits are kept by the type of their values, so they are forgotten by a closure knowing it.
+    Posit(Box<dyn FnOnce(&Database) + Send>),
+}
+
+/// Indexes a posit in the [`OrderedIndexes`], once the bulk load creating it commits.
+type Indexing = Box<dyn FnOnce(&mut OrderedIndexes) + Send>;
+
+/// The changes made and the writes deferred since [`Database::begin`].
+struct Transaction {
+    checkpoint: ThingCheckpoint,
+    changes: Vec<Change>,
+    writes: Vec<PendingWrite>,
+    /// Present in a transaction opened by [`Database::load`], which builds the ordered and word
+    /// indexes of its posits when it commits.
+    indexing: Option<Vec<Indexing>>,
+}
+
+/// The ordered value and time indexes and the word index, locked together so that the posits of a
+/// bulk load are indexed under one lock each.
+struct OrderedIndexes<'db> {
+    i64: MutexGuard<'db, OrderedLookup<i64>>,
+    decimal: MutexGuard<'db, OrderedLookup<Decimal>>,
+    certainty: MutexGuard<'db, OrderedLookup<i8>>,
+    time_value: MutexGuard<'db, OrderedLookup<NaiveDateTime>>,
+    time: MutexGuard<'db, OrderedLookup<NaiveDateTime>>,
+    word: MutexGuard<'db, OrderedLookup<String>>,
+}
+impl OrderedIndexes<'_> {
+    fn insert<V: 'static + DataType>(&mut self, posit: &Posit<V>) {
+        self.time.insert(posit.time().span().0, posit.posit());
+        // Index values of the ordered data types for range predicates
+        let value: &dyn Any = posit.value();
+        if let Some(value) = value.downcast_ref::<i64>() {
+            self.i64.insert(*value, posit.posit());
+        } else if let Some(value) = value.downcast_ref::<Decimal>() {
+            self.decimal.insert(value.clone(), posit.posit());
+        } else if let Some(value) = value.downcast_ref::<Certainty>() {
+            self.certainty.insert(value.percent(), posit.posit());
+        } else if let Some(value) = value.downcast_ref::<Time>() {
+            self.time_value.insert(value.span().0, posit.posit());
+        } else if let Some(value) = value.downcast_ref::<String>() {
+            for word in words(value) {
+                self.word.insert(word, posit.posit());
+            }
+        }
+    }
+}
+
+/// The thread allowed to write, the number of its writes under way, and its open transaction.
+#[derive(Default)]
+struct Writers {
+    owner: Option<ThreadId>,
+    depth: usize,
+    transaction: Option<Transaction>,
+}
+
+impl Writers {
+    /// The open transaction, if the current thread has it.
+    fn transaction(&mut self) -> Option<&mut Transaction> {
+        let owned = self.owner == Some(thread::current().id());
+        self.transaction.as_mut().filter(|_| owned)
+    }
+}
+
+/// Lets the current thread write until dropped.
+struct Writing<'db>(&'db Database);
+impl Drop for Writing<'_> {
+    fn drop(&mut self) {
+        self.0.release_writer();
+    }
 }
 
 // ------------- Database -------------
@@ -554,8 +837,22 @@ pub struct Database {
     pub role_name_to_data_type_lookup: Arc<Mutex<Lookup<Vec<String>, String, OtherHasher>>>,
     /// Type-erased index: posit thing -> its time (for generic time filtering)
     pub posit_time_lookup: Arc<Mutex<HashMap<Thing, Time, ThingHasher>>>,
+    /// Ordered indexes from the values of the ordered data types to the posits having them.
+    pub i64_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<i64>>>,
+    pub decimal_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<Decimal>>>,
+    /// Certainties are keyed by their percentage.
+    pub certainty_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<i8>>>,
+    /// Time values are keyed by the first instant of their [`Time::span`].
+    pub time_value_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<NaiveDateTime>>>,
+    /// Ordered index of posit times, keyed by the first instant of their [`Time::span`].
+    pub time_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<NaiveDateTime>>>,
+    /// Inverted index from the [`words`] of string values to the posits having them.
+    pub word_to_posit_thing_lookup: Arc<Mutex<OrderedLookup<String>>>,
     // responsible for the the persistence layer
     pub persistor: Arc<Mutex<Persistor>>,
+    // writers take turns, so that an open transaction sees no changes but its own
+    writers: Mutex<Writers>,
+    writer_released: Condvar,
 }
 
 impl Database {
@@ -602,7 +899,15 @@ impl Database {
             )),
             role_name_to_data_type_lookup: Arc::new(Mutex::new(role_name_to_data_type_lookup)),
             posit_time_lookup: Arc::new(Mutex::new(posit_time_lookup)),
+            i64_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
+            decimal_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
+            certainty_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
+            time_value_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
+            time_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
+            word_to_posit_thing_lookup: Arc::new(Mutex::new(OrderedLookup::new())),
             persistor: Arc::new(Mutex::new(persistor)),
+            writers: Mutex::new(Writers::default()),
+            writer_released: Condvar::new(),
         };
 
         // Restore the existing database
@@ -672,23 +977,49 @@ impl Database {
     pub fn posit_time_lookup(&self) -> Arc<Mutex<HashMap<Thing, Time, ThingHasher>>> {
         Arc::clone(&self.posit_time_lookup)
     }
+    pub fn i64_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<i64>>> {
+        Arc::clone(&self.i64_to_posit_thing_lookup)
+    }
+    pub fn decimal_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<Decimal>>> {
+        Arc::clone(&self.decimal_to_posit_thing_lookup)
+    }
+    pub fn certainty_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<i8>>> {
+        Arc::clone(&self.certainty_to_posit_thing_lookup)
+    }
+    pub fn time_value_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<NaiveDateTime>>> {
+        Arc::clone(&self.time_value_to_posit_thing_lookup)
+    }
+    pub fn time_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<NaiveDateTime>>> {
+        Arc::clone(&self.time_to_posit_thing_lookup)
+    }
+    pub fn word_to_posit_thing_lookup(&self) -> Arc<Mutex<OrderedLookup<String>>> {
+        Arc::clone(&self.word_to_posit_thing_lookup)
+    }
     pub fn create_thing(&self) -> Arc<Thing> {
+        let _writing = self.writing();
         let thing = self.thing_generator.lock().unwrap().generate();
-        if let Err(e) = self.persistor.lock().unwrap().persist_thing(&thing) { warn!(?e, "persist_thing failed"); }
+        self.persist(Box::new(move |persistor, conn| persistor.write_thing(conn, &thing)));
         Arc::new(thing)
     }
     // functions to create constructs for the keepers to keep that also populate the lookups
     pub fn keep_role(&self, role: Role) -> (Arc<Role>, bool) {
         let (kept_role, previously_kept) = self.role_keeper.lock().unwrap().keep(role);
+        if !previously_kept {
+            self.record(|| Change::Role(Arc::clone(&kept_role)));
+        }
         (kept_role, previously_kept)
     }
     pub fn create_role(&self, role_name: String, reserved: bool) -> (Arc<Role>, bool) {
+        let _writing = self.writing();
         let role_thing = self.thing_generator.lock().unwrap().generate();
         let (kept_role, previously_kept) =
             self.keep_role(Role::new(role_thing, role_name, reserved));
         if !previously_kept {
-            if let Err(e) = self.persistor.lock().unwrap().persist_thing(&kept_role.role()) { warn!(?e, "persist_thing(role) failed"); }
-            if let Err(e) = self.persistor.lock().unwrap().persist_role(&kept_role) { warn!(?e, "persist_role failed"); }
+            let role = Arc::clone(&kept_role);
+            self.persist(Box::new(move |persistor, conn| {
+                persistor.write_thing(conn, &role.role())?;
+                persistor.write_role(conn, &role)
+            }));
         } else {
             self.thing_generator.lock().unwrap().release(role_thing);
         }
@@ -708,10 +1039,12 @@ impl Database {
                     .unwrap()
                     .insert(kept_appearance.role(), Arc::clone(&kept_appearance));
             }
+            self.record(|| Change::Appearance(Arc::clone(&kept_appearance)));
         }
         (kept_appearance, previously_kept)
     }
     pub fn create_apperance(&self, thing: Thing, role: Arc<Role>) -> (Arc<Appearance>, bool) {
+        let _writing = self.writing();
         self.keep_appearance(Appearance::new(thing, role))
     }
     pub fn keep_appearance_set(&self, appearance_set: AppearanceSet) -> (Arc<AppearanceSet>, bool) {
@@ -727,6 +1060,7 @@ impl Database {
                     .unwrap()
                     .insert(Arc::clone(appearance), Arc::clone(&kept_appearance_set));
             }
+            self.record(|| Change::AppearanceSet(Arc::clone(&kept_appearance_set)));
         }
         (kept_appearance_set, previously_kept)
     }
@@ -734,12 +1068,13 @@ impl Database {
         &self,
         appearance_set: Vec<Arc<Appearance>>,
     ) -> (Arc<AppearanceSet>, bool) {
+        let _writing = self.writing();
         self.keep_appearance_set(AppearanceSet::new(appearance_set).unwrap())
     }
     pub fn keep_posit<V: 'static + DataType>(&self, posit: Posit<V>) -> (Arc<Posit<V>>, bool) {
         let (kept_posit, previously_kept) = self.posit_keeper.lock().unwrap().keep(posit);
         if !previously_kept {
-            self.role_name_to_data_type_lookup.lock().unwrap().insert(
+            let new_data_type = self.role_name_to_data_type_lookup.lock().unwrap().insert(
                 kept_posit.appearance_set().roles(),
                 V::DATA_TYPE.to_string(),
             );
@@ -756,6 +1091,7 @@ impl Database {
                 .lock()
                 .unwrap()
                 .insert(kept_posit.posit(), kept_posit.time().clone());
+            self.index_ordered(&kept_posit);
             // Index posit thing by each role in its appearance set
             for appearance in kept_posit.appearance_set().appearances().iter() {
                 let role_thing = appearance.role().role();
@@ -764,24 +1100,311 @@ impl Database {
                     .unwrap()
                     .insert(role_thing, kept_posit.posit());
             }
+            self.record(|| {
+                let posit = Arc::clone(&kept_posit);
+                Change::Posit(Box::new(move |database| database.forget_posit(&posit, new_data_type)))
+            });
         }
         (kept_posit, previously_kept)
     }
+    /// Indexes a posit in the ordered and word indexes, or, during a bulk load, when it commits.
+    fn index_ordered<V: 'static + DataType>(&self, posit: &Arc<Posit<V>>) {
+        if let Some(indexing) = self.writers.lock().unwrap().transaction().and_then(|t| t.indexing.as_mut()) {
+            let posit = Arc::clone(posit);
+            indexing.push(Box::new(move |indexes| indexes.insert(&posit)));
+            return;
+        }
+        self.ordered_indexes().insert(posit);
+    }
+    fn ordered_indexes(&self) -> OrderedIndexes<'_> {
+        OrderedIndexes {
+            i64: self.i64_to_posit_thing_lookup.lock().unwrap(),
+            decimal: self.decimal_to_posit_thing_lookup.lock().unwrap(),
+            certainty: self.certainty_to_posit_thing_lookup.lock().unwrap(),
+            time_value: self.time_value_to_posit_thing_lookup.lock().unwrap(),
+            time: self.time_to_posit_thing_lookup.lock().unwrap(),
+            word: self.word_to_posit_thing_lookup.lock().unwrap(),
+        }
+    }
+    /// Undoes [`Database::keep_posit`], removing the posit from its keeper and the lookups.
+    fn forget_posit<V: 'static + DataType>(&self, posit: &Arc<Posit<V>>, new_data_type: bool) {
+        let thing = posit.posit();
+        self.posit_keeper.lock().unwrap().remove(posit);
+        if new_data_type {
+            self.role_name_to_data_type_lookup
+                .lock()
+                .unwrap()
+                .remove(&posit.appearance_set().roles(), &V::DATA_TYPE.to_string());
+        }
+        self.appearance_set_to_posit_thing_lookup
+            .lock()
+            .unwrap()
+            .remove(&posit.appearance_set(), thing);
+        self.posit_thing_to_appearance_set_lookup.lock().unwrap().remove(&thing);
+        self.posit_time_lookup.lock().unwrap().remove(&thing);
+        self.time_to_posit_thing_lookup
+            .lock()
+            .unwrap()
+            .remove(&posit.time().span().0, thing);
+        let value: &dyn Any = posit.value();
+        if let Some(value) = value.downcast_ref::<i64>() {
+            self.i64_to_posit_thing_lookup.lock().unwrap().remove(value, thing);
+        } else if let Some(value) = value.downcast_ref::<Decimal>() {
+            self.decimal_to_posit_thing_lookup.lock().unwrap().remove(value, thing);
+        } else if let Some(value) = value.downcast_ref::<Certainty>() {
+            self.certainty_to_posit_thing_lookup.lock().unwrap().remove(&value.percent(), thing);
+        } else if let Some(value) = value.downcast_ref::<Time>() {
+            self.time_value_to_posit_thing_lookup.lock().unwrap().remove(&value.span().0, thing);
+        } else if let Some(value) = value.downcast_ref::<String>() {
+            let mut word_lookup = self.word_to_posit_thing_lookup.lock().unwrap();
+            for word in words(value) {
+                word_lookup.remove(&word, thing);
+            }
+        }
+        for appearance in posit.appearance_set().appearances().iter() {
+            self.role_to_posit_thing_lookup
+                .lock()
+                .unwrap()
+                .remove(&appearance.role().role(), thing);
+        }
+    }
     pub fn create_posit<V: 'static + DataType>(
         &self,
         appearance_set: Arc<AppearanceSet>,
         value: V,
         time: Time,
     ) -> Arc<Posit<V>> {
+        self.create_posit_or_existing(appearance_set, value, time).0
+    }
+    /// Like [`Database::create_posit`], also telling whether the posit was already kept.
+    pub fn create_posit_or_existing<V: 'static + DataType>(
+        &self,
+        appearance_set: Arc<AppearanceSet>,
+        value: V,
+        time: Time,
+    ) -> (Arc<Posit<V>>, bool) {
+        let _writing = self.writing();
         let posit_thing = self.thing_generator.lock().unwrap().generate();
         let (kept_posit, previously_kept) =
             self.keep_posit(Posit::new(posit_thing, appearance_set, value, time));
         if !previously_kept {
-            if let Err(e) = self.persistor.lock().unwrap().persist_thing(&kept_posit.posit()) { warn!(?e, "persist_thing(posit) failed"); }
-            if let Err(e) = self.persistor.lock().unwrap().persist_posit(&kept_posit) { warn!(?e, "persist_posit failed"); }
+            let posit = Arc::clone(&kept_posit);
+            self.persist(Box::new(move |persistor, conn| {
+                persistor.write_thing(conn, &posit.posit())?;
+                persistor.write_posit(conn, &posit)
+            }));
         } else {
             self.thing_generator.lock().unwrap().release(posit_thing);
         }
-        kept_posit
+        (kept_posit, previously_kept)
+    }
+    /// Restores a thing under its original identity, as when replaying a dump (see
+    /// [`crate::traqula::restore`]). Fails if the identity is already in use.
+    pub fn restore_thing(&self, thing: Thing) -> Result<Thing, BarecladError> {
+        let _writing = self.writing();
+        self.claim(thing)?;
+        self.persist(Box::new(move |persistor, conn| persistor.write_thing(conn, &thing)));
+        Ok(thing)
+    }
+    /// Restores a posit under its original identity. Fails if the identity is already in use, or
+    /// if the posit is already kept under another one.
+    pub fn restore_posit<V: 'static + DataType>(
+        &self,
+        posit_thing: Thing,
+        appearance_set: Arc<AppearanceSet>,
+        value: V,
+        time: Time,
+    ) -> Result<Arc<Posit<V>>, BarecladError> {
+        let _writing = self.writing();
+        self.claim(posit_thing)?;
+        let (kept_posit, previously_kept) =
+            self.keep_posit(Posit::new(posit_thing, appearance_set, value, time));
+        if previously_kept {
+            self.thing_generator.lock().unwrap().release(posit_thing);
+            return Err(BarecladError::Execution(format!(
+                "Posit {} is already kept as {}",
+                posit_thing,
+                kept_posit.posit()
+            )));
+        }
+        let posit = Arc::clone(&kept_posit);
+        self.persist(Box::new(move |persistor, conn| {
+            persistor.write_thing(conn, &posit.posit())?;
+            persistor.write_posit(conn, &posit)
+        }));
+        Ok(kept_posit)
+    }
+    /// Retains an identity not yet in use, releasing it again if the transaction is rolled back.
+    fn claim(&self, thing: Thing) -> Result<(), BarecladError> {
+        if thing > MAX_RESTORED_THING {
+            return Err(BarecladError::Execution(format!("Thing {} is above the highest identity that can be restored, {}", thing, MAX_RESTORED_THING)));
+        }
+        let mut generator = self.thing_generator.lock().unwrap();
+        if generator.check(thing).is_some() {
+            return Err(BarecladError::Execution(format!("Thing {} is already in use", thing)));
+        }
+        generator.retain(thing);
+        drop(generator);
+        self.record(|| Change::Thing(thing));
+        Ok(())
+    }
+
+    // functions for transactions
+    /// Opens a transaction for the current thread, first waiting for other threads to finish
+    /// writing. Until it is committed or rolled back, only this thread writes.
+    ///
+    /// Reading does not wait: searches on other threads already see the constructs of an open
+    /// transaction (read uncommitted), and stop seeing them if it is rolled back.
+    pub fn begin(&self) -> Result<(), BarecladError> {
+        if self.in_transaction() {
+            return Err(BarecladError::Execution("A transaction is already open".into()));
+        }
+        self.acquire_writer();
+        let checkpoint = self.thing_generator.lock().unwrap().checkpoint();
+        self.writers.lock().unwrap().transaction = Some(Transaction {
+            checkpoint,
+            changes: Vec::new(),
+            writes: Vec::new(),
+            indexing: None,
+        });
+        Ok(())
+    }
+    /// Persists the constructs created in the open transaction in one SQLite transaction. If that
+    /// fails, the transaction is rolled back and the error returned.
+    pub fn commit(&self) -> Result<(), BarecladError> {
+        let transaction = self.end_transaction()?;
+        let result = self.persistor.lock().unwrap().persist_batch(transaction.writes);
+        if result.is_err() {
+            self.undo(transaction.changes, transaction.checkpoint);
+        } else {
+            self.thing_generator.lock().unwrap().keep();
+            if let Some(indexing) = transaction.indexing {
+                let mut indexes = self.ordered_indexes();
+                indexing.into_iter().for_each(|index| index(&mut indexes));
+            }
+        }
+        self.release_writer();
+        result
+    }
+    /// Forgets the constructs created in the open transaction.
+    pub fn rollback(&self) -> Result<(), BarecladError> {
+        let transaction = self.end_transaction()?;
+        self.undo(transaction.changes, transaction.checkpoint);
+        self.release_writer();
+        Ok(())
+    }
+    /// Bulk loads the constructs created by `load`: their rows and ledger entries are written together
+    /// in one SQLite transaction when it returns, or, if it fails, they are forgotten and its error
+    /// returned. Within an open transaction, they simply become part of it.
+    ///
+    /// Outside one, the ordered value and time indexes and the word index are built for the loaded
+    /// posits as the load commits, so searches comparing values or times, or matching words, find
+    /// them only then.
+    /// ```
+    /// use bareclad::construct::{Database, PersistenceMode};
+    /// use bareclad::datatype::Time;
+    /// let db = Database::new(PersistenceMode::InMemory).unwrap();
+    /// let (role, _) = db.create_role("reading".to_string(), false);
+    /// let loaded = db.load(|db| {
+    ///     for reading in 0..1000i64 {
+    ///         let (appearance, _) = db.create_apperance(*db.create_thing(), role.clone());
+    ///         let (appearance_set, _) = db.create_appearance_set(vec![appearance]);
+    ///         db.create_posit(appearance_set, reading, Time::new());
+    ///     }
+    ///     Ok(1000)
+    /// });
+    /// assert_eq!(loaded.unwrap(), db.posit_keeper().lock().unwrap().len());
+    /// ```
+    pub fn load<T>(&self, load: impl FnOnce(&Database) -> Result<T, BarecladError>) -> Result<T, BarecladError> {
+        if self.in_transaction() {
+            return load(self);
+        }
+        self.begin()?;
+        if let Some(transaction) = self.writers.lock().unwrap().transaction() {
+            transaction.indexing = Some(Vec::new());
+        }
+        match load(self) {
+            Ok(loaded) => self.commit().map(|_| loaded),
+            Err(e) => {
+                self.rollback()?;
+                Err(e)
+            }
+        }
+    }
+    /// Whether the current thread has a transaction open.
+    pub fn in_transaction(&self) -> bool {
+        self.writers.lock().unwrap().transaction().is_some()
+    }
+    fn end_transaction(&self) -> Result<Transaction, BarecladError> {
+        if !self.in_transaction() {
+            return Err(BarecladError::Execution("No transaction is open".into()));
+        }
+        Ok(self.writers.lock().unwrap().transaction.take().unwrap())
+    }
+    fn undo(&self, changes: Vec<Change>, checkpoint: ThingCheckpoint) {
+        for change in changes.into_iter().rev() {
+            match change {
+                Change::Role(role) => self.role_keeper.lock().unwrap().remove(&role),
+                Change::Appearance(appearance) => {
+                    self.appearance_keeper.lock().unwrap().remove(&appearance);
+                    self.thing_to_appearance_lookup
+                        .lock()
+                        .unwrap()
+                        .remove(&appearance.thing(), &appearance);
+                    self.role_to_appearance_lookup
+                        .lock()
+                        .unwrap()
+                        .remove(&appearance.role(), &appearance);
+                }
+                Change::AppearanceSet(appearance_set) => {
+                    self.appearance_set_keeper.lock().unwrap().remove(&appearance_set);
+                    for appearance in appearance_set.appearances().iter() {
+                        self.appearance_to_appearance_set_lookup
+                            .lock()
+                            .unwrap()
+                            .remove(appearance, &appearance_set);
+                    }
+                }
+                Change::Posit(forget) => forget(self),
+                Change::Thing(thing) => self.thing_generator.lock().unwrap().release(thing),
+            }
+        }
+        self.thing_generator.lock().unwrap().restore(checkpoint);
+    }
+    /// Records a change for the open transaction of the current thread, if any.
+    fn record(&self, change: impl FnOnce() -> Change) {
+        if let Some(transaction) = self.writers.lock().unwrap().transaction() {
+            transaction.changes.push(change());
+        }
+    }
+    /// Runs a write, or defers it to the commit of the open transaction of the current thread.
+    fn persist(&self, write: PendingWrite) {
+        if let Some(transaction) = self.writers.lock().unwrap().transaction() {
+            transaction.writes.push(write);
+            return;
+        }
+        if let Err(e) = self.persistor.lock().unwrap().persist_batch(vec![write]) { warn!(?e, "persist failed"); }
+    }
+    /// Waits until no other thread is writing, then lets the current one write until released.
+    fn acquire_writer(&self) {
+        let me = thread::current().id();
+        let mut writers = self.writers.lock().unwrap();
+        while writers.owner.is_some_and(|owner| owner != me) {
+            writers = self.writer_released.wait(writers).unwrap();
+        }
+        writers.owner = Some(me);
+        writers.depth += 1;
+    }
+    fn release_writer(&self) {
+        let mut writers = self.writers.lock().unwrap();
+        writers.depth -= 1;
+        if writers.depth == 0 {
+            writers.owner = None;
+            self.writer_released.notify_all();
+        }
+    }
+    fn writing(&self) -> Writing<'_> {
+        self.acquire_writer();
+        Writing(self)
     }
 }
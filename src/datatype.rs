@@ -273,6 +273,14 @@ pub struct Certainty {
 }
 
 impl Certainty {
+    /// A certainty from a reliability in [-1, 1], clamped to that range and rounded to the nearest
+    /// whole percent. Rounding keeps percentages that are not exact binary fractions as written.
+    /// ```
+    /// use bareclad::datatype::Certainty;
+    /// // 100 * 0.29 is 28.999999999999996 in floating point.
+    /// assert_eq!(Certainty::new(0.29).percent(), 29);
+    /// assert_eq!(Certainty::new(-1.5).percent(), -100);
+    /// ```
     pub fn new<T: Into<f64>>(a: T) -> Self {
         let a = a.into();
         let a = if a < -1. {
@@ -283,9 +291,13 @@ impl Certainty {
             a
         };
         Self {
-            alpha: (100f64 * a) as i8,
+            alpha: (100f64 * a).round() as i8,
         }
     }
+    /// The certainty as a whole percentage in [-100, 100].
+    pub fn percent(&self) -> i8 {
+        self.alpha
+    }
     /// Returns true if a set of reliabilities is non-contradictory per
     /// the inequality from Transitional Modeling:
     ///
@@ -555,6 +567,37 @@ impl Time {
             moment: TimeType::DateTime(dt),
         }
     }
+    /// The first and last instants the time covers, e.g. a whole day for a date. The beginning and
+    /// end of time cover the earliest and latest representable instants.
+    ///
+    /// Two times compare as equal when their spans overlap at the coarser of their resolutions, so
+    /// a time ordered before another starts before the other does.
+    /// ```
+    /// use bareclad::datatype::Time;
+    /// let (first, last) = Time::new_year_month_from("2024-02").span();
+    /// assert_eq!(first.to_string(), "2024-02-01 00:00:00");
+    /// assert_eq!(last.to_string(), "2024-02-29 23:59:59.999999999");
+    /// ```
+    pub fn span(&self) -> (NaiveDateTime, NaiveDateTime) {
+        let day = |d: NaiveDate| (d.and_hms_opt(0, 0, 0).unwrap(), d.and_hms_nano_opt(23, 59, 59, 999_999_999).unwrap());
+        // Years beyond the range of chrono saturate to the earliest or latest instant.
+        let saturated = |y: i32| if y < 0 { NaiveDateTime::MIN } else { NaiveDateTime::MAX };
+        let days = |first: Option<NaiveDate>, next: Option<NaiveDate>, y: i32| match (first, next.and_then(|n| n.pred_opt())) {
+            (Some(first), Some(last)) => (day(first).0, day(last).1),
+            _ => (saturated(y), saturated(y)),
+        };
+        match self.moment {
+            TimeType::BeginningOfTime => (NaiveDateTime::MIN, NaiveDateTime::MIN),
+            TimeType::EndOfTime => (NaiveDateTime::MAX, NaiveDateTime::MAX),
+            TimeType::Year(y) => days(NaiveDate::from_ymd_opt(y, 1, 1), y.checked_add(1).and_then(|n| NaiveDate::from_ymd_opt(n, 1, 1)), y),
+            TimeType::YearMonth(y, m) => {
+                let next = if m == 12 { y.checked_add(1).and_then(|n| NaiveDate::from_ymd_opt(n, 1, 1)) } else { NaiveDate::from_ymd_opt(y, m as u32 + 1, 1) };
+                days(NaiveDate::from_ymd_opt(y, m as u32, 1), next, y)
+            }
+            TimeType::Date(d) => day(d),
+            TimeType::DateTime(dt) => (dt, dt),
+        }
+    }
     /// Parse a persisted canonical textual form of Time (no quotes, produced by Display).
     /// Accepted forms:
     /// BOT | EOT | YYYY | YYYY-MM | YYYY-MM-DD | YYYY-MM-DD HH:MM:SS[.fraction] | YYYY-MM-DDTHH:MM:SS[.fraction]
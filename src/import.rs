@@ -0,0 +1,483 @@
+//! Importing CSV extracts as posits, shaped by a mapping specification, and
+//! ingesting posits described by their parts (see [`PositSpec`]).
+//!
+//! A [`Mapping`] names the columns whose values identify things, and describes
+//! the posits to create from every row: the roles the identities appear in,
+//! the column holding the value, its data type, and the column or constant
+//! giving the appearance time. Mappings are usually written as JSON:
+//! ```json
+//! {
+//!     "identities": { "pilot": ["pilot_id"] },
+//!     "posits": [
+//!         { "appearances": [{ "identity": "pilot", "role": "callsign" }],
+//!           "value": "callsign", "type": "String", "time": { "column": "since" } },
+//!         { "appearances": [{ "identity": "pilot", "role": "rank" }],
+//!           "value": "rank", "type": "i64", "time": { "constant": "2025-01-01" } }
+//!     ]
+//! }
+//! ```
+//! Rows with equal key values share a thing, which is new to the database.
+//! Every posit is created through the `Database::create_*` functions, and the
+//! whole import is persisted as one bulk load (see [`Database::load`]).
+//!
+//! # Errors
+//! A row that cannot be imported, such as one with a value not of its type,
+//! adds none of its posits and is reported as a [`RowError`], while the other
+//! rows are imported. Empty value cells leave out their posit. A mapping that
+//! does not fit the header fails the import before any row is read.
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::construct::{AppearanceSet, Database, Role, Thing};
+use crate::datatype::{Certainty, DataType, Decimal, JSON, Time};
+use crate::error::{BarecladError, Result};
+use crate::traqula::parse_time;
+
+/// How the rows of a CSV file become posits.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mapping {
+    /// Field separator, a comma unless given.
+    #[serde(default = "comma")]
+    pub delimiter: char,
+    /// Identities by name, each identified by the values of its key columns.
+    pub identities: HashMap<String, Vec<String>>,
+    /// The posits created from every row.
+    pub posits: Vec<PositMapping>,
+}
+
+fn comma() -> char {
+    ','
+}
+
+/// A posit created from every row.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PositMapping {
+    pub appearances: Vec<AppearanceMapping>,
+    /// Column holding the appearing value.
+    pub value: String,
+    /// Data type the value is read as.
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+    pub time: TimeMapping,
+}
+
+/// An identity appearing in a role.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppearanceMapping {
+    pub identity: String,
+    pub role: String,
+}
+
+/// Data types values can be read as, named as in [`crate::datatype::DataType::DATA_TYPE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ValueType {
+    String,
+    #[serde(rename = "i64")]
+    I64,
+    Decimal,
+    /// Written in percent, as in `75%`.
+    Certainty,
+    Time,
+    #[serde(rename = "JSON")]
+    Json,
+}
+
+impl ValueType {
+    fn name(self) -> &'static str {
+        match self {
+            ValueType::String => "String",
+            ValueType::I64 => "i64",
+            ValueType::Decimal => "Decimal",
+            ValueType::Certainty => "Certainty",
+            ValueType::Time => "Time",
+            ValueType::Json => "JSON",
+        }
+    }
+}
+
+/// Where the appearance time of a posit comes from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeMapping {
+    /// A column holding times, such as `2025-06-18`.
+    Column(String),
+    /// The same time for every row, including the constants `@NOW`, `@BOT` and `@EOT`.
+    Constant(String),
+}
+
+/// A row that was not imported, by the line it starts on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// What an import did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Data rows read, imported or not.
+    pub rows: usize,
+    /// Posits created, or found to exist already.
+    pub posits: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// A value read as its data type.
+enum TypedValue {
+    String(String),
+    I64(i64),
+    Decimal(Decimal),
+    Certainty(Certainty),
+    Time(Time),
+    Json(JSON),
+}
+
+/// A mapping resolved against a header: column indexes, roles and constant times.
+struct Plan {
+    /// Identity names with their key columns.
+    identities: Vec<(String, Vec<usize>)>,
+    posits: Vec<PositPlan>,
+}
+
+struct PositPlan {
+    appearances: Vec<(usize, Arc<Role>)>,
+    value: usize,
+    value_type: ValueType,
+    time: TimePlan,
+}
+
+enum TimePlan {
+    Column(usize),
+    Constant(Time),
+}
+
+/// Import the CSV text read from `reader`, whose first record is a header naming the columns.
+pub fn import_csv<R: Read>(database: &Database, mut reader: R, mapping: &Mapping) -> Result<ImportReport> {
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| BarecladError::Execution(format!("Cannot read CSV: {e}")))?;
+    let mut records = Records::new(&text, mapping.delimiter);
+    let header = match records.next() {
+        Some((_, Ok(header))) => header,
+        Some((line, Err(message))) => return Err(BarecladError::Execution(format!("Bad CSV header on line {line}: {message}"))),
+        None => return Err(BarecladError::Execution("The CSV has no header".into())),
+    };
+    database.load(|database| {
+        let plan = plan(database, mapping, &header)?;
+        let mut report = ImportReport::default();
+        let mut things: HashMap<(usize, Vec<String>), Thing> = HashMap::new();
+        for (line, record) in records.by_ref() {
+            report.rows += 1;
+            let created = record.and_then(|fields| {
+                if fields.len() != header.len() {
+                    return Err(format!("Expected {} fields, found {}", header.len(), fields.len()));
+                }
+                import_row(database, &plan, &fields, &mut things)
+            });
+            match created {
+                Ok(posits) => report.posits += posits,
+                Err(message) => report.errors.push(RowError { line, message }),
+            }
+        }
+        Ok(report)
+    })
+}
+
+/// Resolve the columns, roles and constant times of a mapping, creating roles not yet kept.
+fn plan(database: &Database, mapping: &Mapping, header: &[String]) -> Result<Plan> {
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| BarecladError::Config(format!("Column '{name}' is not in the CSV header")))
+    };
+    let mut names: Vec<&String> = mapping.identities.keys().collect();
+    names.sort();
+    let mut identities = Vec::new();
+    for name in &names {
+        let keys = &mapping.identities[*name];
+        if keys.is_empty() {
+            return Err(BarecladError::Config(format!("Identity '{name}' has no key columns")));
+        }
+        identities.push(((*name).clone(), keys.iter().map(|key| column(key)).collect::<Result<_>>()?));
+    }
+    let mut posits = Vec::new();
+    for posit in &mapping.posits {
+        let mut appearances = Vec::new();
+        for appearance in &posit.appearances {
+            let identity = names
+                .iter()
+                .position(|name| **name == appearance.identity)
+                .ok_or_else(|| BarecladError::Config(format!("Unknown identity '{}'", appearance.identity)))?;
+            if posit.appearances.iter().filter(|other| other.role == appearance.role).count() > 1 {
+                return Err(BarecladError::Config(format!("Role '{}' appears twice in a posit", appearance.role)));
+            }
+            let (role, _) = database.create_role(appearance.role.clone(), false);
+            appearances.push((identity, role));
+        }
+        if appearances.is_empty() {
+            return Err(BarecladError::Config(format!("The posit of column '{}' has no appearances", posit.value)));
+        }
+        let time = match &posit.time {
+            TimeMapping::Column(name) => TimePlan::Column(column(name)?),
+            TimeMapping::Constant(text) => {
+                TimePlan::Constant(parse_time(text).ok_or_else(|| BarecladError::Config(format!("Invalid time '{text}'")))?)
+            }
+        };
+        posits.push(PositPlan { appearances, value: column(&posit.value)?, value_type: posit.value_type, time });
+    }
+    Ok(Plan { identities, posits })
+}
+
+/// Create the posits of a row, once all of its values have been read. Returns the number of posits.
+fn import_row(
+    database: &Database,
+    plan: &Plan,
+    fields: &[String],
+    things: &mut HashMap<(usize, Vec<String>), Thing>,
+) -> std::result::Result<usize, String> {
+    let mut posits = Vec::new();
+    for posit in &plan.posits {
+        let text = fields[posit.value].as_str();
+        if text.is_empty() {
+            continue;
+        }
+        let value = typed_value(text, posit.value_type)?;
+        let time = match &posit.time {
+            TimePlan::Column(column) => parse_time(&fields[*column]).ok_or_else(|| format!("Invalid time '{}'", fields[*column]))?,
+            TimePlan::Constant(time) => time.clone(),
+        };
+        posits.push((posit, value, time));
+    }
+    let mut keys = Vec::new();
+    for (identity, (name, columns)) in plan.identities.iter().enumerate() {
+        let key: Vec<String> = columns.iter().map(|column| fields[*column].clone()).collect();
+        let used = posits.iter().any(|(posit, _, _)| posit.appearances.iter().any(|(i, _)| *i == identity));
+        if used && key.iter().all(|value| value.is_empty()) {
+            return Err(format!("No key for identity '{name}'"));
+        }
+        keys.push(key);
+    }
+    let count = posits.len();
+    for (posit, value, time) in posits {
+        let mut appearances = Vec::new();
+        for (identity, role) in &posit.appearances {
+            let thing = *things
+                .entry((*identity, keys[*identity].clone()))
+                .or_insert_with(|| *database.create_thing());
+            appearances.push(database.create_apperance(thing, Arc::clone(role)).0);
+        }
+        let appearance_set: Arc<AppearanceSet> = database.create_appearance_set(appearances).0;
+        value.create_posit(database, appearance_set, time);
+    }
+    Ok(count)
+}
+
+impl TypedValue {
+    /// Create a posit with this value, returning its identity and whether it was already kept.
+    fn create_posit(self, database: &Database, appearance_set: Arc<AppearanceSet>, time: Time) -> (Thing, bool) {
+        fn create<V: 'static + DataType>(database: &Database, appearance_set: Arc<AppearanceSet>, value: V, time: Time) -> (Thing, bool) {
+            let (posit, existed) = database.create_posit_or_existing(appearance_set, value, time);
+            (posit.posit(), existed)
+        }
+        match self {
+            TypedValue::String(value) => create(database, appearance_set, value, time),
+            TypedValue::I64(value) => create(database, appearance_set, value, time),
+            TypedValue::Decimal(value) => create(database, appearance_set, value, time),
+            TypedValue::Certainty(value) => create(database, appearance_set, value, time),
+            TypedValue::Time(value) => create(database, appearance_set, value, time),
+            TypedValue::Json(value) => create(database, appearance_set, value, time),
+        }
+    }
+}
+
+fn typed_value(text: &str, value_type: ValueType) -> std::result::Result<TypedValue, String> {
+    let invalid = || format!("Invalid {} value '{}'", value_type.name(), text);
+    match value_type {
+        ValueType::String => Ok(TypedValue::String(text.to_string())),
+        ValueType::I64 => text.trim().parse().map(TypedValue::I64).map_err(|_| invalid()),
+        ValueType::Decimal => Decimal::from_str(text.trim()).map(TypedValue::Decimal).ok_or_else(invalid),
+        ValueType::Certainty => match text.trim().strip_suffix('%').map(str::parse::<i8>) {
+            Some(Ok(percent)) if (-100..=100).contains(&percent) => Ok(TypedValue::Certainty(Certainty::new(f64::from(percent) / 100.))),
+            _ => Err(invalid()),
+        },
+        ValueType::Time => parse_time(text).map(TypedValue::Time).ok_or_else(invalid),
+        ValueType::Json => JSON::from_str(text).map(TypedValue::Json).ok_or_else(invalid),
+    }
+}
+
+/// A posit described by its parts, as sent to `POST /v1/posits`:
+/// ```json
+/// { "ref": "fact",
+///   "appearances": [{ "ref": "p", "role": "callsign" }, { "thing": 42, "role": "squadron" }],
+///   "value": "Kestrel", "time": "2025-06-18" }
+/// ```
+/// The `type` of the value may be left out for strings, integers, other numbers (decimals) and
+/// objects or arrays (JSON), and is needed to read strings as certainties, times or decimals.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PositSpec {
+    /// Names the posit, so that later posits of the same request can refer to it.
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+    pub appearances: Vec<AppearanceSpec>,
+    pub value: serde_json::Value,
+    #[serde(default, rename = "type")]
+    pub value_type: Option<ValueType>,
+    pub time: String,
+}
+
+/// An appearance of a kept thing, or of a thing named within the request.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppearanceSpec {
+    #[serde(default)]
+    pub thing: Option<Thing>,
+    /// A name for a new thing, shared by the appearances using it in the same request.
+    #[serde(default, rename = "ref")]
+    pub reference: Option<String>,
+    pub role: String,
+}
+
+/// The posit created for a [`PositSpec`], or the identical one kept before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct IngestedPosit {
+    pub posit: Thing,
+    pub existed: bool,
+}
+
+/// Create the described posits, in order, as one bulk load. Nothing is created unless all of them
+/// are valid; errors tell the (1-based) position of the offending posit, also as their line.
+pub fn ingest_posits(database: &Database, posits: &[PositSpec]) -> Result<Vec<IngestedPosit>> {
+    database.load(|database| {
+        let mut references: HashMap<&str, Thing> = HashMap::new();
+        let mut ingested = Vec::with_capacity(posits.len());
+        for (index, spec) in posits.iter().enumerate() {
+            let invalid = |message: String| BarecladError::Parse { message: format!("Posit {}: {message}", index + 1), line: Some(index + 1), col: None };
+            let value = spec_value(&spec.value, spec.value_type).map_err(invalid)?;
+            let time = parse_time(&spec.time).ok_or_else(|| invalid(format!("Invalid time '{}'", spec.time)))?;
+            if spec.appearances.is_empty() {
+                return Err(invalid("A posit needs at least one appearance".into()));
+            }
+            let mut roles = Vec::new();
+            for appearance in &spec.appearances {
+                let role = database
+                    .role_keeper()
+                    .lock()
+                    .unwrap()
+                    .find(&appearance.role)
+                    .ok_or_else(|| invalid(format!("Unknown role: {}", appearance.role)))?;
+                if spec.appearances.iter().filter(|other| other.role == appearance.role).count() > 1 {
+                    return Err(invalid(format!("Role '{}' appears twice in a posit", appearance.role)));
+                }
+                let thing = match (appearance.thing, appearance.reference.as_deref()) {
+                    (Some(thing), None) => database
+                        .thing_generator()
+                        .lock()
+                        .unwrap()
+                        .check(thing)
+                        .ok_or_else(|| invalid(format!("Unknown thing: {thing}")))?,
+                    (None, Some(reference)) => *references.entry(reference).or_insert_with(|| *database.create_thing()),
+                    _ => return Err(invalid("An appearance needs either a thing or a ref".into())),
+                };
+                roles.push((thing, role));
+            }
+            if let Some(reference) = spec.reference.as_deref().filter(|reference| references.contains_key(reference)) {
+                return Err(invalid(format!("The ref '{reference}' is already in use")));
+            }
+            let appearances = roles.into_iter().map(|(thing, role)| database.create_apperance(thing, role).0).collect();
+            let appearance_set = database.create_appearance_set(appearances).0;
+            let (posit, existed) = value.create_posit(database, appearance_set, time);
+            if let Some(reference) = spec.reference.as_deref() {
+                references.insert(reference, posit);
+            }
+            ingested.push(IngestedPosit { posit, existed });
+        }
+        Ok(ingested)
+    })
+}
+
+/// Read a JSON value as its given data type, or by its JSON type when none is given.
+fn spec_value(value: &serde_json::Value, value_type: Option<ValueType>) -> std::result::Result<TypedValue, String> {
+    use serde_json::Value;
+    match (value, value_type) {
+        (Value::Null | Value::Bool(_), _) => Err(format!("Unsupported value: {value}")),
+        (Value::String(text), Some(value_type)) => typed_value(text, value_type),
+        (value, Some(value_type)) => typed_value(&value.to_string(), value_type),
+        (Value::String(text), None) => Ok(TypedValue::String(text.clone())),
+        (Value::Number(number), None) => match number.as_i64() {
+            Some(int) => Ok(TypedValue::I64(int)),
+            None => typed_value(&number.to_string(), ValueType::Decimal),
+        },
+        (Value::Array(_) | Value::Object(_), None) => typed_value(&value.to_string(), ValueType::Json),
+    }
+}
+
+/// The records of CSV text with their starting lines. Fields may be quoted, with `""` standing for a
+/// quote and line breaks kept; unquoted fields are taken as they are.
+struct Records<'t> {
+    text: &'t str,
+    position: usize,
+    line: usize,
+    delimiter: char,
+}
+
+impl<'t> Records<'t> {
+    fn new(text: &'t str, delimiter: char) -> Self {
+        Records { text: text.strip_prefix('\u{feff}').unwrap_or(text), position: 0, line: 1, delimiter }
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = (usize, std::result::Result<Vec<String>, String>);
+    fn next(&mut self) -> Option<Self::Item> {
+        // Blank lines separate no records.
+        while self.text[self.position..].starts_with(['\r', '\n']) {
+            if self.text[self.position..].starts_with('\n') {
+                self.line += 1;
+            }
+            self.position += 1;
+        }
+        if self.position >= self.text.len() {
+            return None;
+        }
+        let start = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = self.text[self.position..].char_indices().peekable();
+        let mut end = self.text.len() - self.position;
+        while let Some((offset, c)) = chars.next() {
+            if c == '\n' {
+                self.line += 1;
+            }
+            if quoted {
+                match c {
+                    '"' if chars.peek().is_some_and(|(_, next)| *next == '"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => quoted = false,
+                    _ => field.push(c),
+                }
+            } else if c == '"' && field.is_empty() {
+                quoted = true;
+            } else if c == self.delimiter {
+                fields.push(std::mem::take(&mut field));
+            } else if c == '\n' {
+                end = offset + 1;
+                break;
+            } else if c != '\r' {
+                field.push(c);
+            }
+        }
+        self.position += end;
+        if quoted {
+            return Some((start, Err("Unterminated quoted field".into())));
+        }
+        fields.push(field);
+        Some((start, Ok(fields)))
+    }
+}
@@ -17,6 +17,7 @@
 //! * [`datatype`] – The [`datatype::DataType`] trait plus provided concrete types
 //!   (string, numeric, temporal, certainty, JSON, decimal, etc.).
 //! * [`persist`] – SQLite persistence & restoration layer.
+//! * [`import`] – Importing CSV extracts as posits, shaped by a role mapping.
 //! * [`traqula`] – A minimal DSL (parser + engine) for adding roles, posits and performing searches.
 //!
 //! ## Data Types
@@ -62,6 +63,7 @@
 
 pub mod construct;
 pub mod datatype;
+pub mod import;
 pub mod interface;
 pub mod persist;
 pub mod traqula;
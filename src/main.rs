@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::fs::{read_to_string, remove_file};
 
 use bareclad::construct::{Database, PersistenceMode};
+use bareclad::import::Mapping;
 use bareclad::interface::QueryInterface;
 use bareclad::traqula::Engine;
 use bareclad::error::{BarecladError, Result};
@@ -24,6 +25,19 @@ async fn main() {
 }
 
 async fn real_main() -> Result<()> {
+    // Subcommands work on the configured database and exit instead of serving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let task = match args.as_slice() {
+        [] => None,
+        [command, csv, mapping] if command == "import" => Some(Task::Import(csv.clone(), mapping.clone())),
+        [command, file] if command == "dump" => Some(Task::Dump(file.clone())),
+        [command, file] if command == "restore" => Some(Task::Restore(file.clone())),
+        _ => {
+            return Err(BarecladError::Config(
+                "Usage: bareclad [import <csv file> <mapping file> | dump <traqula file> | restore <traqula file>]".into(),
+            ))
+        }
+    };
     let settings = Config::builder()
         .add_source(File::with_name("bareclad.json"))
         .build()
@@ -44,7 +58,7 @@ async fn real_main() -> Result<()> {
         .get("recreate_database_on_startup")
         .map(|v| v == "true")
         .unwrap_or(false);
-    if recreate_database_on_startup {
+    if recreate_database_on_startup && task.is_none() {
         match remove_file(database_file_and_path) {
             Ok(_) => (),
             Err(e) => {
@@ -66,6 +80,12 @@ async fn real_main() -> Result<()> {
         PersistenceMode::InMemory
     };
     let bareclad = Database::new(mode)?;
+    match task {
+        Some(Task::Import(csv, mapping)) => return import_csv(&bareclad, &csv, &mapping),
+        Some(Task::Dump(file)) => return dump(&bareclad, &file),
+        Some(Task::Restore(file)) => return restore(&bareclad, &file),
+        None => (),
+    }
     let db = Arc::new(bareclad);
     let interface = Arc::new(QueryInterface::new(Arc::clone(&db)));
     let traqula_file_to_run_on_startup = settings_lookup
@@ -116,3 +136,45 @@ async fn real_main() -> Result<()> {
         .map_err(|e| BarecladError::Execution(format!("server error: {e}")))?;
     Ok(())
 }
+
+enum Task {
+    Import(String, String),
+    Dump(String),
+    Restore(String),
+}
+
+fn dump(db: &Database, file: &str) -> Result<()> {
+    let script = bareclad::traqula::dump(db)?;
+    std::fs::write(file, script)
+        .map_err(|e| BarecladError::Config(format!("Could not write dump file: {e}")))?;
+    println!("Dumped the database to '{}'.", file);
+    Ok(())
+}
+
+fn restore(db: &Database, file: &str) -> Result<()> {
+    let script = read_to_string(file)
+        .map_err(|e| BarecladError::Config(format!("Could not read dump file: {e}")))?;
+    bareclad::traqula::restore(db, &script)?;
+    println!("Restored the database from '{}'.", file);
+    Ok(())
+}
+
+fn import_csv(db: &Database, csv: &str, mapping: &str) -> Result<()> {
+    let mapping = read_to_string(mapping)
+        .map_err(|e| BarecladError::Config(format!("Could not read mapping file: {e}")))?;
+    let mapping: Mapping = serde_json::from_str(&mapping)
+        .map_err(|e| BarecladError::Config(format!("Invalid mapping: {e}")))?;
+    let csv = std::fs::File::open(csv)
+        .map_err(|e| BarecladError::Config(format!("Could not open CSV file: {e}")))?;
+    let report = bareclad::import::import_csv(db, csv, &mapping)?;
+    for error in &report.errors {
+        println!("Line {}: {}", error.line, error.message);
+    }
+    println!(
+        "Imported {} posits from {} of {} rows.",
+        report.posits,
+        report.rows - report.errors.len(),
+        report.rows
+    );
+    Ok(())
+}
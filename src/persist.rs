@@ -19,6 +19,12 @@
 //!   persisted rows into in-memory keepers.
 //! * New constructs invoke `persist_*` methods which perform idempotent writes
 //!   (checking for existing rows first) and return whether the row already existed.
+//! * Within a transaction the writes are deferred, and [`Persistor::persist_batch`]
+//!   runs them at commit in one SQLite transaction, so that either all of the rows
+//!   and ledger entries are stored or none are.
+//! * Every batch is written on one connection with cached statements. The end of
+//!   the ledger is read once and followed in memory, so hashing a posit needs no
+//!   queries, and `LedgerHead` is updated once, as the batch ends.
 //!
 //! # Adding New Data Types
 //! After implementing [`crate::datatype::DataType`] for a type, extend the
@@ -29,7 +35,8 @@
 //! could propagate a domain error type instead.
 // used for persistence
 use blake3;
-use rusqlite::{Connection, Error, params};
+use rusqlite::types::{ToSqlOutput, Value, ValueRef};
+use rusqlite::{Connection, Error, ToSql, params};
 use crate::error::{BarecladError, Result};
 
 /// 64 zero hex string representing the genesis (no previous) hash in the integrity chain.
@@ -40,11 +47,25 @@ use crate::construct::{Appearance, AppearanceSet, Database, Posit, Role, Thing};
 use crate::datatype::{Certainty, DataType, Decimal, JSON, Time};
 
 // ------------- Persistence -------------
+/// A write deferred until its transaction commits, given the persistor and the connection of the
+/// commit. Returns true if the record already existed.
+pub type PendingWrite = Box<dyn FnOnce(&mut Persistor, &Connection) -> Result<bool> + Send>;
+
+/// The end of the posit ledger, followed in memory while a batch is written.
+struct Ledger {
+    /// The highest posit identity in the ledger, and its hash, which the next posit chains on from.
+    last: Option<(Thing, String)>,
+    /// The hash of the posit written last, and the number of posits in the ledger.
+    head: Option<(String, i64)>,
+}
+
 pub struct Persistor {
     /// File path of the SQLite database, if file-backed. If None, using in-memory (runtime writes/restores are no-ops).
     db_path: Option<String>,
     /// Cache of data type identifiers already inserted into `DataType`.
     seen_data_types: Vec<u8>,
+    /// The end of the ledger, while a batch writing posits is under way.
+    ledger: Option<Ledger>,
 }
 impl Persistor {
     /// Create a file-backed persistor given a filesystem path; opens a connection to initialize schema and records the path for later calls.
@@ -129,6 +150,7 @@ impl Persistor {
         Ok(Persistor {
             db_path: Some(path.to_string()),
             seen_data_types: Vec::new(),
+            ledger: None,
         })
     }
 
@@ -137,61 +159,79 @@ impl Persistor {
         Persistor {
             db_path: None,
             seen_data_types: Vec::new(),
+            ledger: None,
         }
     }
 
-    /// Helper: run an operation with a Connection. For file-backed databases, opens a fresh
-    /// connection per call to avoid sharing Connection across threads. For in-memory, falls back
-    /// to the primary connection created by the caller.
-    fn with_conn<T>(&self, mut op: impl FnMut(&Connection) -> Result<T>) -> Option<Result<T>> {
-        if let Some(ref path) = self.db_path {
-            let conn = match Connection::open(path) { Ok(c) => c, Err(e) => return Some(Err(BarecladError::from(e))) };
+    /// Helper: open a connection for a file-backed database. A fresh connection is opened per call
+    /// to avoid sharing a Connection across threads; in-memory mode has no shared path to reopen, so
+    /// runtime persistence is a no-op and `None` is returned.
+    fn connection(&self) -> Option<Result<Connection>> {
+        let path = self.db_path.as_ref()?;
+        Some(Connection::open(path).map_err(BarecladError::from).inspect(|conn| {
             // Busy timeout helps under concurrent writes
             let _ = conn.busy_timeout(std::time::Duration::from_millis(5000));
-            Some(op(&conn))
-        } else {
-            // In-memory mode: no shared path to reopen; treat persistence as a no-op at runtime
-            None
-        }
+        }))
     }
     /// Persist a thing identity if not already present.
     /// Returns true if the record already existed.
     pub fn persist_thing(&mut self, thing: &Thing) -> Result<bool> {
-        let mut existing = false;
-        if let Some(r) = self.with_conn(|conn| {
-            let mut stmt = conn.prepare("select Thing_Identity from Thing where Thing_Identity = ?")?;
-            match stmt.query_row::<usize, _, _>(params![&thing], |r| r.get(0)) {
-                Ok(_) => { existing = true; }
-                Err(Error::QueryReturnedNoRows) => {
-                    conn.prepare("insert into Thing (Thing_Identity) values (?)")?
-                        .execute(params![&thing])?;
-                }
-                Err(e) => { return Err(BarecladError::Persistence(format!("Thing check failed: {e}"))); }
-            }
-            Ok(())
-        }) { r?; }
-        Ok(existing)
+        self.batch(|persistor, conn| persistor.write_thing(conn, thing))
     }
     /// Persist a role row by unique role name. Returns true if already present.
     pub fn persist_role(&mut self, role: &Role) -> Result<bool> {
-        let mut existing = false;
-        if let Some(r) = self.with_conn(|conn| {
-            let mut stmt = conn.prepare("select Role_Identity from Role where Role = ?")?;
-            match stmt.query_row::<usize, _, _>(params![&role.name()], |r| r.get(0)) {
-                Ok(_) => { existing = true; }
-                Err(Error::QueryReturnedNoRows) => {
-                    conn.prepare("insert into Role (Role_Identity, Role, Reserved) values (?, ?, ?)")?
-                        .execute(params![&role.role(), &role.name(), &role.reserved()])?;
-                }
-                Err(e) => { return Err(BarecladError::Persistence(format!("Role check failed: {e}"))); }
-            }
-            Ok(())
-        }) { r?; }
-        Ok(existing)
+        self.batch(|persistor, conn| persistor.write_role(conn, role))
     }
     /// Persist a posit (idempotent). If unseen, ensures associated value & time
     /// data types are catalogued. Returns true if the posit already existed.
     pub fn persist_posit<V: 'static + DataType>(&mut self, posit: &Posit<V>) -> Result<bool> {
+        self.batch(|persistor, conn| persistor.write_posit(conn, posit))
+    }
+    /// Run writes in a single SQLite transaction on one connection: either all their rows and
+    /// ledger entries are stored, or, when one fails, none are and its error is returned.
+    pub fn persist_batch(&mut self, writes: Vec<PendingWrite>) -> Result<()> {
+        self.batch(|persistor, conn| {
+            for write in writes {
+                write(persistor, conn)?;
+            }
+            Ok(())
+        })
+    }
+    /// Helper: run a write in a SQLite transaction of its own; in-memory mode writes nothing.
+    fn batch<T: Default>(&mut self, write: impl FnOnce(&mut Persistor, &Connection) -> Result<T>) -> Result<T> {
+        let Some(conn) = self.connection() else { return Ok(T::default()) };
+        let mut conn = conn?;
+        let seen_data_types = self.seen_data_types.clone();
+        let result = self.write_batch(&mut conn, write);
+        // Others may write to the file between batches, so the ledger is read anew by the next one.
+        self.ledger = None;
+        if result.is_err() {
+            // Data types catalogued by the rolled back transaction must be inserted again.
+            self.seen_data_types = seen_data_types;
+        }
+        result
+    }
+    fn write_batch<T>(&mut self, conn: &mut Connection, write: impl FnOnce(&mut Persistor, &Connection) -> Result<T>) -> Result<T> {
+        let tx = conn.transaction()?;
+        let result = write(self, &tx)?;
+        if let Some((head, count)) = self.ledger.as_ref().and_then(|ledger| ledger.head.as_ref()) {
+            tx.prepare_cached("insert into LedgerHead (Name, HeadHash, Count) values ('PositLedger', ?, ?) on conflict(Name) do update set HeadHash=excluded.HeadHash, Count=excluded.Count")?
+                .execute(params![head, count])?;
+        }
+        tx.commit()?;
+        Ok(result)
+    }
+    pub(crate) fn write_thing(&mut self, conn: &Connection, thing: &Thing) -> Result<bool> {
+        let inserted = conn.prepare_cached("insert or ignore into Thing (Thing_Identity) values (?)")?
+            .execute(params![&thing])?;
+        Ok(inserted == 0)
+    }
+    pub(crate) fn write_role(&mut self, conn: &Connection, role: &Role) -> Result<bool> {
+        let inserted = conn.prepare_cached("insert into Role (Role_Identity, Role, Reserved) values (?, ?, ?) on conflict(Role) do nothing")?
+            .execute(params![&role.role(), &role.name(), &role.reserved()])?;
+        Ok(inserted == 0)
+    }
+    pub(crate) fn write_posit<V: 'static + DataType>(&mut self, conn: &Connection, posit: &Posit<V>) -> Result<bool> {
         let mut appearances = Vec::new();
         let appearance_set = posit.appearance_set();
         for appearance in appearance_set.appearances().iter() {
@@ -199,64 +239,61 @@ impl Persistor {
                 .push(appearance.thing().to_string() + "," + &appearance.role().role().to_string());
         }
         let apperance_set_as_text = appearances.join("|");
-        let mut existing = false;
-        // Existence check
-        if let Some(r) = self.with_conn(|conn| {
-            let mut stmt = conn.prepare("select Posit_Identity from Posit where AppearanceSet = ? and AppearingValue = ? and AppearanceTime = ?")?;
-            match stmt.query_row::<usize, _, _>(params![&apperance_set_as_text, &posit.value(), &posit.time()], |r| r.get(0)) {
-                Ok(_) => { existing = true; }
-                Err(Error::QueryReturnedNoRows) => { /* insert below */ }
-                Err(e) => { return Err(BarecladError::Persistence(format!("Posit check failed: {e}"))); }
-            }
-            Ok(())
-        }) { r?; }
-        if !existing {
-            // Update cache outside of connection borrow
-            let need_value_dt = !self.seen_data_types.contains(&posit.value().identifier());
-            let need_time_dt = !self.seen_data_types.contains(&posit.time().identifier());
-            if need_value_dt {
-                self.seen_data_types.push(posit.value().identifier());
-            }
-            if need_time_dt {
-                self.seen_data_types.push(posit.time().identifier());
+        for (identifier, data_type) in [
+            (posit.value().identifier(), posit.value().data_type()),
+            (posit.time().identifier(), posit.time().data_type()),
+        ] {
+            if !self.seen_data_types.contains(&identifier) {
+                self.seen_data_types.push(identifier);
+                conn.prepare_cached("insert or ignore into DataType (DataType_Identity, DataType) values (?, ?)")?
+                    .execute(params![&identifier, &data_type])?;
             }
-            // Perform inserts
-            if let Some(r) = self.with_conn(|conn| {
-                if need_value_dt {
-                    conn.prepare("insert or ignore into DataType (DataType_Identity, DataType) values (?, ?)")?
-                        .execute(params![&posit.value().identifier(), &posit.value().data_type()])?;
-                }
-                if need_time_dt {
-                    conn.prepare("insert or ignore into DataType (DataType_Identity, DataType) values (?, ?)")?
-                        .execute(params![&posit.time().identifier(), &posit.time().data_type()])?;
-                }
-                conn.prepare("insert into Posit (Posit_Identity, AppearanceSet, AppearingValue, ValueType_Identity, AppearanceTime) values (?, ?, ?, ?, ?)")?
-                    .execute(params![&posit.posit(), &apperance_set_as_text, &posit.value(), &posit.value().identifier(), &posit.time()])?;
-                let prev_hash: String = {
-                    let mut stmt = conn.prepare("select Hash from PositHash order by Posit_Identity desc limit 1")?;
-                    let mut rows = stmt.query([])?;
-                    if let Some(row) = rows.next()? { row.get::<_, String>(0)? } else { GENESIS_HASH.to_string() }
-                };
-                let mut stmt = conn.prepare("select AppearanceSet, cast(AppearingValue as text), ValueType_Identity, AppearanceTime from Posit where Posit_Identity = ?")?;
-                let mut rows = stmt.query(params![&posit.posit()])?;
-                if let Some(r) = rows.next()? {
-                    let aset_text: String = r.get(0)?;
-                    let value_text: String = r.get(1)?;
-                    let vtid: i64 = r.get(2)?;
-                    let atime_text: String = r.get(3)?;
-                    let input = format!("{}|{}|{}|{}|{}|prev={}", &posit.posit(), aset_text, vtid, value_text, atime_text, prev_hash);
-                    let hash_hex = blake3::hash(input.as_bytes()).to_hex().to_string();
-                    conn.prepare("insert into PositHash (Posit_Identity, PrevHash, Hash) values (?, ?, ?)")?
-                        .execute(params![&posit.posit(), &prev_hash, &hash_hex])?;
-                    let count: i64 = conn.prepare("select count(1) from PositHash")?
-                        .query_row([], |r| r.get(0))?;
-                    conn.prepare("insert into LedgerHead (Name, HeadHash, Count) values ('PositLedger', ?, ?) on conflict(Name) do update set HeadHash=excluded.HeadHash, Count=excluded.Count")?
-                        .execute(params![&hash_hex, &count])?;
-                }
-                Ok(())
-            }) { r?; }
         }
-        Ok(existing)
+        // The unique constraint doubles as the existence check
+        let inserted = conn.prepare_cached("insert into Posit (Posit_Identity, AppearanceSet, AppearingValue, ValueType_Identity, AppearanceTime) values (?, ?, ?, ?, ?) on conflict(AppearanceSet, AppearingValue, AppearanceTime) do nothing")?
+            .execute(params![&posit.posit(), &apperance_set_as_text, &posit.value(), &posit.value().identifier(), &posit.time()])?;
+        if inserted == 0 {
+            return Ok(true);
+        }
+        // Hash the value and time as SQLite renders them as text, which `verify_integrity` reads back.
+        let (value_text, time_text) = match (sql_text(posit.value())?, sql_text(posit.time())?) {
+            (Some(value_text), Some(time_text)) => (value_text, time_text),
+            _ => conn.prepare_cached("select cast(AppearingValue as text), AppearanceTime from Posit where Posit_Identity = ?")?
+                .query_row(params![&posit.posit()], |r| Ok((r.get(0)?, r.get(1)?)))?,
+        };
+        let ledger = self.ledger(conn)?;
+        let prev_hash = ledger.last.as_ref().map_or(GENESIS_HASH, |(_, hash)| hash.as_str()).to_string();
+        let input = format!("{}|{}|{}|{}|{}|prev={}", &posit.posit(), apperance_set_as_text, posit.value().identifier(), value_text, time_text, prev_hash);
+        let hash_hex = blake3::hash(input.as_bytes()).to_hex().to_string();
+        conn.prepare_cached("insert into PositHash (Posit_Identity, PrevHash, Hash) values (?, ?, ?)")?
+            .execute(params![&posit.posit(), &prev_hash, &hash_hex])?;
+        if ledger.last.as_ref().is_none_or(|(last, _)| *last < posit.posit()) {
+            ledger.last = Some((posit.posit(), hash_hex.clone()));
+        }
+        let count = ledger.head.as_ref().map_or(0, |(_, count)| *count) + 1;
+        ledger.head = Some((hash_hex, count));
+        Ok(false)
+    }
+    /// Helper: the end of the ledger, read when the batch writes its first posit.
+    fn ledger(&mut self, conn: &Connection) -> Result<&mut Ledger> {
+        if self.ledger.is_none() {
+            let last = conn.prepare_cached("select Posit_Identity, Hash from PositHash order by Posit_Identity desc limit 1")?
+                .query_row([], |r| Ok((r.get(0)?, r.get(1)?)));
+            let last = match last {
+                Ok(last) => Some(last),
+                Err(Error::QueryReturnedNoRows) => None,
+                Err(e) => return Err(e.into()),
+            };
+            // The head keeps the count, so the hashes need only be counted for ledgers without one
+            let count = match conn.prepare_cached("select Count from LedgerHead where Name = 'PositLedger'")?.query_row([], |r| r.get(0)) {
+                Ok(count) => count,
+                Err(Error::QueryReturnedNoRows) => conn.prepare_cached("select count(1) from PositHash")?.query_row([], |r| r.get(0))?,
+                Err(e) => return Err(e.into()),
+            };
+            let head = last.as_ref().map(|(_, hash): &(Thing, String)| (hash.clone(), count));
+            self.ledger = Some(Ledger { last, head });
+        }
+        Ok(self.ledger.as_mut().unwrap())
     }
     /// Rehydrate all thing identities into the in-memory generator.
     pub fn restore_things(&mut self, db: &Database) -> Result<()> {
@@ -311,6 +348,8 @@ impl Persistor {
                 let thing_id: Thing = thing_txt.parse().map_err(|e| BarecladError::DataCorruption { message: format!("Bad appearance thing id '{thing_txt}': {e}") })?;
                 let role_id: Thing = role_txt.parse().map_err(|e| BarecladError::DataCorruption { message: format!("Bad role id '{role_txt}': {e}") })?;
                 let role_arc = db.role_keeper().lock().unwrap().lookup(&role_id);
+                // Appearing things may be posits themselves (meta-posits) or identities only known through appearances
+                db.thing_generator().lock().unwrap().retain(thing_id);
                 let appearance = Appearance::new(thing_id, role_arc);
                 let (kept_appearance, _) = db.keep_appearance(appearance);
                 appearance_vec.push(kept_appearance);
@@ -411,3 +450,13 @@ impl Persistor {
         }
     }
 }
+
+/// The text SQLite casts a value to, for values stored as integers or text; None for others.
+fn sql_text(value: &dyn ToSql) -> Result<Option<String>> {
+    Ok(match value.to_sql()? {
+        ToSqlOutput::Borrowed(ValueRef::Integer(int)) | ToSqlOutput::Owned(Value::Integer(int)) => Some(int.to_string()),
+        ToSqlOutput::Borrowed(ValueRef::Text(text)) => std::str::from_utf8(text).ok().map(str::to_string),
+        ToSqlOutput::Owned(Value::Text(text)) => Some(text),
+        _ => None,
+    })
+}
@@ -1,5 +1,8 @@
-use std::sync::Arc;
-use axum::{routing::post, Router, Json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use axum::{extract::Path, response::IntoResponse, routing::post, Router, Json};
+use axum::http::HeaderMap;
 use futures_util::StreamExt;
 use axum::http::header;
 use tower_http::cors::{CorsLayer, Any};
@@ -7,7 +10,9 @@ use serde::{Deserialize, Serialize};
 use axum::http::StatusCode;
 use tracing::{info, warn};
 use crate::interface::QueryInterface;
-use crate::traqula::{Engine, CollectedResultSet, RowSink, SinkFlow, MultiStreamCallbacks};
+use crate::traqula::{Engine, CollectedResult, CollectedResultSet, PreparedScript, RowSink, SinkFlow, MultiStreamCallbacks, Param};
+use crate::error::BarecladError;
+use crate::import::{ingest_posits, IngestedPosit, PositSpec};
 
 #[derive(Deserialize)]
 pub struct QueryRequest {
@@ -16,6 +21,9 @@ pub struct QueryRequest {
     pub stream: bool,
     #[serde(default)]
     pub timeout_ms: Option<u64>,
+    /// Values for `$name` placeholders in the script.
+    #[serde(default)]
+    pub params: Option<HashMap<String, serde_json::Value>>,
 }
 
 #[derive(Serialize)]
@@ -32,77 +40,293 @@ pub struct QueryResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limited: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")] 
-    pub rows: Option<Vec<Vec<String>>>,
+    pub rows: Option<Vec<Vec<Option<String>>>>,
     #[serde(skip_serializing_if = "Option::is_none")] 
     pub error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result_sets: Option<Vec<MultiResultSet>>,
 }
 
+#[derive(Deserialize)]
+pub struct PrepareRequest {
+    pub script: String,
+}
+
+#[derive(Serialize)]
+pub struct PrepareResponse {
+    pub handle: u64,
+    pub status: String,
+    /// Names of the `$name` placeholders the script expects.
+    pub params: Vec<String>,
+    pub result_sets: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ExecutePreparedRequest {
+    #[serde(default)]
+    pub params: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[derive(Serialize)]
+pub struct PositsResponse {
+    pub status: String,
+    pub elapsed_ms: f64,
+    /// The identity of each posit sent, in order, and whether it already existed.
+    pub posits: Vec<IngestedPosit>,
+}
+
+/// Most prepared scripts kept at once; preparing another drops the one executed longest ago.
+const PREPARED_CAPACITY: usize = 1024;
+
+/// Scripts registered with `POST /v1/prepare`, executed by handle with `POST /v1/prepared/:handle`
+/// and dropped with `DELETE /v1/prepared/:handle`, or when more than [`PREPARED_CAPACITY`] are kept.
+#[derive(Default)]
+struct PreparedRegistry {
+    last_handle: AtomicU64,
+    /// Each script with the tick of its last use.
+    scripts: Mutex<HashMap<u64, (Arc<PreparedScript>, u64)>>,
+    ticks: AtomicU64,
+}
+impl PreparedRegistry {
+    /// Keep a prepared script, dropping the least recently used one when full, and return its handle.
+    fn insert(&self, prepared: PreparedScript) -> u64 {
+        let handle = self.last_handle.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut scripts = self.scripts.lock().unwrap();
+        if scripts.len() >= PREPARED_CAPACITY {
+            let oldest = scripts.iter().min_by_key(|(_, (_, used))| *used).map(|(handle, _)| *handle);
+            if let Some(oldest) = oldest {
+                scripts.remove(&oldest);
+                info!(handle=oldest, "prepared script evicted");
+            }
+        }
+        scripts.insert(handle, (Arc::new(prepared), self.ticks.fetch_add(1, Ordering::Relaxed)));
+        handle
+    }
+    fn get(&self, handle: u64) -> Option<Arc<PreparedScript>> {
+        let mut scripts = self.scripts.lock().unwrap();
+        let (prepared, used) = scripts.get_mut(&handle)?;
+        *used = self.ticks.fetch_add(1, Ordering::Relaxed);
+        Some(Arc::clone(prepared))
+    }
+    fn remove(&self, handle: u64) -> bool {
+        self.scripts.lock().unwrap().remove(&handle).is_some()
+    }
+}
+
 #[derive(Serialize)]
 pub struct MultiResultSet {
     pub columns: Vec<String>,
     pub row_types: Vec<Vec<String>>,
     pub row_count: usize,
     pub limited: bool,
-    pub rows: Vec<Vec<String>>,
+    pub rows: Vec<Vec<Option<String>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub search: Option<String>,
 }
 
+/// Cells of type `null` (variables left unbound by an `optional` pattern) are sent as JSON nulls.
+fn json_row(row: Vec<String>, types: &[String]) -> Vec<Option<String>> {
+    row.into_iter().enumerate().map(|(i, cell)| (types.get(i).map(String::as_str) != Some("null")).then_some(cell)).collect()
+}
+
+fn json_rows(rows: Vec<Vec<String>>, row_types: &[Vec<String>]) -> Vec<Vec<Option<String>>> {
+    rows.into_iter().enumerate().map(|(i, row)| json_row(row, row_types.get(i).map(Vec::as_slice).unwrap_or_default())).collect()
+}
+
+/// Typed binding of a JSON parameter: strings, integers, other numbers (as decimals) and objects (as JSON values).
+/// Strings also bind to time and certainty placeholders when they are written in those formats.
+fn json_param(name: &str, value: &serde_json::Value) -> Result<Param, BarecladError> {
+    match value {
+        serde_json::Value::String(s) => Ok(Param::String(s.clone())),
+        serde_json::Value::Number(n) => Ok(n.as_i64().map(Param::Int).unwrap_or_else(|| Param::Decimal(n.to_string()))),
+        serde_json::Value::Object(_) => Ok(Param::Json(value.to_string())),
+        _ => Err(BarecladError::Parse { message: format!("Parameter ${} must be a string, number or object", name), line: None, col: None }),
+    }
+}
+
+fn json_params(params: &HashMap<String, serde_json::Value>) -> Result<HashMap<String, Param>, BarecladError> {
+    params.iter().map(|(name, value)| json_param(name, value).map(|p| (name.clone(), p))).collect()
+}
+
+/// Where the events of a streamed script go. A script that writes holds the single writer of the
+/// database until it ends, so its events are held back and sent once it has committed or rolled
+/// back; a slow client would otherwise keep every other writer waiting.
+struct Outbox {
+    tx: tokio::sync::mpsc::Sender<String>,
+    held: Option<Vec<String>>,
+}
+impl Outbox {
+    fn new(tx: tokio::sync::mpsc::Sender<String>, hold: bool) -> Self {
+        Self { tx, held: hold.then(Vec::new) }
+    }
+    /// Send an event, or hold it back; false once the client is gone.
+    fn send(&mut self, event: String) -> bool {
+        match &mut self.held {
+            Some(held) => {
+                held.push(event);
+                true
+            }
+            None => self.tx.blocking_send(event).is_ok(),
+        }
+    }
+    /// Forget the events held back, which were those of a script that failed and was rolled back.
+    fn discard(&mut self) {
+        if let Some(held) = &mut self.held {
+            held.clear();
+        }
+    }
+    /// Send the events held back.
+    fn release(&mut self) {
+        for event in self.held.take().into_iter().flatten() {
+            if self.tx.blocking_send(event).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Status and body for a failed query. Requests that do not parse (including parameter binding) are
+/// bad requests and requests that cannot be carried out, such as a search on an unknown role, are
+/// unprocessable; other errors are the server's.
+fn error_body(e: &BarecladError, elapsed_ms: f64) -> (StatusCode, String) {
+    let status = match e {
+        BarecladError::Parse { .. } => StatusCode::BAD_REQUEST,
+        BarecladError::Execution(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let msg = format!("{e}");
+    warn!(%msg, code=%status.as_u16(), "query error");
+    let body = QueryResponse { id: 0, status: "error".into(), elapsed_ms, columns: None, row_types: None, row_count: None, limited: None, rows: None, error: Some(msg), result_sets: None };
+    (status, serde_json::to_string(&body).unwrap())
+}
+
+fn unknown_handle(handle: u64, elapsed_ms: f64) -> (StatusCode, String) {
+    let msg = format!("Unknown prepared script: {handle}");
+    warn!(%msg, code=404u16, "query error");
+    let body = QueryResponse { id: 0, status: "error".into(), elapsed_ms, columns: None, row_types: None, row_count: None, limited: None, rows: None, error: Some(msg), result_sets: None };
+    (StatusCode::NOT_FOUND, serde_json::to_string(&body).unwrap())
+}
+
+/// The posits of a `POST /v1/posits` body: one posit object or an array of them, or with an
+/// NDJSON content type one posit object per line. Errors tell the offending posit, or NDJSON line.
+fn posit_specs(body: &str, ndjson: bool) -> Result<Vec<PositSpec>, BarecladError> {
+    let invalid = |what: &str, line: usize, e: serde_json::Error| BarecladError::Parse { message: format!("{what} {line}: {e}"), line: Some(line), col: None };
+    if ndjson {
+        return body
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(index, text)| serde_json::from_str(text).map_err(|e| invalid("Line", index + 1, e)))
+            .collect();
+    }
+    let value: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| BarecladError::Parse { message: format!("Invalid JSON: {e}"), line: Some(e.line()), col: Some(e.column()) })?;
+    match value {
+        serde_json::Value::Array(values) => values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| serde_json::from_value(value).map_err(|e| invalid("Posit", index + 1, e)))
+            .collect(),
+        value => Ok(vec![serde_json::from_value(value).map_err(|e| invalid("Posit", 1, e))?]),
+    }
+}
+
+fn json_response(status: StatusCode, body_json: String) -> axum::response::Response {
+    axum::response::Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(body_json))
+        .unwrap()
+}
+
+/// Status and body for the rows of a non-streamed execution: one result set, or several (the inner `Err`).
+fn collected_body(rows_result: Result<Result<CollectedResult, Vec<CollectedResultSet>>, BarecladError>, elapsed_ms_f64: f64) -> (StatusCode, String) {
+    match rows_result {
+        Ok(Ok(result)) => {
+            info!(ms=elapsed_ms_f64, rows=result.row_count, limited=result.limited, "query complete");
+            let rows = json_rows(result.rows, &result.row_types);
+            let body = QueryResponse { id: 0, status: "ok".into(), elapsed_ms: elapsed_ms_f64, columns: Some(result.columns), row_types: Some(result.row_types), row_count: Some(result.row_count), limited: Some(result.limited), rows: Some(rows), error: None, result_sets: None };
+            (StatusCode::OK, serde_json::to_string(&body).unwrap())
+        }
+        Ok(Err(multi_sets)) => {
+            let total_rows: usize = multi_sets.iter().map(|m| m.row_count).sum();
+            info!(ms=elapsed_ms_f64, total_rows, searches=multi_sets.len(), "multi-search complete");
+            let result_sets: Vec<MultiResultSet> = multi_sets.into_iter().map(|m: CollectedResultSet| MultiResultSet { rows: json_rows(m.rows, &m.row_types), columns: m.columns, row_types: m.row_types, row_count: m.row_count, limited: m.limited, search: m.search }).collect();
+            let body = QueryResponse { id: 0, status: "ok".into(), elapsed_ms: elapsed_ms_f64, columns: None, row_types: None, row_count: Some(total_rows), limited: None, rows: None, error: None, result_sets: Some(result_sets) };
+            (StatusCode::OK, serde_json::to_string(&body).unwrap())
+        }
+        Err(e) => error_body(&e, elapsed_ms_f64),
+    }
+}
+
 pub fn router(interface: Arc<QueryInterface>) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
-        .allow_methods([axum::http::Method::POST])
+        .allow_methods([axum::http::Method::POST, axum::http::Method::DELETE])
         .allow_headers(Any);
+    let registry = Arc::new(PreparedRegistry::default());
+    let prepare_iface = Arc::clone(&interface);
+    let prepare_registry = Arc::clone(&registry);
+    let execute_iface = Arc::clone(&interface);
+    let execute_registry = Arc::clone(&registry);
+    let posits_iface = Arc::clone(&interface);
     Router::new()
         .route("/v1/query", post(move |Json(req): Json<QueryRequest>| {
         let iface = Arc::clone(&interface);
         async move {
             // We run the query in a blocking thread since Engine is synchronous today.
             let started = std::time::Instant::now();
-            let script = req.script.clone();
+            let prepared = Engine::new(iface.database()).prepare(&req.script).and_then(|prepared| {
+                let params = req.params.as_ref().map(json_params).transpose()?.unwrap_or_default();
+                Ok((prepared, params))
+            });
+            let (prepared, params) = match prepared {
+                Ok(bound) => bound,
+                Err(e) => {
+                    let (status, body_json) = error_body(&e, started.elapsed().as_secs_f64() * 1000.0);
+                    return Ok::<_, (StatusCode, &'static str)>(json_response(status, body_json));
+                }
+            };
             let do_stream = req.stream;
             let _timeout = req.timeout_ms.map(std::time::Duration::from_millis);
             if do_stream {
-                // Attempt streaming if exactly one 'search' token (tokenized) appears; else fall back.
-                let search_count = script
-                    .split(|c: char| c.is_whitespace() || c==';' || c==',')
-                    .filter(|t| *t == "search")
-                    .count();
+                // Stream a single result set directly, several with framing; scripts without searches fall back.
+                let search_count = prepared.result_sets();
                 if search_count == 1 {
                     info!(target: "bareclad::server", event="stream_start", "starting streaming execution");
                     let (tx, rx) = tokio::sync::mpsc::channel::<String>(64);
                     tokio::task::spawn_blocking(move || {
-                        let engine = Engine::new(iface.database());
-                        struct StreamingSink { tx: tokio::sync::mpsc::Sender<String>, limited: bool, rows: usize }
+                        let engine = Engine::new(iface.database()).atomic();
+                        struct StreamingSink { outbox: Outbox, limited: bool, rows: usize }
                         impl RowSink for StreamingSink {
                             fn on_meta(&mut self, columns: &[String]) -> SinkFlow {
                                 let meta = serde_json::json!({"event":"meta","columns": columns, "row_types": [], "row_count": 0, "limited": false});
-                                if self.tx.blocking_send(format!("data: {}\n\n", meta)).is_err() { return SinkFlow::Stop; }
+                                if !self.outbox.send(format!("data: {}\n\n", meta)) { return SinkFlow::Stop; }
                                 SinkFlow::Continue
                             }
                             fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow {
-                                let ev = serde_json::json!({"event":"row","row": row, "types": types});
-                                if self.tx.blocking_send(format!("data: {}\n\n", ev)).is_err() { return SinkFlow::Stop; }
+                                let ev = serde_json::json!({"event":"row","row": json_row(row, &types), "types": types});
+                                if !self.outbox.send(format!("data: {}\n\n", ev)) { return SinkFlow::Stop; }
                                 self.rows +=1; SinkFlow::Continue
                             }
                         }
-                        let mut sink = StreamingSink { tx: tx.clone(), limited:false, rows:0 };
-                        match engine.execute_stream_single(&script, &mut sink) {
+                        let mut sink = StreamingSink { outbox: Outbox::new(tx, prepared.writes()), limited:false, rows:0 };
+                        match engine.execute_prepared_stream_single(&prepared, &params, &mut sink) {
                             Ok((_cols, limited, row_count)) => {
                                 sink.limited = limited; sink.rows = row_count; // ensure final values
                                 let end = serde_json::json!({"event":"end","row_count": row_count, "limited": limited});
-                                let _ = tx.blocking_send(format!("data: {}\n\n", end));
+                                sink.outbox.send(format!("data: {}\n\n", end));
                                 info!(target: "bareclad::server", event="stream_complete", rows=row_count, limited=limited, "streaming execution finished");
                             }
                             Err(e) => {
+                                sink.outbox.discard();
                                 let err = serde_json::json!({"event":"error","error": format!("{}", e)});
-                                let _ = tx.blocking_send(format!("data: {}\n\n", err));
-                                let _ = tx.blocking_send("data: {\"event\":\"end\"}\n\n".to_string());
+                                sink.outbox.send(format!("data: {}\n\n", err));
+                                sink.outbox.send("data: {\"event\":\"end\"}\n\n".to_string());
                                 warn!(target: "bareclad::server", error=%e, event="stream_error", "streaming execution error");
                             }
                         }
+                        sink.outbox.release();
                     });
                     let rx_stream = tokio_stream::wrappers::ReceiverStream::new(rx)
                         .map(|chunk| Ok::<_, std::io::Error>(axum::body::Bytes::from(chunk)));
@@ -113,23 +337,24 @@ pub fn router(interface: Arc<QueryInterface>) -> Router {
                         .header(header::CONNECTION, "keep-alive")
                         .body(axum::body::Body::from_stream(rx_stream))
                         .unwrap();
-                    return Ok::<_, (StatusCode, &'static str)>((StatusCode::OK, response));
+                    return Ok::<_, (StatusCode, &'static str)>(response);
                 } else if search_count > 1 {
                     info!(target: "bareclad::server", event="stream_start_multi", searches=search_count, "starting multi-search streaming execution");
                     let (tx, rx) = tokio::sync::mpsc::channel::<String>(128);
                     tokio::task::spawn_blocking(move || {
-                        let engine = Engine::new(iface.database());
-                        struct MultiCb { tx: tokio::sync::mpsc::Sender<String>, total_rows: usize }
+                        let engine = Engine::new(iface.database()).atomic();
+                        struct MultiCb { outbox: Outbox, total_rows: usize }
                         impl MultiStreamCallbacks for MultiCb {
-                            fn on_result_set_start(&mut self, set_index: usize, columns: &[String], search_text: &str) { let ev=serde_json::json!({"event":"result_set_start","index": set_index, "columns": columns, "search": search_text}); let _=self.tx.blocking_send(format!("data: {}\n\n", ev)); }
-                            fn on_row(&mut self, set_index: usize, row: Vec<String>, types: Vec<String>) -> bool { self.total_rows+=1; let ev=serde_json::json!({"event":"row","index": set_index, "row": row, "types": types}); self.tx.blocking_send(format!("data: {}\n\n", ev)).is_ok() }
-                            fn on_result_set_end(&mut self, set_index: usize, row_count: usize, limited: bool) { let ev=serde_json::json!({"event":"result_set_end","index": set_index, "row_count": row_count, "limited": limited}); let _=self.tx.blocking_send(format!("data: {}\n\n", ev)); }
+                            fn on_result_set_start(&mut self, set_index: usize, columns: &[String], search_text: &str) { let ev=serde_json::json!({"event":"result_set_start","index": set_index, "columns": columns, "search": search_text}); self.outbox.send(format!("data: {}\n\n", ev)); }
+                            fn on_row(&mut self, set_index: usize, row: Vec<String>, types: Vec<String>) -> bool { self.total_rows+=1; let ev=serde_json::json!({"event":"row","index": set_index, "row": json_row(row, &types), "types": types}); self.outbox.send(format!("data: {}\n\n", ev)) }
+                            fn on_result_set_end(&mut self, set_index: usize, row_count: usize, limited: bool) { let ev=serde_json::json!({"event":"result_set_end","index": set_index, "row_count": row_count, "limited": limited}); self.outbox.send(format!("data: {}\n\n", ev)); }
                         }
-                        let mut cb = MultiCb { tx: tx.clone(), total_rows: 0 };
-                        match engine.execute_stream_multi(&script, &mut cb) {
-                            Ok(()) => { let end=serde_json::json!({"event":"multi_end","total_rows": cb.total_rows}); let _=tx.blocking_send(format!("data: {}\n\n", end)); let _=tx.blocking_send("data: {\"event\":\"end\"}\n\n".to_string()); info!(target: "bareclad::server", event="stream_complete_multi", total_rows=cb.total_rows, "multi-search streaming finished"); },
-                            Err(e) => { let err=serde_json::json!({"event":"error","error": format!("{}", e)}); let _=tx.blocking_send(format!("data: {}\n\n", err)); let _=tx.blocking_send("data: {\"event\":\"multi_end\"}\n\n".to_string()); let _=tx.blocking_send("data: {\"event\":\"end\"}\n\n".to_string()); warn!(target: "bareclad::server", error=%e, event="stream_error_multi", "multi-search streaming error"); }
+                        let mut cb = MultiCb { outbox: Outbox::new(tx, prepared.writes()), total_rows: 0 };
+                        match engine.execute_prepared_stream_multi(&prepared, &params, &mut cb) {
+                            Ok(()) => { let end=serde_json::json!({"event":"multi_end","total_rows": cb.total_rows}); cb.outbox.send(format!("data: {}\n\n", end)); cb.outbox.send("data: {\"event\":\"end\"}\n\n".to_string()); info!(target: "bareclad::server", event="stream_complete_multi", total_rows=cb.total_rows, "multi-search streaming finished"); },
+                            Err(e) => { cb.outbox.discard(); let err=serde_json::json!({"event":"error","error": format!("{}", e)}); cb.outbox.send(format!("data: {}\n\n", err)); cb.outbox.send("data: {\"event\":\"multi_end\"}\n\n".to_string()); cb.outbox.send("data: {\"event\":\"end\"}\n\n".to_string()); warn!(target: "bareclad::server", error=%e, event="stream_error_multi", "multi-search streaming error"); }
                         }
+                        cb.outbox.release();
                     });
                     let rx_stream = tokio_stream::wrappers::ReceiverStream::new(rx)
                         .map(|chunk| Ok::<_, std::io::Error>(axum::body::Bytes::from(chunk)));
@@ -140,55 +365,114 @@ pub fn router(interface: Arc<QueryInterface>) -> Router {
                         .header(header::CONNECTION, "keep-alive")
                         .body(axum::body::Body::from_stream(rx_stream))
                         .unwrap();
-                    return Ok::<_, (StatusCode, &'static str)>((StatusCode::OK, response));
+                    return Ok::<_, (StatusCode, &'static str)>(response);
                 }
                 // Else fall through to normal non-stream path if no searches
             }
             let rows_result = tokio::task::spawn_blocking(move || {
-                let engine = Engine::new(iface.database());
-                let search_count = script.matches("search ").count();
-                if search_count > 1 {
-                    match engine.execute_collect_multi(&script) {
-                        Ok(multi) => Ok::<Result<_, _>, _>(Err(multi)), // Err variant inside Ok signifies multi
-                        Err(e) => Err(e),
-                    }
+                let engine = Engine::new(iface.database()).atomic();
+                if prepared.result_sets() > 1 {
+                    engine.execute_prepared_multi(&prepared, &params).map(Err) // Err variant inside Ok signifies multi
                 } else {
-                    engine.execute_collect(&script).map(|single| Ok(single))
+                    engine.execute_prepared(&prepared, &params).map(Ok)
                 }
             }).await.map_err(|e| {
                 warn!(error=%e, "Join error");
                 (StatusCode::INTERNAL_SERVER_ERROR, "Join error")
             })?;
-            let total_elapsed = started.elapsed();
-            let elapsed_ms_f64 = total_elapsed.as_secs_f64() * 1000.0;
-            let (status, body_json) = match rows_result {
-                Ok(Ok(result)) => {
-                    info!(ms=elapsed_ms_f64, rows=result.row_count, limited=result.limited, "query complete");
-                    let body = QueryResponse { id: 0, status: "ok".into(), elapsed_ms: elapsed_ms_f64, columns: Some(result.columns), row_types: Some(result.row_types), row_count: Some(result.row_count), limited: Some(result.limited), rows: Some(result.rows), error: None, result_sets: None };
+            let (status, body_json) = collected_body(rows_result, started.elapsed().as_secs_f64() * 1000.0);
+            Ok::<_, (StatusCode, &'static str)>(json_response(status, body_json))
+        }
+    }))
+    .route("/v1/posits", post(move |headers: HeaderMap, body: String| {
+        let iface = Arc::clone(&posits_iface);
+        async move {
+            let started = std::time::Instant::now();
+            let elapsed_ms = || started.elapsed().as_secs_f64() * 1000.0;
+            let ndjson = headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.contains("ndjson"));
+            let ingested = match posit_specs(&body, ndjson) {
+                Ok(specs) => tokio::task::spawn_blocking(move || ingest_posits(iface.database(), &specs))
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(error=%e, "Join error");
+                        Err(BarecladError::Invariant("Join error".into()))
+                    }),
+                Err(e) => Err(e),
+            };
+            let (status, body_json) = match ingested {
+                Ok(posits) => {
+                    info!(ms=elapsed_ms(), posits=posits.len(), "posits ingested");
+                    let body = PositsResponse { status: "ok".into(), elapsed_ms: elapsed_ms(), posits };
                     (StatusCode::OK, serde_json::to_string(&body).unwrap())
                 }
-                Ok(Err(multi_sets)) => {
-                    let total_rows: usize = multi_sets.iter().map(|m| m.row_count).sum();
-                    info!(ms=elapsed_ms_f64, total_rows, searches=multi_sets.len(), "multi-search complete");
-                    let result_sets: Vec<MultiResultSet> = multi_sets.into_iter().map(|m: CollectedResultSet| MultiResultSet { columns: m.columns, row_types: m.row_types, row_count: m.row_count, limited: m.limited, rows: m.rows, search: m.search }).collect();
-                    let body = QueryResponse { id: 0, status: "ok".into(), elapsed_ms: elapsed_ms_f64, columns: None, row_types: None, row_count: Some(total_rows), limited: None, rows: None, error: None, result_sets: Some(result_sets) };
+                Err(e) => error_body(&e, elapsed_ms()),
+            };
+            json_response(status, body_json)
+        }
+    }))
+    .route("/v1/prepare", post(move |Json(req): Json<PrepareRequest>| {
+        let iface = Arc::clone(&prepare_iface);
+        let registry = Arc::clone(&prepare_registry);
+        async move {
+            let started = std::time::Instant::now();
+            let engine = Engine::new(iface.database());
+            let (status, body_json) = match engine.prepare(&req.script) {
+                Ok(prepared) => {
+                    let params = prepared.params().into_iter().map(String::from).collect();
+                    let result_sets = prepared.result_sets();
+                    let handle = registry.insert(prepared);
+                    let body = PrepareResponse { handle, status: "ok".into(), params, result_sets };
+                    info!(handle, "script prepared");
                     (StatusCode::OK, serde_json::to_string(&body).unwrap())
                 }
+                Err(e) => error_body(&e, started.elapsed().as_secs_f64() * 1000.0),
+            };
+            json_response(status, body_json)
+        }
+    }))
+    .route("/v1/prepared/:handle", post(move |Path(handle): Path<u64>, Json(req): Json<ExecutePreparedRequest>| {
+        let iface = Arc::clone(&execute_iface);
+        let registry = Arc::clone(&execute_registry);
+        async move {
+            let started = std::time::Instant::now();
+            let elapsed_ms = || started.elapsed().as_secs_f64() * 1000.0;
+            let Some(prepared) = registry.get(handle) else {
+                let (status, body_json) = unknown_handle(handle, elapsed_ms());
+                return json_response(status, body_json);
+            };
+            let params = match req.params.as_ref().map(json_params).transpose() {
+                Ok(params) => params.unwrap_or_default(),
+                Err(e) => {
+                    let (status, body_json) = error_body(&e, elapsed_ms());
+                    return json_response(status, body_json);
+                }
+            };
+            let rows_result = tokio::task::spawn_blocking(move || {
+                let engine = Engine::new(iface.database()).atomic();
+                if prepared.result_sets() > 1 {
+                    engine.execute_prepared_multi(&prepared, &params).map(Err)
+                } else {
+                    engine.execute_prepared(&prepared, &params).map(Ok)
+                }
+            }).await;
+            let (status, body_json) = match rows_result {
+                Ok(rows_result) => collected_body(rows_result, elapsed_ms()),
                 Err(e) => {
-                    let is_parse = matches!(e, crate::error::BarecladError::Parse { .. });
-                    let status = if is_parse { StatusCode::BAD_REQUEST } else { StatusCode::INTERNAL_SERVER_ERROR };
-                    let msg = format!("{e}");
-                    warn!(%msg, code=%status.as_u16(), "query error");
-                    let body = QueryResponse { id: 0, status: "error".into(), elapsed_ms: elapsed_ms_f64, columns: None, row_types: None, row_count: None, limited: None, rows: None, error: Some(msg), result_sets: None };
-                    (status, serde_json::to_string(&body).unwrap())
+                    warn!(error=%e, "Join error");
+                    error_body(&BarecladError::Invariant("Join error".into()), elapsed_ms())
                 }
             };
-            let response = axum::response::Response::builder()
-                .status(status)
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(axum::body::Body::from(body_json))
-                .unwrap();
-            Ok::<_, (StatusCode, &'static str)>((StatusCode::OK, response))
+            json_response(status, body_json)
+        }
+    }).delete(move |Path(handle): Path<u64>| async move {
+        if registry.remove(handle) {
+            StatusCode::NO_CONTENT.into_response()
+        } else {
+            let (status, body_json) = unknown_handle(handle, 0.0);
+            json_response(status, body_json)
         }
     }))
     .layer(cors)
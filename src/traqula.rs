@@ -4,10 +4,32 @@
 //! * Multi-result collection: `Engine::execute_collect_multi` returns one result set per `search` in a script.
 //! * Variable–variable predicates:
 //!   - Time vs time comparisons (e.g. `where t1 < t2`).
-//!   - Value vs value comparisons for numbers, decimals, certainties, and strings (equality only for strings).
+//!   - Value vs value comparisons for numbers, decimals, certainties, and strings (strings order by Unicode code points, as in `order by`).
 //! * Certainty literals are percent-only (`75%`, `-10%`); bare numbers like `0.75` no longer auto-convert.
 //! * Ordering on certainty variables requires both sides to be certainties (percent forms); mixed certainty/numeric ordering yields an execution error mentioning a missing percent sign.
 //! * Numeric ordering/equality supports `i64` and `Decimal` interop (coerced during comparison).
+//! * Where conditions combine with `and`, `or`, `not` and parentheses (`not` binds tightest, then `and`, then `or`).
+//! * `x between a and b` (inclusive) and `x in (a, b)` desugar to `>=`/`<=` and `==` comparisons, so the same type rules apply.
+//! * Aggregates (`count`, `count(distinct x)`, `min`, `max`, `sum`, `avg`) fold bindings per group; plain return variables are implicit group keys unless `group by` lists them.
+//! * `order by` sorts projected rows (cross-resolution times, numeric coercion between `i64` and `Decimal`) before `limit` applies.
+//! * `define search x as search ... return w;` names a search; recalling `x` in a pattern or testing `w in x` / `w in (search ... return w)` uses its things as a set.
+//! * `$name` placeholders stand for value, time and certainty literals; `Engine::execute_with_params` binds typed [`Param`] values.
+//! * `Engine::prepare` validates a script once into a [`PreparedScript`]; `Engine::execute_prepared` runs it with new parameters.
+//! * Scripts are parsed into the typed syntax tree of [`ast`] (commands, patterns, predicates and projections with source spans), which the engine executes and tools such as formatters and linters can build on.
+//! * Search patterns are matched by ascending estimated candidate count, respecting the variables each needs bound; literal `where` comparisons every result depends on filter candidates early (see `planner`).
+//! * `x matches "words prefix*"` keeps string values containing every word of the text, case-insensitively; a trailing `*` matches word prefixes (see `construct::words`).
+//! * `x like "Ca%"` (`%` any run, `_` any character) and `x ~ "^Ca"` (regular expression) keep string values matching the whole pattern or containing a match of the expression; invalid expressions are parse errors.
+//! * `g->"key"->0` follows object keys and array indexes into JSON values in `where` comparisons, `return`, aggregates and `order by`; the scalar found is typed as `i64`, `Decimal`, `String` or `bool` (compared with `true`/`false`), and missing paths give null (see `json_path`).
+//! * Literal values and times in patterns and pushed down comparisons of integers, decimals, certainties and times draw their candidates from the ordered indexes of the [`Database`] (see `index`).
+//! * `explain search ...;` returns the plan of a search as a result set; `explain analyze` runs it and adds per-step candidates, bindings, filtered rows and timings.
+//! * `not [...]` patterns drop the bindings they match once all other patterns are matched; their own new variables stay local.
+//! * `optional [...]` patterns keep bindings they cannot extend; variables they introduce are returned as nulls (type `null`), skipped by aggregates and sorted after values (ascending).
+//! * `add assertion [positor, posit, certainty, time]` records assertions over the reserved `ascertains`/`posit` roles; posit variables (`+p [...]`, `p [...]`) join with identities of the same name.
+//! * Posit identities are things: `add posit +p [...], [{(p, posit), (+s, source)}, "scanned letter", '2024']` records a meta-posit about `p`.
+//! * `check consistency [scope] [as of <time>];` reports positors whose latest assertions about posits differing only in value fail `Certainty::consistent`.
+//! * `by <positor|*> certainty <comparator> <certainty>` after a search pattern keeps posits whose latest assertion by the positor (respecting `as of`) satisfies the comparison.
+//! * `load [...] values ($a, ...) (...), ...;` adds posit templates once per row, with new identities per row, and stores the whole command in one batch (see `Database::load`).
+//! * [`dump`] writes a database as a script whose variables are named after identities (`+p12`, `t11`); [`restore`] replays such a script into a database, keeping every identity.
 //! * Execution errors surface unknown variables and mismatched ordering types early, halting evaluation.
 //!
 //! These enhancements are intentionally conservative: unsupported comparisons are rejected with clear errors rather than coerced implicitly.
@@ -16,11 +38,12 @@
 //! domain specific language used to:
 //! * add roles
 //! * insert ("posit") propositions
+//! * add assertions (certainties of positors about posits)
 //! * perform simple pattern based searches over existing posits
 //!
 //! The language is defined in the grammar file `traqula.pest`. Commands are
-//! parsed into a tree of `Pair<Rule>` values which the [`Engine`] walks to
-//! mutate the in-memory [`Database`].
+//! parsed into the typed syntax tree of the [`ast`] module which the [`Engine`]
+//! executes against the in-memory [`Database`].
 //!
 //! # Result Sets
 //! Internally query evaluation uses a compact tri-state result representation
@@ -44,14 +67,26 @@
 //! NOTE: The search functionality is still evolving; many captured variables
 //! are currently parsed but not yet materialized into final query outputs.
 //! Debug logging is gated behind `cfg(debug_assertions)` where appropriate.
-use crate::construct::{Database, OtherHasher, Thing};
+use crate::construct::{Database, OtherHasher, PositKeeper, Thing};
 use crate::datatype::{Certainty, Decimal, JSON, Time};
 use chrono::NaiveDateTime; // needed for defensive datetime validation in parse_time
 // (regex-based time parsing removed in favor of direct parsing)
 use chrono::NaiveDate;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+pub mod ast;
+mod dump;
+mod explain;
+mod index;
+mod json_path;
+mod planner;
+
+pub use dump::{dump, restore};
+use explain::{PatternProfile, PredicateProfile, Profile};
+use std::time::{Duration, Instant};
+use ast::{AggregateFn, ReturnItem};
 
 // used for internal result sets
 use roaring::RoaringTreemap;
@@ -71,7 +106,7 @@ pub enum ResultSetMode {
 ///
 /// Public fields allow light‑weight pattern matching by the engine. External
 /// crates should treat this as opaque and rely on future higher level APIs.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResultSet {
     pub mode: ResultSetMode,
     pub thing: Option<Thing>,
@@ -389,47 +424,12 @@ pub fn posits_involving_thing(database: &Database, thing: Thing) -> ResultSet {
 }
 
 // value parsers
-fn parse_string(value: &str) -> Option<String> {
-    let mut c = value.chars();
-    c.next();
-    c.next_back();
-    Some(c.collect::<String>().replace("\"\"", "\""))
-}
-fn parse_string_constant(_value: &str) -> Option<String> {
-    None
-}
-fn parse_i64(value: &str) -> Option<i64> {
-    match value.parse::<i64>() {
-        Ok(v) => Some(v),
-        Err(_) => None,
-    }
-}
-fn parse_i64_constant(_value: &str) -> Option<i64> {
-    None
-}
-fn parse_certainty(value: &str) -> Option<Certainty> {
-    let raw = value.trim().trim_end_matches('%');
-    if let Ok(v) = raw.parse::<f64>() {
-        // Accept either fraction [-1,1] or percent [-100,100]
-        let f = if v.abs() > 1.0 { v / 100.0 } else { v };
-        Some(Certainty::new(f))
-    } else { None }
-}
-fn parse_certainty_constant(_value: &str) -> Option<Certainty> {
-    None
-}
 fn parse_decimal(value: &str) -> Option<Decimal> {
     Decimal::from_str(value)
 }
-fn parse_decimal_constant(_value: &str) -> Option<Decimal> {
-    None
-}
 fn parse_json(value: &str) -> Option<JSON> {
     JSON::from_str(value)
 }
-fn parse_json_constant(_value: &str) -> Option<JSON> {
-    None
-}
 /// Parse a time literal or constant used in Traqula.
 pub fn parse_time(value: &str) -> Option<Time> {
     // 1. Fast path for constants (@NOW etc.)
@@ -505,18 +505,1030 @@ fn parse_time_constant(value: &str) -> Option<Time> {
     }
 }
 
+/// An appearing value in each data type its literal converts to; constants are times.
+#[derive(Default, Clone)]
+struct TypedValue {
+    json: Option<JSON>,
+    string: Option<String>,
+    time: Option<Time>,
+    decimal: Option<Decimal>,
+    int: Option<i64>,
+    certainty: Option<Certainty>,
+}
+impl TypedValue {
+    fn of(value: &ast::Value) -> Self {
+        let mut typed = TypedValue::default();
+        match value {
+            ast::Value::Json(text) => typed.json = parse_json(text),
+            ast::Value::String(text) => typed.string = Some(text.clone()),
+            ast::Value::Time(_) | ast::Value::Constant(_) => typed.time = time_of(value),
+            ast::Value::Certainty(percent) => typed.certainty = (-100..=100).contains(percent).then(|| Certainty::new(f64::from(*percent) / 100.0)),
+            ast::Value::Decimal(text) => typed.decimal = parse_decimal(text),
+            ast::Value::Int(int) => typed.int = Some(*int),
+            ast::Value::Bool(_) | ast::Value::Param(_) => (),
+        }
+        typed
+    }
+}
+/// The time of a time literal or constant.
+fn time_of(value: &ast::Value) -> Option<Time> {
+    match value {
+        ast::Value::Time(text) => parse_time(text),
+        ast::Value::Constant(name) => parse_time_constant(name),
+        _ => None,
+    }
+}
+
 use pest::Parser;
-use pest::error::ErrorVariant;
-use pest::iterators::Pair;
 use pest_derive::Parser;
 
 #[derive(Parser)]
 #[grammar = "traqula.pest"] // relative to src
 struct TraqulaParser;
 
+/// Right hand side literal of a value predicate, classified from its source text.
+#[derive(Debug, Clone)]
+enum RhsValueKind { Cert(i8), Int(i64), Decimal(String), String(String), Bool(bool), Const(String) }
+
+/// A single comparison from a `where` clause.
+#[derive(Debug, Clone)]
+enum Predicate {
+    /// Time variable against a time literal or constant: (var, comparator, time).
+    Time(String, String, Time),
+    /// Variable against variable; time or value semantics are resolved per binding: (lhs, comparator, rhs).
+    Variables(String, String, String),
+    /// Value variable against a literal: (lhs, comparator, rhs).
+    Value(String, String, RhsValueKind),
+    /// Time variable against time variable only, as implied by `as of <variable>`: (lhs, comparator, rhs).
+    TimeVariables(String, String, String),
+    /// Identity variable in the set of a named search or subquery: (var, set reference as written).
+    Member(String, String),
+    /// String value variable containing the words of a text: (var, text).
+    Matches(String, String),
+    /// String value variable against a `like` pattern, with its translation to an anchored regex: (var, pattern, regex).
+    Like(String, String, regex::Regex),
+    /// String value variable against a regular expression: (var, regex).
+    Regex(String, regex::Regex),
+    /// Scalar extracted from a JSON value variable against a literal: (var, path, comparator, rhs).
+    JsonPath(String, Vec<ast::PathStep>, String, RhsValueKind),
+}
+
+impl std::fmt::Display for Predicate {
+    /// The comparison in Traqula syntax, as shown by `explain`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Predicate::Time(var, op, time) => write!(f, "{} {} '{}'", var, op, time),
+            Predicate::Variables(lhs, op, rhs) | Predicate::TimeVariables(lhs, op, rhs) => write!(f, "{} {} {}", lhs, op, rhs),
+            Predicate::Value(lhs, op, rhs) => match rhs {
+                RhsValueKind::Cert(percent) => write!(f, "{} {} {}%", lhs, op, percent),
+                RhsValueKind::Int(i) => write!(f, "{} {} {}", lhs, op, i),
+                RhsValueKind::Decimal(text) | RhsValueKind::Const(text) => write!(f, "{} {} {}", lhs, op, text),
+                RhsValueKind::String(text) => write!(f, "{} {} \"{}\"", lhs, op, text.replace('"', "\"\"")),
+                RhsValueKind::Bool(b) => write!(f, "{} {} {}", lhs, op, b),
+            },
+            // Subqueries are keyed by their position in the script rather than their text.
+            Predicate::Member(var, set) if set.starts_with('(') => write!(f, "{} in (search ...)", var),
+            Predicate::Member(var, set) => write!(f, "{} in {}", var, set),
+            Predicate::Matches(var, text) => write!(f, "{} matches \"{}\"", var, text.replace('"', "\"\"")),
+            Predicate::Like(var, pattern, _) => write!(f, "{} like \"{}\"", var, pattern.replace('"', "\"\"")),
+            Predicate::Regex(var, regex) => write!(f, "{} ~ \"{}\"", var, regex.as_str().replace('"', "\"\"")),
+            Predicate::JsonPath(var, path, op, rhs) => write!(f, "{}", Predicate::Value(ast::path_text(var, path), op.clone(), rhs.clone())),
+        }
+    }
+}
+
+/// Boolean expression over predicates, mirroring the `where` clause as written.
+#[derive(Debug, Clone)]
+enum Condition {
+    Atom(Predicate),
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+impl Condition {
+    /// Evaluate the tree left to right with short-circuiting, delegating comparisons to `atom`.
+    fn evaluate(&self, atom: &mut dyn FnMut(&Predicate) -> bool) -> bool {
+        match self {
+            Condition::Atom(p) => atom(p),
+            Condition::And(terms) => {
+                for t in terms { if !t.evaluate(atom) { return false; } }
+                true
+            }
+            Condition::Or(terms) => {
+                for t in terms { if t.evaluate(atom) { return true; } }
+                false
+            }
+            Condition::Not(inner) => !inner.evaluate(atom),
+        }
+    }
+    /// Collect every predicate in the tree (in written order).
+    fn predicates<'a>(&'a self, out: &mut Vec<&'a Predicate>) {
+        match self {
+            Condition::Atom(p) => out.push(p),
+            Condition::And(terms) | Condition::Or(terms) => { for t in terms { t.predicates(out); } }
+            Condition::Not(inner) => inner.predicates(out),
+        }
+    }
+    /// For every predicate in the order of [`Condition::predicates`], whether it is under an odd number of `not`s.
+    fn negations(&self, negated: bool, out: &mut Vec<bool>) {
+        match self {
+            Condition::Atom(_) => out.push(negated),
+            Condition::And(terms) | Condition::Or(terms) => { for t in terms { t.negations(negated, out); } }
+            Condition::Not(inner) => inner.negations(!negated, out),
+        }
+    }
+}
+
+fn parse_certainty_literal(raw: &str) -> Option<i8> {
+    let s = raw.trim();
+    if s.ends_with('%') { if let Ok(v)=s.trim_end_matches('%').parse::<i16>() { if (-100..=100).contains(&v) { return Some(v as i8); } } return None; }
+    None // only percent-suffixed forms are certainty literals now
+}
+
+/// Compare two times with a `where` comparator.
+fn compare_times(lhs: &Time, rhs: &Time, op: &str) -> bool {
+    match op {
+        "<" => lhs < rhs,
+        "<=" => lhs <= rhs,
+        ">" => lhs > rhs,
+        ">=" => lhs >= rhs,
+        "==" | "=" => lhs == rhs,
+        _ => false,
+    }
+}
+
+/// Compare two numbers with a `where` comparator.
+fn cmp_numeric(lhs: f64, rhs: f64, op: &str) -> bool {
+    match op {
+        "<" => lhs < rhs,
+        "<=" => lhs <= rhs,
+        ">" => lhs > rhs,
+        ">=" => lhs >= rhs,
+        "=" | "==" => (lhs - rhs).abs() < 1e-9,
+        _ => false,
+    }
+}
+/// Compare two decimals with a `where` comparator.
+fn cmp_bigdecimal(lhs: &bigdecimal::BigDecimal, rhs: &bigdecimal::BigDecimal, op: &str) -> bool {
+    use std::cmp::Ordering::*;
+    match (lhs.cmp(rhs), op) {
+        (Less, "<") | (Less, "<=") => true,
+        (Equal, "<=" | ">=" | "=" | "==") => true,
+        (Greater, ">" | ">=") => true,
+        (Less, ">=") | (Greater, "<=") => false,
+        (Less, ">") | (Greater, "<") => false,
+        (Equal, _ ) => op == "=" || op == "==",
+        _ => false,
+    }
+}
+/// Compare two strings with a `where` comparator, collated by Unicode code points (as `order by` sorts them).
+fn cmp_strings(lhs: &str, rhs: &str, op: &str) -> bool {
+    match op {
+        "<" => lhs < rhs,
+        "<=" => lhs <= rhs,
+        ">" => lhs > rhs,
+        ">=" => lhs >= rhs,
+        "=" | "==" => lhs == rhs,
+        _ => false,
+    }
+}
+/// Compare the appearing value of a posit, of one of the `allowed` data types, with a literal from a
+/// `where` clause. Comparisons the types do not support are errors rather than false.
+fn compare_value(posit_keeper: &mut PositKeeper, allowed: &HashSet<String>, pid: Thing, lhs: &str, op: &str, rhs: &RhsValueKind) -> Result<bool, crate::error::BarecladError> {
+    let error = |message: String| Err(crate::error::BarecladError::Execution(message));
+    let ordering = matches!(op, "<"|"<="|">"|">=");
+    // Generic ordering mismatch: if RHS numeric and allowed doesn't include a numeric type
+    if ordering && matches!(rhs, RhsValueKind::Int(_) | RhsValueKind::Decimal(_)) && !(allowed.contains("i64") || allowed.contains("Decimal")) {
+        // If this variable is a certainty, produce the more helpful percent sign guidance.
+        if allowed.contains("Certainty") {
+            return error(format!("Ordering comparison requires a percent sign (%) for certainty variable '{}' (e.g. 75%)", lhs));
+        }
+        return error(format!("Ordering comparison not allowed: variable '{}' of non-numeric type used with '{}'", lhs, op));
+    }
+    // No appearing value is a boolean; booleans only come from JSON paths.
+    if let RhsValueKind::Bool(b) = rhs {
+        return if ordering { error(format!("Ordering comparison not allowed for boolean literal: {} {} {}", lhs, op, b)) } else { Ok(false) };
+    }
+    // Helper macros to attempt extraction
+    macro_rules! grab_string { ($t:ty, $label:expr) => { if allowed.contains($label) { if let Some(p) = posit_keeper.posit::<$t>(pid) { Some(format!("{}", p.value())) } else { None } } else { None } }; }
+    // Try in a precedence order; note we only need the one matching RHS kind.
+    let lhs_val = match rhs {
+        RhsValueKind::Int(_) => grab_string!(i64, "i64").or(grab_string!(Decimal, "Decimal")),
+        RhsValueKind::Cert(_) => grab_string!(Certainty, "Certainty"),
+        RhsValueKind::Decimal(_) => grab_string!(Decimal, "Decimal").or(grab_string!(i64, "i64")),
+        RhsValueKind::String(_) | RhsValueKind::Const(_) => grab_string!(String, "String").or(grab_string!(JSON, "JSON")).or(grab_string!(Certainty, "Certainty")).or(grab_string!(i64, "i64")),
+        RhsValueKind::Bool(_) => None,
+    };
+    let Some(lhs_val) = lhs_val else { return Ok(false) };
+    // Detect ordering mismatch: certainty value (by display pattern) vs int/decimal RHS lacking %.
+    if ordering && matches!(rhs, RhsValueKind::Int(_) | RhsValueKind::Decimal(_)) && (lhs_val == "1" || lhs_val == "-1" || lhs_val == "0" || lhs_val.starts_with("0.") || lhs_val.starts_with("-0.")) {
+        return error(format!("Ordering comparison requires a percent sign (%) for certainty variable '{}' (e.g. 75%)", lhs));
+    }
+    // Comparison dispatch
+    match rhs {
+        RhsValueKind::Int(r) => {
+            use bigdecimal::BigDecimal; use std::str::FromStr;
+            if let Ok(l) = lhs_val.parse::<i64>() { Ok(cmp_numeric(l as f64, *r as f64, op)) }
+            else if let Ok(lbd) = BigDecimal::from_str(&lhs_val) { Ok(cmp_bigdecimal(&lbd, &BigDecimal::from(*r), op)) }
+            else if ordering { error(format!("Type mismatch for ordering: value '{}' not comparable to int literal {}", lhs_val, r)) }
+            else { Ok(false) }
+        }
+        RhsValueKind::Cert(rpct) => {
+            // Compare the percentage itself, as the certainty index does, rather than its display.
+            let l_pct_opt = posit_keeper.posit::<Certainty>(pid).map(|p| p.value().percent());
+            if let Some(lpct) = l_pct_opt { Ok(cmp_numeric(lpct as f64, *rpct as f64, op)) }
+            else if ordering { error(format!("Type mismatch for ordering: value '{}' not comparable to certainty literal {}%", lhs_val, rpct)) }
+            else { Ok(false) }
+        }
+        RhsValueKind::Decimal(rraw) => {
+            // compare as BigDecimal via string parse fallback to f64
+            use bigdecimal::BigDecimal; use std::str::FromStr;
+            let lbd = BigDecimal::from_str(&lhs_val).or_else(|_| BigDecimal::from_str("0")).unwrap();
+            let rbd = BigDecimal::from_str(rraw).or_else(|_| BigDecimal::from_str("0")).unwrap();
+            Ok(cmp_bigdecimal(&lbd, &rbd, op))
+        }
+        RhsValueKind::String(rstr) => {
+            if ordering {
+                // Only string values have a collation; other values read as text still cannot be ordered.
+                let string = if allowed.contains("String") { posit_keeper.posit::<String>(pid) } else { None };
+                return match string {
+                    Some(p) => Ok(cmp_strings(p.value(), rstr, op)),
+                    None => error(format!("Ordering comparison not allowed for string literal: {} {} '{}'", lhs, op, rstr)),
+                };
+            }
+            Ok((op == "=" || op == "==") && lhs_val == *rstr)
+        }
+        RhsValueKind::Const(rconst) => {
+            if ordering { return error(format!("Ordering comparison not allowed for constant literal: {} {} '{}'", lhs, op, rconst)); }
+            Ok((op == "=" || op == "==") && lhs_val == *rconst)
+        }
+        RhsValueKind::Bool(_) => Ok(false),
+    }
+}
+
+/// Build the condition tree of a `where` clause; comparisons are classified into predicates. A literal
+/// that cannot be compared, such as a time that does not exist, is an error rather than no filter.
+fn condition_of(condition: &ast::Condition) -> Result<Condition, crate::error::BarecladError> {
+    let negate = |test: Condition, negated: bool| if negated { Condition::Not(Box::new(test)) } else { test };
+    // `x between a and b` desugars into `x >= a and x <= b`, and `x in (a, b)` into `x == a or x == b`,
+    // so both reuse the strict per-type comparison rules. `x in <named search or subquery>` is a set
+    // membership test instead. A leading `not` negates the whole test.
+    let atoms = |variable: &str, comparisons: Vec<(&str, &ast::Operand)>, span: &ast::Span| -> Result<Vec<Condition>, crate::error::BarecladError> {
+        comparisons.into_iter().map(|(op, rhs)| classify_predicate(variable, op, rhs).map(Condition::Atom).ok_or_else(|| uncomparable(rhs, span))).collect()
+    };
+    Ok(match condition {
+        ast::Condition::Or(terms) => Condition::Or(terms.iter().map(condition_of).collect::<Result<_, _>>()?),
+        ast::Condition::And(terms) => Condition::And(terms.iter().map(condition_of).collect::<Result<_, _>>()?),
+        ast::Condition::Not(inner) => Condition::Not(Box::new(condition_of(inner)?)),
+        ast::Condition::Compare { variable, comparator, operand, span } => {
+            Condition::Atom(classify_predicate(variable, comparator.as_str(), operand).ok_or_else(|| uncomparable(operand, span))?)
+        }
+        ast::Condition::Between { variable, negated, low, high, span } => negate(Condition::And(atoms(variable, vec![(">=", low), ("<=", high)], span)?), *negated),
+        ast::Condition::In { variable, negated, operands, span } => negate(Condition::Or(atoms(variable, operands.iter().map(|rhs| ("==", rhs)).collect(), span)?), *negated),
+        ast::Condition::InSet { variable, negated, set, .. } => negate(Condition::Atom(Predicate::Member(variable.clone(), set_key(set))), *negated),
+        ast::Condition::Matches { variable, negated, text, .. } => match &text.value {
+            ast::Value::String(text) => negate(Condition::Atom(Predicate::Matches(variable.clone(), text.clone())), *negated),
+            other => return Err(unbound_text("matches", other)),
+        },
+        // Patterns are checked when parsed or bound, so they compile here.
+        ast::Condition::Like { variable, negated, pattern, .. } => match &pattern.value {
+            ast::Value::String(pattern) => {
+                let regex = regex::Regex::new(&like_regex(pattern)).map_err(|e| crate::error::BarecladError::Invariant(format!("like pattern did not compile: {}", e)))?;
+                negate(Condition::Atom(Predicate::Like(variable.clone(), pattern.clone(), regex)), *negated)
+            }
+            other => return Err(unbound_text("like", other)),
+        },
+        // JSON holds no times, so time literals and constants only compare as constants.
+        ast::Condition::JsonPath { variable, path, comparator, operand, span } => {
+            let literal = ast::Operand::Literal(operand.clone());
+            let rhs = match classify_predicate(variable, comparator.as_str(), &literal).ok_or_else(|| uncomparable(&literal, span))? {
+                Predicate::Value(_, _, rhs) => rhs,
+                Predicate::Time(..) => RhsValueKind::Const(operand.value.to_string()),
+                other => return Err(crate::error::BarecladError::Invariant(format!("JSON path compared with {:?}", other))),
+            };
+            Condition::Atom(Predicate::JsonPath(variable.clone(), path.clone(), comparator.as_str().to_string(), rhs))
+        }
+        ast::Condition::Regex { variable, pattern, .. } => match &pattern.value {
+            ast::Value::String(pattern) => {
+                let regex = regex::Regex::new(pattern).map_err(|e| crate::error::BarecladError::Invariant(format!("regex did not compile: {}", e)))?;
+                Condition::Atom(Predicate::Regex(variable.clone(), regex))
+            }
+            other => return Err(unbound_text("regex", other)),
+        },
+    })
+}
+
+/// A `matches`, `like` or `regex` operand that is not text, which parsing and binding rule out.
+fn unbound_text(predicate: &str, value: &ast::Value) -> crate::error::BarecladError {
+    crate::error::BarecladError::Invariant(format!("{} operand is not a string: {}", predicate, value))
+}
+
+/// Why an operand of a `where` comparison cannot be compared, positioned at the operand when it is a literal.
+fn uncomparable(operand: &ast::Operand, span: &ast::Span) -> crate::error::BarecladError {
+    let (message, span) = match operand {
+        ast::Operand::Literal(literal) => (format!("Literal cannot be compared: {}", literal.value), &literal.span),
+        ast::Operand::Variable(name) => (format!("Variable cannot be compared: {}", name), span),
+    };
+    crate::error::BarecladError::Parse { message, line: Some(span.line), col: Some(span.col) }
+}
+
+/// Translate a `like` pattern into a regular expression matching whole values: `%` matches any run of
+/// characters, `_` any single character, and everything else itself.
+fn like_regex(pattern: &str) -> String {
+    let mut regex = String::from("(?s)^");
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Build a predicate comparing `lhs` against a variable or literal operand.
+fn classify_predicate(lhs: &str, op: &str, rhs: &ast::Operand) -> Option<Predicate> {
+    let (lv, o) = (lhs.to_string(), op.to_string());
+    let literal = match rhs {
+        ast::Operand::Variable(rv) => return Some(Predicate::Variables(lv, o, rv.clone())),
+        ast::Operand::Literal(literal) => &literal.value,
+    };
+    let rhs_kind = match literal {
+        ast::Value::Time(text) => return parse_time(text).map(|t| Predicate::Time(lv, o, t)),
+        ast::Value::Constant(name) => match parse_time_constant(name) {
+            Some(t) => return Some(Predicate::Time(lv, o, t)),
+            None => RhsValueKind::Const(literal.to_string()),
+        },
+        ast::Value::String(s) => RhsValueKind::String(s.clone()),
+        ast::Value::Certainty(pct) => RhsValueKind::Cert(i8::try_from(*pct).ok().filter(|p| (-100..=100).contains(p))?),
+        ast::Value::Decimal(raw) => RhsValueKind::Decimal(raw.clone()),
+        ast::Value::Int(iv) => RhsValueKind::Int(*iv),
+        ast::Value::Bool(b) => RhsValueKind::Bool(*b),
+        ast::Value::Json(_) | ast::Value::Param(_) => RhsValueKind::Const(literal.to_string()),
+    };
+    Some(Predicate::Value(lv, o, rhs_kind))
+}
+
+/// Key of a named search or subquery after `in`, under which its things are kept while a search runs.
+fn set_key(set: &ast::SetReference) -> String {
+    match set {
+        ast::SetReference::Named(name) => name.clone(),
+        ast::SetReference::Subquery(query) => format!("({}..{})", query.span.start, query.span.end),
+    }
+}
+
+/// Outermost set references of a condition; nested subqueries resolve their own when evaluated.
+fn set_references<'a>(condition: &'a ast::Condition, out: &mut Vec<&'a ast::SetReference>) {
+    match condition {
+        ast::Condition::Or(terms) | ast::Condition::And(terms) => terms.iter().for_each(|t| set_references(t, out)),
+        ast::Condition::Not(inner) => set_references(inner, out),
+        ast::Condition::InSet { set, .. } => out.push(set),
+        _ => {}
+    }
+}
+
+/// Names recalled in search patterns without being inserted there; such names may refer to named searches.
+fn pattern_recalls(patterns: &[ast::Pattern]) -> Vec<String> {
+    let mut inserted: Vec<String> = Vec::new();
+    let mut recalled: Vec<String> = Vec::new();
+    for pattern in patterns {
+        let things = pattern.appearances.iter().map(|a| &a.thing);
+        for term in pattern.posit.iter().chain(things).chain([&pattern.value, &pattern.time]).chain(pattern.as_of.iter()) {
+            match term {
+                ast::Term::Insert(name) => inserted.push(name.clone()),
+                ast::Term::Recall(name) => recalled.push(name.clone()),
+                ast::Term::Union(names) => recalled.extend(names.iter().cloned()),
+                _ => {}
+            }
+        }
+        recalled.extend(pattern.certainty.iter().filter_map(|c| c.positor.clone()));
+    }
+    recalled.sort();
+    recalled.dedup();
+    recalled.retain(|n| !inserted.contains(n));
+    recalled
+}
+
+/// Names a search may use to refer to named searches, including those in nested subqueries.
+fn referenced_names(query: &ast::Search) -> Vec<String> {
+    let mut names = pattern_recalls(&query.patterns);
+    let mut sets = Vec::new();
+    if let Some(condition) = &query.condition {
+        set_references(condition, &mut sets);
+    }
+    for set in sets {
+        match set {
+            ast::SetReference::Named(name) => names.push(name.clone()),
+            ast::SetReference::Subquery(subquery) => names.extend(referenced_names(subquery)),
+        }
+    }
+    names
+}
+
+/// A value bound to a `$name` placeholder, see [`Engine::execute_with_params`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    String(String),
+    Int(i64),
+    /// Decimal in plain notation, e.g. `"12.50"`.
+    Decimal(String),
+    /// Certainty in whole percent, -100 to 100.
+    Certainty(i8),
+    Time(Time),
+    /// A JSON object.
+    Json(String),
+}
+
+/// Kind of literal a placeholder stands for, decided by where it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamKind { Value, Time, Certainty, Text, Regex }
+
+/// A placeholder in a script: its name (without `$`), kind and position.
+#[derive(Debug, Clone)]
+struct ParamSlot { name: String, kind: ParamKind, span: ast::Span }
+
+type ParamVisitor<'v> = dyn FnMut(&mut ast::Literal, ParamKind) -> Result<(), crate::error::BarecladError> + 'v;
+
+/// Visit the placeholders of a script in written order, together with the kind of literal each stands for.
+fn visit_params(script: &mut ast::Script, visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    for command in &mut script.commands {
+        match command {
+            ast::Command::AddPosit(add) => {
+                for posit in &mut add.posits {
+                    visit_param(&mut posit.value, ParamKind::Value, visit)?;
+                    visit_param(&mut posit.time, ParamKind::Time, visit)?;
+                }
+            }
+            ast::Command::AddAssertion(add) => {
+                for assertion in &mut add.assertions {
+                    visit_param(&mut assertion.certainty, ParamKind::Certainty, visit)?;
+                    visit_param(&mut assertion.time, ParamKind::Time, visit)?;
+                }
+            }
+            ast::Command::DefineSearch(define) => visit_search_params(&mut define.query, visit)?,
+            ast::Command::Search(search) => visit_search_params(search, visit)?,
+            ast::Command::Explain(explain) => visit_search_params(&mut explain.search, visit)?,
+            ast::Command::CheckConsistency(check) => visit_term_param(check.as_of.as_mut(), ParamKind::Time, visit)?,
+            // The placeholders bound by the rows of a load are not parameters of the script.
+            ast::Command::Load(load) => {
+                for posit in &mut load.posits {
+                    for (literal, kind) in [(&mut posit.value, ParamKind::Value), (&mut posit.time, ParamKind::Time)] {
+                        if !matches!(&literal.value, ast::Value::Param(name) if load.columns.contains(name)) {
+                            visit_param(literal, kind, visit)?;
+                        }
+                    }
+                }
+            }
+            ast::Command::AddRole(_) | ast::Command::Transaction(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn visit_param(literal: &mut ast::Literal, kind: ParamKind, visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    if matches!(literal.value, ast::Value::Param(_)) { visit(literal, kind) } else { Ok(()) }
+}
+
+fn visit_term_param(term: Option<&mut ast::Term>, kind: ParamKind, visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    match term {
+        Some(ast::Term::Literal(literal)) => visit_param(literal, kind, visit),
+        _ => Ok(()),
+    }
+}
+
+/// A placeholder compared with a time variable of the search (bound in a time position or by `as of`) is a time.
+fn visit_search_params(search: &mut ast::Search, visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    let mut time_vars: Vec<String> = Vec::new();
+    for pattern in &mut search.patterns {
+        visit_term_param(Some(&mut pattern.value), ParamKind::Value, visit)?;
+        visit_term_param(Some(&mut pattern.time), ParamKind::Time, visit)?;
+        visit_term_param(pattern.as_of.as_mut(), ParamKind::Time, visit)?;
+        if let Some(filter) = &mut pattern.certainty {
+            visit_param(&mut filter.certainty, ParamKind::Certainty, visit)?;
+        }
+        for term in std::iter::once(&pattern.time).chain(pattern.as_of.iter()) {
+            if let ast::Term::Insert(name) | ast::Term::Recall(name) = term {
+                time_vars.push(name.clone());
+            }
+        }
+    }
+    match &mut search.condition {
+        Some(condition) => visit_condition_params(condition, &time_vars, visit),
+        None => Ok(()),
+    }
+}
+
+fn visit_condition_params(condition: &mut ast::Condition, time_vars: &[String], visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    let kind = |variable: &String| if time_vars.contains(variable) { ParamKind::Time } else { ParamKind::Value };
+    match condition {
+        ast::Condition::Or(terms) | ast::Condition::And(terms) => {
+            for term in terms {
+                visit_condition_params(term, time_vars, visit)?;
+            }
+            Ok(())
+        }
+        ast::Condition::Not(inner) => visit_condition_params(inner, time_vars, visit),
+        ast::Condition::Compare { variable, operand, .. } => visit_operand_param(operand, kind(variable), visit),
+        ast::Condition::Between { variable, low, high, .. } => {
+            let kind = kind(variable);
+            visit_operand_param(low, kind, visit)?;
+            visit_operand_param(high, kind, visit)
+        }
+        ast::Condition::In { variable, operands, .. } => {
+            let kind = kind(variable);
+            for operand in operands {
+                visit_operand_param(operand, kind, visit)?;
+            }
+            Ok(())
+        }
+        ast::Condition::InSet { set: ast::SetReference::Subquery(query), .. } => visit_search_params(query, visit),
+        ast::Condition::InSet { .. } => Ok(()),
+        ast::Condition::Matches { text, .. } | ast::Condition::Like { pattern: text, .. } => visit_param(text, ParamKind::Text, visit),
+        ast::Condition::Regex { pattern, .. } => visit_param(pattern, ParamKind::Regex, visit),
+        ast::Condition::JsonPath { operand, .. } => visit_param(operand, ParamKind::Value, visit),
+    }
+}
+
+fn visit_operand_param(operand: &mut ast::Operand, kind: ParamKind, visit: &mut ParamVisitor) -> Result<(), crate::error::BarecladError> {
+    match operand {
+        ast::Operand::Literal(literal) => visit_param(literal, kind, visit),
+        ast::Operand::Variable(_) => Ok(()),
+    }
+}
+
+/// Value of a time literal, using the constants for the abstract time points.
+fn time_value(time: &Time) -> ast::Value {
+    match time.to_string().as_str() {
+        "BOT" => ast::Value::Constant("BOT".to_string()),
+        "EOT" => ast::Value::Constant("EOT".to_string()),
+        text => ast::Value::Time(text.to_string()),
+    }
+}
+
+/// The literal a bound value stands for in `slot`, or why the value does not fit.
+fn bound_value(slot: &ParamSlot, value: &Param) -> Result<ast::Value, crate::error::BarecladError> {
+    let mistyped = |what: &str| crate::error::BarecladError::Parse {
+        message: format!("Parameter ${} must be {}", slot.name, what),
+        line: Some(slot.span.line),
+        col: Some(slot.span.col),
+    };
+    let certainty = |pct: i8| if (-100..=100).contains(&pct) { Ok(ast::Value::Certainty(pct.into())) } else { Err(mistyped("a certainty between -100% and 100%")) };
+    match (slot.kind, value) {
+        (ParamKind::Time, Param::Time(t)) => Ok(time_value(t)),
+        (ParamKind::Time, Param::String(s)) => parse_time(s).map(|t| time_value(&t)).ok_or_else(|| mistyped("a time")),
+        (ParamKind::Time, _) => Err(mistyped("a time")),
+        (ParamKind::Certainty, Param::Certainty(pct)) => certainty(*pct),
+        (ParamKind::Certainty, Param::String(s)) => parse_certainty_literal(s).ok_or_else(|| mistyped("a certainty")).and_then(certainty),
+        (ParamKind::Certainty, _) => Err(mistyped("a certainty")),
+        (ParamKind::Text, Param::String(s)) | (ParamKind::Value, Param::String(s)) => Ok(ast::Value::String(s.clone())),
+        (ParamKind::Text, _) => Err(mistyped("a string")),
+        (ParamKind::Regex, Param::String(s)) => match regex::Regex::new(s) {
+            Ok(_) => Ok(ast::Value::String(s.clone())),
+            Err(e) => Err(mistyped(&format!("a valid regular expression ({})", ast::regex_error(&e)))),
+        },
+        (ParamKind::Regex, _) => Err(mistyped("a string")),
+        (ParamKind::Value, Param::Int(i)) => Ok(ast::Value::Int(*i)),
+        (ParamKind::Value, Param::Decimal(d)) => {
+            let digits = d.strip_prefix('-').unwrap_or(d);
+            let plain = digits.split_once('.').is_some_and(|(w, f)| !w.is_empty() && !f.is_empty() && (w.to_string() + f).chars().all(|c| c.is_ascii_digit()));
+            if plain { Ok(ast::Value::Decimal(d.clone())) } else { Err(mistyped("a decimal in plain notation (e.g. 12.50)")) }
+        }
+        (ParamKind::Value, Param::Certainty(pct)) => certainty(*pct),
+        (ParamKind::Value, Param::Time(t)) => Ok(time_value(t)),
+        (ParamKind::Value, Param::Json(j)) => {
+            let whole = TraqulaParser::parse(Rule::json, j).is_ok_and(|p| p.as_str().len() == j.len());
+            if whole && JSON::from_str(j).is_some() { Ok(ast::Value::Json(j.clone())) } else { Err(mistyped("a JSON object")) }
+        }
+    }
+}
+
+/// A script parsed and validated once, with the positions and kinds of its `$name` placeholders, so that it can
+/// be executed many times with different parameters (see [`Engine::prepare`]). Execution substitutes the bound
+/// values into the syntax tree without parsing the script again.
+#[derive(Debug, Clone)]
+pub struct PreparedScript {
+    script: ast::Script,
+    slots: Vec<ParamSlot>,
+    result_sets: usize,
+}
+impl PreparedScript {
+    fn new(traqula: &str) -> Result<Self, crate::error::BarecladError> {
+        let mut script = ast::parse(traqula.trim())?;
+        let mut slots = Vec::new();
+        visit_params(&mut script, &mut |literal, kind| {
+            if let ast::Value::Param(name) = &literal.value {
+                slots.push(ParamSlot { name: name.clone(), kind, span: literal.span });
+            }
+            Ok(())
+        })?;
+        slots.sort_by_key(|slot| slot.span.start);
+        let result_sets = script.commands.iter().filter(|c| matches!(c, ast::Command::Search(_) | ast::Command::CheckConsistency(_) | ast::Command::Explain(_))).count();
+        Ok(Self { script, slots, result_sets })
+    }
+    /// The script as prepared (trimmed).
+    pub fn text(&self) -> &str {
+        &self.script.source
+    }
+    /// The syntax tree of the script, with placeholders as [`ast::Value::Param`].
+    pub fn script(&self) -> &ast::Script {
+        &self.script
+    }
+    /// Names of the parameters (without `$`) in order of first appearance.
+    pub fn params(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for slot in &self.slots {
+            if !names.contains(&slot.name.as_str()) {
+                names.push(&slot.name);
+            }
+        }
+        names
+    }
+    /// Number of searches and consistency checks, each producing a result set.
+    pub fn result_sets(&self) -> usize {
+        self.result_sets
+    }
+    /// Whether the script adds roles, posits or assertions, or opens transactions of its own, so that
+    /// running it holds the writer of the database until it ends.
+    pub fn writes(&self) -> bool {
+        self.script.commands.iter().any(|c| matches!(c, ast::Command::AddRole(_) | ast::Command::AddPosit(_) | ast::Command::AddAssertion(_) | ast::Command::Load(_) | ast::Command::Transaction(_)))
+    }
+    /// The syntax tree with every placeholder replaced by its bound value.
+    fn bound_script(&self, params: &HashMap<String, Param>) -> Result<ast::Script, crate::error::BarecladError> {
+        let mut script = self.script.clone();
+        visit_params(&mut script, &mut |literal, kind| {
+            let ast::Value::Param(name) = &literal.value else { return Ok(()) };
+            let slot = ParamSlot { name: name.clone(), kind, span: literal.span };
+            let value = params.get(&slot.name).ok_or_else(|| missing_param(&slot))?;
+            literal.value = bound_value(&slot, value)?;
+            Ok(())
+        })?;
+        Ok(script)
+    }
+}
+
+fn missing_param(slot: &ParamSlot) -> crate::error::BarecladError {
+    crate::error::BarecladError::Parse { message: format!("Missing parameter: ${}", slot.name), line: Some(slot.span.line), col: Some(slot.span.col) }
+}
+
+/// Parse a script run without parameters, so that any placeholder in it is a missing parameter.
+fn parse_unbound(traqula: &str) -> Result<ast::Script, crate::error::BarecladError> {
+    let mut script = ast::parse(traqula.trim())?;
+    visit_params(&mut script, &mut |literal, kind| {
+        let ast::Value::Param(name) = &literal.value else { return Ok(()) };
+        Err(missing_param(&ParamSlot { name: name.clone(), kind, span: literal.span }))
+    })?;
+    Ok(script)
+}
+
+/// The literal of a row of a `load` command standing for a placeholder of a template, or the template's
+/// own literal. Like bound parameters, times may be given as strings.
+fn row_value(load: &ast::Load, row: &[ast::Literal], literal: &ast::Literal, kind: ParamKind) -> Result<ast::Literal, crate::error::BarecladError> {
+    let column = match &literal.value {
+        ast::Value::Param(name) => load.columns.iter().position(|column| column == name),
+        _ => None,
+    };
+    let Some(column) = column else { return Ok(literal.clone()) };
+    let value = &row[column];
+    match (kind, &value.value) {
+        (ParamKind::Time, ast::Value::Time(_) | ast::Value::Constant(_)) | (ParamKind::Value, _) => Ok(value.clone()),
+        (ParamKind::Time, ast::Value::String(text)) if parse_time(text).is_some() => {
+            Ok(ast::Literal { value: time_value(&parse_time(text).unwrap()), span: value.span })
+        }
+        _ => Err(crate::error::BarecladError::Parse {
+            message: format!("Row value for ${} must be a time: {}", load.columns[column], value.value),
+            line: Some(value.span.line),
+            col: Some(value.span.col),
+        }),
+    }
+}
+
+/// Typed view of a projected cell so aggregates do not have to reparse display strings.
+#[derive(Debug, Clone)]
+enum CellValue {
+    Int(i64),
+    Decimal(bigdecimal::BigDecimal),
+    /// Certainty in whole percent.
+    Certainty(i32),
+    Time(Time),
+    Text(String),
+    Thing(Thing),
+    Other,
+    /// A variable left unbound by an `optional` pattern.
+    Null,
+}
+impl CellValue {
+    /// Values of the same family can be compared and combined: numbers (i64/Decimal), certainties, times.
+    fn family(&self) -> Option<&'static str> {
+        match self {
+            CellValue::Int(_) | CellValue::Decimal(_) => Some("numeric"),
+            CellValue::Certainty(_) => Some("Certainty"),
+            CellValue::Time(_) => Some("Time"),
+            CellValue::Text(_) | CellValue::Thing(_) | CellValue::Other | CellValue::Null => None,
+        }
+    }
+    /// Position of this value's kind when `order by` meets values that do not compare with each other.
+    fn rank(&self) -> u8 {
+        match self {
+            CellValue::Int(_) | CellValue::Decimal(_) => 0,
+            CellValue::Certainty(_) => 1,
+            CellValue::Time(_) => 2,
+            CellValue::Text(_) => 3,
+            CellValue::Thing(_) => 4,
+            CellValue::Other => 5,
+            CellValue::Null => 6,
+        }
+    }
+    fn number(&self) -> Option<bigdecimal::BigDecimal> {
+        match self {
+            CellValue::Int(i) => Some(bigdecimal::BigDecimal::from(*i)),
+            CellValue::Decimal(d) => Some(d.clone()),
+            _ => None,
+        }
+    }
+    fn compare(&self, other: &CellValue) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (CellValue::Certainty(a), CellValue::Certainty(b)) => Some(a.cmp(b)),
+            (CellValue::Time(a), CellValue::Time(b)) => a.partial_cmp(b),
+            (CellValue::Text(a), CellValue::Text(b)) => Some(a.cmp(b)),
+            (CellValue::Thing(a), CellValue::Thing(b)) => Some(a.cmp(b)),
+            _ => Some(self.number()?.cmp(&other.number()?)),
+        }
+    }
+}
+
+/// Total order used by `order by`: comparable values use [`CellValue::compare`] (numeric coercion,
+/// cross-resolution time ordering); anything else falls back to kind rank, then display text.
+fn order_values(a: &(String, CellValue), b: &(String, CellValue)) -> std::cmp::Ordering {
+    a.1.compare(&b.1).unwrap_or_else(|| a.1.rank().cmp(&b.1.rank()).then_with(|| a.0.cmp(&b.0)))
+}
+
+/// A projected row awaiting emission: cells, their type names, and its `order by` keys.
+type PendingRow = (Vec<String>, Vec<String>, Vec<(String, CellValue)>);
+/// One aggregation group: key cells with their types, typed key values, and one accumulator per aggregate.
+type Group = (Vec<(String, String)>, Vec<CellValue>, Vec<Accumulator>);
+
+/// Where an `order by` key is read from when sorting projected rows.
+#[derive(Debug, Clone)]
+enum OrderKey {
+    /// A returned column, by index.
+    Column(usize),
+    /// A bound variable or JSON path into one that is not returned (non-aggregate searches only).
+    Variable(String, Vec<ast::PathStep>),
+    /// A group key that is not returned (aggregate searches only), by index into the group keys.
+    GroupKey(usize),
+}
+
+/// Running state of one aggregate within one group.
+struct Accumulator {
+    func: AggregateFn,
+    distinct: bool,
+    variable: String,
+    seen: std::collections::HashSet<(String, String)>,
+    count: i64,
+    family: Option<&'static str>,
+    best: Option<(CellValue, String, String)>,
+    sum: bigdecimal::BigDecimal,
+    sum_is_decimal: bool,
+}
+impl Accumulator {
+    fn new(func: AggregateFn, distinct: bool, variable: &str) -> Self {
+        Accumulator {
+            func,
+            distinct,
+            variable: variable.to_string(),
+            seen: std::collections::HashSet::new(),
+            count: 0,
+            family: None,
+            best: None,
+            sum: bigdecimal::BigDecimal::from(0),
+            sum_is_decimal: false,
+        }
+    }
+    /// Fold one cell into the aggregate. Nulls are skipped; unsupported or mixed types yield an error message.
+    fn add(&mut self, text: &str, data_type: &str, value: &CellValue) -> Result<(), String> {
+        if matches!(value, CellValue::Null) {
+            return Ok(());
+        }
+        if self.distinct && !self.seen.insert((text.to_string(), data_type.to_string())) {
+            return Ok(());
+        }
+        if self.func != AggregateFn::Count {
+            let allowed = match self.func {
+                AggregateFn::Min | AggregateFn::Max => value.family().is_some(),
+                AggregateFn::Sum => value.family() == Some("numeric"),
+                _ => matches!(value.family(), Some("numeric") | Some("Certainty")),
+            };
+            if !allowed {
+                return Err(format!("Aggregate {}() not supported for {} variable '{}'", self.func.name(), data_type, self.variable));
+            }
+            match self.family {
+                Some(f) if Some(f) != value.family() => {
+                    return Err(format!("Aggregate {}() over '{}' mixes {} and {} values", self.func.name(), self.variable, f, data_type));
+                }
+                _ => self.family = value.family(),
+            }
+        }
+        self.count += 1;
+        match self.func {
+            AggregateFn::Count => {}
+            AggregateFn::Min | AggregateFn::Max => {
+                let wanted = if self.func == AggregateFn::Min { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater };
+                let replace = match &self.best {
+                    None => true,
+                    Some((best, _, _)) => value.compare(best) == Some(wanted),
+                };
+                if replace {
+                    self.best = Some((value.clone(), text.to_string(), data_type.to_string()));
+                }
+            }
+            AggregateFn::Sum | AggregateFn::Avg => {
+                match value {
+                    CellValue::Certainty(pct) => self.sum += bigdecimal::BigDecimal::from(*pct),
+                    other => {
+                        self.sum_is_decimal |= matches!(other, CellValue::Decimal(_));
+                        if let Some(n) = other.number() { self.sum += n; }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Final (display, type, typed value) of the aggregate.
+    fn finish(&self) -> (String, String, CellValue) {
+        use bigdecimal::ToPrimitive;
+        match self.func {
+            AggregateFn::Count => (self.count.to_string(), "i64".into(), CellValue::Int(self.count)),
+            AggregateFn::Min | AggregateFn::Max => match &self.best {
+                Some((value, text, data_type)) => (text.clone(), data_type.clone(), value.clone()),
+                None => (String::new(), "null".into(), CellValue::Null),
+            },
+            AggregateFn::Sum => {
+                if self.count == 0 { return (String::new(), "null".into(), CellValue::Null); }
+                if let Some(i) = self.sum.to_i64().filter(|_| !self.sum_is_decimal) {
+                    return (i.to_string(), "i64".into(), CellValue::Int(i));
+                }
+                (self.sum.to_string(), "Decimal".into(), CellValue::Decimal(self.sum.clone()))
+            }
+            AggregateFn::Avg => {
+                if self.count == 0 { return (String::new(), "null".into(), CellValue::Null); }
+                let mean = &self.sum / bigdecimal::BigDecimal::from(self.count);
+                if self.family == Some("Certainty") {
+                    let pct = mean.round(0).to_i64().unwrap_or(0);
+                    (Certainty::new(pct as f64 / 100.0).to_string(), "Certainty".into(), CellValue::Certainty(pct as i32))
+                } else {
+                    // Six fractional digits keep repeating decimals readable; trailing zeros are dropped.
+                    let rounded = mean.round(6);
+                    let text = rounded.to_string();
+                    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.').to_string() } else { text };
+                    (text, "Decimal".into(), CellValue::Decimal(rounded))
+                }
+            }
+        }
+    }
+}
+
+/// Things currently bound to a variable, or `None` when the variable is unknown or bound to nothing.
+fn bound_things(variables: &Variables, name: &str) -> Option<Vec<Thing>> {
+    let result_set = variables.get(name)?;
+    match result_set.mode {
+        ResultSetMode::Empty => None,
+        ResultSetMode::Thing => Some(vec![result_set.thing.unwrap()]),
+        ResultSetMode::Multi => Some(result_set.multi.as_ref().unwrap().iter().collect()),
+    }
+}
+
+/// Binds the things appearing in the roles of a pattern's candidate posits to its identity variables.
+/// All things a variable takes in the pattern are gathered first and then intersected once with any
+/// earlier binding, so a recalled variable acts as a join filter rather than narrowing to the last
+/// candidate seen. Optional patterns never narrow an earlier binding.
+fn bind_pattern_things(database: &Database, variables: &mut Variables, cands: &RoaringTreemap, local_variables: &[String], unions: &[Option<Vec<String>>], roles: &[&str], optional: bool) {
+    let mut pattern_bound: HashMap<String, ResultSet> = HashMap::new();
+    let lookup = database.posit_thing_to_appearance_set_lookup();
+    let guard = lookup.lock().unwrap();
+    for id in cands.iter() {
+        let appset = guard.get(&id).unwrap();
+        for (i, token) in local_variables.iter().enumerate() {
+            if token == "*" {
+                continue;
+            }
+            let Some(bound) = appset.appearances().iter().find(|a| a.role().name() == roles[i]).map(|a| a.thing()) else {
+                continue;
+            };
+            match unions.get(i) {
+                Some(Some(union_names)) => {
+                    for member in union_names {
+                        pattern_bound.entry(member.to_string()).or_insert_with(ResultSet::new).insert(bound);
+                    }
+                }
+                _ => {
+                    let name = token.strip_prefix('+').unwrap_or(token);
+                    pattern_bound.entry(name.to_string()).or_insert_with(ResultSet::new).insert(bound);
+                }
+            }
+        }
+    }
+    drop(guard);
+    for (key, bound_set) in pattern_bound {
+        match variables.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(bound_set);
+            }
+            Entry::Occupied(mut entry) => {
+                let rs = entry.get_mut();
+                if rs.mode == ResultSetMode::Empty {
+                    // Bind empty recall var
+                    *rs = bound_set;
+                } else if !optional {
+                    rs.intersect_with(&bound_set);
+                }
+            }
+        }
+    }
+}
+
+/// Assertions about posits, keyed by the asserted posit, as (positor, appearance time, certainty).
+type AssertionsByPosit = HashMap<Thing, Vec<(Thing, Time, Certainty)>>;
+
+/// A `not [...]` pattern, evaluated once all other patterns of its search have been matched.
+/// Only variables bound by the rest of the search join it with the bindings; the others are existential.
+struct NegatedPattern {
+    /// Position of the pattern in the search as written.
+    pattern: usize,
+    /// Posits matching the pattern on its own.
+    candidates: RoaringTreemap,
+    /// Identity variables (union members share one entry) with the role they appear in.
+    identities: Vec<(Vec<String>, String)>,
+    posit_var: Option<String>,
+    value_var: Option<String>,
+    time_var: Option<String>,
+    /// Certainty filter whose positor variable must be resolved per binding, with its 'as of' time.
+    certainty: Option<(CertaintyFilter, AssertionsByPosit, Option<Time>)>,
+}
+
+/// Per-pattern `by <positor|*> certainty <op> <certainty>` filter.
+struct CertaintyFilter {
+    /// Positor variable, or `None` for `by *` (any positor).
+    positor: Option<String>,
+    op: String,
+    percent: i32,
+}
+impl CertaintyFilter {
+    /// The filter of a pattern, whose certainty was checked when parsed or bound.
+    fn new(condition: &ast::CertaintyCondition) -> Result<Self, crate::error::BarecladError> {
+        match condition.certainty.value {
+            ast::Value::Certainty(percent) => Ok(Self { positor: condition.positor.clone(), op: condition.comparator.as_str().to_string(), percent: percent.into() }),
+            ref other => Err(crate::error::BarecladError::Invariant(format!("Certainty filter without a certainty: {}", other))),
+        }
+    }
+    /// Whether the latest assertion (at or before `as_of`) of any of the positors satisfies the comparison.
+    /// A positor with several assertions at its latest time satisfies it if any of them does;
+    /// posits nobody has asserted never do.
+    fn holds(&self, assertions: &[(Thing, Time, Certainty)], positors: Option<&[Thing]>, as_of: Option<&Time>) -> bool {
+        latest_assertions(assertions, positors, as_of).values().flatten().any(|c| {
+            let percent = (f64::from(*c) * 100.0).round() as i32;
+            match self.op.as_str() {
+                "<" => percent < self.percent,
+                "<=" => percent <= self.percent,
+                ">" => percent > self.percent,
+                ">=" => percent >= self.percent,
+                _ => percent == self.percent,
+            }
+        })
+    }
+}
+
+/// Latest certainties per positor (at or before `as_of`), keeping every assertion tied at the latest time.
+fn latest_assertions<'a>(assertions: &'a [(Thing, Time, Certainty)], positors: Option<&[Thing]>, as_of: Option<&Time>) -> HashMap<Thing, Vec<&'a Certainty>> {
+    let mut latest: HashMap<Thing, (&Time, Vec<&Certainty>)> = HashMap::new();
+    for (positor, time, certainty) in assertions {
+        if positors.is_some_and(|p| !p.contains(positor)) || as_of.is_some_and(|t| time > t) {
+            continue;
+        }
+        match latest.get_mut(positor) {
+            Some((best, certainties)) if time > *best => {
+                *best = time;
+                *certainties = vec![certainty];
+            }
+            Some((best, certainties)) if time == *best => certainties.push(certainty),
+            Some(_) => {}
+            None => {
+                latest.insert(*positor, (time, vec![certainty]));
+            }
+        }
+    }
+    latest.into_iter().map(|(positor, (_, certainties))| (positor, certainties)).collect()
+}
+
 /// Execution engine binding a parsed Traqula script to a concrete database.
 pub struct Engine<'en> {
     database: &'en Database,
+    /// Searches named with `define search`, kept for the lifetime of the engine.
+    named_searches: Mutex<HashMap<String, Arc<ast::Search>>>,
+    /// Whether scripts adding constructs without transaction commands of their own run as one transaction.
+    atomic: bool,
+    /// Variable bindings at `begin`, restored on `rollback` so that no variable recalls forgotten things.
+    begun_with: Mutex<Option<Variables>>,
 }
 /// Control flow returned by a sink after receiving a row.
 pub enum SinkFlow { Continue, Stop }
@@ -558,388 +1570,699 @@ pub struct CollectedResultSet {
 impl<'en> Engine<'en> {
     /// Create a new engine borrowing the provided database.
     pub fn new(database: &'en Database) -> Self {
-        Self { database }
+        Self { database, named_searches: Mutex::new(HashMap::new()), atomic: false, begun_with: Mutex::new(None) }
     }
 
-    /// Execute a single-search script in streaming fashion using the provided RowSink.
-    /// Returns (columns, limited, row_count) or an error. If the script has zero or multiple search commands an error is returned.
-    pub fn execute_stream_single<S: RowSink>(&self, traqula: &str, sink: &mut S) -> Result<(Vec<String>, bool, usize), crate::error::BarecladError> {
-        let mut variables: Variables = Variables::default();
-        let parse_result = TraqulaParser::parse(Rule::traqula, traqula.trim());
-        let pairs = match parse_result {
-            Ok(p) => p,
-            Err(err) => {
-                let mut msg = format!("{}", err);
-                if let ErrorVariant::ParsingError { positives, negatives: _ } = err.variant {
-                    if !positives.is_empty() {
-                        let mut expected: Vec<&'static str> = positives.iter().map(|r| friendly_rule_name(*r)).collect();
-                        expected.sort(); expected.dedup();
-                        msg.push_str(&format!("\nExpected one of: {}", expected.join(", ")));
-                    }
+    /// Make every script adding roles, posits or assertions all-or-nothing, as if it were enclosed
+    /// in `begin;` and `commit;`, unless it has transaction commands of its own.
+    pub fn atomic(mut self) -> Self {
+        self.atomic = true;
+        self
+    }
+
+    /// Run the commands of a script. A transaction the script fails in or leaves open is rolled
+    /// back, and in an [atomic](Engine::atomic) engine the script may run as one transaction.
+    /// Within a transaction opened by the caller, the script simply takes part in it.
+    fn run_script<T>(&self, script: &ast::Script, run: impl FnOnce() -> Result<T, crate::error::BarecladError>) -> Result<T, crate::error::BarecladError> {
+        if self.database.in_transaction() {
+            return run();
+        }
+        let implicit = self.atomic
+            && script.commands.iter().any(|c| matches!(c, ast::Command::AddRole(_) | ast::Command::AddPosit(_) | ast::Command::AddAssertion(_) | ast::Command::Load(_)))
+            && !script.commands.iter().any(|c| matches!(c, ast::Command::Transaction(_)));
+        if implicit {
+            self.database.begin()?;
+        }
+        let result = run();
+        if !self.database.in_transaction() {
+            return result;
+        }
+        match result {
+            Ok(value) if implicit => self.database.commit().map(|_| value),
+            Ok(_) => {
+                self.database.rollback()?;
+                Err(crate::error::BarecladError::Execution("Transaction was neither committed nor rolled back, so it was rolled back".into()))
+            }
+            Err(e) => {
+                self.database.rollback()?;
+                Err(e)
+            }
+        }
+    }
+    /// Run a command in the open transaction, or else in a transaction of its own.
+    fn atomically<T>(&self, run: impl FnOnce() -> Result<T, crate::error::BarecladError>) -> Result<T, crate::error::BarecladError> {
+        self.database.load(|_| run())
+    }
+    /// Handle `begin;`, `commit;` or `rollback;`.
+    fn transaction(&self, command: &ast::Transaction, variables: &mut Variables) -> Result<(), crate::error::BarecladError> {
+        let mut begun_with = self.begun_with.lock().unwrap();
+        match command.action {
+            ast::TransactionAction::Begin => {
+                self.database.begin()?;
+                *begun_with = Some(variables.clone());
+                Ok(())
+            }
+            ast::TransactionAction::Commit => {
+                let committed = self.database.commit();
+                // A failed commit is rolled back.
+                if let (Err(_), Some(bound)) = (&committed, begun_with.take()) {
+                    *variables = bound;
                 }
-                return Err(crate::error::BarecladError::Parse { message: msg, line: None, col: None });
+                committed
             }
-        };
-        let search_count = pairs.clone().filter(|p| p.as_rule()==Rule::search).count();
+            ast::TransactionAction::Rollback => {
+                self.database.rollback()?;
+                if let Some(bound) = begun_with.take() {
+                    *variables = bound;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Execute a single-search script in streaming fashion using the provided RowSink.
+    /// Returns (columns, limited, row_count) or an error. If the script has zero or multiple search commands an error is returned.
+    pub fn execute_stream_single<S: RowSink>(&self, traqula: &str, sink: &mut S) -> Result<(Vec<String>, bool, usize), crate::error::BarecladError> {
+        self.stream_script_single(&parse_unbound(traqula)?, sink)
+    }
+
+    /// Execute a prepared script with bound parameters in streaming fashion like [`Engine::execute_stream_single`].
+    pub fn execute_prepared_stream_single<S: RowSink>(&self, prepared: &PreparedScript, params: &HashMap<String, Param>, sink: &mut S) -> Result<(Vec<String>, bool, usize), crate::error::BarecladError> {
+        self.stream_script_single(&prepared.bound_script(params)?, sink)
+    }
+
+    /// Stream a parsed single-search script like [`Engine::execute_stream_single`].
+    fn stream_script_single<S: RowSink>(&self, script: &ast::Script, sink: &mut S) -> Result<(Vec<String>, bool, usize), crate::error::BarecladError> {
+        let mut variables: Variables = Variables::default();
+        let search_count = script.commands.iter().filter(|c| matches!(c, ast::Command::Search(_) | ast::Command::CheckConsistency(_) | ast::Command::Explain(_))).count();
         if search_count != 1 { return Err(crate::error::BarecladError::Execution(format!("execute_stream_single expects exactly one search, found {}", search_count))); }
         let mut return_columns: Option<Vec<String>> = None; // will be populated when return clause processed
         let mut total_rows = 0usize; let mut limited=false;
-        for command in pairs { match command.as_rule() { Rule::add_role => self.add_role(command), Rule::add_posit => self.add_posit(command, &mut variables), Rule::search => {
-            // limit extraction
-            let mut limit=None; let cloned=command.clone(); for c in cloned.into_inner(){ if c.as_rule()==Rule::limit_clause { for p in c.into_inner(){ if let Ok(v)=p.as_str().parse::<usize>() { limit=Some(v);} } } }
-            let mut err=None; struct CountingSink<'a, T: RowSink> { inner: &'a mut T, limit: Option<usize>, count: usize, limited: bool }
-            impl<'a, T: RowSink> RowSink for CountingSink<'a, T> {
-                fn on_meta(&mut self, columns: &[String]) -> SinkFlow { self.inner.on_meta(columns) }
-                fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow {
-                    if let Some(l)=self.limit { if self.count >= l { self.limited=true; return SinkFlow::Stop; } }
-                    match self.inner.push(row, types) {
-                        SinkFlow::Continue => {
-                            self.count +=1;
-                            if let Some(l)=self.limit { if self.count>=l { self.limited=true; return SinkFlow::Stop; } }
-                            SinkFlow::Continue
-                        }
-                        stop => stop
+        struct CountingSink<'a, T: RowSink> { inner: &'a mut T, limit: Option<usize>, count: usize, limited: bool }
+        impl<'a, T: RowSink> RowSink for CountingSink<'a, T> {
+            fn on_meta(&mut self, columns: &[String]) -> SinkFlow { self.inner.on_meta(columns) }
+            fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow {
+                if let Some(l) = self.limit && self.count >= l { self.limited=true; return SinkFlow::Stop; }
+                match self.inner.push(row, types) {
+                    SinkFlow::Continue => {
+                        self.count +=1;
+                        if let Some(l) = self.limit && self.count>=l { self.limited=true; return SinkFlow::Stop; }
+                        SinkFlow::Continue
                     }
+                    stop => stop
                 }
             }
-            let mut wrapper = CountingSink { inner: sink, limit, count:0, limited:false };
+        }
+        self.run_script(script, || {
+        for command in &script.commands { match command { ast::Command::AddRole(command) => self.add_role(command)?, ast::Command::AddPosit(command) => self.add_posit(command, &mut variables)?, ast::Command::AddAssertion(command) => self.add_assertion(command, &mut variables)?, ast::Command::DefineSearch(command) => self.define_search(command)?, ast::Command::Search(command) => {
+            let mut err=None;
+            let mut wrapper = CountingSink { inner: sink, limit: command.limit, count:0, limited:false };
             self.search(command, &mut variables, &mut wrapper, &mut return_columns, &mut err);
             if let Some(e)=err { return Err(e); }
-            total_rows = wrapper.count; limited = wrapper.limited; }, Rule::EOI => (), _=>() } }
+            total_rows = wrapper.count; limited = wrapper.limited; }, ast::Command::Explain(command) => { total_rows = self.explain(script, command, &mut variables, sink, &mut return_columns)?; }, ast::Command::CheckConsistency(command) => {
+            let mut wrapper = CountingSink { inner: sink, limit: None, count:0, limited:false };
+            self.check_consistency(command, &variables, &mut wrapper, &mut return_columns)?;
+            total_rows = wrapper.count; }, ast::Command::Transaction(command) => self.transaction(command, &mut variables)?, ast::Command::Load(command) => self.load(command, &mut variables)? } }
+        Ok(())
+        })?;
         Ok((return_columns.unwrap_or_default(), limited, total_rows))
     }
     /// Handle an `add role` command.
-    fn add_role(&self, command: Pair<Rule>) {
-        let mut added = 0usize;
-        for role in command.into_inner() {
-            let name = role.as_str().trim();
-            let (_r, existed) = self.database.create_role(name.to_string(), false);
-            if !existed { added +=1; info!(target: "bareclad::traqula", event="add_role", role=name, "role added"); } else { info!(target: "bareclad::traqula", event="add_role", role=name, existed=true, "role already existed"); }
-        }
-        if added>0 { info!(target: "bareclad::traqula", event="add_role_batch", added, "roles batch added"); }
+    fn add_role(&self, command: &ast::AddRole) -> Result<(), crate::error::BarecladError> {
+        self.atomically(|| {
+            let mut added = 0usize;
+            for name in &command.roles {
+                let (_r, existed) = self.database.create_role(name.to_string(), false);
+                if !existed { added +=1; info!(target: "bareclad::traqula", event="add_role", role=name, "role added"); } else { info!(target: "bareclad::traqula", event="add_role", role=name, existed=true, "role already existed"); }
+            }
+            if added>0 { info!(target: "bareclad::traqula", event="add_role_batch", added, "roles batch added"); }
+            Ok(())
+        })
     }
     /// Handle an `add posit` command producing one or more posits.
-    fn add_posit(&self, command: Pair<Rule>, variables: &mut Variables) {
-        for structure in command.into_inner() {
-            let mut variable: Option<String> = None;
-            let mut posits: Vec<Thing> = Vec::new();
-            let mut value_as_json: Option<JSON> = None;
-            let mut value_as_string: Option<String> = None;
-            let mut value_as_time: Option<Time> = None;
-            let mut value_as_decimal: Option<Decimal> = None;
-            let mut value_as_i64: Option<i64> = None;
-            let mut value_as_certainty: Option<Certainty> = None;
-            let mut time: Option<Time> = None;
-            let mut local_variables = Vec::new();
-            let mut roles = Vec::new();
-            match structure.as_rule() {
-                Rule::posit => {
-                    for component in structure.into_inner() {
-                        match component.as_rule() {
-                            Rule::insert => {
-                                variable = Some(
-                                    component.into_inner().next().unwrap().as_str().to_string(),
-                                );
-                                //println!("Insert: {:?}", &variable);
-                            }
-                            Rule::appearance_set => {
-                                for member in component.into_inner() {
-                                    for appearance in member.into_inner() {
-                                        match appearance.as_rule() {
-                                            Rule::insert => {
-                                                let local_variable = appearance
-                                                    .into_inner()
-                                                    .next()
-                                                    .unwrap()
-                                                    .as_str();
-                                                local_variables.push(local_variable);
-                                                let thing = self
-                                                    .database
-                                                    .thing_generator()
-                                                    .lock()
-                                                    .unwrap()
-                                                    .generate();
-                                                match variables.entry(local_variable.to_string()) {
-                                                    Entry::Vacant(entry) => {
-                                                        let mut result_set = ResultSet::new();
-                                                        result_set.insert(thing);
-                                                        entry.insert(result_set);
-                                                    }
-                                                    Entry::Occupied(mut entry) => {
-                                                        entry.get_mut().insert(thing);
-                                                    }
-                                                }
-                                            }
-                                            Rule::recall => {
-                                                local_variables.push(
-                                                    appearance
-                                                        .into_inner()
-                                                        .next()
-                                                        .unwrap()
-                                                        .as_str(),
-                                                );
-                                            }
-                                            Rule::role => {
-                                                roles.push(appearance.as_str());
-                                            }
-                                            _ => println!("Unknown appearance: {:?}", appearance),
-                                        }
-                                    }
-                                }
-                            }
-                            Rule::appearing_value => {
-                                for value_type in component.into_inner() {
-                                    match value_type.as_rule() {
-                                        Rule::constant => {
-                                            //println!("Constant: {}", value_type.as_str());
-                                            value_as_json =
-                                                parse_json_constant(value_type.as_str());
-                                            value_as_string =
-                                                parse_string_constant(value_type.as_str());
-                                            value_as_time =
-                                                parse_time_constant(value_type.as_str());
-                                            value_as_certainty =
-                                                parse_certainty_constant(value_type.as_str());
-                                            value_as_decimal =
-                                                parse_decimal_constant(value_type.as_str());
-                                            value_as_i64 = parse_i64_constant(value_type.as_str());
-                                        }
-                                        Rule::json => {
-                                            //println!("JSON: {}", value_type.as_str());
-                                            value_as_json = parse_json(value_type.as_str());
-                                        }
-                                        Rule::string => {
-                                            //println!("String: {}", value_type.as_str());
-                                            value_as_string = parse_string(value_type.as_str());
-                                        }
-                                        Rule::time => {
-                                            //println!("Time: {}", value_type.as_str());
-                                            value_as_time = parse_time(value_type.as_str());
-                                        }
-                                        Rule::certainty => {
-                                            //println!("Certainty: {}", value_type.as_str());
-                                            value_as_certainty =
-                                                parse_certainty(value_type.as_str());
-                                        }
-                                        Rule::decimal => {
-                                            //println!("Decimal: {}", value_type.as_str());
-                                            value_as_decimal = parse_decimal(value_type.as_str());
-                                        }
-                                        Rule::int => {
-                                            //println!("i64: {}", value_type.as_str());
-                                            value_as_i64 = parse_i64(value_type.as_str());
-                                        }
-                                        _ => println!("Unknown value type: {:?}", value_type),
-                                    }
+    /// Posit identities bound with `+p [...]` are things like any other, so later appearances such as
+    /// `(p, posit)` attach sources, comments or other meta-posits to individual facts.
+    fn add_posit(&self, command: &ast::AddPosit, variables: &mut Variables) -> Result<(), crate::error::BarecladError> {
+        self.atomically(|| {
+            for posit in &command.posits {
+                let mut posits: Vec<Thing> = Vec::new();
+                let TypedValue {
+                    json: value_as_json,
+                    string: value_as_string,
+                    time: value_as_time,
+                    decimal: value_as_decimal,
+                    int: value_as_i64,
+                    certainty: value_as_certainty,
+                } = TypedValue::of(&posit.value.value);
+                let Some(time) = time_of(&posit.time.value) else {
+                    return Err(crate::error::BarecladError::Execution(format!("Invalid time in posit: {}", posit.time.value)));
+                };
+                let kept_roles = posit.appearances.iter().map(|appearance| {
+                    self.database.role_keeper().lock().unwrap().find(&appearance.role).ok_or_else(|| {
+                        crate::error::BarecladError::Execution(format!("Unknown role: {}", appearance.role))
+                    })
+                }).collect::<Result<Vec<_>, _>>()?;
+                let mut local_variables = Vec::new();
+                let mut roles = Vec::new();
+                for appearance in &posit.appearances {
+                    match &appearance.thing {
+                        ast::Term::Insert(local_variable) => {
+                            local_variables.push(local_variable.as_str());
+                            let thing = self
+                                .database
+                                .thing_generator()
+                                .lock()
+                                .unwrap()
+                                .generate();
+                            match variables.entry(local_variable.to_string()) {
+                                Entry::Vacant(entry) => {
+                                    let mut result_set = ResultSet::new();
+                                    result_set.insert(thing);
+                                    entry.insert(result_set);
                                 }
-                            }
-                            Rule::appearance_time => {
-                                for time_type in component.into_inner() {
-                                    match time_type.as_rule() {
-                                        Rule::constant => {
-                                            time = parse_time_constant(time_type.as_str());
-                                            if time.is_none() {
-                                                eprintln!("Failed to parse time constant: {}", time_type.as_str());
-                                            }
-                                        }
-                                        Rule::time => {
-                                            //println!("Time: {}", value_type.as_str());
-                                            time = parse_time(time_type.as_str());
-                                            if time.is_none() {
-                                                eprintln!("Failed to parse time: {}", time_type.as_str());
-                                            }
-                                        }
-                                        _ => println!("Unknown time type: {:?}", time_type),
-                                    }
+                                Entry::Occupied(mut entry) => {
+                                    entry.get_mut().insert(thing);
                                 }
                             }
-                            _ => println!("Unknown component: {:?}", component),
+                        }
+                        ast::Term::Recall(local_variable) => local_variables.push(local_variable.as_str()),
+                        other => {
+                            return Err(crate::error::BarecladError::Execution(format!("Unexpected appearance in posit: {:?}", other)));
                         }
                     }
-                    let mut variable_to_things = HashMap::new();
-                    for local_variable in &local_variables {
-                        variable_to_things.insert(*local_variable, Vec::new());
-                    }
-                    for i in 0..local_variables.len() {
-                        let things = variable_to_things.get_mut(local_variables[i]).unwrap();
-                        let result_set = variables.get(local_variables[i]).unwrap();
-                        match result_set.mode {
-                            ResultSetMode::Empty => (),
-                            ResultSetMode::Thing => {
-                                things.push(result_set.thing.unwrap());
-                            }
-                            ResultSetMode::Multi => {
-                                let multi = result_set.multi.as_ref().unwrap();
-                                for thing in multi {
-                                    things.push(thing);
-                                }
+                    roles.push(appearance.role.as_str());
+                }
+                let mut variable_to_things = HashMap::new();
+                for local_variable in &local_variables {
+                    variable_to_things.insert(*local_variable, Vec::new());
+                }
+                for i in 0..local_variables.len() {
+                    let things = variable_to_things.get_mut(local_variables[i]).unwrap();
+                    let result_set = variables.get(local_variables[i]).ok_or_else(|| {
+                        crate::error::BarecladError::Execution(format!("Unknown variable in posit: {}", local_variables[i]))
+                    })?;
+                    match result_set.mode {
+                        ResultSetMode::Empty => (),
+                        ResultSetMode::Thing => {
+                            things.push(result_set.thing.unwrap());
+                        }
+                        ResultSetMode::Multi => {
+                            let multi = result_set.multi.as_ref().unwrap();
+                            for thing in multi {
+                                things.push(thing);
                             }
                         }
                     }
-                    let mut things_for_roles = Vec::new();
-                    for i in 0..local_variables.len() {
-                        let things_for_role = variable_to_things.get(local_variables[i]).unwrap();
-                        things_for_roles.push(things_for_role.as_slice());
-                    }
-
-                    // Reorder roles and their candidate lists by ascending cardinality to improve iteration locality
-                    let mut order: Vec<usize> = (0..things_for_roles.len()).collect();
-                    order.sort_by_key(|&i| things_for_roles[i].len());
-                    let roles_ord: Vec<&str> = order.iter().map(|&i| roles[i]).collect();
-                    let things_for_roles_ord: Vec<&[Thing]> =
-                        order.iter().map(|&i| things_for_roles[i]).collect();
-
-                    // Stream the Cartesian product (indices) to avoid allocating all combinations.
-                    let mut appearance_sets = Vec::new();
-                    for_each_cartesian_indices(things_for_roles_ord.as_slice(), |idxs| {
-                        let mut appearances = Vec::new();
-                        for i in 0..idxs.len() {
-                            let role = self
-                                .database
-                                .role_keeper()
-                                .lock()
-                                .unwrap()
-                                .get(roles_ord[i]);
-                            let thing = things_for_roles_ord[i][idxs[i]];
-                            let (appearance, _) =
-                                self.database.create_apperance(thing, Arc::clone(&role));
-                            appearances.push(appearance);
-                        }
-                        let (appearance_set, _) = self.database.create_appearance_set(appearances);
-                        appearance_sets.push(appearance_set);
-                    });
+                }
+                let mut things_for_roles = Vec::new();
+                for i in 0..local_variables.len() {
+                    let things_for_role = variable_to_things.get(local_variables[i]).unwrap();
+                    things_for_roles.push(things_for_role.as_slice());
+                }
+
+                // Reorder roles and their candidate lists by ascending cardinality to improve iteration locality
+                let mut order: Vec<usize> = (0..things_for_roles.len()).collect();
+                order.sort_by_key(|&i| things_for_roles[i].len());
+                let roles_ord: Vec<&str> = order.iter().map(|&i| roles[i]).collect();
+                let kept_roles_ord: Vec<_> = order.iter().map(|&i| &kept_roles[i]).collect();
+                let things_for_roles_ord: Vec<&[Thing]> =
+                    order.iter().map(|&i| things_for_roles[i]).collect();
 
-                    // println!("Appearance sets {:?}", appearance_sets);
+                // Stream the Cartesian product (indices) to avoid allocating all combinations.
+                let mut appearance_sets = Vec::new();
+                for_each_cartesian_indices(things_for_roles_ord.as_slice(), |idxs| {
+                    let mut appearances = Vec::new();
+                    for i in 0..idxs.len() {
+                        let thing = things_for_roles_ord[i][idxs[i]];
+                        let (appearance, _) =
+                            self.database.create_apperance(thing, Arc::clone(kept_roles_ord[i]));
+                        appearances.push(appearance);
+                    }
+                    let (appearance_set, _) = self.database.create_appearance_set(appearances);
+                    appearance_sets.push(appearance_set);
+                });
 
-                    for appearance_set in appearance_sets {
-                        // create the posit of the found type
-                        if time.is_none() {
-                            eprintln!("Error: No valid time specified for posit. Skipping.");
-                            continue;
+                for appearance_set in appearance_sets {
+                    // create the posit of the found type
+                    let time_value = time.clone();
+                    if value_as_json.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_json.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed for clean startup output
+                    } else if value_as_string.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_string.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed
+                    } else if value_as_time.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_time.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed
+                    } else if value_as_certainty.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_certainty.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed
+                    } else if value_as_decimal.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_decimal.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed
+                    } else if value_as_i64.is_some() {
+                        let kept_posit = self.database.create_posit(
+                            appearance_set,
+                            value_as_i64.clone().unwrap(),
+                            time_value.clone(),
+                        );
+                        posits.push(kept_posit.posit());
+                        // debug posit creation suppressed
+                    }
+                }
+                if !posits.is_empty() {
+                    // summarize roles_ord (roles after reordering) if available
+                    info!(target: "bareclad::traqula", event="add_posit", created=posits.len(), roles=%roles_ord.join(","), value_kind=%if value_as_json.is_some(){"json"} else if value_as_string.is_some(){"string"} else if value_as_time.is_some(){"time"} else if value_as_certainty.is_some(){"certainty"} else if value_as_decimal.is_some(){"decimal"} else if value_as_i64.is_some(){"i64"} else {"unknown"}, "posits created");
+                }
+                if let Some(variable) = &posit.variable {
+                    match variables.entry(variable.clone()) {
+                        Entry::Vacant(entry) => {
+                            let mut result_set = ResultSet::new();
+                            for posit in posits {
+                                result_set.insert(posit);
+                            }
+                            entry.insert(result_set);
                         }
-                        let time_value = time.clone().unwrap();
-                        
-                        if value_as_json.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_json.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed for clean startup output
-                        } else if value_as_string.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_string.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed
-                        } else if value_as_time.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_time.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed
-                        } else if value_as_certainty.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_certainty.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed
-                        } else if value_as_decimal.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_decimal.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed
-                        } else if value_as_i64.is_some() {
-                            let kept_posit = self.database.create_posit(
-                                appearance_set,
-                                value_as_i64.clone().unwrap(),
-                                time_value.clone(),
-                            );
-                            posits.push(kept_posit.posit());
-                            // debug posit creation suppressed
+                        Entry::Occupied(mut entry) => {
+                            let result_set = entry.get_mut();
+                            for posit in posits {
+                                result_set.insert(posit);
+                            }
                         }
                     }
-                    if !posits.is_empty() {
-                        // summarize roles_ord (roles after reordering) if available
-                        info!(target: "bareclad::traqula", event="add_posit", created=posits.len(), roles=%roles_ord.join(","), value_kind=%if value_as_json.is_some(){"json"} else if value_as_string.is_some(){"string"} else if value_as_time.is_some(){"time"} else if value_as_certainty.is_some(){"certainty"} else if value_as_decimal.is_some(){"decimal"} else if value_as_i64.is_some(){"i64"} else {"unknown"}, "posits created");
+                }
+            }
+            Ok(())
+        })
+    }
+    /// Handle a `load` command, adding its posits once per row. Identities inserted by the templates are
+    /// new for every row and stay local to it, while posit variables (`+p [...]`) collect the posits of
+    /// all rows. The command is loaded as a whole in one transaction, see [`Database::load`].
+    fn load(&self, command: &ast::Load, variables: &mut Variables) -> Result<(), crate::error::BarecladError> {
+        let appearances = || command.posits.iter().flat_map(|posit| &posit.appearances);
+        let inserted: HashSet<&str> = appearances()
+            .filter_map(|appearance| match &appearance.thing {
+                ast::Term::Insert(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        // Script variables recalled by the templates are the same for every row.
+        let recalled: Variables = appearances()
+            .filter_map(|appearance| match &appearance.thing {
+                ast::Term::Recall(name) if !inserted.contains(name.as_str()) => variables.get(name).map(|bound| (name.clone(), bound.clone())),
+                _ => None,
+            })
+            .collect();
+        let mut add = ast::AddPosit { posits: command.posits.clone(), span: command.span };
+        self.database.load(|_| {
+            for row in &command.rows {
+                for (template, posit) in command.posits.iter().zip(add.posits.iter_mut()) {
+                    posit.value = row_value(command, row, &template.value, ParamKind::Value)?;
+                    posit.time = row_value(command, row, &template.time, ParamKind::Time)?;
+                }
+                let mut row_variables = recalled.clone();
+                self.add_posit(&add, &mut row_variables)?;
+                for name in command.posits.iter().filter_map(|posit| posit.variable.as_ref()) {
+                    if let Some(posits) = row_variables.remove(name) {
+                        *variables.entry(name.clone()).or_insert_with(ResultSet::new) |= &posits;
                     }
                 }
-                _ => println!("Unknown structure: {:?}", structure),
             }
-            if variable.is_some() {
-                match variables.entry(variable.unwrap()) {
-                    Entry::Vacant(entry) => {
-                        let mut result_set = ResultSet::new();
-                        for posit in posits {
-                            result_set.insert(posit);
-                        }
-                        entry.insert(result_set);
+            Ok(())
+        })?;
+        info!(target: "bareclad::traqula", event="load", rows=command.rows.len(), "rows loaded");
+        Ok(())
+    }
+    /// Add assertions: posits over the reserved `ascertains` and `posit` roles whose certainty
+    /// states how strongly a positor believes in a posit (`-100%` effectively retracts it).
+    /// The asserted variable must be bound to existing posits, e.g. by `add posit +p [...]` or `search +p [...]`.
+    fn add_assertion(&self, command: &ast::AddAssertion, variables: &mut Variables) -> Result<(), crate::error::BarecladError> {
+        self.atomically(|| {
+            for assertion in &command.assertions {
+                let name = match &assertion.positor {
+                    ast::Term::Insert(name) => {
+                        let thing = self.database.thing_generator().lock().unwrap().generate();
+                        variables.entry(name.clone()).or_insert_with(ResultSet::new).insert(thing);
+                        name
+                    }
+                    ast::Term::Recall(name) => name,
+                    other => return Err(crate::error::BarecladError::Execution(format!("Unknown positor in assertion: {:?}", other))),
+                };
+                let positors = bound_things(variables, name).ok_or_else(|| {
+                    crate::error::BarecladError::Execution(format!("Unknown variable in assertion: {}", name))
+                })?;
+                let name = &assertion.posit;
+                let asserted = bound_things(variables, name).ok_or_else(|| {
+                    crate::error::BarecladError::Execution(format!("Unknown variable in assertion: {}", name))
+                })?;
+                {
+                    let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
+                    let aset_guard = aset_lookup.lock().unwrap();
+                    if let Some(thing) = asserted.iter().find(|t| !aset_guard.contains_key(t)) {
+                        return Err(crate::error::BarecladError::Execution(format!("Variable '{}' is bound to {} which is not a posit", name, thing)));
+                    }
+                }
+                let certainty = TypedValue::of(&assertion.certainty.value).certainty;
+                let time = time_of(&assertion.time.value);
+                let certainty = certainty.ok_or_else(|| crate::error::BarecladError::Execution("Assertion requires a certainty between -100% and 100% (e.g. 75%)".into()))?;
+                let time = time.ok_or_else(|| crate::error::BarecladError::Execution("Assertion requires a valid time".into()))?;
+                let (ascertains, posit_role) = {
+                    let role_keeper = self.database.role_keeper();
+                    let guard = role_keeper.lock().unwrap();
+                    (guard.get("ascertains"), guard.get("posit"))
+                };
+                let mut created: Vec<Thing> = Vec::new();
+                for positor in &positors {
+                    for posit in &asserted {
+                        let (by, _) = self.database.create_apperance(*positor, Arc::clone(&ascertains));
+                        let (of, _) = self.database.create_apperance(*posit, Arc::clone(&posit_role));
+                        let (appearance_set, _) = self.database.create_appearance_set(vec![by, of]);
+                        created.push(self.database.create_posit(appearance_set, certainty.clone(), time.clone()).posit());
+                    }
+                }
+                info!(target: "bareclad::traqula", event="add_assertion", created=created.len(), "assertions created");
+                if let Some(name) = &assertion.variable {
+                    let result_set = variables.entry(name.clone()).or_insert_with(ResultSet::new);
+                    for thing in created {
+                        result_set.insert(thing);
                     }
-                    Entry::Occupied(mut entry) => {
-                        let result_set = entry.get_mut();
-                        for posit in posits {
-                            result_set.insert(posit);
+                }
+            }
+            Ok(())
+        })
+    }
+    /// Collect the assertions made about the given posits, following (posit, posit) appearances
+    /// to the assertion posits whose appearance sets also hold an (positor, ascertains) appearance.
+    fn assertions_about(&self, posits: &RoaringTreemap) -> AssertionsByPosit {
+        let mut found = AssertionsByPosit::new();
+        let appearance_lookup = self.database.thing_to_appearance_lookup();
+        let appearance_guard = appearance_lookup.lock().unwrap();
+        let set_lookup = self.database.appearance_to_appearance_set_lookup();
+        let set_guard = set_lookup.lock().unwrap();
+        let posit_lookup = self.database.appearance_set_to_posit_thing_lookup();
+        let posit_guard = posit_lookup.lock().unwrap();
+        let time_lookup = self.database.posit_time_lookup();
+        let time_guard = time_lookup.lock().unwrap();
+        let posit_keeper = self.database.posit_keeper();
+        let mut pk_guard = posit_keeper.lock().unwrap();
+        for posit in posits.iter() {
+            let Some(appearances) = appearance_guard.get(&posit) else { continue };
+            for appearance in appearances.iter().filter(|a| a.role().name() == "posit") {
+                let Some(appearance_sets) = set_guard.get(appearance) else { continue };
+                for appearance_set in appearance_sets {
+                    let Some(positor) = appearance_set.appearances().iter().find(|a| a.role().name() == "ascertains").map(|a| a.thing()) else { continue };
+                    let Some(assertions) = posit_guard.get(appearance_set) else { continue };
+                    for assertion in assertions.iter() {
+                        if let (Some(time), Some(p)) = (time_guard.get(&assertion), pk_guard.posit::<Certainty>(assertion)) {
+                            found.entry(posit).or_default().push((positor, time.clone(), p.value().clone()));
                         }
                     }
                 }
             }
         }
+        found
+    }
+    /// Display text and data type name of a posit's appearing value.
+    fn posit_value(&self, posit: Thing) -> Option<(String, String)> {
+        let roles = self.database.posit_thing_to_appearance_set_lookup().lock().unwrap().get(&posit)?.roles();
+        let allowed = self.database.role_name_to_data_type_lookup().lock().unwrap().lookup(&roles).clone();
+        let posit_keeper = self.database.posit_keeper();
+        let mut pk_guard = posit_keeper.lock().unwrap();
+        let mut value: Option<(String, &str)> = None;
+        if allowed.contains("String") { value = pk_guard.posit::<String>(posit).map(|p| (p.value().to_string(), "String")); }
+        if value.is_none() && allowed.contains("JSON") { value = pk_guard.posit::<JSON>(posit).map(|p| (p.value().to_string(), "JSON")); }
+        if value.is_none() && allowed.contains("Decimal") { value = pk_guard.posit::<Decimal>(posit).map(|p| (p.value().to_string(), "Decimal")); }
+        if value.is_none() && allowed.contains("i64") { value = pk_guard.posit::<i64>(posit).map(|p| (p.value().to_string(), "i64")); }
+        if value.is_none() && allowed.contains("Certainty") { value = pk_guard.posit::<Certainty>(posit).map(|p| (p.value().to_string(), "Certainty")); }
+        if value.is_none() && allowed.contains("Time") { value = pk_guard.posit::<Time>(posit).map(|p| (p.value().to_string(), "Time")); }
+        value.map(|(text, data_type)| (text, data_type.to_string()))
+    }
+    /// Handle a `define search` command. Definitions last as long as the engine and may use searches
+    /// defined before them, but never refer to themselves, directly or through other named searches.
+    fn define_search(&self, command: &ast::DefineSearch) -> Result<(), crate::error::BarecladError> {
+        let name = &command.name;
+        let mut named = self.named_searches.lock().unwrap();
+        let mut pending = referenced_names(&command.query);
+        let mut visited: Vec<String> = Vec::new();
+        while let Some(next) = pending.pop() {
+            if &next == name {
+                return Err(crate::error::BarecladError::Execution(format!("Named search '{}' cannot refer to itself", name)));
+            }
+            if visited.contains(&next) {
+                continue;
+            }
+            if let Some(query) = named.get(&next) {
+                pending.extend(referenced_names(query));
+            }
+            visited.push(next);
+        }
+        info!(target: "bareclad::traqula", event="define_search", name=%name, "named search defined");
+        named.insert(name.clone(), Arc::new(command.query.clone()));
+        Ok(())
+    }
+    /// Evaluate a named search or subquery into the things of its single returned identity column.
+    fn evaluate_set(&self, query: &ast::Search, label: &str) -> Result<RoaringTreemap, crate::error::BarecladError> {
+        struct SetSink { rows: Vec<(Vec<String>, Vec<String>)>, limit: Option<usize> }
+        impl RowSink for SetSink {
+            fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow {
+                self.rows.push((row, types));
+                if self.limit.is_some_and(|l| self.rows.len() >= l) { SinkFlow::Stop } else { SinkFlow::Continue }
+            }
+        }
+        let mut sink = SetSink { rows: Vec::new(), limit: query.limit };
+        let mut variables = Variables::default();
+        let mut columns: Option<Vec<String>> = None;
+        let mut err = None;
+        self.search(query, &mut variables, &mut sink, &mut columns, &mut err);
+        if let Some(e) = err {
+            return Err(e);
+        }
+        let single_identity = || crate::error::BarecladError::Execution(format!("{} must return a single identity column", label));
+        if columns.is_some_and(|c| c.len() != 1) {
+            return Err(single_identity());
+        }
+        let mut things = RoaringTreemap::new();
+        for (row, types) in sink.rows.iter().filter(|(_, types)| types[0] != "null") {
+            match (types[0].as_str(), row[0].parse::<Thing>()) {
+                ("Thing", Ok(thing)) => { things.insert(thing); }
+                _ => return Err(single_identity()),
+            }
+        }
+        Ok(things)
+    }
+    /// Bind named searches recalled in the patterns of a search to the things they return.
+    /// Returns the names whose sets are empty, since an empty result set otherwise counts as unbound.
+    fn bind_named_searches(&self, patterns: &[ast::Pattern], variables: &mut Variables) -> Result<Vec<String>, crate::error::BarecladError> {
+        let mut empty = Vec::new();
+        for name in pattern_recalls(patterns) {
+            let Some(query) = self.named_searches.lock().unwrap().get(&name).cloned() else { continue };
+            let things = self.evaluate_set(&query, &format!("Named search '{}'", name))?;
+            if things.is_empty() {
+                empty.push(name.clone());
+            }
+            let mut result_set = ResultSet::new();
+            result_set.insert_many(&things);
+            variables.insert(name, result_set);
+        }
+        Ok(empty)
+    }
+    /// Handle a `check consistency` command. Asserted posits in scope are grouped by appearance set and
+    /// appearance time, so posits in a group differ only in value. Within each group the latest certainties
+    /// of every positor are checked with `Certainty::consistent`, and one row is emitted per assertion of a
+    /// positor contradicting itself.
+    fn check_consistency(&self, command: &ast::CheckConsistency, variables: &Variables, sink: &mut dyn RowSink, return_columns: &mut Option<Vec<String>>) -> Result<(), crate::error::BarecladError> {
+        // Each scope entry requires an appearance with the role (None = any) of one of the things (None = any)
+        let mut scope: Vec<(Option<Vec<Thing>>, Option<String>)> = Vec::new();
+        for appearance in command.scope.iter().flatten() {
+            let things = match &appearance.thing {
+                ast::Term::Wildcard => None,
+                ast::Term::Insert(name) => {
+                    return Err(crate::error::BarecladError::Execution(format!("check consistency cannot bind variables: +{}", name)));
+                }
+                ast::Term::Recall(name) => Some(vec![name.as_str()]),
+                ast::Term::Union(names) => Some(names.iter().map(String::as_str).collect()),
+                ast::Term::Literal(_) => None,
+            };
+            let things = match things {
+                None => None,
+                Some(names) => {
+                    let mut things = Vec::new();
+                    for name in names {
+                        things.extend(bound_things(variables, name).ok_or_else(|| {
+                            crate::error::BarecladError::Execution(format!("Unknown variable in check consistency: {}", name))
+                        })?);
+                    }
+                    Some(things)
+                }
+            };
+            scope.push((things, appearance.role.clone()));
+        }
+        let as_of = match &command.as_of {
+            None => None,
+            Some(ast::Term::Literal(literal)) if matches!(literal.value, ast::Value::Time(_) | ast::Value::Constant(_)) => time_of(&literal.value),
+            Some(_) => return Err(crate::error::BarecladError::Execution("check consistency requires a time literal or constant in 'as of'".into())),
+        };
+        let columns: Vec<String> = ["group", "positor", "posit", "value", "time", "certainty"].iter().map(|c| c.to_string()).collect();
+        if return_columns.is_none() {
+            *return_columns = Some(columns.clone());
+            if let SinkFlow::Stop = sink.on_meta(&columns) {
+                return Ok(());
+            }
+        }
+        // Asserted posits are the things appearing in the reserved 'posit' role
+        let posit_role = self.database.role_keeper().lock().unwrap().get("posit");
+        let asserted: RoaringTreemap = self
+            .database
+            .role_to_appearance_lookup()
+            .lock()
+            .unwrap()
+            .get(&posit_role)
+            .map(|appearances| appearances.iter().map(|a| a.thing()).collect())
+            .unwrap_or_default();
+        let assertions = self.assertions_about(&asserted);
+        let mut grouped: HashMap<(usize, Time), Vec<Thing>> = HashMap::new();
+        {
+            let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
+            let aset_guard = aset_lookup.lock().unwrap();
+            let time_lookup = self.database.posit_time_lookup();
+            let time_guard = time_lookup.lock().unwrap();
+            for posit in assertions.keys() {
+                let (Some(appearance_set), Some(time)) = (aset_guard.get(posit), time_guard.get(posit)) else { continue };
+                let in_scope = scope.iter().all(|(things, role)| {
+                    appearance_set.appearances().iter().any(|a| {
+                        role.as_ref().is_none_or(|r| a.role().name() == r) && things.as_ref().is_none_or(|t| t.contains(&a.thing()))
+                    })
+                });
+                if in_scope {
+                    grouped.entry((Arc::as_ptr(appearance_set) as usize, time.clone())).or_default().push(*posit);
+                }
+            }
+        }
+        let mut groups: Vec<(Time, Vec<Thing>)> = grouped
+            .into_iter()
+            .filter(|(_, posits)| posits.len() > 1)
+            .map(|((_, time), mut posits)| {
+                posits.sort();
+                (time, posits)
+            })
+            .collect();
+        groups.sort_by(|a, b| a.1.cmp(&b.1));
+        let mut violating_groups = 0usize;
+        for (time, posits) in groups {
+            let mut by_positor: std::collections::BTreeMap<Thing, Vec<(Thing, Certainty)>> = std::collections::BTreeMap::new();
+            for posit in &posits {
+                for (positor, certainties) in latest_assertions(&assertions[posit], None, as_of.as_ref()) {
+                    by_positor.entry(positor).or_default().extend(certainties.into_iter().map(|c| (*posit, c.clone())));
+                }
+            }
+            by_positor.retain(|_, opinions| {
+                let certainties: Vec<Certainty> = opinions.iter().map(|(_, c)| c.clone()).collect();
+                !Certainty::consistent(&certainties)
+            });
+            if by_positor.is_empty() {
+                continue;
+            }
+            violating_groups += 1;
+            for (positor, opinions) in by_positor {
+                for (posit, certainty) in opinions {
+                    let (value, value_type) = self.posit_value(posit).unwrap_or_default();
+                    let row = vec![violating_groups.to_string(), positor.to_string(), posit.to_string(), value, time.to_string(), certainty.to_string()];
+                    let types = vec!["i64".into(), "Thing".into(), "Thing".into(), value_type, "Time".into(), "Certainty".into()];
+                    if let SinkFlow::Stop = sink.push(row, types) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        info!(target: "bareclad::traqula", event="check_consistency", violating_groups, "consistency checked");
+        Ok(())
     }
-    fn search(&self, command: Pair<Rule>, variables: &mut Variables, sink: &mut dyn RowSink, return_columns: &mut Option<Vec<String>>, exec_error: &mut Option<crate::error::BarecladError>) {
-        // Helper numeric comparison
-        fn cmp_numeric(lhs: f64, rhs: f64, op: &str) -> bool {
-            match op {
-                "<" => lhs < rhs,
-                "<=" => lhs <= rhs,
-                ">" => lhs > rhs,
-                ">=" => lhs >= rhs,
-                "=" | "==" => (lhs - rhs).abs() < 1e-9,
-                _ => false,
+    /// Handle an `explain [analyze] search ...;` command: one row per pattern in evaluation order, per
+    /// comparison of the `where` clause and for the projection. Returns the number of rows emitted.
+    fn explain(&self, script: &ast::Script, command: &ast::Explain, variables: &mut Variables, sink: &mut dyn RowSink, return_columns: &mut Option<Vec<String>>) -> Result<usize, crate::error::BarecladError> {
+        // An analyzed search runs in full, but its rows are only counted up to its limit.
+        struct CountingSink { rows: usize, limit: Option<usize> }
+        impl RowSink for CountingSink {
+            fn push(&mut self, _row: Vec<String>, _types: Vec<String>) -> SinkFlow {
+                if self.limit.is_some_and(|l| self.rows >= l) { return SinkFlow::Stop; }
+                self.rows += 1;
+                SinkFlow::Continue
+            }
+        }
+        let started = Instant::now();
+        let mut profile = Profile::new(command.analyze);
+        let mut counter = CountingSink { rows: 0, limit: command.search.limit };
+        let mut error = None;
+        self.search_profiled(&command.search, variables, &mut counter, &mut None, &mut error, Some(&mut profile));
+        if let Some(e) = error {
+            return Err(e);
+        }
+        profile.rows = counter.rows;
+        profile.elapsed = started.elapsed();
+        let (columns, rows) = explain::result_set(script, &command.search, &profile);
+        if return_columns.is_none() {
+            *return_columns = Some(columns.clone());
+            if let SinkFlow::Stop = sink.on_meta(&columns) {
+                return Ok(0);
             }
         }
-        fn cmp_bigdecimal(lhs: &bigdecimal::BigDecimal, rhs: &bigdecimal::BigDecimal, op: &str) -> bool {
-            use std::cmp::Ordering::*;
-            match (lhs.cmp(rhs), op) {
-                (Less, "<") | (Less, "<=") => true,
-                (Equal, "<=" | "=" | "==") => true,
-                (Greater, ">" | ">=") => true,
-                (Less, ">=") | (Greater, "<=") => false,
-                (Less, ">") | (Greater, "<") => false,
-                (Equal, _ ) => op == "=" || op == "==",
-                _ => false,
+        let mut emitted = 0;
+        for (row, types) in rows {
+            emitted += 1;
+            if let SinkFlow::Stop = sink.push(row, types) {
+                break;
             }
         }
+        info!(target: "bareclad::traqula", event="explain", analyze=command.analyze, rows=profile.rows, "search explained");
+        Ok(emitted)
+    }
+    fn search(&self, query: &ast::Search, variables: &mut Variables, sink: &mut dyn RowSink, return_columns: &mut Option<Vec<String>>, exec_error: &mut Option<crate::error::BarecladError>) {
+        self.search_profiled(query, variables, sink, return_columns, exec_error, None);
+    }
+    /// Run a search, recording its plan and, when analyzing, the statistics of each step in `profile`.
+    /// A profile that is not analyzing stops the search once it is planned.
+    fn search_profiled(&self, query: &ast::Search, variables: &mut Variables, sink: &mut dyn RowSink, return_columns: &mut Option<Vec<String>>, exec_error: &mut Option<crate::error::BarecladError>, mut profile: Option<&mut Profile>) {
         // Track variables referenced in this search command to guide projection
         let mut active_vars: std::collections::HashSet<String> = std::collections::HashSet::new();
         // Track candidate posits per bound time variable name (e.g., t, tw, birth_t)
         let mut time_var_candidates: HashMap<String, RoaringTreemap> = HashMap::new();
     // value_var_candidates removed (late pruning only during filtering stage)
-        // Parsed where conditions on time variables: var -> (comparator, Time)
-        let mut where_time: Vec<(String, String, Time)> = Vec::new();
-        // Parsed where conditions between time variables: (var1, comparator, var2)
+        // Implicit time comparisons between variables introduced by 'as of <variable>': (var1, comparator, var2)
         let mut where_time_var: Vec<(String, String, String)> = Vec::new();
-    // Parsed generic value conditions: (lhs_var, op, Rhs)
-    #[derive(Debug, Clone)]
-    enum RhsValueKind { Cert(i8), Int(i64), Decimal(String), String(String), Const(String) }
-    let mut where_value: Vec<(String, String, RhsValueKind)> = Vec::new();
-    let mut where_value_var: Vec<(String, String, String)> = Vec::new();
-    fn parse_certainty_literal(raw: &str) -> Option<i8> {
-        let s = raw.trim();
-        if s.ends_with('%') { if let Ok(v)=s.trim_end_matches('%').parse::<i16>() { if (-100..=100).contains(&v) { return Some(v as i8); } } return None; }
-        None // only percent-suffixed forms are certainty literals now
-    }
-    // Parsed variable-to-variable value comparisons (both non-time for now): (lhs, op, rhs)
-    // (variable-to-variable value comparisons omitted in current implementation)
+        // Parsed where clause as a boolean expression tree (None when absent); comparisons are
+        // classified per predicate and evaluated per binding.
+        let where_condition: Option<Condition> = match query.condition.as_ref().map(condition_of).transpose() {
+            Ok(condition) => condition,
+            Err(e) => {
+                *exec_error = Some(e);
+                return;
+            }
+        };
         // Track kinds of variables seen in this search (identity, value, time)
         #[derive(Debug, Clone, Copy, PartialEq, Eq)]
         enum VarKind {
@@ -971,1437 +2294,1525 @@ impl<'en> Engine<'en> {
         #[allow(unused_mut)]
         let mut enumeration_started = false; // tracks if bindings vector has been seeded
         let mut variable_kinds: HashMap<String, VarKind> = HashMap::new();
+        // Variables introduced by an 'optional' pattern; bindings the pattern could not extend leave them null.
+        let mut nullable_vars: std::collections::HashSet<String> = std::collections::HashSet::new();
+        // 'not' patterns are collected while matching and applied once the search clause is complete.
+        let mut negated_patterns: Vec<NegatedPattern> = Vec::new();
+        // Things of each named search or subquery used with 'in', keyed by the reference as written.
+        let mut member_sets: HashMap<String, RoaringTreemap> = HashMap::new();
         // Track whether any clause in this search failed (no candidates after constraints)
         let mut any_clause_failed: bool = false;
+        // 'order by' follows the return clause but must be known while projecting: (key expression, descending).
+        let order_by: Vec<(ReturnItem, bool)> = query.order_by.iter().map(|o| (o.item.clone(), o.descending)).collect();
+        // Named searches recalled in patterns are evaluated into sets of things up front; those returning
+        // nothing match nothing rather than counting as unbound.
+        let empty_named = match self.bind_named_searches(&query.patterns, variables) {
+            Ok(empty) => empty,
+            Err(e) => {
+                *exec_error = Some(e);
+                return;
+            }
+        };
+        if let Err(e) = planner::check_negated_variables(query, variables) {
+            *exec_error = Some(e);
+            return;
+        }
+        // Patterns are matched in the order chosen by the planner, most selective first.
+        let plan = planner::plan(self.database, query, where_condition.as_ref(), variables);
+        if let Some(profile) = profile.as_deref_mut() {
+            let mut predicates = Vec::new();
+            if let Some(condition) = &where_condition { condition.predicates(&mut predicates); }
+            profile.predicates = predicates.iter().map(|p| PredicateProfile { predicate: p.to_string(), evaluated: 0, filtered: 0, elapsed: Duration::ZERO }).collect();
+            profile.patterns = query.patterns.iter().map(|_| PatternProfile::default()).collect();
+            profile.plan = Some(plan.clone());
+            if !profile.analyze { return; }
+        }
         // (LIMIT handled externally by a wrapping sink)
-        for clause in command.into_inner() {
-            match clause.as_rule() {
-                Rule::search_clause => {
-                    let mut pattern_index = 0usize; // diagnostic counter
-                    for structure in clause.into_inner() {
-                        let mut variable: Option<String> = None;
-                        let mut _posits: Vec<Thing> = Vec::new();
-                        let mut _value_as_json: Option<JSON> = None;
-                        let mut _value_as_string: Option<String> = None;
-                        let mut _value_as_time: Option<Time> = None;
-                        let mut _value_as_decimal: Option<Decimal> = None;
-                        let mut _value_as_i64: Option<i64> = None;
-                        let mut _value_as_certainty: Option<Certainty> = None;
-                        let mut _value_as_variable: Option<&str> = None;
-                        let mut _value_is_wildcard = false;
-                        let mut _time: Option<Time> = None;
-                        let mut _time_as_variable: Option<&str> = None;
-                        let mut _time_is_wildcard = false;
-                        let mut local_variables = Vec::new();
-                        // Track unions like (w|h, name) => ["w","h"]. Parallel to local_variables by index; None for non-union
-                        let mut local_variable_unions: Vec<Option<Vec<String>>> = Vec::new();
-                        let mut roles = Vec::new();
-                        match structure.as_rule() {
-                            Rule::posit_search => {
-                                let diag_pattern_id = pattern_index; pattern_index +=1; // increment early for logging
-                                // Track optional per-clause 'as of' time
-                                let mut _as_of_time: Option<Time> = None;
-                                let mut _as_of_var: Option<String> = None;
-                                for component in structure.into_inner() {
-                                    match component.as_rule() {
-                                        Rule::insert => {
-                                            variable = Some(
-                                                component
-                                                    .into_inner()
-                                                    .next()
-                                                    .unwrap()
-                                                    .as_str()
-                                                    .to_string(),
-                                            );
-                                            if let Some(v) = &variable {
-                                                active_vars
-                                                    .insert(v.trim_start_matches('+').to_string());
-                                                // Treat posit variables as identity-like so they can be returned
-                                                variable_kinds.insert(
-                                                    v.trim_start_matches('+').to_string(),
-                                                    VarKind::Identity,
-                                                );
-                                            }
-                                            //println!("Insert: {}", &variable.as_ref().unwrap());
-                                        }
-                                        Rule::appearance_set_search => {
-                                            // println!("Appearance set search: {}", component.as_str());
-                                            for member in component.into_inner() {
-                                                for appearance in member.into_inner() {
-                                                    match appearance.as_rule() {
-                                                        Rule::insert => {
-                                                            let local_variable = appearance
-                                                                .into_inner()
-                                                                .next()
-                                                                .unwrap()
-                                                                .as_str();
-                                                            local_variables.push(local_variable);
-                                                            local_variable_unions.push(None);
-                                                            match variables
-                                                                .entry(local_variable.to_string())
-                                                            {
-                                                                Entry::Vacant(entry) => {
-                                                                    let result_set =
-                                                                        ResultSet::new();
-                                                                    entry.insert(result_set);
-                                                                }
-                                                                _ => (),
-                                                            }
-                                                            active_vars
-                                                                .insert(local_variable.to_string());
-                                                            variable_kinds.insert(
-                                                                local_variable.to_string(),
-                                                                VarKind::Identity,
-                                                            );
-                                                        }
-                                                        Rule::wildcard => {
-                                                            local_variables
-                                                                .push(appearance.as_str());
-                                                            local_variable_unions.push(None);
-                                                            //println!("wildcard");
-                                                        }
-                                                        Rule::recall => {
-                                                            local_variables.push(
-                                                                appearance
-                                                                    .into_inner()
-                                                                    .next()
-                                                                    .unwrap()
-                                                                    .as_str(),
-                                                            );
-                                                            local_variable_unions.push(None);
-                                                            if let Some(v) = local_variables.last()
-                                                            {
-                                                                active_vars
-                                                                    .insert((*v).to_string());
-                                                            }
-                                                            if let Some(v) = local_variables.last()
-                                                            {
-                                                                variable_kinds.insert(
-                                                                    (*v).to_string(),
-                                                                    VarKind::Identity,
-                                                                );
-                                                            }
-                                                        }
-                                                        Rule::recall_union => {
-                                                            // Collect all recall names separated by '|'
-                                                            let mut names: Vec<String> = Vec::new();
-                                                            for part in appearance.into_inner() {
-                                                                // parts alternate: recall, '|', recall, '|', ... but pest grouped only recalls due to rule
-                                                                if part.as_rule() == Rule::recall {
-                                                                    names.push(
-                                                                        part.into_inner()
-                                                                            .next()
-                                                                            .unwrap()
-                                                                            .as_str()
-                                                                            .to_string(),
-                                                                    );
-                                                                }
-                                                            }
-                                                            // Store a synthetic token representing the union; we use "w|h" literal for variable token, but keep union list separately
-                                                            let token = names.join("|");
-                                                            local_variables.push(Box::leak(
-                                                                token.into_boxed_str(),
-                                                            ));
-                                                            local_variable_unions
-                                                                .push(Some(names.clone()));
-                                                            for n in names {
-                                                                variable_kinds.insert(
-                                                                    n.clone(),
-                                                                    VarKind::Identity,
-                                                                );
-                                                                active_vars.insert(n);
-                                                            }
-                                                        }
-                                                        Rule::role => {
-                                                            roles.push(appearance.as_str());
-                                                        }
-                                                        _ => println!(
-                                                            "Unknown appearance: {:?}",
-                                                            appearance
-                                                        ),
+        for step in &plan.steps {
+            let diag_pattern_id = step.pattern;
+            let pattern = &query.patterns[step.pattern];
+            info!(target:"bareclad::stream", event="pattern_planned", pattern=diag_pattern_id, estimate=?step.estimate, pushed=step.pushed.len());
+            if let Some(profile) = profile.as_deref_mut() { profile.start_pattern(diag_pattern_id, bindings.len()); }
+            let mut variable: Option<String> = None;
+            let mut posit_var_recalled = false;
+            let typed_value = match &pattern.value {
+                ast::Term::Literal(literal) => TypedValue::of(&literal.value),
+                _ => TypedValue::default(),
+            };
+            let TypedValue {
+                json: _value_as_json,
+                string: _value_as_string,
+                time: _value_as_time,
+                decimal: _value_as_decimal,
+                int: _value_as_i64,
+                certainty: _value_as_certainty,
+            } = typed_value.clone();
+            let mut _value_as_variable: Option<&str> = None;
+            let mut _time: Option<Time> = None;
+            let mut _time_as_variable: Option<&str> = None;
+            let mut local_variables: Vec<String> = Vec::new();
+            // Track unions like (w|h, name) => ["w","h"]. Parallel to local_variables by index; None for non-union
+            let mut local_variable_unions: Vec<Option<Vec<String>>> = Vec::new();
+            let mut roles: Vec<&str> = Vec::new();
+            // Track optional per-clause 'as of' time
+            let mut _as_of_time: Option<Time> = None;
+            let mut _as_of_var: Option<String> = None;
+            let mut certainty_filter = match pattern.certainty.as_ref().map(CertaintyFilter::new).transpose() {
+                Ok(filter) => filter,
+                Err(e) => {
+                    *exec_error = Some(e);
+                    return;
+                }
+            };
+            // An optional pattern never fails the search; it only extends the bindings it can.
+            let optional = pattern.optional;
+            let negated = pattern.negated;
+            let known_before: Vec<String> = variable_kinds.keys().cloned().collect();
+            let variables_before: std::collections::HashSet<String> = variables.keys().cloned().collect();
+            let failed_before = any_clause_failed;
+            match &pattern.posit {
+                Some(ast::Term::Insert(name)) => {
+                    active_vars.insert(name.clone());
+                    // Treat posit variables as identity-like so they can be returned
+                    variable_kinds.insert(name.clone(), VarKind::Identity);
+                    variable = Some(name.clone());
+                }
+                Some(ast::Term::Recall(name)) => {
+                    // Recalled posit variable (e.g. `p [...]`): restrict to posits bound earlier
+                    active_vars.insert(name.clone());
+                    variable_kinds.insert(name.clone(), VarKind::Identity);
+                    posit_var_recalled = true;
+                    variable = Some(name.clone());
+                }
+                _ => {}
+            }
+            for appearance in &pattern.appearances {
+                match &appearance.thing {
+                    ast::Term::Insert(name) => {
+                        variables.entry(name.clone()).or_insert_with(ResultSet::new);
+                        active_vars.insert(name.clone());
+                        variable_kinds.insert(name.clone(), VarKind::Identity);
+                        local_variables.push(name.clone());
+                        local_variable_unions.push(None);
+                    }
+                    ast::Term::Recall(name) => {
+                        active_vars.insert(name.clone());
+                        variable_kinds.insert(name.clone(), VarKind::Identity);
+                        local_variables.push(name.clone());
+                        local_variable_unions.push(None);
+                    }
+                    ast::Term::Union(names) => {
+                        // A synthetic "w|h" token stands for the union; its members are kept separately
+                        for name in names {
+                            variable_kinds.insert(name.clone(), VarKind::Identity);
+                            active_vars.insert(name.clone());
+                        }
+                        local_variables.push(names.join("|"));
+                        local_variable_unions.push(Some(names.clone()));
+                    }
+                    _ => {
+                        local_variables.push("*".to_string());
+                        local_variable_unions.push(None);
+                    }
+                }
+                match &appearance.role {
+                    Some(role) => roles.push(role),
+                    // A wildcard role is recorded like a wildcard thing
+                    None => {
+                        local_variables.push("*".to_string());
+                        local_variable_unions.push(None);
+                    }
+                }
+            }
+            match &pattern.value {
+                ast::Term::Insert(name) | ast::Term::Recall(name) => {
+                    _value_as_variable = Some(name);
+                    active_vars.insert(name.clone());
+                    variable_kinds.insert(name.clone(), VarKind::Value);
+                }
+                _ => {}
+            }
+            match &pattern.time {
+                ast::Term::Insert(name) | ast::Term::Recall(name) => {
+                    _time_as_variable = Some(name);
+                    active_vars.insert(name.clone());
+                    variable_kinds.insert(name.clone(), VarKind::Time);
+                }
+                ast::Term::Literal(literal) => _time = time_of(&literal.value),
+                _ => {}
+            }
+            match &pattern.as_of {
+                Some(ast::Term::Recall(name)) => {
+                    // Variable as_of: record the variable name for per-binding snapshot reduction.
+                    _as_of_var = Some(name.clone());
+                    // Preserve the legacy predicate as a safety net when a time var exists in this pattern.
+                    if let Some(time_var) = _time_as_variable {
+                        where_time_var.push((time_var.to_string(), "<=".to_string(), name.clone()));
+                    }
+                }
+                Some(ast::Term::Literal(literal)) => _as_of_time = time_of(&literal.value),
+                _ => {}
+            }
+            // Minimal evaluation: compute candidates by role intersection and bind variables
+            if !roles.is_empty() {
+                info!(target:"bareclad::stream", event="pattern_start", pattern_index=diag_pattern_id, roles=?roles, local_vars=?local_variables, unions=?local_variable_unions.iter().map(|u| u.as_ref().map(|v| v.join("|"))).collect::<Vec<_>>(), as_of_literal=%_as_of_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default());
+                // Intersect role bitmaps
+                let mut candidates: Option<RoaringTreemap> = None;
+                for role_name in &roles {
+                    let role = self.database.role_keeper().lock().unwrap().find(role_name);
+                    // Nothing appears in an unknown role: an optional pattern then binds nulls and a
+                    // negated one excludes nothing, while any other pattern is in error.
+                    let Some(role) = role else {
+                        if !optional && !negated {
+                            *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown role: {}", role_name)));
+                            return;
+                        }
+                        candidates = Some(RoaringTreemap::new());
+                        break;
+                    };
+                    let bm_clone = {
+                        let lk = self.database.role_to_posit_thing_lookup();
+                        let guard = lk.lock().unwrap();
+                        guard.get(&role.role()).cloned().unwrap_or_default()
+                    };
+                    candidates = Some(match candidates {
+                        None => bm_clone,
+                        Some(mut acc) => {
+                            acc &= &bm_clone;
+                            acc
+                        }
+                    });
+                }
+                if let Some(cands_initial) = candidates {
+                    info!(target:"bareclad::stream", event="candidates_initial", count=cands_initial.len(), roles=?roles, time_literal=%_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default(), as_of_literal=%_as_of_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default());
+                    // Optional time filter for any role when a literal/constant time is provided
+                    let mut cands = cands_initial;
+                    if let Some(ref t) = _time {
+                        cands = index::narrow_to_time(self.database, t, &cands);
+                        let mut filtered = RoaringTreemap::new();
+                        let tk = self.database.posit_time_lookup();
+                        let guard = tk.lock().unwrap();
+                        for id in cands.iter() {
+                            if let Some(pt) = guard.get(&id) {
+                                if pt == t {
+                                    filtered.insert(id);
+                                }
+                            }
+                        }
+                        cands = filtered;
+                        info!(target:"bareclad::stream", event="time_filter", remaining=cands.len());
+                        if cands.is_empty() {
+                            any_clause_failed = true;
+                        }
+                    }
+                    // Optional per-clause 'as of' reduction: keep latest time <= as_of for each appearance set
+                    if let Some(ref as_of) = _as_of_time {
+                        if !cands.is_empty() {
+                            let time_lk = self.database.posit_time_lookup();
+                            let time_guard = time_lk.lock().unwrap();
+                            let aset_lk = self
+                                .database
+                                .posit_thing_to_appearance_set_lookup();
+                            let aset_guard = aset_lk.lock().unwrap();
+                            // Map: appearance set ptr address -> (best_time, Vec<Thing>) to keep all ties
+                            use std::collections::HashMap as StdHashMap;
+                            let mut best: StdHashMap<
+                                usize,
+                                (Time, Vec<Thing>),
+                            > = StdHashMap::new();
+                            for pid in cands.iter() {
+                                if let Some(pt) = time_guard.get(&pid) {
+                                    if pt <= as_of {
+                                        if let Some(aset) = aset_guard.get(&pid)
+                                        {
+                                            let key =
+                                                Arc::as_ptr(aset) as usize;
+                                            match best.get_mut(&key) {
+                                                Some((bt, ids)) => {
+                                                    if pt > bt {
+                                                        *bt = pt.clone();
+                                                        ids.clear();
+                                                        ids.push(pid);
+                                                    } else if pt == bt {
+                                                        ids.push(pid);
                                                     }
                                                 }
-                                            }
-                                        }
-                                        Rule::appearing_value_search => {
-                                            // println!("Appearing value search: {}", component.as_str());
-                                            for value_type in component.into_inner() {
-                                                match value_type.as_rule() {
-                                                    Rule::insert | Rule::recall => {
-                                                        let local_variable = value_type
-                                                            .into_inner()
-                                                            .next()
-                                                            .unwrap()
-                                                            .as_str();
-                                                        _value_as_variable = Some(local_variable);
-                                                        active_vars
-                                                            .insert(local_variable.to_string());
-                                                        variable_kinds.insert(
-                                                            local_variable.to_string(),
-                                                            VarKind::Value,
-                                                        );
-                                                    }
-                                                    Rule::wildcard => {
-                                                        _value_is_wildcard = true;
-                                                        //println!("wildcard");
-                                                    }
-                                                    Rule::constant => {
-                                                        //println!("Constant: {}", value_type.as_str());
-                                                        _value_as_json = parse_json_constant(
-                                                            value_type.as_str(),
-                                                        );
-                                                        _value_as_string = parse_string_constant(
-                                                            value_type.as_str(),
-                                                        );
-                                                        _value_as_time = parse_time_constant(
-                                                            value_type.as_str(),
-                                                        );
-                                                        _value_as_certainty =
-                                                            parse_certainty_constant(
-                                                                value_type.as_str(),
-                                                            );
-                                                        _value_as_decimal = parse_decimal_constant(
-                                                            value_type.as_str(),
-                                                        );
-                                                        _value_as_i64 =
-                                                            parse_i64_constant(value_type.as_str());
-                                                    }
-                                                    Rule::json => {
-                                                        //println!("JSON: {}", value_type.as_str());
-                                                        _value_as_json =
-                                                            parse_json(value_type.as_str());
-                                                    }
-                                                    Rule::string => {
-                                                        //println!("String: {}", value_type.as_str());
-                                                        _value_as_string =
-                                                            parse_string(value_type.as_str());
-                                                    }
-                                                    Rule::time => {
-                                                        //println!("Time: {}", value_type.as_str());
-                                                        _value_as_time =
-                                                            parse_time(value_type.as_str());
-                                                    }
-                                                    Rule::certainty => {
-                                                        //println!("Certainty: {}", value_type.as_str());
-                                                        _value_as_certainty =
-                                                            parse_certainty(value_type.as_str());
-                                                    }
-                                                    Rule::decimal => {
-                                                        //println!("Decimal: {}", value_type.as_str());
-                                                        _value_as_decimal =
-                                                            parse_decimal(value_type.as_str());
-                                                    }
-                                                    Rule::int => {
-                                                        //println!("i64: {}", value_type.as_str());
-                                                        _value_as_i64 =
-                                                            parse_i64(value_type.as_str());
-                                                    }
-                                                    _ => println!(
-                                                        "Unknown value type: {:?}",
-                                                        value_type
-                                                    ),
+                                                None => {
+                                                    best.insert(
+                                                        key,
+                                                        (pt.clone(), vec![pid]),
+                                                    );
                                                 }
                                             }
                                         }
-                                        Rule::appearance_time_search => {
-                                            // println!("Appearing time search: {}", component.as_str());
-                                            for time_type in component.into_inner() {
-                                                match time_type.as_rule() {
-                                                    Rule::insert | Rule::recall => {
-                                                        let local_variable = time_type
-                                                            .into_inner()
-                                                            .next()
-                                                            .unwrap()
-                                                            .as_str();
-                                                        _time_as_variable = Some(local_variable);
-                                                        active_vars
-                                                            .insert(local_variable.to_string());
-                                                        variable_kinds.insert(
-                                                            local_variable.to_string(),
-                                                            VarKind::Time,
-                                                        );
-                                                    }
-                                                    Rule::wildcard => {
-                                                        _time_is_wildcard = true;
-                                                        //println!("wildcard");
-                                                    }
-                                                    Rule::constant => {
-                                                        _time =
-                                                            parse_time_constant(time_type.as_str());
-                                                    }
-                                                    Rule::time => {
-                                                        //println!("Time: {}", value_type.as_str());
-                                                        _time = parse_time(time_type.as_str());
-                                                    }
-                                                    _ => println!(
-                                                        "Unknown time type: {:?}",
-                                                        time_type
-                                                    ),
-                                                }
+                                    }
+                                }
+                            }
+                            let mut reduced = RoaringTreemap::new();
+                            for (_k, (_bt, ids)) in best.into_iter() {
+                                for id in ids {
+                                    reduced.insert(id);
+                                }
+                            }
+                            cands = reduced;
+                            info!(target:"bareclad::stream", event="snapshot_reduced", remaining=cands.len(), as_of=%format!("{}", as_of));
+                            if cands.is_empty() {
+                                any_clause_failed = true;
+                            }
+                        }
+                    }
+                    // Optional certainty filter: keep posits whose latest assertion by the positor(s) satisfies
+                    // the comparison. With a variable 'as of' or a positor bound earlier in this search the
+                    // filter is (re)applied per binding during the merge below.
+                    let mut certainty_positors: Option<Vec<Thing>> = None;
+                    let mut certainty_assertions = AssertionsByPosit::new();
+                    if let Some(filter) = &certainty_filter {
+                        if let Some(name) = &filter.positor {
+                            match bound_things(variables, name) {
+                                Some(things) => certainty_positors = Some(things),
+                                None => {
+                                    *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown positor variable in certainty filter: {}", name)));
+                                    return;
+                                }
+                            }
+                        }
+                        certainty_assertions = self.assertions_about(&cands);
+                        if _as_of_var.is_none() {
+                            cands = cands.iter().filter(|pid| {
+                                let assertions = certainty_assertions.get(pid).map(Vec::as_slice).unwrap_or_default();
+                                filter.holds(assertions, certainty_positors.as_deref(), _as_of_time.as_ref())
+                            }).collect();
+                            info!(target:"bareclad::stream", event="certainty_filtered", remaining=cands.len());
+                            if cands.is_empty() {
+                                any_clause_failed = true;
+                            }
+                        }
+                    }
+                    // Optional value filter for any role when a literal/constant value is provided
+                    if _value_as_string.is_some() || _value_as_i64.is_some() || _value_as_decimal.is_some() || _value_as_certainty.is_some() || _value_as_time.is_some() || _value_as_json.is_some() {
+                        if let Some(narrowed) = index::narrow_to_value(self.database, &typed_value, &cands) {
+                            cands = narrowed;
+                        }
+                        let mut filtered = RoaringTreemap::new();
+                        let pk = self.database.posit_keeper();
+                        let tp = self.database.role_name_to_data_type_lookup();
+                        let mut pk_guard = pk.lock().unwrap();
+                        let tp_guard = tp.lock().unwrap();
+                        let aset_lk = self.database.posit_thing_to_appearance_set_lookup();
+                        let aset_guard = aset_lk.lock().unwrap();
+                        for id in cands.iter() {
+                            if let Some(aset) = aset_guard.get(&id) {
+                                let mut role_names: Vec<String> = aset.appearances().iter().map(|a| a.role().name().to_string()).collect();
+                                role_names.sort();
+                                let allowed = tp_guard.lookup(&role_names);
+                                let mut matches = false;
+                                if let Some(ref val) = _value_as_string {
+                                    if allowed.contains("String") {
+                                        if let Some(p) = pk_guard.posit::<String>(id) {
+                                            if p.value() == val {
+                                                matches = true;
                                             }
                                         }
-                                        Rule::as_of_clause => {
-                                            // Parse: as of <constant|time|recall>
-                                            for part in component.into_inner() {
-                                                match part.as_rule() {
-                                                    Rule::constant => {
-                                                        _as_of_time =
-                                                            parse_time_constant(part.as_str());
-                                                    }
-                                                    Rule::time => {
-                                                        _as_of_time = parse_time(part.as_str());
-                                                    }
-                                                    Rule::recall => {
-                                                        // Variable as_of: record the variable name for per-binding snapshot reduction.
-                                                        _as_of_var = Some(part.as_str().to_string());
-                                                        // Preserve the legacy predicate as a safety net when a time var exists in this pattern.
-                                                        if let Some(time_var) = _time_as_variable {
-                                                            where_time_var.push((time_var.to_string(), "<=".to_string(), part.as_str().to_string()));
-                                                        }
-                                                    }
-                                                    _ => {}
-                                                }
+                                    }
+                                }
+                                if let Some(val) = _value_as_i64 {
+                                    if allowed.contains("i64") {
+                                        if let Some(p) = pk_guard.posit::<i64>(id) {
+                                            if p.value() == &val {
+                                                matches = true;
                                             }
                                         }
-                                        _ => println!("Unknown component: {:?}", component),
                                     }
                                 }
-                                // Minimal evaluation: compute candidates by role intersection and bind variables
-                                if !roles.is_empty() {
-                                    info!(target:"bareclad::stream", event="pattern_start", pattern_index=diag_pattern_id, roles=?roles, local_vars=?local_variables, unions=?local_variable_unions.iter().map(|u| u.as_ref().map(|v| v.join("|"))).collect::<Vec<_>>(), as_of_literal=%_as_of_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default());
-                                    // Intersect role bitmaps
-                                    let mut candidates: Option<RoaringTreemap> = None;
-                                    for role_name in &roles {
-                                        let role_thing = {
-                                            let rk = self.database.role_keeper();
-                                            let rk_guard = rk.lock().unwrap();
-                                            rk_guard.get(role_name).role()
-                                        };
-                                        let bm_clone = {
-                                            let lk = self.database.role_to_posit_thing_lookup();
-                                            let guard = lk.lock().unwrap();
-                                            guard.lookup(&role_thing).clone()
-                                        };
-                                        candidates = Some(match candidates {
-                                            None => bm_clone,
-                                            Some(mut acc) => {
-                                                acc &= &bm_clone;
-                                                acc
+                                if let Some(ref val) = _value_as_decimal {
+                                    if allowed.contains("Decimal") {
+                                        if let Some(p) = pk_guard.posit::<Decimal>(id) {
+                                            if p.value() == val {
+                                                matches = true;
                                             }
-                                        });
+                                        }
                                     }
-                                    if let Some(cands_initial) = candidates {
-                                        info!(target:"bareclad::stream", event="candidates_initial", count=cands_initial.len(), roles=?roles, time_literal=%_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default(), as_of_literal=%_as_of_time.as_ref().map(|t|format!("{}",t)).unwrap_or_default());
-                                        // Optional time filter for any role when a literal/constant time is provided
-                                        let mut cands = cands_initial;
-                                        if let Some(ref t) = _time {
-                                            let mut filtered = RoaringTreemap::new();
-                                            let tk = self.database.posit_time_lookup();
-                                            let guard = tk.lock().unwrap();
-                                            for id in cands.iter() {
-                                                if let Some(pt) = guard.get(&id) {
-                                                    if pt == t {
-                                                        filtered.insert(id);
-                                                    }
-                                                }
+                                }
+                                if let Some(ref val) = _value_as_certainty {
+                                    if allowed.contains("Certainty") {
+                                        if let Some(p) = pk_guard.posit::<Certainty>(id) {
+                                            if p.value() == val {
+                                                matches = true;
                                             }
-                                            cands = filtered;
-                                            info!(target:"bareclad::stream", event="time_filter", remaining=cands.len());
-                                            if cands.is_empty() {
-                                                any_clause_failed = true;
+                                        }
+                                    }
+                                }
+                                if let Some(ref val) = _value_as_time {
+                                    if allowed.contains("Time") {
+                                        if let Some(p) = pk_guard.posit::<Time>(id) {
+                                            if p.value() == val {
+                                                matches = true;
                                             }
                                         }
-                                        // Optional per-clause 'as of' reduction: keep latest time <= as_of for each appearance set
-                                        if let Some(ref as_of) = _as_of_time {
-                                            if !cands.is_empty() {
-                                                let time_lk = self.database.posit_time_lookup();
-                                                let time_guard = time_lk.lock().unwrap();
-                                                let aset_lk = self
-                                                    .database
-                                                    .posit_thing_to_appearance_set_lookup();
-                                                let aset_guard = aset_lk.lock().unwrap();
-                                                // Map: appearance set ptr address -> (best_time, Vec<Thing>) to keep all ties
-                                                use std::collections::HashMap as StdHashMap;
-                                                let mut best: StdHashMap<
-                                                    usize,
-                                                    (Time, Vec<Thing>),
-                                                > = StdHashMap::new();
-                                                for pid in cands.iter() {
-                                                    if let Some(pt) = time_guard.get(&pid) {
-                                                        if pt <= as_of {
-                                                            if let Some(aset) = aset_guard.get(&pid)
-                                                            {
-                                                                let key =
-                                                                    Arc::as_ptr(aset) as usize;
-                                                                match best.get_mut(&key) {
-                                                                    Some((bt, ids)) => {
-                                                                        if pt > bt {
-                                                                            *bt = pt.clone();
-                                                                            ids.clear();
-                                                                            ids.push(pid);
-                                                                        } else if pt == bt {
-                                                                            ids.push(pid);
-                                                                        }
-                                                                    }
-                                                                    None => {
-                                                                        best.insert(
-                                                                            key,
-                                                                            (pt.clone(), vec![pid]),
-                                                                        );
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                let mut reduced = RoaringTreemap::new();
-                                                for (_k, (_bt, ids)) in best.into_iter() {
-                                                    for id in ids {
-                                                        reduced.insert(id);
-                                                    }
-                                                }
-                                                cands = reduced;
-                                                info!(target:"bareclad::stream", event="snapshot_reduced", remaining=cands.len(), as_of=%format!("{}", as_of));
-                                                if cands.is_empty() {
-                                                    any_clause_failed = true;
-                                                }
+                                    }
+                                }
+                                if let Some(ref val) = _value_as_json {
+                                    if allowed.contains("JSON") {
+                                        if let Some(p) = pk_guard.posit::<JSON>(id) {
+                                            if p.value() == val {
+                                                matches = true;
                                             }
                                         }
-                                        // Optional value filter for any role when a literal/constant value is provided
-                                        if _value_as_string.is_some() || _value_as_i64.is_some() || _value_as_decimal.is_some() || _value_as_certainty.is_some() || _value_as_time.is_some() || _value_as_json.is_some() {
-                                            let mut filtered = RoaringTreemap::new();
-                                            let pk = self.database.posit_keeper();
-                                            let tp = self.database.role_name_to_data_type_lookup();
-                                            let mut pk_guard = pk.lock().unwrap();
-                                            let tp_guard = tp.lock().unwrap();
-                                            let aset_lk = self.database.posit_thing_to_appearance_set_lookup();
-                                            let aset_guard = aset_lk.lock().unwrap();
-                                            for id in cands.iter() {
-                                                if let Some(aset) = aset_guard.get(&id) {
-                                                    let mut role_names: Vec<String> = aset.appearances().iter().map(|a| a.role().name().to_string()).collect();
-                                                    role_names.sort();
-                                                    let allowed = tp_guard.lookup(&role_names);
-                                                    let mut matches = false;
-                                                    if let Some(ref val) = _value_as_string {
-                                                        if allowed.contains("String") {
-                                                            if let Some(p) = pk_guard.posit::<String>(id) {
-                                                                if p.value() == val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    if let Some(val) = _value_as_i64 {
-                                                        if allowed.contains("i64") {
-                                                            if let Some(p) = pk_guard.posit::<i64>(id) {
-                                                                if p.value() == &val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    if let Some(ref val) = _value_as_decimal {
-                                                        if allowed.contains("Decimal") {
-                                                            if let Some(p) = pk_guard.posit::<Decimal>(id) {
-                                                                if p.value() == val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    if let Some(ref val) = _value_as_certainty {
-                                                        if allowed.contains("Certainty") {
-                                                            if let Some(p) = pk_guard.posit::<Certainty>(id) {
-                                                                if p.value() == val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    if let Some(ref val) = _value_as_time {
-                                                        if allowed.contains("Time") {
-                                                            if let Some(p) = pk_guard.posit::<Time>(id) {
-                                                                if p.value() == val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
+                                    }
+                                }
+                                if matches {
+                                    filtered.insert(id);
+                                }
+                            }
+                        }
+                        cands = filtered;
+                        info!(target:"bareclad::stream", event="value_filter", remaining=cands.len());
+                        if cands.is_empty() {
+                            any_clause_failed = true;
+                        }
+                    }
+                    // Comparisons with literals pushed down from the 'where' clause by the planner
+                    if !step.pushed.is_empty() && !cands.is_empty() {
+                        let pk = self.database.posit_keeper();
+                        let tp = self.database.role_name_to_data_type_lookup();
+                        let aset_lk = self.database.posit_thing_to_appearance_set_lookup();
+                        let time_lk = self.database.posit_time_lookup();
+                        let mut pk_guard = pk.lock().unwrap();
+                        let tp_guard = tp.lock().unwrap();
+                        let aset_guard = aset_lk.lock().unwrap();
+                        let time_guard = time_lk.lock().unwrap();
+                        let before = cands.len();
+                        for predicate in &step.pushed {
+                            if let Some(narrowed) = index::narrow(self.database, predicate, &cands) {
+                                cands = narrowed;
+                            }
+                        }
+                        cands = cands.iter().filter(|pid| step.pushed.iter().all(|predicate| match predicate {
+                            Predicate::Time(_, op, time) => time_guard.get(pid).is_some_and(|t| compare_times(t, time, op)),
+                            // Errors are left for the 'where' clause to report, so candidates that raise one are kept.
+                            Predicate::Value(lhs, op, rhs) => aset_guard.get(pid).is_some_and(|aset| {
+                                let allowed = tp_guard.lookup(&aset.roles()).clone();
+                                compare_value(&mut pk_guard, &allowed, *pid, lhs, op, rhs).unwrap_or(true)
+                            }),
+                            Predicate::Like(_, _, regex) | Predicate::Regex(_, regex) => pk_guard.posit::<String>(*pid).is_some_and(|p| regex.is_match(p.value())),
+                            _ => true,
+                        })).collect();
+                        info!(target:"bareclad::stream", event="pushdown_filter", predicates=step.pushed.len(), remaining=cands.len());
+                        if let Some(profile) = profile.as_deref_mut() { profile.patterns[diag_pattern_id].filtered = before - cands.len(); }
+                        if cands.is_empty() {
+                            any_clause_failed = true;
+                        }
+                    }
+                    // (as-of moved to after local identity constraints)
+                    // Apply local identity variable constraints to filter candidates (e.g., (w, name) restricts to bound wife)
+                    if !local_variables.is_empty() && !cands.is_empty() {
+                        let lk = self
+                            .database
+                            .posit_thing_to_appearance_set_lookup();
+                        let aset_guard = lk.lock().unwrap();
+                        let mut filtered = RoaringTreemap::new();
+                        'cand: for id in cands.iter() {
+                            let appset = match aset_guard.get(&id) {
+                                Some(aset) => aset,
+                                None => continue,
+                            };
+                            for (i, token) in local_variables.iter().enumerate()
+                            {
+                                if *token == "*" {
+                                    continue;
+                                }
+                                let role_name = roles[i];
+                                let bound_opt = appset
+                                    .appearances()
+                                    .iter()
+                                    .find(|a| a.role().name() == role_name)
+                                    .map(|a| a.thing());
+                                if let Some(bound_id) = bound_opt {
+                                    // Determine if this bound_id satisfies existing variable bindings (support unions)
+                                    let union_names = local_variable_unions
+                                        .get(i)
+                                        .and_then(|u| u.as_ref());
+                                    let satisfies = if let Some(names) =
+                                        union_names
+                                    {
+                                        // If any union member is already bound (non-empty) and contains bound_id, accept;
+                                        // if none are effectively bound (all empty or absent), don't restrict.
+                                        let mut any_bound = false;
+                                        let mut any_match = false;
+                                        for name in names.iter() {
+                                            if let Some(rs) = variables.get(name) {
+                                                match rs.mode {
+                                                    ResultSetMode::Thing => {
+                                                        any_bound = true;
+                                                        any_match |= rs.thing.unwrap() == bound_id;
                                                     }
-                                                    if let Some(ref val) = _value_as_json {
-                                                        if allowed.contains("JSON") {
-                                                            if let Some(p) = pk_guard.posit::<JSON>(id) {
-                                                                if p.value() == val {
-                                                                    matches = true;
-                                                                }
-                                                            }
-                                                        }
+                                                    ResultSetMode::Multi => {
+                                                        any_bound = true;
+                                                        any_match |= rs.multi.as_ref().unwrap().contains(bound_id);
                                                     }
-                                                    if matches {
-                                                        filtered.insert(id);
+                                                    ResultSetMode::Empty => {
+                                                        // Treat empty as unbound, unless it is a named search returning nothing
+                                                        any_bound |= empty_named.contains(name);
                                                     }
                                                 }
                                             }
-                                            cands = filtered;
-                                            info!(target:"bareclad::stream", event="value_filter", remaining=cands.len());
-                                            if cands.is_empty() {
-                                                any_clause_failed = true;
+                                        }
+                                        if any_bound { any_match } else { true }
+                                    } else {
+                                        let key = token
+                                            .strip_prefix('+')
+                                            .unwrap_or(token);
+                                        if let Some(rs) = variables.get(key) {
+                                            match rs.mode {
+                                                ResultSetMode::Thing => {
+                                                    rs.thing.unwrap() == bound_id
+                                                }
+                                                ResultSetMode::Multi => rs.multi.as_ref().unwrap().contains(bound_id),
+                                                ResultSetMode::Empty => !empty_named.iter().any(|n| n == key), // treat empty as unbound
                                             }
+                                        } else {
+                                            // Unbound variable – don't restrict
+                                            true
                                         }
-                                        // (as-of moved to after local identity constraints)
-                                        // Apply local identity variable constraints to filter candidates (e.g., (w, name) restricts to bound wife)
-                                        if !local_variables.is_empty() && !cands.is_empty() {
-                                            let lk = self
-                                                .database
-                                                .posit_thing_to_appearance_set_lookup();
-                                            let aset_guard = lk.lock().unwrap();
-                                            let mut filtered = RoaringTreemap::new();
-                                            'cand: for id in cands.iter() {
-                                                let appset = match aset_guard.get(&id) {
-                                                    Some(aset) => aset,
-                                                    None => continue,
-                                                };
-                                                for (i, token) in local_variables.iter().enumerate()
+                                    };
+                                    if !satisfies {
+                                        continue 'cand;
+                                    }
+                                } else {
+                                    continue 'cand;
+                                }
+                            }
+                            filtered.insert(id);
+                        }
+                        cands = filtered;
+                        if cands.is_empty() {
+                            any_clause_failed = true;
+                        }
+                    }
+                    if posit_var_recalled {
+                        if let Some(rs) = variable.as_ref().and_then(|name| variables.get(name)) {
+                            match rs.mode {
+                                ResultSetMode::Thing => {
+                                    let keep = rs.thing.unwrap();
+                                    let kept = cands.contains(keep);
+                                    cands.clear();
+                                    if kept { cands.insert(keep); }
+                                }
+                                ResultSetMode::Multi => cands &= rs.multi.as_ref().unwrap(),
+                                ResultSetMode::Empty if empty_named.contains(variable.as_ref().unwrap()) => cands.clear(),
+                                ResultSetMode::Empty => {}
+                            }
+                        }
+                        if cands.is_empty() {
+                            any_clause_failed = true;
+                        }
+                    }
+                    info!(target:"bareclad::stream", event="pattern_end", pattern_index=diag_pattern_id, final_candidates=cands.len(), any_clause_failed=any_clause_failed, enumeration_started=enumeration_started);
+                    if let Some(profile) = profile.as_deref_mut() { profile.patterns[diag_pattern_id].candidates = Some(cands.len()); }
+                    // A negated pattern binds nothing: keep its matches for the exclusion step and forget its own variables
+                    if negated {
+                        if _as_of_var.is_some() {
+                            *exec_error = Some(crate::error::BarecladError::Execution("'as of <variable>' is not supported in a 'not' pattern".into()));
+                            return;
+                        }
+                        let mut identities: Vec<(Vec<String>, String)> = Vec::new();
+                        for (i, token) in local_variables.iter().enumerate() {
+                            if *token == "*" {
+                                continue;
+                            }
+                            let names = match local_variable_unions.get(i) {
+                                Some(Some(names)) => names.clone(),
+                                _ => vec![token.trim_start_matches('+').to_string()],
+                            };
+                            identities.push((names, roles[i].to_string()));
+                        }
+                        let name = |v: &str| v.trim_start_matches('+').to_string();
+                        let negation = NegatedPattern {
+                            pattern: diag_pattern_id,
+                            identities,
+                            posit_var: variable.as_deref().map(name),
+                            value_var: _value_as_variable.map(name),
+                            time_var: _time_as_variable.map(name),
+                            certainty: certainty_filter
+                                .take()
+                                .filter(|f| f.positor.is_some())
+                                .map(|f| (f, std::mem::take(&mut certainty_assertions), _as_of_time.clone())),
+                            candidates: cands,
+                        };
+                        negated_patterns.push(negation);
+                        any_clause_failed = failed_before;
+                        let introduced: Vec<String> = variable_kinds.keys().filter(|k| !known_before.contains(k)).cloned().collect();
+                        for name in introduced {
+                            variable_kinds.remove(&name);
+                            active_vars.remove(&name);
+                            if !variables_before.contains(&name) {
+                                variables.remove(&name);
+                            }
+                        }
+                        continue;
+                    }
+                    // Remember candidate posits for projection when returning values/times
+                    // (legacy single-role candidate capture removed)
+                    // If the appearing value used a variable (e.g., +n or n), capture its candidates
+                    if let Some(vname) = _value_as_variable { active_vars.insert(vname.to_string()); }
+                    // If the time slot used a variable, capture its candidate posits under that variable name
+                    if let Some(varname) = _time_as_variable {
+                        time_var_candidates
+                            .insert(varname.to_string(), cands.clone());
+                        active_vars.insert(varname.to_string());
+                    }
+                    // Bind outer posit variable (e.g., +p); a recalled one already narrowed the candidates
+                    if let Some(var) = variable.as_ref().filter(|_| !posit_var_recalled) {
+                        let name = var.strip_prefix('+').unwrap_or(var);
+                        match variables.entry(name.to_string()) {
+                            Entry::Vacant(entry) => {
+                                let mut rs = ResultSet::new();
+                                rs.insert_many(&cands);
+                                entry.insert(rs);
+                            }
+                            Entry::Occupied(mut entry) => {
+                                let rs = entry.get_mut();
+                                rs.insert_many(&cands);
+                            }
+                        }
+                    }
+                    // Bind local variables from appearance roles (e.g., +w with role "wife")
+                    if !local_variables.is_empty() {
+                        bind_pattern_things(self.database, variables, &cands, &local_variables, &local_variable_unions, &roles, optional);
+                    }
+                    // No role-specific special-case filters; recall variables act as join filters generically.
+                    // ---------------- Binding enumeration (multiplicity preservation) ----------------
+                    if !cands.is_empty() {
+                        // Collect identity variable assignments for each candidate posit
+                        let aset_lookup = self
+                            .database
+                            .posit_thing_to_appearance_set_lookup();
+                        let aset_guard = aset_lookup.lock().unwrap();
+                        let mut candidate_info: Vec<(
+                            Thing,
+                            HashMap<String, Thing>,
+                        )> = Vec::new();
+                        for pid in cands.iter() {
+                            if let Some(appset) = aset_guard.get(&pid) {
+                                // First collect per-variable identity maps; union variables may yield multiple maps (one per union member)
+                                let mut pending_maps: Vec<
+                                    HashMap<String, Thing>,
+                                > = vec![HashMap::new()];
+                                for (i, token) in
+                                    local_variables.iter().enumerate()
+                                {
+                                    if *token == "*" {
+                                        continue;
+                                    }
+                                    let role_name = roles[i];
+                                    if let Some(thing) = appset
+                                        .appearances()
+                                        .iter()
+                                        .find(|a| a.role().name() == role_name)
+                                        .map(|a| a.thing())
+                                    {
+                                        if let Some(Some(union_names)) =
+                                            local_variable_unions.get(i)
+                                        {
+                                            // For a union, branch the maps: each union member may independently match this thing.
+                                            let mut branched: Vec<
+                                                HashMap<String, Thing>,
+                                            > = Vec::with_capacity(
+                                                pending_maps.len()
+                                                    * union_names.len(),
+                                            );
+                                            for uname in union_names.iter() {
+                                                for existing in
+                                                    pending_maps.iter()
                                                 {
-                                                    if *token == "*" {
-                                                        continue;
-                                                    }
-                                                    let role_name = roles[i];
-                                                    let bound_opt = appset
-                                                        .appearances()
-                                                        .iter()
-                                                        .find(|a| a.role().name() == role_name)
-                                                        .map(|a| a.thing());
-                                                    if let Some(bound_id) = bound_opt {
-                                                        // Determine if this bound_id satisfies existing variable bindings (support unions)
-                                                        let union_names = local_variable_unions
-                                                            .get(i)
-                                                            .and_then(|u| u.as_ref());
-                                                        let satisfies = if let Some(names) =
-                                                            union_names
-                                                        {
-                                                            // If any union member is already bound (non-empty) and contains bound_id, accept;
-                                                            // if none are effectively bound (all empty or absent), don't restrict.
-                                                            let mut any_bound = false;
-                                                            let mut any_match = false;
-                                                            for name in names.iter() {
-                                                                if let Some(rs) = variables.get(name) {
-                                                                    match rs.mode {
-                                                                        ResultSetMode::Thing => {
-                                                                            any_bound = true;
-                                                                            any_match |= rs.thing.unwrap() == bound_id;
-                                                                        }
-                                                                        ResultSetMode::Multi => {
-                                                                            any_bound = true;
-                                                                            any_match |= rs.multi.as_ref().unwrap().contains(bound_id);
-                                                                        }
-                                                                        ResultSetMode::Empty => {
-                                                                            // Treat empty as unbound
-                                                                        }
-                                                                    }
-                                                                }
-                                                            }
-                                                            if any_bound { any_match } else { true }
-                                                        } else {
-                                                            let key = token
-                                                                .strip_prefix('+')
-                                                                .unwrap_or(token);
-                                                            if let Some(rs) = variables.get(key) {
-                                                                match rs.mode {
-                                                                    ResultSetMode::Thing => {
-                                                                        rs.thing.unwrap() == bound_id
-                                                                    }
-                                                                    ResultSetMode::Multi => rs.multi.as_ref().unwrap().contains(bound_id),
-                                                                    ResultSetMode::Empty => true, // treat empty as unbound
-                                                                }
-                                                            } else {
-                                                                // Unbound variable – don't restrict
-                                                                true
-                                                            }
-                                                        };
-                                                        if !satisfies {
-                                                            continue 'cand;
-                                                        }
-                                                    } else {
-                                                        continue 'cand;
-                                                    }
+                                                    let mut cloned =
+                                                        existing.clone();
+                                                    cloned.insert(
+                                                        uname.clone(),
+                                                        thing,
+                                                    );
+                                                    branched.push(cloned);
                                                 }
-                                                filtered.insert(id);
                                             }
-                                            cands = filtered;
-                                            if cands.is_empty() {
-                                                any_clause_failed = true;
+                                            pending_maps = branched;
+                                        } else {
+                                            // Skip synthetic union token like "w|h"; only insert real variable names
+                                            if token.contains('|') {
+                                                continue;
                                             }
-                                        }
-                                        info!(target:"bareclad::stream", event="pattern_end", pattern_index=diag_pattern_id, final_candidates=cands.len(), any_clause_failed=any_clause_failed, enumeration_started=enumeration_started);
-                                        // Remember candidate posits for projection when returning values/times
-                                        // (legacy single-role candidate capture removed)
-                                        // If the appearing value used a variable (e.g., +n or n), capture its candidates
-                                        if let Some(vname) = _value_as_variable { active_vars.insert(vname.to_string()); }
-                                        // If the time slot used a variable, capture its candidate posits under that variable name
-                                        if let Some(varname) = _time_as_variable {
-                                            time_var_candidates
-                                                .insert(varname.to_string(), cands.clone());
-                                            active_vars.insert(varname.to_string());
-                                        }
-                                        // Bind outer posit variable (e.g., +p)
-                                        if let Some(var) = &variable {
-                                            let name = var.strip_prefix('+').unwrap_or(var);
-                                            match variables.entry(name.to_string()) {
-                                                Entry::Vacant(entry) => {
-                                                    let mut rs = ResultSet::new();
-                                                    rs.insert_many(&cands);
-                                                    entry.insert(rs);
-                                                }
-                                                Entry::Occupied(mut entry) => {
-                                                    let rs = entry.get_mut();
-                                                    rs.insert_many(&cands);
-                                                }
+                                            let vname = token
+                                                .strip_prefix('+')
+                                                .unwrap_or(token)
+                                                .to_string();
+                                            for m in pending_maps.iter_mut() {
+                                                m.insert(vname.clone(), thing);
                                             }
                                         }
-                                        // Bind local variables from appearance roles (e.g., +w with role "wife")
-                                        if !local_variables.is_empty() {
-                                            for id in cands.iter() {
-                                                let appset: Arc<crate::construct::AppearanceSet> = {
-                                                    let lk = self
-                                                        .database
-                                                        .posit_thing_to_appearance_set_lookup();
-                                                    let guard = lk.lock().unwrap();
-                                                    Arc::clone(guard.get(&id).unwrap())
-                                                };
-                                                for (i, token) in local_variables.iter().enumerate()
-                                                {
-                                                    if *token == "*" {
-                                                        continue;
-                                                    }
-                                                    let vname =
-                                                        token.strip_prefix('+').unwrap_or(token);
-                                                    let role_name = roles[i];
-                                                    if let Some(bound) = appset
-                                                        .appearances()
-                                                        .iter()
-                                                        .find(|a| a.role().name() == role_name)
-                                                        .map(|a| a.thing())
-                                                    {
-                                                        // recall variables intersect as join filters; inserts also intersect
-                                                        if let Some(Some(union_names)) =
-                                                            local_variable_unions.get(i)
-                                                        {
-                                                            for member in union_names {
-                                                                let key = member.to_string();
-                                                                match variables.entry(key.clone()) {
-                                                                    Entry::Vacant(entry) => {
-                                                                        let mut rs =
-                                                                            ResultSet::new();
-                                                                        rs.insert(bound);
-                                                                        entry.insert(rs);
-                                                                    }
-                                                                    Entry::Occupied(mut entry) => {
-                                                                        let rs = entry.get_mut();
-                                                                        if rs.mode
-                                                                            == ResultSetMode::Empty
-                                                                        {
-                                                                            rs.insert(bound);
-                                                                        } else {
-                                                                            let mut narrowed =
-                                                                                ResultSet::new();
-                                                                            narrowed.insert(bound);
-                                                                            rs.intersect_with(
-                                                                                &narrowed,
-                                                                            );
-                                                                        }
-                                                                    }
-                                                                }
-                                                            }
-                                                        } else {
-                                                            let key = vname.to_string();
-                                                            match variables.entry(key.clone()) {
-                                                                Entry::Vacant(entry) => {
-                                                                    // Bind when not present (insert or recall)
-                                                                    let mut rs = ResultSet::new();
-                                                                    rs.insert(bound);
-                                                                    entry.insert(rs);
-                                                                }
-                                                                Entry::Occupied(mut entry) => {
-                                                                    let rs = entry.get_mut();
-                                                                    if rs.mode
-                                                                        == ResultSetMode::Empty
-                                                                    {
-                                                                        // Bind empty recall var
-                                                                        rs.insert(bound);
-                                                                    } else {
-                                                                        // Intersect existing with this bound only for inserted variables
-                                                                        let mut narrowed =
-                                                                            ResultSet::new();
-                                                                        narrowed.insert(bound);
-                                                                        rs.intersect_with(
-                                                                            &narrowed,
-                                                                        );
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
+                                    }
+                                }
+                                for id_map in pending_maps.into_iter() {
+                                    candidate_info.push((pid, id_map));
+                                }
+                                info!(target:"bareclad::stream", event="enumeration_candidate_info", entries=candidate_info.len(), enumeration_started=?enumeration_started);
+                            }
+                        }
+                        // Names for value/time/posit variables (strip plus)
+                        let posit_var_name = variable.as_ref().map(|v| {
+                            v.strip_prefix('+').unwrap_or(v).to_string()
+                        });
+                        let value_var_name = _value_as_variable.map(|v| {
+                            v.strip_prefix('+').unwrap_or(v).to_string()
+                        });
+                        let time_var_name = _time_as_variable.map(|v| {
+                            v.strip_prefix('+').unwrap_or(v).to_string()
+                        });
+                        // Per-binding certainty check: a positor bound by an earlier pattern narrows the
+                        // positors to that binding's thing, and a variable 'as of' resolves per binding.
+                        let certainty_holds = |pid: &Thing, positor: Option<&Thing>, as_of: Option<&Time>| {
+                            certainty_filter.as_ref().is_none_or(|filter| {
+                                let positors = positor.map(std::slice::from_ref).or(certainty_positors.as_deref());
+                                filter.holds(certainty_assertions.get(pid).map(Vec::as_slice).unwrap_or_default(), positors, as_of)
+                            })
+                        };
+                        if !enumeration_started {
+                            for (pid, id_map) in candidate_info.iter() {
+                                if _as_of_var.is_some() && !certainty_holds(pid, None, None) {
+                                    continue;
+                                }
+                                let mut b = Binding::new();
+                                b.identities.extend(
+                                    id_map.iter().map(|(k, v)| (k.clone(), *v)),
+                                );
+                                if let Some(ref pn) = posit_var_name {
+                                    b.posit_vars.insert(pn.clone(), *pid);
+                                }
+                                if let Some(ref vn) = value_var_name {
+                                    b.value_slots.insert(
+                                        vn.clone(),
+                                        (*pid, VarKind::Value),
+                                    );
+                                }
+                                if let Some(ref tn) = time_var_name {
+                                    b.value_slots.insert(
+                                        tn.clone(),
+                                        (*pid, VarKind::Time),
+                                    );
+                                }
+                                bindings.push(b);
+                            }
+                            enumeration_started = true;
+                            info!(target:"bareclad::stream", event="enumeration_seed", seeded_bindings=bindings.len());
+                        } else {
+                            let mut new_bindings: Vec<Binding> = Vec::new();
+                            // Guards and caches for per-binding snapshot reduction when using variable as-of
+                            // Acquire time lookup guard; re-use existing aset_guard from candidate enumeration to avoid re-lock deadlock
+                            let time_lk = self.database.posit_time_lookup();
+                            let time_guard = time_lk.lock().unwrap();
+                            for existing in bindings.iter() {
+                                let merged_before = new_bindings.len();
+                                // Optional: resolve as-of time for this binding
+                                let binding_as_of_time: Option<Time> = if let Some(ref asv) = _as_of_var {
+                                    if let Some((pid_t, VarKind::Time)) = existing.value_slots.get(asv) {
+                                        time_guard.get(pid_t).cloned()
+                                    } else { None }
+                                } else { None };
+                                // Precompute best-per-appearance-set once per binding when variable as-of is in effect
+                                let best_cache: Option<std::collections::HashMap<usize, Thing>> = if let Some(ref as_of_time) = binding_as_of_time {
+                                    let mut cache = std::collections::HashMap::new();
+                                    for cid in cands.iter() {
+                                        if let Some(aset_c) = aset_guard.get(&cid) {
+                                            if let Some(ct) = time_guard.get(&cid) {
+                                                if ct <= as_of_time {
+                                                    let key = Arc::as_ptr(aset_c) as usize;
+                                                    let replace = if let Some(prev_pid) = cache.get(&key) {
+                                                        if let (Some(prev_t), Some(cur_t)) = (time_guard.get(prev_pid), time_guard.get(&cid)) {
+                                                            cur_t > prev_t
+                                                        } else { false }
+                                                    } else { true };
+                                                    if replace { cache.insert(key, cid); }
                                                 }
                                             }
-                                            // All identity variables are handled generically; no role-specific special-cases here.
                                         }
-                                        // No role-specific special-case filters; recall variables act as join filters generically.
-                                        // ---------------- Binding enumeration (multiplicity preservation) ----------------
-                                        if !cands.is_empty() {
-                                            // Collect identity variable assignments for each candidate posit
-                                            let aset_lookup = self
-                                                .database
-                                                .posit_thing_to_appearance_set_lookup();
-                                            let aset_guard = aset_lookup.lock().unwrap();
-                                            let mut candidate_info: Vec<(
-                                                Thing,
-                                                HashMap<String, Thing>,
-                                            )> = Vec::new();
-                                            for pid in cands.iter() {
-                                                if let Some(appset) = aset_guard.get(&pid) {
-                                                    // First collect per-variable identity maps; union variables may yield multiple maps (one per union member)
-                                                    let mut pending_maps: Vec<
-                                                        HashMap<String, Thing>,
-                                                    > = vec![HashMap::new()];
-                                                    for (i, token) in
-                                                        local_variables.iter().enumerate()
-                                                    {
-                                                        if *token == "*" {
-                                                            continue;
-                                                        }
-                                                        let role_name = roles[i];
-                                                        if let Some(thing) = appset
-                                                            .appearances()
-                                                            .iter()
-                                                            .find(|a| a.role().name() == role_name)
-                                                            .map(|a| a.thing())
-                                                        {
-                                                            if let Some(Some(union_names)) =
-                                                                local_variable_unions.get(i)
-                                                            {
-                                                                // For a union, branch the maps: each union member may independently match this thing.
-                                                                let mut branched: Vec<
-                                                                    HashMap<String, Thing>,
-                                                                > = Vec::with_capacity(
-                                                                    pending_maps.len()
-                                                                        * union_names.len(),
-                                                                );
-                                                                for uname in union_names.iter() {
-                                                                    for existing in
-                                                                        pending_maps.iter()
-                                                                    {
-                                                                        let mut cloned =
-                                                                            existing.clone();
-                                                                        cloned.insert(
-                                                                            uname.clone(),
-                                                                            thing,
-                                                                        );
-                                                                        branched.push(cloned);
-                                                                    }
-                                                                }
-                                                                pending_maps = branched;
-                                                            } else {
-                                                                // Skip synthetic union token like "w|h"; only insert real variable names
-                                                                if token.contains('|') {
-                                                                    continue;
-                                                                }
-                                                                let vname = token
-                                                                    .strip_prefix('+')
-                                                                    .unwrap_or(token)
-                                                                    .to_string();
-                                                                for m in pending_maps.iter_mut() {
-                                                                    m.insert(vname.clone(), thing);
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                    for id_map in pending_maps.into_iter() {
-                                                        candidate_info.push((pid, id_map));
-                                                    }
-                                                    info!(target:"bareclad::stream", event="enumeration_candidate_info", entries=candidate_info.len(), enumeration_started=?enumeration_started);
-                                                }
+                                    }
+                                    info!(target:"bareclad::stream", event="snapshot_reduced_per_binding", aset_count=cache.len());
+                                    Some(cache)
+                                } else { None };
+                                for (pid, id_map) in candidate_info.iter() {
+                                    // Identity compatibility
+                                    let mut ok = true;
+                                    for (k, v) in id_map.iter() {
+                                        // A posit variable (e.g. +p) joins with an identity of the same name (e.g. (p, posit))
+                                        if let Some(prev) =
+                                            existing.identities.get(k).or_else(|| existing.posit_vars.get(k))
+                                        {
+                                            if prev != v {
+                                                ok = false;
+                                                break;
                                             }
-                                            // Names for value/time/posit variables (strip plus)
-                                            let posit_var_name = variable.as_ref().map(|v| {
-                                                v.strip_prefix('+').unwrap_or(v).to_string()
-                                            });
-                                            let value_var_name = _value_as_variable.map(|v| {
-                                                v.strip_prefix('+').unwrap_or(v).to_string()
-                                            });
-                                            let time_var_name = _time_as_variable.map(|v| {
-                                                v.strip_prefix('+').unwrap_or(v).to_string()
-                                            });
-                                            if !enumeration_started {
-                                                for (pid, id_map) in candidate_info.iter() {
-                                                    let mut b = Binding::new();
-                                                    b.identities.extend(
-                                                        id_map.iter().map(|(k, v)| (k.clone(), *v)),
-                                                    );
-                                                    if let Some(ref pn) = posit_var_name {
-                                                        b.posit_vars.insert(pn.clone(), *pid);
-                                                    }
-                                                    if let Some(ref vn) = value_var_name {
-                                                        b.value_slots.insert(
-                                                            vn.clone(),
-                                                            (*pid, VarKind::Value),
-                                                        );
-                                                    }
-                                                    if let Some(ref tn) = time_var_name {
-                                                        b.value_slots.insert(
-                                                            tn.clone(),
-                                                            (*pid, VarKind::Time),
-                                                        );
-                                                    }
-                                                    bindings.push(b);
-                                                }
-                                                enumeration_started = true;
-                                                info!(target:"bareclad::stream", event="enumeration_seed", seeded_bindings=bindings.len());
+                                        }
+                                    }
+                                    if !ok {
+                                        continue;
+                                    }
+                                    // If variable as-of is in effect and bound for this row, enforce snapshot: pid must be the latest <= as_of among cands for its appearance set
+                                    if let (Some(_), Some(cache)) = (&binding_as_of_time, &best_cache) {
+                                        if let Some(aset_of_pid) = aset_guard.get(pid) {
+                                            let key = Arc::as_ptr(aset_of_pid) as usize;
+                                            if let Some(best_pid) = cache.get(&key) {
+                                                if pid != best_pid { continue; }
                                             } else {
-                                                let mut new_bindings: Vec<Binding> = Vec::new();
-                                                // Guards and caches for per-binding snapshot reduction when using variable as-of
-                                                // Acquire time lookup guard; re-use existing aset_guard from candidate enumeration to avoid re-lock deadlock
-                                                let time_lk = self.database.posit_time_lookup();
-                                                let time_guard = time_lk.lock().unwrap();
-                                                for existing in bindings.iter() {
-                                                    // Optional: resolve as-of time for this binding
-                                                    let binding_as_of_time: Option<Time> = if let Some(ref asv) = _as_of_var {
-                                                        if let Some((pid_t, VarKind::Time)) = existing.value_slots.get(asv) {
-                                                            time_guard.get(pid_t).cloned()
-                                                        } else { None }
-                                                    } else { None };
-                                                    // Precompute best-per-appearance-set once per binding when variable as-of is in effect
-                                                    let best_cache: Option<std::collections::HashMap<usize, Thing>> = if let Some(ref as_of_time) = binding_as_of_time {
-                                                        let mut cache = std::collections::HashMap::new();
-                                                        for cid in cands.iter() {
-                                                            if let Some(aset_c) = aset_guard.get(&cid) {
-                                                                if let Some(ct) = time_guard.get(&cid) {
-                                                                    if ct <= as_of_time {
-                                                                        let key = Arc::as_ptr(aset_c) as usize;
-                                                                        let replace = if let Some(prev_pid) = cache.get(&key) {
-                                                                            if let (Some(prev_t), Some(cur_t)) = (time_guard.get(prev_pid), time_guard.get(&cid)) {
-                                                                                cur_t > prev_t
-                                                                            } else { false }
-                                                                        } else { true };
-                                                                        if replace { cache.insert(key, cid); }
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                        info!(target:"bareclad::stream", event="snapshot_reduced_per_binding", aset_count=cache.len());
-                                                        Some(cache)
-                                                    } else { None };
-                                                    for (pid, id_map) in candidate_info.iter() {
-                                                        // Identity compatibility
-                                                        let mut ok = true;
-                                                        for (k, v) in id_map.iter() {
-                                                            if let Some(prev) =
-                                                                existing.identities.get(k)
-                                                            {
-                                                                if prev != v {
-                                                                    ok = false;
-                                                                    break;
-                                                                }
-                                                            }
-                                                        }
-                                                        if !ok {
-                                                            continue;
-                                                        }
-                                                        // If variable as-of is in effect and bound for this row, enforce snapshot: pid must be the latest <= as_of among cands for its appearance set
-                                                        if let (Some(_), Some(cache)) = (&binding_as_of_time, &best_cache) {
-                                                            if let Some(aset_of_pid) = aset_guard.get(pid) {
-                                                                let key = Arc::as_ptr(aset_of_pid) as usize;
-                                                                if let Some(best_pid) = cache.get(&key) {
-                                                                    if pid != best_pid { continue; }
-                                                                } else {
-                                                                    continue;
-                                                                }
-                                                            }
-                                                        }
-                                                        // Posit variable compatibility
-                                                        if let Some(ref pn) = posit_var_name {
-                                                            if let Some(prev) =
-                                                                existing.posit_vars.get(pn)
-                                                            {
-                                                                if prev != pid {
-                                                                    continue;
-                                                                }
-                                                            }
-                                                        }
-                                                        // Value variable compatibility
-                                                        if let Some(ref vn) = value_var_name {
-                                                            if let Some((prev_pid, _)) =
-                                                                existing.value_slots.get(vn)
-                                                            {
-                                                                if prev_pid != pid {
-                                                                    continue;
-                                                                }
-                                                            }
-                                                        }
-                                                        // Time variable compatibility
-                                                        if let Some(ref tn) = time_var_name {
-                                                            if let Some((prev_pid, _)) =
-                                                                existing.value_slots.get(tn)
-                                                            {
-                                                                if prev_pid != pid {
-                                                                    continue;
-                                                                }
-                                                            }
-                                                        }
-                                                        // Merge
-                                                        let mut merged = existing.clone();
-                                                        for (k, v) in id_map.iter() {
-                                                            merged
-                                                                .identities
-                                                                .entry(k.clone())
-                                                                .or_insert(*v);
-                                                        }
-                                                        if let Some(ref pn) = posit_var_name {
-                                                            merged
-                                                                .posit_vars
-                                                                .entry(pn.clone())
-                                                                .or_insert(*pid);
-                                                        }
-                                                        if let Some(ref vn) = value_var_name {
-                                                            merged
-                                                                .value_slots
-                                                                .entry(vn.clone())
-                                                                .or_insert((*pid, VarKind::Value));
-                                                        }
-                                                        if let Some(ref tn) = time_var_name {
-                                                            merged
-                                                                .value_slots
-                                                                .entry(tn.clone())
-                                                                .or_insert((*pid, VarKind::Time));
-                                                        }
-                                                        new_bindings.push(merged);
-                                                    }
-                                                }
-                                                bindings = new_bindings;
-                                                if bindings.is_empty() {
-                                                    any_clause_failed = true;
-                                                }
-                                                info!(target:"bareclad::stream", event="enumeration_merge", merged_bindings=bindings.len(), any_clause_failed=any_clause_failed);
+                                                continue;
                                             }
                                         }
                                     }
-                                }
-                            }
-                            _ => println!("Unknown posit structure: {:?}", structure),
-                        }
-                        // local variable debug output suppressed
-                    }
-                }
-                Rule::where_clause => {
-                    // Extended parser: collect time comparisons (existing behavior) and stash generic ones for future evaluation.
-                    // Unsupported (non-time) conditions are currently parsed but not evaluated: we log once if encountered.
-                    // (Previously logged unsupported conditions; now we capture generics silently.)
-                    for part in clause.into_inner() {
-                        match part.as_rule() {
-                            Rule::condition => {
-                                let mut lhs_var: Option<String> = None;
-                                let mut op: Option<String> = None;
-                                let mut rhs_time: Option<Time> = None;
-                                let mut rhs_is_time = false;
-                                let mut rhs_raw: Option<String> = None; // generic string form
-                                let mut rhs_var: Option<String> = None;
-                                for c in part.into_inner() {
-                                    match c.as_rule() {
-                                        Rule::recall => {
-                                            if lhs_var.is_none() {
-                                                lhs_var = Some(c.into_inner().next().unwrap().as_str().to_string());
-                                            } else if rhs_var.is_none() {
-                                                rhs_var = Some(c.into_inner().next().unwrap().as_str().to_string());
+                                    let bound_positor = certainty_filter.as_ref().and_then(|f| f.positor.as_ref()).and_then(|n| existing.identities.get(n));
+                                    if (_as_of_var.is_some() || bound_positor.is_some()) && !certainty_holds(pid, bound_positor, binding_as_of_time.as_ref().or(_as_of_time.as_ref())) {
+                                        continue;
+                                    }
+                                    // Posit variable compatibility
+                                    if let Some(ref pn) = posit_var_name {
+                                        if let Some(prev) =
+                                            existing.posit_vars.get(pn).or_else(|| existing.identities.get(pn))
+                                        {
+                                            if prev != pid {
+                                                continue;
                                             }
                                         }
-                                        Rule::comparator => op = Some(c.as_str().to_string()),
-                                        Rule::constant => {
-                                            // Could be time constant
-                                            if let Some(t) = parse_time_constant(c.as_str()) { rhs_time = Some(t); rhs_is_time = true; }
-                                            rhs_raw = Some(c.as_str().to_string());
-                                        }
-                                        Rule::time => { rhs_time = parse_time(c.as_str()); rhs_is_time = true; rhs_raw = Some(c.as_str().to_string()); }
-                                        // literals
-                                        Rule::certainty | Rule::decimal | Rule::int | Rule::string => {
-                                            rhs_raw = Some(c.as_str().to_string());
+                                    }
+                                    // Value variable compatibility
+                                    if let Some(ref vn) = value_var_name {
+                                        if let Some((prev_pid, _)) =
+                                            existing.value_slots.get(vn)
+                                        {
+                                            if prev_pid != pid {
+                                                continue;
+                                            }
                                         }
-                                        Rule::rhs_value => {
-                                            // unwrap one level
-                                            for r in c.into_inner() {
-                                                match r.as_rule() {
-                                                    Rule::constant => { if let Some(t)=parse_time_constant(r.as_str()) { rhs_time=Some(t); rhs_is_time=true; } rhs_raw=Some(r.as_str().to_string()); }
-                                                    Rule::time => { rhs_time = parse_time(r.as_str()); rhs_is_time=true; rhs_raw=Some(r.as_str().to_string()); }
-                                                    Rule::certainty | Rule::decimal | Rule::int | Rule::string => { rhs_raw=Some(r.as_str().to_string()); }
-                                                    _ => {}
-                                                }
+                                    }
+                                    // Time variable compatibility
+                                    if let Some(ref tn) = time_var_name {
+                                        if let Some((prev_pid, _)) =
+                                            existing.value_slots.get(tn)
+                                        {
+                                            if prev_pid != pid {
+                                                continue;
                                             }
                                         }
-                                        _ => {}
                                     }
-                                }
-                                if rhs_is_time {
-                                    if let (Some(v), Some(o), Some(t)) = (lhs_var.clone(), op.clone(), rhs_time) { where_time.push((v, o, t)); }
-                                } else if let (Some(lv), Some(o)) = (lhs_var.clone(), op.clone()) {
-                                    if let Some(rv) = rhs_var.clone() {
-                                        // Defer classification: push to both time_var and value_var lists; execution will keep the valid kind.
-                                        where_time_var.push((lv.clone(), o.clone(), rv.clone()));
-                                        where_value_var.push((lv, o, rv));
-                                    } else if let Some(raw) = rhs_raw.clone() {
-                                        let trimmed = raw.trim();
-                                        let rhs_kind = if trimmed.starts_with('"') && trimmed.ends_with('"') { RhsValueKind::String(trimmed.trim_matches('"').to_string()) }
-                                            else if trimmed.ends_with('%') { if let Some(cpct)=parse_certainty_literal(trimmed) { RhsValueKind::Cert(cpct) } else { RhsValueKind::Const(trimmed.to_string()) } }
-                                            else if trimmed.contains('.') && trimmed.chars().all(|c| c.is_ascii_digit() || c=='.' || c=='-' ) { RhsValueKind::Decimal(trimmed.to_string()) }
-                                            else if let Ok(iv) = trimmed.parse::<i64>() { RhsValueKind::Int(iv) } else { RhsValueKind::Const(trimmed.to_string()) };
-                                        where_value.push((lv, o, rhs_kind));
+                                    // Merge
+                                    let mut merged = existing.clone();
+                                    for (k, v) in id_map.iter() {
+                                        merged
+                                            .identities
+                                            .entry(k.clone())
+                                            .or_insert(*v);
+                                    }
+                                    if let Some(ref pn) = posit_var_name {
+                                        merged
+                                            .posit_vars
+                                            .entry(pn.clone())
+                                            .or_insert(*pid);
+                                    }
+                                    if let Some(ref vn) = value_var_name {
+                                        merged
+                                            .value_slots
+                                            .entry(vn.clone())
+                                            .or_insert((*pid, VarKind::Value));
+                                    }
+                                    if let Some(ref tn) = time_var_name {
+                                        merged
+                                            .value_slots
+                                            .entry(tn.clone())
+                                            .or_insert((*pid, VarKind::Time));
                                     }
+                                    new_bindings.push(merged);
                                 }
+                                // An optional pattern keeps a binding it could not extend
+                                if optional && new_bindings.len() == merged_before {
+                                    new_bindings.push(existing.clone());
+                                }
+                            }
+                            bindings = new_bindings;
+                            if bindings.is_empty() {
+                                any_clause_failed = true;
                             }
-                            _ => {}
+                            info!(target:"bareclad::stream", event="enumeration_merge", merged_bindings=bindings.len(), any_clause_failed=any_clause_failed);
                         }
                     }
                 }
-                Rule::return_clause => {
-                    let mut returns: Vec<String> = Vec::new();
-                    for structure in clause.into_inner() {
-                        if structure.as_rule() == Rule::recall {
-                            returns
-                                .push(structure.into_inner().next().unwrap().as_str().to_string());
-                        }
+            }
+            if optional {
+                any_clause_failed = failed_before;
+                nullable_vars.extend(variable_kinds.keys().filter(|k| !known_before.contains(k)).cloned());
+            }
+        }
+        if let Some(profile) = profile.as_deref_mut() { profile.finish_pattern(bindings.len()); }
+        // Exclude bindings matched by a 'not' pattern. A pattern joined through a single identity variable
+        // is evaluated as a bitmap difference; anything else is checked against each binding.
+        if enumeration_started {
+            let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
+            let aset_guard = aset_lookup.lock().unwrap();
+            let bound = |b: &Binding, name: &str| b.identities.get(name).or_else(|| b.posit_vars.get(name)).copied();
+            for mut negation in negated_patterns.drain(..) {
+                let (started, before) = (Instant::now(), bindings.len());
+                // Variables bound by this search, or by an earlier one, join; the others match anything.
+                let joins = |name: &String| variable_kinds.contains_key(name) || variables.get(name).is_some_and(|rs| rs.mode != ResultSetMode::Empty);
+                negation.identities.retain_mut(|(names, _)| {
+                    names.retain(joins);
+                    !names.is_empty()
+                });
+                negation.posit_var = negation.posit_var.filter(joins);
+                negation.value_var = negation.value_var.filter(joins);
+                negation.time_var = negation.time_var.filter(joins);
+                negation.certainty = negation.certainty.filter(|(f, _, _)| f.positor.as_ref().is_some_and(joins));
+                let matches: Vec<(Thing, Vec<Option<Thing>>)> = negation
+                    .candidates
+                    .iter()
+                    .filter_map(|pid| {
+                        let appset = aset_guard.get(&pid)?;
+                        let things = negation.identities.iter().map(|(_, role)| appset.appearances().iter().find(|a| a.role().name() == role).map(|a| a.thing())).collect();
+                        Some((pid, things))
+                    })
+                    .collect();
+                let single_join = match negation.identities.as_slice() {
+                    [(names, _)] if names.len() == 1 && negation.posit_var.is_none() && negation.value_var.is_none() && negation.time_var.is_none() && negation.certainty.is_none() => Some(&names[0]),
+                    _ => None,
+                };
+                if let Some(name) = single_join {
+                    let matched: RoaringTreemap = matches.iter().filter_map(|(_, things)| things[0]).collect();
+                    bindings.retain(|b| match bound(b, name) {
+                        Some(thing) => !matched.contains(thing),
+                        None => matched.is_empty(),
+                    });
+                    if let Some(rs) = variables.get_mut(name) {
+                        let mut excluded = ResultSet::new();
+                        excluded.insert_many(&matched);
+                        rs.difference_with(&excluded);
                     }
-                    let first_time = return_columns.is_none();
-                    if first_time { *return_columns = Some(returns.clone()); }
-                    // Emit meta as soon as we know the column set (only once per search)
-                    if first_time {
-                        if let Some(cols) = return_columns.as_ref() {
-                            if let SinkFlow::Stop = sink.on_meta(cols) { return; }
+                } else {
+                    bindings.retain(|b| {
+                        !matches.iter().any(|(pid, things)| {
+                            negation.identities.iter().zip(things).all(|((names, _), thing)| {
+                                let expected: Vec<Thing> = names.iter().filter_map(|n| bound(b, n)).collect();
+                                expected.is_empty() || thing.is_some_and(|t| expected.contains(&t))
+                            }) && negation.posit_var.as_deref().and_then(|n| bound(b, n)).is_none_or(|p| p == *pid)
+                                && negation.value_var.as_ref().and_then(|n| b.value_slots.get(n)).is_none_or(|(p, _)| p == pid)
+                                && negation.time_var.as_ref().and_then(|n| b.value_slots.get(n)).is_none_or(|(p, _)| p == pid)
+                                && negation.certainty.as_ref().is_none_or(|(filter, assertions, as_of)| {
+                                    filter.positor.as_ref().and_then(|n| b.identities.get(n)).is_none_or(|positor| {
+                                        filter.holds(assertions.get(pid).map(Vec::as_slice).unwrap_or_default(), Some(std::slice::from_ref(positor)), as_of.as_ref())
+                                    })
+                                })
+                        })
+                    });
+                }
+                info!(target:"bareclad::stream", event="negation_applied", candidates=negation.candidates.len(), bitmap=single_join.is_some(), remaining_bindings=bindings.len());
+                if let Some(profile) = profile.as_deref_mut() {
+                    let stats = &mut profile.patterns[negation.pattern];
+                    stats.filtered = (before - bindings.len()) as u64;
+                    stats.bindings = Some(bindings.len());
+                    stats.elapsed += started.elapsed();
+                }
+            }
+        }
+        if let Some(condition) = &query.condition {
+            // Named searches and subqueries after 'in' are evaluated once into sets of things.
+            let mut references = Vec::new();
+            set_references(condition, &mut references);
+            for reference in references {
+                let result = match reference {
+                    ast::SetReference::Subquery(subquery) => self.evaluate_set(subquery, "Subquery"),
+                    ast::SetReference::Named(name) => {
+                        let query = self.named_searches.lock().unwrap().get(name).cloned();
+                        match query {
+                            Some(query) => self.evaluate_set(&query, &format!("Named search '{}'", name)),
+                            None => Err(crate::error::BarecladError::Execution(format!("Unknown named search: {}", name))),
                         }
                     }
-                    if any_clause_failed {
+                };
+                match result {
+                    Ok(things) => { member_sets.insert(set_key(reference), things); }
+                    Err(e) => {
+                        *exec_error = Some(e);
                         return;
                     }
-                    if enumeration_started {
-                        info!(target:"bareclad::stream", event="projection_start", bindings=bindings.len(), return_cols=?return_columns.as_ref().unwrap_or(&Vec::new()));
-                        // (debug logging removed)
-                        // Validate variable references in value predicates
-                        if exec_error.is_none() {
-                            for (lhs, _op, _rhs) in &where_value {
-                                if !variable_kinds.contains_key(lhs) {
-                                    *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", lhs)));
-                                    break;
-                                }
+                }
+            }
+        }
+        let items = query.returns.clone();
+        let explicit_group_by = query.group_by.clone();
+        let has_aggregates = items.iter().any(|i| i.aggregate.is_some());
+        // Without aggregates 'group by' has nothing to fold; otherwise plain variables must be group keys.
+        if let Some(keys) = &explicit_group_by {
+            if !has_aggregates {
+                *exec_error = Some(crate::error::BarecladError::Execution("'group by' requires at least one aggregate in the return clause".into()));
+                return;
+            }
+            if let Some(item) = items.iter().find(|i| i.aggregate.is_none() && (!keys.contains(&i.variable) || !i.path.is_empty())) {
+                *exec_error = Some(crate::error::BarecladError::Execution(format!("Variable '{}' must appear in group by or inside an aggregate", item.column_name())));
+                return;
+            }
+        }
+        // Group keys are variables or JSON paths into them.
+        let group_keys: Vec<(String, Vec<ast::PathStep>)> = match explicit_group_by {
+            Some(keys) => keys.into_iter().map(|k| (k, Vec::new())).collect(),
+            None => items.iter().filter(|i| i.aggregate.is_none()).map(|i| (i.variable.clone(), i.path.clone())).collect(),
+        };
+        let returns: Vec<String> = items.iter().map(|i| i.column_name()).collect();
+        let mut order_keys: Vec<(OrderKey, bool)> = Vec::with_capacity(order_by.len());
+        for (key, descending) in &order_by {
+            let name = key.column_name();
+            let resolved = if let Some(i) = returns.iter().position(|c| *c == name) {
+                Some(OrderKey::Column(i))
+            } else if key.aggregate.is_some() {
+                None
+            } else if has_aggregates {
+                group_keys.iter().position(|(k, path)| *k == key.variable && *path == key.path).map(OrderKey::GroupKey)
+            } else if variable_kinds.contains_key(&key.variable) {
+                Some(OrderKey::Variable(key.variable.clone(), key.path.clone()))
+            } else {
+                *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in order by: {}", key.variable)));
+                return;
+            };
+            let Some(resolved) = resolved else {
+                *exec_error = Some(crate::error::BarecladError::Execution(format!("order by '{}' must be a returned column or group key", name)));
+                return;
+            };
+            order_keys.push((resolved, *descending));
+        }
+        let first_time = return_columns.is_none();
+        if first_time { *return_columns = Some(returns.clone()); }
+        // Emit meta as soon as we know the column set (only once per search)
+        if first_time {
+            if let Some(cols) = return_columns.as_ref() {
+                if let SinkFlow::Stop = sink.on_meta(cols) { return; }
+            }
+        }
+        if any_clause_failed {
+            return;
+        }
+        if enumeration_started {
+            info!(target:"bareclad::stream", event="projection_start", bindings=bindings.len(), return_cols=?return_columns.as_ref().unwrap_or(&Vec::new()));
+            // (debug logging removed)
+            // Validate variable references in value predicates
+            let mut predicates: Vec<&Predicate> = Vec::new();
+            if let Some(cond) = &where_condition { cond.predicates(&mut predicates); }
+            if exec_error.is_none() {
+                for p in &predicates {
+                    if let Predicate::Value(lhs, _, _) | Predicate::Member(lhs, _) | Predicate::Matches(lhs, _) | Predicate::Like(lhs, _, _) | Predicate::Regex(lhs, _) | Predicate::JsonPath(lhs, _, _, _) = p
+                        && !variable_kinds.contains_key(lhs)
+                    {
+                        *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", lhs)));
+                        break;
+                    }
+                }
+            }
+            if exec_error.is_some() { return; }
+            if where_condition.is_some() || !where_time_var.is_empty() {
+                // The posits containing the words of each text tested with 'matches'.
+                let text_matches: HashMap<&str, RoaringTreemap> = predicates.iter().filter_map(|p| match p {
+                    Predicate::Matches(_, text) => Some((text.as_str(), index::matching(self.database, text))),
+                    _ => None,
+                }).collect();
+                let posit_keeper = self.database.posit_keeper();
+                let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
+                let type_partitions = self.database.role_name_to_data_type_lookup();
+                let time_lookup = self.database.posit_time_lookup();
+                let mut pk_guard = posit_keeper.lock().unwrap();
+                let aset_guard = aset_lookup.lock().unwrap();
+                let tp_guard = type_partitions.lock().unwrap();
+                let guard_time = time_lookup.lock().unwrap();
+                // Evaluate a single comparison against a binding; errors are recorded in exec_error and reject the binding.
+                let mut atom = |b: &Binding, predicate: &Predicate| -> bool {
+                    match predicate {
+                        Predicate::Time(v, op, tcmp) => {
+                            if let Some((pid, VarKind::Time)) = b.value_slots.get(v) {
+                                guard_time.get(pid).is_some_and(|pt| compare_times(pt, tcmp, op))
+                            } else {
+                                false
                             }
                         }
-                        if exec_error.is_some() { return; }
-                        if !where_time.is_empty() {
-                            let tk = self.database.posit_time_lookup();
-                            let guard_time = tk.lock().unwrap();
-                            bindings.retain(|b| {
-                                for (v, op, tcmp) in &where_time {
-                                    if let Some((pid, VarKind::Time)) = b.value_slots.get(v) {
-                                        if let Some(pt) = guard_time.get(pid) {
-                                            let ok = match op.as_str() {
-                                                "<" => pt < tcmp,
-                                                "<=" => pt <= tcmp,
-                                                ">" => pt > tcmp,
-                                                ">=" => pt >= tcmp,
-                                                "==" | "=" => pt == tcmp,
-                                                _ => false,
-                                            };
-                                            if !ok {
-                                                return false;
-                                            }
-                                        } else {
-                                            return false;
-                                        }
-                                    } else {
-                                        return false;
+                        Predicate::TimeVariables(v1, op, v2) => {
+                            if let (Some((pid1, VarKind::Time)), Some((pid2, VarKind::Time))) = (b.value_slots.get(v1), b.value_slots.get(v2)) {
+                                if let (Some(pt1), Some(pt2)) = (guard_time.get(pid1), guard_time.get(pid2)) {
+                                    match op.as_str() {
+                                        "<" => pt1 < pt2,
+                                        "<=" => pt1 <= pt2,
+                                        ">" => pt1 > pt2,
+                                        ">=" => pt1 >= pt2,
+                                        "==" | "=" => pt1 == pt2,
+                                        _ => false,
                                     }
+                                } else { false }
+                            } else { true }
+                        }
+                        Predicate::Member(var, set) => {
+                            let Some(thing) = b.identities.get(var).or_else(|| b.posit_vars.get(var)) else {
+                                if !nullable_vars.contains(var) && exec_error.is_none() {
+                                    *exec_error = Some(crate::error::BarecladError::Execution(format!("Membership in a search requires an identity variable: {}", var)));
                                 }
-                                true
-                            });
+                                return false;
+                            };
+                            member_sets.get(set).is_some_and(|things| things.contains(*thing))
                         }
-                        if !where_time_var.is_empty() {
-                            let tk = self.database.posit_time_lookup();
-                            let guard_time = tk.lock().unwrap();
-                            bindings.retain(|b| {
-                                for (v1, op, v2) in &where_time_var {
-                                    if let (Some((pid1, VarKind::Time)), Some((pid2, VarKind::Time))) = (b.value_slots.get(v1), b.value_slots.get(v2)) {
-                                        if let (Some(pt1), Some(pt2)) = (guard_time.get(pid1), guard_time.get(pid2)) {
-                                            let ok = match op.as_str() {
-                                                "<" => pt1 < pt2,
-                                                "<=" => pt1 <= pt2,
-                                                ">" => pt1 > pt2,
-                                                ">=" => pt1 >= pt2,
-                                                "==" | "=" => pt1 == pt2,
-                                                _ => false,
-                                            };
-                                            if !ok { return false; }
-                                        } else { return false; }
-                                    } // else skip (handled in value stage if applicable)
+                        Predicate::Variables(l, op, r) => {
+                            if [l, r].iter().any(|v| nullable_vars.contains(*v) && !b.value_slots.contains_key(*v)) { return false; } // comparisons with null never hold
+                            let (lpid, lkind) = if let Some(t) = b.value_slots.get(l) { *t } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", l))); } return false; };
+                            let (rpid, rkind) = if let Some(t) = b.value_slots.get(r) { *t } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", r))); } return false; };
+                            if lkind == VarKind::Time && rkind == VarKind::Time {
+                                return if let (Some(pt1), Some(pt2)) = (guard_time.get(&lpid), guard_time.get(&rpid)) {
+                                    match op.as_str() {
+                                        "<" => pt1 < pt2,
+                                        "<=" => pt1 <= pt2,
+                                        ">" => pt1 > pt2,
+                                        ">=" => pt1 >= pt2,
+                                        "==" | "=" => pt1 == pt2,
+                                        _ => false,
+                                    }
+                                } else { false };
+                            }
+                            if lkind == VarKind::Time || rkind == VarKind::Time { return true; } // mixed time/value comparisons are not restricting
+                            if lkind != VarKind::Value || rkind != VarKind::Value { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in value predicate: {} or {}", l, r))); } return false; }
+                            let l_roles = if let Some(app) = aset_guard.get(&lpid) { app.roles() } else { return false; };
+                            let r_roles = if let Some(app) = aset_guard.get(&rpid) { app.roles() } else { return false; };
+                            let l_allowed = tp_guard.lookup(&l_roles).clone();
+                            let r_allowed = tp_guard.lookup(&r_roles).clone();
+                            let ordering = matches!(op.as_str(), "<"|"<="|">"|">=");
+                            macro_rules! grab_val { ($allowed:expr, $pid:expr, $numeric_first:expr) => {{
+                                let mut out: Option<(String,String)> = None;
+                                if out.is_none() && $numeric_first && $allowed.contains("Decimal") { if let Some(p)=pk_guard.posit::<Decimal>($pid) { out=Some((p.value().to_string(), "Decimal".to_string())); } }
+                                if out.is_none() && $numeric_first && $allowed.contains("i64") { if let Some(p)=pk_guard.posit::<i64>($pid) { out=Some((p.value().to_string(), "i64".to_string())); } }
+                                if out.is_none() && $allowed.contains("String") { if let Some(p)=pk_guard.posit::<String>($pid) { out=Some((p.value().to_string(), "String".to_string())); } }
+                                if out.is_none() && $allowed.contains("JSON") { if let Some(p)=pk_guard.posit::<JSON>($pid) { out=Some((p.value().to_string(), "JSON".to_string())); } }
+                                if out.is_none() && $allowed.contains("Certainty") { if let Some(p)=pk_guard.posit::<Certainty>($pid) { out=Some((p.value().to_string(), "Certainty".to_string())); } }
+                                if out.is_none() && !$numeric_first && $allowed.contains("Decimal") { if let Some(p)=pk_guard.posit::<Decimal>($pid) { out=Some((p.value().to_string(), "Decimal".to_string())); } }
+                                if out.is_none() && !$numeric_first && $allowed.contains("i64") { if let Some(p)=pk_guard.posit::<i64>($pid) { out=Some((p.value().to_string(), "i64".to_string())); } }
+                                out
+                            }}}
+                            let l_val = grab_val!(l_allowed, lpid, ordering);
+                            let r_val = grab_val!(r_allowed, rpid, ordering);
+                            let (l_text, l_type) = if let Some(v)=l_val { v } else { return false; };
+                            let (r_text, r_type) = if let Some(v)=r_val { v } else { return false; };
+                            if ordering {
+                                if (l_type=="Certainty") ^ (r_type=="Certainty") { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison requires both sides to be certainties or percent sign (%) certainty mismatch: {} {} {}", l, op, r))); } false }
+                                else if l_type=="Certainty" && r_type=="Certainty" {
+                                    let to_pct = |s:&str| if s=="1" {100} else if s=="-1" {-100} else if s=="0" {0} else if s.starts_with("0.") || s.starts_with("-0.") { (s.parse::<f64>().unwrap_or(0.0)*100.0) as i32 } else {0};
+                                    cmp_numeric(to_pct(&l_text) as f64, to_pct(&r_text) as f64, op)
+                                } else if (l_type=="i64" || l_type=="Decimal") && (r_type=="i64" || r_type=="Decimal") {
+                                    use bigdecimal::BigDecimal; use std::str::FromStr; let lbd=BigDecimal::from_str(&l_text).unwrap_or_else(|_| BigDecimal::from(0)); let rbd=BigDecimal::from_str(&r_text).unwrap_or_else(|_| BigDecimal::from(0)); cmp_bigdecimal(&lbd,&rbd,op)
+                                } else if l_type=="String" && r_type=="String" {
+                                    cmp_strings(&l_text, &r_text, op)
+                                } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison not allowed for value variables: {}({}) {} {}({})", l, l_type, op, r, r_type))); } false }
+                            } else { // equality
+                                if op != "=" && op != "==" { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unsupported comparison operator '{}' for value variables", op))); } false }
+                                else if l_type=="Certainty" && r_type=="Certainty" { l_text==r_text }
+                                else if (l_type=="i64"||l_type=="Decimal") && (r_type=="i64"||r_type=="Decimal") { let lf=l_text.parse::<f64>().unwrap_or(0.0); let rf=r_text.parse::<f64>().unwrap_or(0.0); (lf-rf).abs()<1e-9 }
+                                else { l_text==r_text }
+                            }
+                        }
+                        Predicate::Matches(var, text) => {
+                            if nullable_vars.contains(var) && !b.value_slots.contains_key(var) { return false; }
+                            match b.value_slots.get(var) {
+                                Some((pid, VarKind::Value)) => text_matches.get(text.as_str()).is_some_and(|posits| posits.contains(*pid)),
+                                _ => {
+                                    if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in matches: {}", var))); }
+                                    false
                                 }
-                                true
-                            });
+                            }
                         }
-                        if bindings.is_empty() { return; }
-                        if !where_value_var.is_empty() {
-                            let posit_keeper = self.database.posit_keeper();
-                            let type_partitions = self.database.role_name_to_data_type_lookup();
-                            let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
-                            let mut pk_guard = posit_keeper.lock().unwrap();
-                            let tp_guard = type_partitions.lock().unwrap();
-                            let aset_guard = aset_lookup.lock().unwrap();
-                            bindings.retain(|b| {
-                                for (l, op, r) in &where_value_var {
-                                    let (lpid, lkind) = if let Some(t) = b.value_slots.get(l) { *t } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", l))); } return false; };
-                                    let (rpid, rkind) = if let Some(t) = b.value_slots.get(r) { *t } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", r))); } return false; };
-                                    if lkind == VarKind::Time || rkind == VarKind::Time { continue; } // handled by where_time_var stage
-                                    if lkind != VarKind::Value || rkind != VarKind::Value { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in value predicate: {} or {}", l, r))); } return false; }
-                                    let l_roles = if let Some(app) = aset_guard.get(&lpid) { app.roles() } else { return false; };
-                                    let r_roles = if let Some(app) = aset_guard.get(&rpid) { app.roles() } else { return false; };
-                                    let l_allowed = tp_guard.lookup(&l_roles).clone();
-                                    let r_allowed = tp_guard.lookup(&r_roles).clone();
-                                    let ordering = matches!(op.as_str(), "<"|"<="|">"|">=");
-                                    macro_rules! grab_val { ($allowed:expr, $pid:expr, $numeric_first:expr) => {{
-                                        let mut out: Option<(String,String)> = None;
-                                        if out.is_none() && $numeric_first && $allowed.contains("Decimal") { if let Some(p)=pk_guard.posit::<Decimal>($pid) { out=Some((p.value().to_string(), "Decimal".to_string())); } }
-                                        if out.is_none() && $numeric_first && $allowed.contains("i64") { if let Some(p)=pk_guard.posit::<i64>($pid) { out=Some((p.value().to_string(), "i64".to_string())); } }
-                                        if out.is_none() && $allowed.contains("String") { if let Some(p)=pk_guard.posit::<String>($pid) { out=Some((p.value().to_string(), "String".to_string())); } }
-                                        if out.is_none() && $allowed.contains("JSON") { if let Some(p)=pk_guard.posit::<JSON>($pid) { out=Some((p.value().to_string(), "JSON".to_string())); } }
-                                        if out.is_none() && $allowed.contains("Certainty") { if let Some(p)=pk_guard.posit::<Certainty>($pid) { out=Some((p.value().to_string(), "Certainty".to_string())); } }
-                                        if out.is_none() && !$numeric_first && $allowed.contains("Decimal") { if let Some(p)=pk_guard.posit::<Decimal>($pid) { out=Some((p.value().to_string(), "Decimal".to_string())); } }
-                                        if out.is_none() && !$numeric_first && $allowed.contains("i64") { if let Some(p)=pk_guard.posit::<i64>($pid) { out=Some((p.value().to_string(), "i64".to_string())); } }
-                                        out
-                                    }}}
-                                    let l_val = grab_val!(l_allowed, lpid, ordering);
-                                    let r_val = grab_val!(r_allowed, rpid, ordering);
-                                    let (l_text, l_type) = if let Some(v)=l_val { v } else { return false; };
-                                    let (r_text, r_type) = if let Some(v)=r_val { v } else { return false; };
-                                    let pass = if ordering {
-                                        if (l_type=="Certainty") ^ (r_type=="Certainty") { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison requires both sides to be certainties or percent sign (%) certainty mismatch: {} {} {}", l, op, r))); } false }
-                                        else if l_type=="Certainty" && r_type=="Certainty" {
-                                            let to_pct = |s:&str| if s=="1" {100} else if s=="-1" {-100} else if s=="0" {0} else if s.starts_with("0.") || s.starts_with("-0.") { (s.parse::<f64>().unwrap_or(0.0)*100.0) as i32 } else {0};
-                                            cmp_numeric(to_pct(&l_text) as f64, to_pct(&r_text) as f64, op)
-                                        } else if (l_type=="i64" || l_type=="Decimal") && (r_type=="i64" || r_type=="Decimal") {
-                                            use bigdecimal::BigDecimal; use std::str::FromStr; let lbd=BigDecimal::from_str(&l_text).unwrap_or_else(|_| BigDecimal::from(0)); let rbd=BigDecimal::from_str(&r_text).unwrap_or_else(|_| BigDecimal::from(0)); cmp_bigdecimal(&lbd,&rbd,op)
-                                        } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison not allowed for value variables: {}({}) {} {}({})", l, l_type, op, r, r_type))); } false }
-                                    } else { // equality
-                                        if op != "=" && op != "==" { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unsupported comparison operator '{}' for value variables", op))); } false }
-                                        else if l_type=="Certainty" && r_type=="Certainty" { l_text==r_text }
-                                        else if (l_type=="i64"||l_type=="Decimal") && (r_type=="i64"||r_type=="Decimal") { let lf=l_text.parse::<f64>().unwrap_or(0.0); let rf=r_text.parse::<f64>().unwrap_or(0.0); (lf-rf).abs()<1e-9 }
-                                        else if l_type=="String" && r_type=="String" { l_text==r_text }
-                                        else { l_text==r_text }
-                                    };
-                                    if !pass { return false; }
+                        Predicate::Like(var, _, regex) | Predicate::Regex(var, regex) => {
+                            if nullable_vars.contains(var) && !b.value_slots.contains_key(var) { return false; }
+                            match b.value_slots.get(var) {
+                                // Values of other types never match a pattern.
+                                Some((pid, VarKind::Value)) => pk_guard.posit::<String>(*pid).is_some_and(|p| regex.is_match(p.value())),
+                                _ => {
+                                    if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in pattern: {}", var))); }
+                                    false
                                 }
-                                true
-                            });
-                            if exec_error.is_some() { return; }
-                            if bindings.is_empty() { return; }
+                            }
                         }
-                        if !where_value.is_empty() {
-                            let posit_keeper = self.database.posit_keeper();
-                            let type_partitions = self.database.role_name_to_data_type_lookup();
-                            let mut pk_guard = posit_keeper.lock().unwrap();
-                            let tp_guard = type_partitions.lock().unwrap();
-                            bindings.retain(|b| {
-                                for (lhs, op, rhs) in &where_value {
-                                    // locate lhs posit/value
-                                    let (pid, vkind) = if let Some(tup) = b.value_slots.get(lhs) { *tup } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", lhs))); } return false; };
-                                    if vkind != VarKind::Value { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in value predicate: {}", lhs))); } return false; }
-                                    // Determine allowed types for this posit
-                                    // We need appearance set to determine role datatypes; reuse logic from projection path.
-                                    let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
-                                    let aset_guard = aset_lookup.lock().unwrap();
-                                    let val_string_opt = if let Some(appset) = aset_guard.get(&pid) {
-                                        let roles = appset.roles();
-                                        let allowed = tp_guard.lookup(&roles).clone();
-                                        let ordering = matches!(op.as_str(), "<"|"<="|">"|">=");
-                                        // Generic ordering mismatch: if RHS numeric and allowed doesn't include a numeric type
-                                        if ordering {
-                                            match rhs {
-                                                RhsValueKind::Int(_) | RhsValueKind::Decimal(_) => {
-                                                    let numeric_allowed = allowed.contains("i64") || allowed.contains("Decimal");
-                                                    if !numeric_allowed {
-                                                        // If this variable is a certainty, produce the more helpful percent sign guidance.
-                                                        if allowed.contains("Certainty") {
-                                                            if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison requires a percent sign (%) for certainty variable '{}' (e.g. 75%)", lhs))); }
-                                                        } else {
-                                                            if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison not allowed: variable '{}' of non-numeric type used with '{}'", lhs, op))); }
-                                                        }
-                                                        return false;
-                                                    }
-                                                }
-                                                _ => {}
-                                            }
-                                        }
-                                        // Helper macros to attempt extraction
-                                        macro_rules! grab_string { ($t:ty, $label:expr) => { if allowed.contains($label) { if let Some(p) = pk_guard.posit::<$t>(pid) { Some(format!("{}", p.value())) } else { None } } else { None } }; }
-                                        // Try in a precedence order; note we only need the one matching RHS kind.
-                                        match rhs {
-                                            RhsValueKind::Int(_) => grab_string!(i64, "i64"),
-                                            RhsValueKind::Cert(_) => grab_string!(Certainty, "Certainty"),
-                                            RhsValueKind::Decimal(_) => grab_string!(Decimal, "Decimal").or(grab_string!(i64, "i64")),
-                                            RhsValueKind::String(_) | RhsValueKind::Const(_) => grab_string!(String, "String").or(grab_string!(JSON, "JSON")).or(grab_string!(Certainty, "Certainty")).or(grab_string!(i64, "i64")),
-                                        }
-                                    } else { None };
-                                    let lhs_val = if let Some(v) = val_string_opt { v } else { return false; };
-                                    // Detect ordering mismatch: certainty value (by display pattern) vs int/decimal RHS lacking %.
-                                    let ordering = matches!(op.as_str(), "<"|"<="|">"|">=");
-                                    if ordering {
-                                        if matches!(rhs, RhsValueKind::Int(_) | RhsValueKind::Decimal(_)) && (lhs_val == "1" || lhs_val == "-1" || lhs_val == "0" || lhs_val.starts_with("0.") || lhs_val.starts_with("-0.")) {
-                                            if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison requires a percent sign (%) for certainty variable '{}' (e.g. 75%)", lhs))); }
-                                            return false;
+                        Predicate::JsonPath(var, path, op, rhs) => {
+                            if nullable_vars.contains(var) && !b.value_slots.contains_key(var) { return false; }
+                            match b.value_slots.get(var) {
+                                // Values other than JSON have no paths to follow.
+                                Some((pid, VarKind::Value)) => {
+                                    let scalar = pk_guard.posit::<JSON>(*pid).map(|p| json_path::extract(p.value(), path)).unwrap_or(json_path::Scalar::Null);
+                                    match json_path::compare(&scalar, &ast::path_text(var, path), op, rhs) {
+                                        Ok(held) => held,
+                                        Err(e) => {
+                                            if exec_error.is_none() { *exec_error = Some(e); }
+                                            false
                                         }
                                     }
-                                    // Comparison dispatch
-                                    let pass = match rhs {
-                                        RhsValueKind::Int(r) => {
-                                            if let Ok(l) = lhs_val.parse::<i64>() { cmp_numeric(l as f64, *r as f64, op) } else { if ["<","<=",">",">="].contains(&op.as_str()) && exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Type mismatch for ordering: value '{}' not comparable to int literal {}", lhs_val, r))); } false }
-                                        }
-                                        RhsValueKind::Cert(rpct) => {
-                                            // lhs_val is display (e.g., 0.75, -0.25, 1, -1, 0)
-                                            let l_pct_opt = if lhs_val == "1" { Some(100) } else if lhs_val == "-1" { Some(-100) } else if lhs_val == "0" { Some(0) } else if lhs_val.starts_with("0.") || lhs_val.starts_with("-0.") { lhs_val.parse::<f64>().ok().map(|f| (f*100.0) as i32) } else { None };
-                                            if let Some(lpct) = l_pct_opt { cmp_numeric(lpct as f64, *rpct as f64, op) } else { if ["<","<=",">",">="].contains(&op.as_str()) && exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Type mismatch for ordering: value '{}' not comparable to certainty literal {}%", lhs_val, rpct))); } false }
-                                        }
-                                        RhsValueKind::Decimal(rraw) => {
-                                            // compare as BigDecimal via string parse fallback to f64
-                                            use bigdecimal::BigDecimal; use std::str::FromStr;
-                                            let lbd = BigDecimal::from_str(&lhs_val).or_else(|_| BigDecimal::from_str("0")).unwrap();
-                                            let rbd = BigDecimal::from_str(rraw).or_else(|_| BigDecimal::from_str("0")).unwrap();
-                                            cmp_bigdecimal(&lbd, &rbd, op)
-                                        }
-                                        RhsValueKind::String(rstr) => {
-                                            if ["<","<=",">",">="].contains(&op.as_str()) { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison not allowed for string literal: {} {} '{}'", lhs, op, rstr))); } return false; }
-                                            if op == "=" || op == "==" { lhs_val == *rstr } else { false }
-                                        }
-                                        RhsValueKind::Const(rconst) => {
-                                            if ["<","<=",">",">="].contains(&op.as_str()) { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Ordering comparison not allowed for constant literal: {} {} '{}'", lhs, op, rconst))); } return false; }
-                                            if op == "=" || op == "==" { lhs_val == *rconst } else { false }
-                                        }
-                                    };
-                                    if !pass { return false; }
                                 }
-                                true
-                            });
-                            if exec_error.is_some() { return; }
-                            if bindings.is_empty() { return; }
-                        }
-                        let posit_keeper = self.database.posit_keeper();
-                        let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
-                        let type_partitions = self.database.role_name_to_data_type_lookup();
-                        let time_lookup = self.database.posit_time_lookup();
-                        let mut pk_guard = posit_keeper.lock().unwrap();
-                        let aset_guard = aset_lookup.lock().unwrap();
-                        let tp_guard = type_partitions.lock().unwrap();
-                        let time_guard = time_lookup.lock().unwrap();
-
-                        // Column-level inference removed; we now collect a per-row types vector.
-                        // Emission handled after full clause scan; see post-clause block.
-                        if !enumeration_started {
-                            info!(target:"bareclad::stream", event="projection_skipped", reason="no_enumeration", any_clause_failed=any_clause_failed);
-                            return;
+                                _ => {
+                                    if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in JSON path: {}", var))); }
+                                    false
+                                }
+                            }
                         }
-                        for b in bindings.iter() {
-                            info!(target:"bareclad::stream", event="row_binding_iter", identities=b.identities.len(), value_slots=b.value_slots.len(), posit_vars=b.posit_vars.len());
-                            let mut row: Vec<String> = Vec::with_capacity(returns.len());
-                            let mut types_row: Vec<String> = Vec::with_capacity(returns.len());
-                            let mut row_ok = true;
-                            for rv in &returns {
-                                match variable_kinds.get(rv) {
-                                    Some(VarKind::Identity) => {
-                                        if let Some(idt) = b.identities.get(rv) {
-                                            row.push(format!("{}", idt));
-                                            types_row.push("Thing".into());
-                                        } else if let Some(pid) = b.posit_vars.get(rv) {
-                                            row.push(format!("{}", pid));
-                                            types_row.push("Thing".into());
-                                        } else {
-                                            info!(target:"bareclad::stream", event="row_skip", reason="missing_identity", var=%rv);
-                                            row_ok = false;
-                                            break;
-                                        }
-                                    }
-                                    Some(VarKind::Value) | Some(VarKind::Time) => {
-                                        if let Some((pid, kind)) = b.value_slots.get(rv) {
-                                            if let Some(appset) = aset_guard.get(pid) {
-                                                let roles = appset.roles();
-                                                let allowed = tp_guard.lookup(&roles).clone();
-                                                let mut captured: Option<String> = None;
-                                                if *kind == VarKind::Time {
-                                                    if let Some(pt) = time_guard.get(pid) {
-                                                        captured = Some(format!("{}", pt));
-                                                        types_row.push("Time".into());
-                                                    } else {
-                                                        row_ok = false; // missing time (should not happen)
-                                                        break;
-                                                    }
-                                                } else {
-                                                    if allowed.contains("String") { if let Some(p) = pk_guard.posit::<String>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("String".into()); } }
-                                                    if captured.is_none() && allowed.contains("JSON") { if let Some(p) = pk_guard.posit::<JSON>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("JSON".into()); } }
-                                                    if captured.is_none() && allowed.contains("Decimal") { if let Some(p) = pk_guard.posit::<Decimal>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("Decimal".into()); } }
-                                                    if captured.is_none() && allowed.contains("i64") { if let Some(p) = pk_guard.posit::<i64>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("i64".into()); } }
-                                                    if captured.is_none() && allowed.contains("Certainty") { if let Some(p) = pk_guard.posit::<Certainty>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("Certainty".into()); } }
-                                                    if captured.is_none() && allowed.contains("Time") { if let Some(p) = pk_guard.posit::<Time>(*pid) { captured = Some(format!("{}", p.value())); types_row.push("Time".into()); } }
-                                                }
-                                                if let Some(cell) = captured {
-                                                    row.push(cell);
-                                                    if types_row.len() < row.len() { types_row.push("Unknown".into()); }
-                                                } else {
-                                                    info!(target:"bareclad::stream", event="row_skip", reason="no_capture", var=%rv);
-                                                    row_ok = false;
-                                                    break;
-                                                }
-                                            } else { // aset_guard.get(pid) None
-                                                info!(target:"bareclad::stream", event="row_skip", reason="missing_aset", pid=*pid, var=%rv);
-                                                row_ok = false;
-                                                break;
-                                            }
-                                        } else { // b.value_slots.get(rv) None
-                                            info!(target:"bareclad::stream", event="row_skip", reason="missing_value_slot", var=%rv);
-                                            row_ok = false;
-                                            break;
-                                        }
-                                    } // Value | Time
-                                    _ => {
-                                        info!(target:"bareclad::stream", event="row_skip", reason="unknown_kind", var=%rv);
-                                        row_ok = false;
-                                        break;
-                                    }
+                        Predicate::Value(lhs, op, rhs) => {
+                            // locate lhs posit/value; comparisons with null never hold
+                            if nullable_vars.contains(lhs) && !b.value_slots.contains_key(lhs) { return false; }
+                            let (pid, vkind) = if let Some(tup) = b.value_slots.get(lhs) { *tup } else { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Unknown variable in predicate: {}", lhs))); } return false; };
+                            if vkind != VarKind::Value { if exec_error.is_none() { *exec_error = Some(crate::error::BarecladError::Execution(format!("Non-value variable used in value predicate: {}", lhs))); } return false; }
+                            // Determine allowed types for this posit from the data types seen for its role set.
+                            let Some(appset) = aset_guard.get(&pid) else { return false };
+                            let allowed = tp_guard.lookup(&appset.roles()).clone();
+                            match compare_value(&mut pk_guard, &allowed, pid, lhs, op, rhs) {
+                                Ok(holds) => holds,
+                                Err(e) => {
+                                    if exec_error.is_none() { *exec_error = Some(e); }
+                                    false
                                 }
                             }
-                            if row_ok {
-                                if let SinkFlow::Stop = sink.push(row, types_row) { break; }
+                        }
+                    }
+                };
+                let implicit: Vec<Predicate> = where_time_var.iter().map(|(l, o, r)| Predicate::TimeVariables(l.clone(), o.clone(), r.clone())).collect();
+                // When profiling, each comparison counts the bindings it is evaluated for and those it rejects.
+                let profiled: Vec<&Predicate> = if profile.is_some() { predicates.iter().copied().chain(&implicit).collect() } else { Vec::new() };
+                let mut counts: Vec<(u64, u64, Duration)> = vec![(0, 0, Duration::ZERO); profiled.len()];
+                // A comparison under 'not' counts against a binding when it holds.
+                let mut negated: Vec<bool> = Vec::new();
+                if let Some(cond) = &where_condition { cond.negations(false, &mut negated); }
+                negated.resize(profiled.len(), false);
+                let mut failed: Vec<usize> = Vec::new();
+                bindings.retain(|b| {
+                    failed.clear();
+                    let mut check = |p: &Predicate| {
+                        let Some(i) = profiled.iter().position(|q| std::ptr::eq(*q, p)) else { return atom(b, p) };
+                        let started = Instant::now();
+                        let holds = atom(b, p);
+                        counts[i].0 += 1;
+                        counts[i].2 += started.elapsed();
+                        if holds == negated[i] { failed.push(i); }
+                        holds
+                    };
+                    // Implicit 'as of <variable>' comparisons are conjoined ahead of the written where clause.
+                    let keep = implicit.iter().all(&mut check) && where_condition.as_ref().is_none_or(|cond| cond.evaluate(&mut check));
+                    if !keep {
+                        for &i in &failed { counts[i].1 += 1; }
+                    }
+                    keep
+                });
+                if let Some(profile) = profile {
+                    profile.predicates = profiled.iter().zip(counts).map(|(p, (evaluated, filtered, elapsed))| PredicateProfile { predicate: p.to_string(), evaluated, filtered, elapsed }).collect();
+                }
+            }
+            if exec_error.is_some() { return; }
+            if bindings.is_empty() { return; }
+            let posit_keeper = self.database.posit_keeper();
+            let aset_lookup = self.database.posit_thing_to_appearance_set_lookup();
+            let type_partitions = self.database.role_name_to_data_type_lookup();
+            let time_lookup = self.database.posit_time_lookup();
+            let mut pk_guard = posit_keeper.lock().unwrap();
+            let aset_guard = aset_lookup.lock().unwrap();
+            let tp_guard = type_partitions.lock().unwrap();
+            let time_guard = time_lookup.lock().unwrap();
+
+            // Column-level inference removed; we now collect a per-row types vector.
+            // Emission handled after full clause scan; see post-clause block.
+            if !enumeration_started {
+                info!(target:"bareclad::stream", event="projection_skipped", reason="no_enumeration", any_clause_failed=any_clause_failed);
+                return;
+            }
+            // Resolve one variable of a binding, or a JSON path into it, into (display, type, typed value).
+            let mut capture = |b: &Binding, rv: &str, path: &[ast::PathStep]| -> Option<(String, String, CellValue)> {
+                if !path.is_empty() {
+                    // Paths into unbound variables and values other than JSON extract null.
+                    let json = match b.value_slots.get(rv) {
+                        Some((pid, VarKind::Value)) => pk_guard.posit::<JSON>(*pid),
+                        _ => None,
+                    };
+                    return Some(json.map(|p| json_path::extract(p.value(), path)).unwrap_or(json_path::Scalar::Null).cell());
+                }
+                if nullable_vars.contains(rv) && !b.identities.contains_key(rv) && !b.posit_vars.contains_key(rv) && !b.value_slots.contains_key(rv) {
+                    return Some((String::new(), "null".into(), CellValue::Null));
+                }
+                match variable_kinds.get(rv) {
+                    Some(VarKind::Identity) => {
+                        if let Some(idt) = b.identities.get(rv).or_else(|| b.posit_vars.get(rv)) {
+                            Some((format!("{}", idt), "Thing".into(), CellValue::Thing(*idt)))
+                        } else {
+                            info!(target:"bareclad::stream", event="row_skip", reason="missing_identity", var=%rv);
+                            None
+                        }
+                    }
+                    Some(VarKind::Value) | Some(VarKind::Time) => {
+                        let Some((pid, kind)) = b.value_slots.get(rv) else {
+                            info!(target:"bareclad::stream", event="row_skip", reason="missing_value_slot", var=%rv);
+                            return None;
+                        };
+                        let Some(appset) = aset_guard.get(pid) else {
+                            info!(target:"bareclad::stream", event="row_skip", reason="missing_aset", pid=*pid, var=%rv);
+                            return None;
+                        };
+                        if *kind == VarKind::Time {
+                            // missing time should not happen
+                            return time_guard.get(pid).map(|pt| (format!("{}", pt), "Time".into(), CellValue::Time(pt.clone())));
+                        }
+                        let roles = appset.roles();
+                        let allowed = tp_guard.lookup(&roles).clone();
+                        if allowed.contains("String") { if let Some(p) = pk_guard.posit::<String>(*pid) { return Some((format!("{}", p.value()), "String".into(), CellValue::Text(p.value().clone()))); } }
+                        if allowed.contains("JSON") { if let Some(p) = pk_guard.posit::<JSON>(*pid) { return Some((format!("{}", p.value()), "JSON".into(), CellValue::Other)); } }
+                        if allowed.contains("Decimal") { if let Some(p) = pk_guard.posit::<Decimal>(*pid) { return Some((format!("{}", p.value()), "Decimal".into(), CellValue::Decimal((**p.value()).clone()))); } }
+                        if allowed.contains("i64") { if let Some(p) = pk_guard.posit::<i64>(*pid) { return Some((format!("{}", p.value()), "i64".into(), CellValue::Int(*p.value()))); } }
+                        if allowed.contains("Certainty") { if let Some(p) = pk_guard.posit::<Certainty>(*pid) { return Some((format!("{}", p.value()), "Certainty".into(), CellValue::Certainty((f64::from(p.value()) * 100.0).round() as i32))); } }
+                        if allowed.contains("Time") { if let Some(p) = pk_guard.posit::<Time>(*pid) { return Some((format!("{}", p.value()), "Time".into(), CellValue::Time(p.value().clone()))); } }
+                        info!(target:"bareclad::stream", event="row_skip", reason="no_capture", var=%rv);
+                        None
+                    }
+                    _ => {
+                        info!(target:"bareclad::stream", event="row_skip", reason="unknown_kind", var=%rv);
+                        None
+                    }
+                }
+            };
+            // Rows awaiting emission with their sort keys; unordered plain searches stream directly instead.
+            let mut pending: Vec<PendingRow> = Vec::new();
+            if !has_aggregates {
+                'rows: for b in bindings.iter() {
+                    info!(target:"bareclad::stream", event="row_binding_iter", identities=b.identities.len(), value_slots=b.value_slots.len(), posit_vars=b.posit_vars.len());
+                    let mut row: Vec<String> = Vec::with_capacity(items.len());
+                    let mut types_row: Vec<String> = Vec::with_capacity(items.len());
+                    let mut values: Vec<CellValue> = Vec::with_capacity(items.len());
+                    for item in &items {
+                        let Some((cell, data_type, value)) = capture(b, &item.variable, &item.path) else { continue 'rows; };
+                        row.push(cell);
+                        types_row.push(data_type);
+                        values.push(value);
+                    }
+                    if order_keys.is_empty() {
+                        if let SinkFlow::Stop = sink.push(row, types_row) { break; }
+                        continue;
+                    }
+                    let mut sort_keys: Vec<(String, CellValue)> = Vec::with_capacity(order_keys.len());
+                    for (key, _) in &order_keys {
+                        match key {
+                            OrderKey::Column(i) => sort_keys.push((row[*i].clone(), values[*i].clone())),
+                            OrderKey::Variable(v, path) => {
+                                let Some((cell, _, value)) = capture(b, v, path) else { continue 'rows; };
+                                sort_keys.push((cell, value));
                             }
+                            OrderKey::GroupKey(_) => {}
+                        }
+                    }
+                    pending.push((row, types_row, sort_keys));
+                }
+            } else {
+                // Fold bindings into groups keyed by the group variables' values, in first-seen order.
+                let mut groups: Vec<Group> = Vec::new();
+                let mut group_index: HashMap<Vec<(String, String)>, usize> = HashMap::new();
+                'bindings: for b in bindings.iter() {
+                    let mut key: Vec<(String, String)> = Vec::with_capacity(group_keys.len());
+                    let mut key_values: Vec<CellValue> = Vec::with_capacity(group_keys.len());
+                    for (k, path) in &group_keys {
+                        let Some((cell, data_type, value)) = capture(b, k, path) else { continue 'bindings; };
+                        key.push((cell, data_type));
+                        key_values.push(value);
+                    }
+                    let mut args: Vec<(String, String, CellValue)> = Vec::new();
+                    for item in items.iter().filter(|i| i.aggregate.is_some()) {
+                        let Some(arg) = capture(b, &item.variable, &item.path) else { continue 'bindings; };
+                        args.push(arg);
+                    }
+                    let gi = *group_index.entry(key.clone()).or_insert_with(|| {
+                        let accumulators = items.iter().filter_map(|i| i.aggregate.map(|a| Accumulator::new(a.function, a.distinct, &ast::path_text(&i.variable, &i.path)))).collect();
+                        groups.push((key, key_values, accumulators));
+                        groups.len() - 1
+                    });
+                    for (acc, (text, data_type, value)) in groups[gi].2.iter_mut().zip(args.iter()) {
+                        if let Err(msg) = acc.add(text, data_type, value) {
+                            *exec_error = Some(crate::error::BarecladError::Execution(msg));
+                            return;
                         }
-                        info!(target:"bareclad::stream", event="projection_complete");
-                        return;
                     }
-                } // end Rule::return_clause branch
-                Rule::limit_clause => { /* ignored here */ }
-                _ => println!("Unknown clause: {:?}", clause),
+                }
+                for (key, key_values, accumulators) in groups {
+                    let mut row: Vec<String> = Vec::with_capacity(items.len());
+                    let mut types_row: Vec<String> = Vec::with_capacity(items.len());
+                    let mut values: Vec<CellValue> = Vec::with_capacity(items.len());
+                    let mut aggregates = accumulators.iter();
+                    for item in &items {
+                        let (cell, data_type, value) = if item.aggregate.is_some() {
+                            aggregates.next().map(|a| a.finish()).unwrap_or((String::new(), "Unknown".into(), CellValue::Other))
+                        } else {
+                            let pos = group_keys.iter().position(|(k, path)| *k == item.variable && *path == item.path).unwrap_or(0);
+                            (key[pos].0.clone(), key[pos].1.clone(), key_values[pos].clone())
+                        };
+                        row.push(cell);
+                        types_row.push(data_type);
+                        values.push(value);
+                    }
+                    let sort_keys: Vec<(String, CellValue)> = order_keys.iter().map(|(k, _)| match k {
+                        OrderKey::Column(i) => (row[*i].clone(), values[*i].clone()),
+                        OrderKey::GroupKey(i) => (key[*i].0.clone(), key_values[*i].clone()),
+                        OrderKey::Variable(..) => (String::new(), CellValue::Other),
+                    }).collect();
+                    pending.push((row, types_row, sort_keys));
+                }
+            }
+            // Stable sort keeps enumeration order among ties; the sink applies any limit after ordering.
+            if !order_keys.is_empty() {
+                pending.sort_by(|a, b| {
+                    for (i, (_, descending)) in order_keys.iter().enumerate() {
+                        let o = order_values(&a.2[i], &b.2[i]);
+                        let o = if *descending { o.reverse() } else { o };
+                        if o != std::cmp::Ordering::Equal { return o; }
+                    }
+                    std::cmp::Ordering::Equal
+                });
+            }
+            for (row, types_row, _) in pending {
+                if let SinkFlow::Stop = sink.push(row, types_row) { break; }
             }
+            info!(target:"bareclad::stream", event="projection_complete");
         }
     }
     // Backwards compatible wrapper retaining original signature (prints rows)
-    fn search_print(&self, command: Pair<Rule>, variables: &mut Variables) {
+    fn search_print(&self, command: &ast::Search, variables: &mut Variables) {
         let mut cols=None; let mut err=None; struct PrintSink; impl RowSink for PrintSink { fn push(&mut self, row: Vec<String>, _types: Vec<String>) -> SinkFlow { println!("{}", row.join(", ")); SinkFlow::Continue } } let mut ps=PrintSink; self.search(command, variables, &mut ps, &mut cols, &mut err); if let Some(e)=err { eprintln!("{}", e); }
     }
+    fn check_consistency_print(&self, command: &ast::CheckConsistency, variables: &Variables) {
+        let mut cols=None; struct PrintSink; impl RowSink for PrintSink { fn push(&mut self, row: Vec<String>, _types: Vec<String>) -> SinkFlow { println!("{}", row.join(", ")); SinkFlow::Continue } } let mut ps=PrintSink; if let Err(e) = self.check_consistency(command, variables, &mut ps, &mut cols) { eprintln!("{}", e); }
+    }
+    fn explain_print(&self, script: &ast::Script, command: &ast::Explain, variables: &mut Variables) {
+        let mut cols=None; struct PrintSink; impl RowSink for PrintSink { fn push(&mut self, row: Vec<String>, _types: Vec<String>) -> SinkFlow { println!("{}", row.join(", ")); SinkFlow::Continue } } let mut ps=PrintSink; if let Err(e) = self.explain(script, command, variables, &mut ps, &mut cols) { eprintln!("{}", e); }
+    }
     /// Parse and execute a Traqula script (one or more commands).
     pub fn execute(&self, traqula: &str) {
         let mut variables: Variables = Variables::default();
-        let parse_result = TraqulaParser::parse(Rule::traqula, traqula.trim());
-        let traqula = match parse_result {
-            Ok(pairs) => pairs,
-            Err(err) => {
+        let script = match parse_unbound(traqula) {
+            Ok(script) => script,
+            Err(e) => {
                 // Print a helpful parse error with expected tokens and context
-                eprintln!("Traqula parse error:\n{}", err);
-                if let ErrorVariant::ParsingError {
-                    positives,
-                    negatives: _,
-                } = err.variant
-                {
-                    if !positives.is_empty() {
-                        let mut expected: Vec<&'static str> =
-                            positives.iter().map(|r| friendly_rule_name(*r)).collect();
-                        expected.sort();
-                        expected.dedup();
-                        eprintln!("Expected one of: {}", expected.join(", "));
-                    }
-                }
+                eprintln!("Traqula parse error:\n{}", e);
                 return;
             }
         };
-        for command in traqula {
-            match command.as_rule() {
-                Rule::add_role => self.add_role(command),
-                Rule::add_posit => self.add_posit(command, &mut variables),
-                Rule::search => { // reset limit per search
-                    self.search_print(command, &mut variables);
-                },
-                Rule::EOI => (), // end of input
-                _ => println!("Unknown command: {:?}", command),
+        let result = self.run_script(&script, || {
+            for command in &script.commands {
+                let result = match command {
+                    ast::Command::AddRole(command) => self.add_role(command),
+                    ast::Command::AddPosit(command) => self.add_posit(command, &mut variables),
+                    ast::Command::AddAssertion(command) => self.add_assertion(command, &mut variables),
+                    ast::Command::DefineSearch(command) => self.define_search(command),
+                    ast::Command::Search(command) => { self.search_print(command, &mut variables); Ok(()) }
+                    ast::Command::CheckConsistency(command) => { self.check_consistency_print(command, &variables); Ok(()) }
+                    ast::Command::Explain(command) => { self.explain_print(&script, command, &mut variables); Ok(()) }
+                    ast::Command::Transaction(command) => self.transaction(command, &mut variables),
+                    ast::Command::Load(command) => self.load(command, &mut variables),
+                };
+                if let Err(e) = result {
+                    // The rest of a failed transaction is abandoned, so that it is rolled back as a whole.
+                    if self.database.in_transaction() {
+                        return Err(e);
+                    }
+                    eprintln!("{}", e);
+                }
             }
-        }
+            Ok(())
+        });
+        if let Err(e) = result { eprintln!("{}", e); }
         // suppressed variable dump in release/normal runs
     }
 
     /// Execute a script and collect printed row outputs (one Vec<String> per returned row).
     /// This is a stop-gap until the search pipeline is refactored to emit structured rows directly.
     pub fn execute_collect(&self, traqula: &str) -> Result<CollectedResult, crate::error::BarecladError> {
+        self.collect_script(&parse_unbound(traqula)?)
+    }
+
+    /// Execute a parsed script, collecting rows like [`Engine::execute_collect`].
+    fn collect_script(&self, script: &ast::Script) -> Result<CollectedResult, crate::error::BarecladError> {
         let mut variables: Variables = Variables::default();
         struct CollectSink { rows: Vec<Vec<String>>, types: Vec<Vec<String>>, limit: Option<usize>, limited: bool }
-        impl RowSink for CollectSink { fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { if let Some(l) = self.limit { if self.rows.len() >= l { self.limited = true; return SinkFlow::Stop; } } self.rows.push(row); self.types.push(types); if let Some(l)=self.limit { if self.rows.len() >= l { self.limited = true; return SinkFlow::Stop; } } SinkFlow::Continue } }
+        impl RowSink for CollectSink { fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { if let Some(l) = self.limit && self.rows.len() >= l { self.limited = true; return SinkFlow::Stop; } self.rows.push(row); self.types.push(types); if let Some(l) = self.limit && self.rows.len() >= l { self.limited = true; return SinkFlow::Stop; } SinkFlow::Continue } }
         let mut collector = CollectSink { rows: Vec::new(), types: Vec::new(), limit: None, limited: false };
         let mut return_columns: Option<Vec<String>> = None;
-        // grammar now supports optional limit clause; parse directly
-        let parse_result = TraqulaParser::parse(Rule::traqula, traqula.trim());
-        let traqula = match parse_result {
-            Ok(pairs) => pairs,
-            Err(err) => {
-                let mut msg = format!("{}", err);
-                if let ErrorVariant::ParsingError { positives, negatives: _ } = err.variant {
-                    if !positives.is_empty() {
-                        let mut expected: Vec<&'static str> = positives.iter().map(|r| friendly_rule_name(*r)).collect();
-                        expected.sort(); expected.dedup();
-                        msg.push_str(&format!("\nExpected one of: {}", expected.join(", ")));
-                    }
-                }
-                return Err(crate::error::BarecladError::Parse { message: msg, line: None, col: None });
-            }
-        };
         let mut search_count = 0usize;
-        for command in traqula {
-            match command.as_rule() {
-                Rule::add_role => self.add_role(command),
-                Rule::add_posit => self.add_posit(command, &mut variables),
-                Rule::search => {
+        self.run_script(script, || {
+        for command in &script.commands {
+            match command {
+                ast::Command::AddRole(command) => self.add_role(command)?,
+                ast::Command::AddPosit(command) => self.add_posit(command, &mut variables)?,
+                ast::Command::AddAssertion(command) => self.add_assertion(command, &mut variables)?,
+                ast::Command::DefineSearch(command) => self.define_search(command)?,
+                ast::Command::Search(command) => {
                     search_count += 1;
-                    // Extract per-search limit and install into sink (overwrite any prior; only meaningful when one search in script)
-                    let limit = { let mut l=None; let cloned=command.clone(); for c in cloned.into_inner(){ if c.as_rule()==Rule::limit_clause { for p in c.into_inner(){ if let Ok(v)=p.as_str().parse::<usize>() { l=Some(v);} } } } l };
-                    collector.limit = limit;
+                    // Install the per-search limit into the sink (overwrite any prior; only meaningful when one search in script)
+                    collector.limit = command.limit;
                     let mut err=None; self.search(command, &mut variables, &mut collector, &mut return_columns, &mut err); if let Some(e)=err { return Err(e); }
                 }
-                Rule::EOI => (),
-                _ => (),
+                ast::Command::CheckConsistency(command) => {
+                    search_count += 1;
+                    collector.limit = None;
+                    self.check_consistency(command, &variables, &mut collector, &mut return_columns)?;
+                }
+                ast::Command::Explain(command) => {
+                    search_count += 1;
+                    collector.limit = None;
+                    self.explain(script, command, &mut variables, &mut collector, &mut return_columns)?;
+                }
+                ast::Command::Transaction(command) => self.transaction(command, &mut variables)?,
+                ast::Command::Load(command) => self.load(command, &mut variables)?,
             }
         }
+        Ok(())
+        })?;
         let cols = return_columns.unwrap_or_default();
         let row_count = collector.rows.len();
         let limited = search_count == 1 && collector.limited;
         Ok(CollectedResult { columns: cols, rows: collector.rows, row_types: collector.types, row_count, limited })
     }
 
+    /// Bind `$name` placeholders to typed values and execute the script like [`Engine::execute_collect`].
+    /// Values are bound into the parsed script, so they can never change its structure. Placeholders in time
+    /// and certainty positions also accept strings in those formats (`'2024-01-01'` without quotes, `75%`).
+    /// Missing and mistyped parameters are parse errors at the position of the placeholder; unused parameters
+    /// are ignored.
+    pub fn execute_with_params(&self, traqula: &str, params: &HashMap<String, Param>) -> Result<CollectedResult, crate::error::BarecladError> {
+        self.collect_script(&PreparedScript::new(traqula)?.bound_script(params)?)
+    }
+
+    /// Parse and validate a script once for repeated execution with [`Engine::execute_prepared`].
+    pub fn prepare(&self, traqula: &str) -> Result<PreparedScript, crate::error::BarecladError> {
+        PreparedScript::new(traqula)
+    }
+
+    /// Execute a prepared script with bound parameters, collecting rows like [`Engine::execute_collect`].
+    pub fn execute_prepared(&self, prepared: &PreparedScript, params: &HashMap<String, Param>) -> Result<CollectedResult, crate::error::BarecladError> {
+        self.collect_script(&prepared.bound_script(params)?)
+    }
+
+    /// Execute a prepared script with bound parameters, collecting one result set per search like [`Engine::execute_collect_multi`].
+    pub fn execute_prepared_multi(&self, prepared: &PreparedScript, params: &HashMap<String, Param>) -> Result<Vec<CollectedResultSet>, crate::error::BarecladError> {
+        self.collect_script_multi(&prepared.bound_script(params)?)
+    }
+
     /// Execute a script and collect separate result sets for each search command.
     /// This provides the foundation for a multi-result JSON protocol.
     pub fn execute_collect_multi(&self, traqula: &str) -> Result<Vec<CollectedResultSet>, crate::error::BarecladError> {
+        self.collect_script_multi(&parse_unbound(traqula)?)
+    }
+
+    /// Execute a parsed script, collecting one result set per search like [`Engine::execute_collect_multi`].
+    fn collect_script_multi(&self, script: &ast::Script) -> Result<Vec<CollectedResultSet>, crate::error::BarecladError> {
         let mut variables: Variables = Variables::default();
-        // Parse once
-        let parse_result = TraqulaParser::parse(Rule::traqula, traqula.trim());
-        let traqula = match parse_result {
-            Ok(pairs) => pairs,
-            Err(err) => {
-                let mut msg = format!("{}", err);
-                if let ErrorVariant::ParsingError { positives, negatives: _ } = err.variant {
-                    if !positives.is_empty() {
-                        let mut expected: Vec<&'static str> = positives.iter().map(|r| friendly_rule_name(*r)).collect();
-                        expected.sort(); expected.dedup();
-                        msg.push_str(&format!("\nExpected one of: {}", expected.join(", ")));
-                    }
-                }
-                return Err(crate::error::BarecladError::Parse { message: msg, line: None, col: None });
-            }
-        };
         let mut results: Vec<CollectedResultSet> = Vec::new();
-        for command in traqula {
-            match command.as_rule() {
-                Rule::add_role => self.add_role(command),
-                Rule::add_posit => self.add_posit(command, &mut variables),
-                Rule::search => {
-                    struct LocalSink { rows: Vec<Vec<String>>, types: Vec<Vec<String>>, limit: Option<usize>, limited: bool }
-                    impl RowSink for LocalSink { fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { if let Some(l)=self.limit { if self.rows.len() >= l { self.limited=true; return SinkFlow::Stop; }} self.rows.push(row); self.types.push(types); if let Some(l)=self.limit { if self.rows.len() >= l { self.limited=true; return SinkFlow::Stop; }} SinkFlow::Continue } }
-                    let mut sink = LocalSink { rows: Vec::new(), types: Vec::new(), limit: None, limited:false };
-                    // Capture raw search text before moving command into search execution
-                    let raw_search_string = command.as_str().trim().to_string();
-                    sink.limit = { let mut l=None; let cloned=command.clone(); for c in cloned.into_inner(){ if c.as_rule()==Rule::limit_clause { for p in c.into_inner(){ if let Ok(v)=p.as_str().parse::<usize>() { l=Some(v);} } } } l };
+        struct LocalSink { rows: Vec<Vec<String>>, types: Vec<Vec<String>>, limit: Option<usize>, limited: bool }
+        impl RowSink for LocalSink { fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { if let Some(l) = self.limit && self.rows.len() >= l { self.limited=true; return SinkFlow::Stop; } self.rows.push(row); self.types.push(types); if let Some(l) = self.limit && self.rows.len() >= l { self.limited=true; return SinkFlow::Stop; } SinkFlow::Continue } }
+        self.run_script(script, || {
+        for command in &script.commands {
+            match command {
+                ast::Command::AddRole(command) => self.add_role(command)?,
+                ast::Command::AddPosit(command) => self.add_posit(command, &mut variables)?,
+                ast::Command::AddAssertion(command) => self.add_assertion(command, &mut variables)?,
+                ast::Command::DefineSearch(command) => self.define_search(command)?,
+                ast::Command::Search(command) => {
+                    let mut sink = LocalSink { rows: Vec::new(), types: Vec::new(), limit: command.limit, limited:false };
+                    let raw_search_string = script.text(command.span).trim().to_string();
                     let mut local_return_columns: Option<Vec<String>> = None;
                     let mut err=None; self.search(command, &mut variables, &mut sink, &mut local_return_columns, &mut err); if let Some(e)=err { return Err(e); }
                     let cols = local_return_columns.unwrap_or_default();
@@ -2413,58 +3824,73 @@ impl<'en> Engine<'en> {
                     let preserve: std::collections::HashSet<&str> = ["idw","idh"].into_iter().collect();
                     variables.retain(|k,_| preserve.contains(k.as_str()));
                 }
-                Rule::EOI => (),
-                _ => (),
+                ast::Command::CheckConsistency(command) => {
+                    let mut sink = LocalSink { rows: Vec::new(), types: Vec::new(), limit: None, limited:false };
+                    let raw_text = script.text(command.span).trim().to_string();
+                    let mut local_return_columns: Option<Vec<String>> = None;
+                    self.check_consistency(command, &variables, &mut sink, &mut local_return_columns)?;
+                    let row_count = sink.rows.len();
+                    results.push(CollectedResultSet { columns: local_return_columns.unwrap_or_default(), rows: sink.rows, row_types: sink.types, row_count, limited: false, search: Some(raw_text) });
+                }
+                ast::Command::Explain(command) => {
+                    let mut sink = LocalSink { rows: Vec::new(), types: Vec::new(), limit: None, limited:false };
+                    let raw_text = script.text(command.span).trim().to_string();
+                    let mut local_return_columns: Option<Vec<String>> = None;
+                    self.explain(script, command, &mut variables, &mut sink, &mut local_return_columns)?;
+                    let row_count = sink.rows.len();
+                    results.push(CollectedResultSet { columns: local_return_columns.unwrap_or_default(), rows: sink.rows, row_types: sink.types, row_count, limited: false, search: Some(raw_text) });
+                }
+                ast::Command::Transaction(command) => self.transaction(command, &mut variables)?,
+                ast::Command::Load(command) => self.load(command, &mut variables)?,
             }
         }
+        Ok(())
+        })?;
         Ok(results)
     }
 
     /// Execute a script containing multiple searches (>=1) and stream each result set with framing callbacks.
     /// Maintains standard variable scoping semantics across searches.
     pub fn execute_stream_multi<C: MultiStreamCallbacks>(&self, traqula: &str, callbacks: &mut C) -> Result<(), crate::error::BarecladError> {
+        self.stream_script_multi(&parse_unbound(traqula)?, callbacks)
+    }
+
+    /// Execute a prepared script with bound parameters, streaming each result set like [`Engine::execute_stream_multi`].
+    pub fn execute_prepared_stream_multi<C: MultiStreamCallbacks>(&self, prepared: &PreparedScript, params: &HashMap<String, Param>, callbacks: &mut C) -> Result<(), crate::error::BarecladError> {
+        self.stream_script_multi(&prepared.bound_script(params)?, callbacks)
+    }
+
+    /// Stream a parsed script like [`Engine::execute_stream_multi`].
+    fn stream_script_multi<C: MultiStreamCallbacks>(&self, script: &ast::Script, callbacks: &mut C) -> Result<(), crate::error::BarecladError> {
         let mut variables: Variables = Variables::default();
-        let parse_result = TraqulaParser::parse(Rule::traqula, traqula.trim());
-        let pairs = match parse_result {
-            Ok(p) => p,
-            Err(err) => {
-                let mut msg = format!("{}", err);
-                if let ErrorVariant::ParsingError { positives, negatives: _ } = err.variant {
-                    if !positives.is_empty() {
-                        let mut expected: Vec<&'static str> = positives.iter().map(|r| friendly_rule_name(*r)).collect();
-                        expected.sort(); expected.dedup();
-                        msg.push_str(&format!("\nExpected one of: {}", expected.join(", ")));
-                    }
-                }
-                return Err(crate::error::BarecladError::Parse { message: msg, line: None, col: None });
-            }
-        };
         let mut set_index = 0usize;
-        for command in pairs { match command.as_rule() {
-            Rule::add_role => self.add_role(command),
-            Rule::add_posit => self.add_posit(command, &mut variables),
-            Rule::search => {
-                // Extract limit for this search
-                let search_text_full = command.as_str().trim().to_string();
-                let mut limit=None; let cloned=command.clone(); for c in cloned.into_inner(){ if c.as_rule()==Rule::limit_clause { for p in c.into_inner(){ if let Ok(v)=p.as_str().parse::<usize>() { limit=Some(v);} } } }
-                // Per-set sink bridging to callbacks
-                struct SetSink<'a, C: MultiStreamCallbacks> { cb: &'a mut C, idx: usize, started: bool, search_text: &'a str }
-                impl<'a, C: MultiStreamCallbacks> RowSink for SetSink<'a, C> {
-                    fn on_meta(&mut self, columns: &[String]) -> SinkFlow { self.started=true; info!(target:"bareclad::stream", event="set_meta", set_index=self.idx, cols=?columns, search=?self.search_text); self.cb.on_result_set_start(self.idx, columns, self.search_text); SinkFlow::Continue }
-                    fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { if self.cb.on_row(self.idx, row, types) { SinkFlow::Continue } else { SinkFlow::Stop } }
-                }
+        // Per-set sink bridging to callbacks
+        struct SetSink<'a, C: MultiStreamCallbacks> { cb: &'a mut C, idx: usize, started: bool, search_text: &'a str, count: usize }
+        impl<'a, C: MultiStreamCallbacks> RowSink for SetSink<'a, C> {
+            fn on_meta(&mut self, columns: &[String]) -> SinkFlow { self.started=true; info!(target:"bareclad::stream", event="set_meta", set_index=self.idx, cols=?columns, search=?self.search_text); self.cb.on_result_set_start(self.idx, columns, self.search_text); SinkFlow::Continue }
+            fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow { self.count+=1; if self.cb.on_row(self.idx, row, types) { SinkFlow::Continue } else { SinkFlow::Stop } }
+        }
+        self.run_script(script, || {
+        for command in &script.commands { match command {
+            ast::Command::AddRole(command) => self.add_role(command)?,
+            ast::Command::AddPosit(command) => self.add_posit(command, &mut variables)?,
+            ast::Command::AddAssertion(command) => self.add_assertion(command, &mut variables)?,
+            ast::Command::DefineSearch(command) => self.define_search(command)?,
+            ast::Command::Search(command) => {
+                let search_text_full = script.text(command.span).trim().to_string();
+                let limit = command.limit;
                 struct CountingSetSink<'a, C: MultiStreamCallbacks> { inner: SetSink<'a, C>, limit: Option<usize>, count: usize, limited: bool }
                 impl<'a, C: MultiStreamCallbacks> RowSink for CountingSetSink<'a, C> {
                     fn on_meta(&mut self, columns: &[String]) -> SinkFlow { self.inner.on_meta(columns) }
                     fn push(&mut self, row: Vec<String>, types: Vec<String>) -> SinkFlow {
-                        if let Some(l)=self.limit { if self.count>=l { self.limited=true; return SinkFlow::Stop; } }
+                        if let Some(l) = self.limit && self.count>=l { self.limited=true; return SinkFlow::Stop; }
                         match self.inner.push(row, types) {
-                            SinkFlow::Continue => { self.count+=1; if let Some(l)=self.limit { if self.count>=l { self.limited=true; return SinkFlow::Stop; } } SinkFlow::Continue },
+                            SinkFlow::Continue => { self.count+=1; if let Some(l) = self.limit && self.count>=l { self.limited=true; return SinkFlow::Stop; } SinkFlow::Continue },
                             stop => stop,
                         }
                     }
                 }
-                let mut sink = CountingSetSink { inner: SetSink { cb: callbacks, idx: set_index, started:false, search_text: &search_text_full }, limit, count:0, limited:false };
+                let mut sink = CountingSetSink { inner: SetSink { cb: callbacks, idx: set_index, started:false, search_text: &search_text_full, count:0 }, limit, count:0, limited:false };
                 let mut return_columns: Option<Vec<String>> = None; // ignored here beyond meta
                 let mut err=None;
                 self.search(command, &mut variables, &mut sink, &mut return_columns, &mut err);
@@ -2482,10 +3908,29 @@ impl<'en> Engine<'en> {
                 }
                 set_index +=1;
             }
-            Rule::EOI => (),
-            _ => (),
+            ast::Command::CheckConsistency(command) => {
+                let text = script.text(command.span).trim().to_string();
+                let mut sink = SetSink { cb: callbacks, idx: set_index, started:false, search_text: &text, count:0 };
+                let mut return_columns: Option<Vec<String>> = None;
+                self.check_consistency(command, &variables, &mut sink, &mut return_columns)?;
+                let row_count = sink.count;
+                callbacks.on_result_set_end(set_index, row_count, false);
+                set_index +=1;
+            }
+            ast::Command::Explain(command) => {
+                let text = script.text(command.span).trim().to_string();
+                let mut sink = SetSink { cb: callbacks, idx: set_index, started:false, search_text: &text, count:0 };
+                let mut return_columns: Option<Vec<String>> = None;
+                self.explain(script, command, &mut variables, &mut sink, &mut return_columns)?;
+                let row_count = sink.count;
+                callbacks.on_result_set_end(set_index, row_count, false);
+                set_index +=1;
+            }
+            ast::Command::Transaction(command) => self.transaction(command, &mut variables)?,
+            ast::Command::Load(command) => self.load(command, &mut variables)?,
         }}
         Ok(())
+        })
     }
 }
 
@@ -2495,6 +3940,7 @@ fn friendly_rule_name(rule: Rule) -> &'static str {
         Rule::traqula => "Traqula script",
         Rule::add_role => "add role",
         Rule::add_posit => "add posit",
+        Rule::add_assertion => "add assertion",
         Rule::search => "search",
         Rule::search_clause => "search clause",
         Rule::where_clause => "where clause",
@@ -2515,12 +3961,49 @@ fn friendly_rule_name(rule: Rule) -> &'static str {
         Rule::json => "JSON literal",
         Rule::string => "string literal",
         Rule::int => "integer literal",
+        Rule::boolean => "boolean literal (true/false)",
         Rule::decimal => "decimal literal",
         Rule::certainty => "certainty (e.g., 100%)",
         Rule::time => "time literal (e.g., 'YYYY-MM-DD')",
         Rule::constant => "time constant (@NOW/@BOT/@EOT)",
+        Rule::param => "parameter ($name)",
         Rule::as_of_clause => "as of <time> or <variable>",
+        Rule::certainty_clause => "by <positor> certainty <comparator> <certainty>",
+        Rule::check_consistency => "check consistency",
+        Rule::consistency_scope => "consistency scope [{(...)}, *, *]",
         Rule::comparator => "comparator (<, <=, >, >=, =, ==)",
+        Rule::condition | Rule::negation | Rule::conjunction | Rule::disjunction => "condition (e.g., x = 1)",
+        Rule::between_condition => "between condition (e.g., t between '2020' and '2021')",
+        Rule::in_condition => "in condition (e.g., s in (\"open\", \"closed\"))",
+        Rule::matches_condition => "matches condition (e.g., r matches \"phase shift*\")",
+        Rule::like_condition => "like condition (e.g., n like \"Ca%\")",
+        Rule::regex_condition => "regex condition (e.g., n ~ \"^Ca\")",
+        Rule::path_condition => "JSON path condition (e.g., g->\"revision\" > 2)",
+        Rule::json_path => "JSON path (e.g., g->\"name\")",
+        Rule::path_index => "array index",
+        Rule::define_search => "define search <name> as search ...",
+        Rule::subquery => "search ... return <variable>",
+        Rule::set_reference => "named search or (search ... return <variable>)",
+        Rule::or_kw => "or",
+        Rule::and_kw => "and",
+        Rule::not_kw => "not",
+        Rule::between_kw => "between",
+        Rule::in_kw => "in",
+        Rule::matches_kw => "matches",
+        Rule::like_kw => "like",
+        Rule::optional_kw => "optional",
+        Rule::explain => "explain [analyze] search ...",
+        Rule::analyze_kw => "analyze",
+        Rule::transaction => "begin, commit or rollback",
+        Rule::begin_kw => "begin",
+        Rule::commit_kw => "commit",
+        Rule::rollback_kw => "rollback",
+        Rule::load => "load [...] values ($name, ...) (...)",
+        Rule::load_kw => "load",
+        Rule::values_kw => "values",
+        Rule::load_columns => "placeholders ($name, ...)",
+        Rule::load_row => "row of literals (...)",
+        Rule::row_value => "literal",
         _ => "token",
     }
 }
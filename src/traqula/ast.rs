@@ -0,0 +1,857 @@
+//! Typed syntax tree of Traqula scripts.
+//!
+//! [`parse`] turns a script into a [`Script`]: its commands with their patterns, predicates and
+//! projections, each node carrying the [`Span`] of the text it was parsed from. The
+//! [`Engine`](super::Engine) executes this tree; tools such as formatters and linters can walk it
+//! without depending on the grammar.
+//!
+//! ```
+//! use bareclad::traqula::ast::{self, Command, Term};
+//! let script = ast::parse("search [{(+p, name)}, +n, *] return n;").unwrap();
+//! let Command::Search(search) = &script.commands[0] else { unreachable!() };
+//! assert_eq!(search.patterns[0].value, Term::Insert("n".into()));
+//! assert_eq!(script.text(search.patterns[0].span), "[{(+p, name)}, +n, *]");
+//! ```
+use std::fmt;
+
+use pest::Parser;
+use pest::error::{ErrorVariant, LineColLocation};
+use pest::iterators::Pair;
+
+use super::{Rule, TraqulaParser, friendly_rule_name};
+use crate::error::BarecladError;
+
+/// Location of a node in the parsed text: byte offsets and the 1-based line and column of its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+impl Span {
+    /// The span of a pair without the whitespace that non-atomic rules consume after it.
+    fn of(pair: &Pair<Rule>) -> Self {
+        let span = pair.as_span();
+        // The pair looks its position up in a line index, where the span would scan the whole text before it.
+        let (line, col) = pair.line_col();
+        Span { start: span.start(), end: span.start() + span.as_str().trim_end().len(), line, col }
+    }
+}
+
+/// A parsed script together with the text it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script {
+    pub source: String,
+    pub commands: Vec<Command>,
+}
+impl Script {
+    /// The source text covered by `span`.
+    pub fn text(&self, span: Span) -> &str {
+        &self.source[span.start..span.end]
+    }
+}
+
+/// One semicolon-terminated command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    AddRole(AddRole),
+    AddPosit(AddPosit),
+    AddAssertion(AddAssertion),
+    DefineSearch(DefineSearch),
+    Search(Search),
+    CheckConsistency(CheckConsistency),
+    Explain(Explain),
+    Transaction(Transaction),
+    Load(Load),
+}
+impl Command {
+    pub fn span(&self) -> Span {
+        match self {
+            Command::AddRole(c) => c.span,
+            Command::AddPosit(c) => c.span,
+            Command::AddAssertion(c) => c.span,
+            Command::DefineSearch(c) => c.span,
+            Command::Search(c) => c.span,
+            Command::CheckConsistency(c) => c.span,
+            Command::Explain(c) => c.span,
+            Command::Transaction(c) => c.span,
+            Command::Load(c) => c.span,
+        }
+    }
+}
+
+/// `add role name, age;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddRole {
+    pub roles: Vec<String>,
+    pub span: Span,
+}
+
+/// `add posit [...], [...];`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddPosit {
+    pub posits: Vec<Posit>,
+    pub span: Span,
+}
+
+/// `+p [{(+a, name)}, "Archie", '2020-01-01']` in an `add posit` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posit {
+    /// Variable bound to the created posits (`+p`).
+    pub variable: Option<String>,
+    pub appearances: Vec<Appearance>,
+    pub value: Literal,
+    pub time: Literal,
+    pub span: Span,
+}
+
+/// `(+a, name)` or `(a, name)`; the thing is a [`Term::Insert`] or [`Term::Recall`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Appearance {
+    pub thing: Term,
+    pub role: String,
+    pub span: Span,
+}
+
+/// `add assertion [...], [...];`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AddAssertion {
+    pub assertions: Vec<Assertion>,
+    pub span: Span,
+}
+
+/// `+a [positor, posit, 75%, '2021-01-01']`; the positor is a [`Term::Insert`] or [`Term::Recall`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assertion {
+    /// Variable bound to the created assertions (`+a`).
+    pub variable: Option<String>,
+    pub positor: Term,
+    pub posit: String,
+    pub certainty: Literal,
+    pub time: Literal,
+    pub span: Span,
+}
+
+/// `load [{(+a, name)}, $name, $since] values ($name, $since) ("Archie", '2020-01-01'), ...;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Load {
+    /// Templates added once per row.
+    pub posits: Vec<Posit>,
+    /// Names of the placeholders bound by each row, without `$`.
+    pub columns: Vec<String>,
+    /// Literals of each row, one per column.
+    pub rows: Vec<Vec<Literal>>,
+    pub span: Span,
+}
+
+/// `define search name as search ... return x;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineSearch {
+    pub name: String,
+    pub query: Search,
+    pub span: Span,
+}
+
+/// A `search` command, the query of a named search, or a subquery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Search {
+    pub patterns: Vec<Pattern>,
+    /// The `where` clause.
+    pub condition: Option<Condition>,
+    pub returns: Vec<ReturnItem>,
+    /// Keys listed in `group by`.
+    pub group_by: Option<Vec<String>>,
+    pub order_by: Vec<OrderItem>,
+    pub limit: Option<usize>,
+    pub span: Span,
+}
+
+/// `[optional|not] [+p|p] [{...}, value, time] [as of ...] [by ... certainty ...]` in a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub optional: bool,
+    pub negated: bool,
+    /// Posit variable in front of the brackets, a [`Term::Insert`] or [`Term::Recall`].
+    pub posit: Option<Term>,
+    /// Appearances between the braces; empty for `*`.
+    pub appearances: Vec<SearchAppearance>,
+    pub value: Term,
+    pub time: Term,
+    /// `as of <time>` or `as of <variable>`.
+    pub as_of: Option<Term>,
+    pub certainty: Option<CertaintyCondition>,
+    pub span: Span,
+}
+
+/// `(+a, name)`, `(a|b, name)` or `(*, *)` in a search pattern or consistency scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchAppearance {
+    pub thing: Term,
+    /// `None` for `*`.
+    pub role: Option<String>,
+    pub span: Span,
+}
+
+/// A position that takes a wildcard, a variable or a literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Wildcard,
+    /// `+x`, binding a new variable.
+    Insert(String),
+    /// `x`, using a bound variable.
+    Recall(String),
+    /// `a|b`, any of the bound variables.
+    Union(Vec<String>),
+    Literal(Literal),
+}
+
+/// `by <positor|*> certainty <comparator> <certainty>` after a search pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertaintyCondition {
+    /// `None` for `by *`.
+    pub positor: Option<String>,
+    pub comparator: Comparator,
+    pub certainty: Literal,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// `=`
+    Equal,
+    /// `==`
+    DoubleEqual,
+}
+impl Comparator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Comparator::Less => "<",
+            Comparator::LessOrEqual => "<=",
+            Comparator::Greater => ">",
+            Comparator::GreaterOrEqual => ">=",
+            Comparator::Equal => "=",
+            Comparator::DoubleEqual => "==",
+        }
+    }
+}
+
+/// Boolean expression of a `where` clause. Parentheses only group, so they leave no node of their own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Or(Vec<Condition>),
+    And(Vec<Condition>),
+    Not(Box<Condition>),
+    /// `x < 5`, `x = y`
+    Compare { variable: String, comparator: Comparator, operand: Operand, span: Span },
+    /// `x [not] between a and b`
+    Between { variable: String, negated: bool, low: Operand, high: Operand, span: Span },
+    /// `x [not] in (a, b, ...)`
+    In { variable: String, negated: bool, operands: Vec<Operand>, span: Span },
+    /// `x [not] in name` or `x [not] in (search ... return y)`
+    InSet { variable: String, negated: bool, set: SetReference, span: Span },
+    /// `x [not] matches "words prefix*"`
+    Matches { variable: String, negated: bool, text: Literal, span: Span },
+    /// `x [not] like "pre%fix_"`
+    Like { variable: String, negated: bool, pattern: Literal, span: Span },
+    /// `x ~ "^re(gex)?$"`
+    Regex { variable: String, pattern: Literal, span: Span },
+    /// `g->"revision" > 2`, comparing a scalar extracted from a JSON value.
+    JsonPath { variable: String, path: Vec<PathStep>, comparator: Comparator, operand: Literal, span: Span },
+}
+
+/// Right hand side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Variable(String),
+    Literal(Literal),
+}
+
+/// A set of things after `in`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetReference {
+    Named(String),
+    Subquery(Box<Search>),
+}
+
+/// One step of a JSON path after `->`: an object key or an array index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathStep {
+    Key(String),
+    Index(usize),
+}
+impl fmt::Display for PathStep {
+    /// Traqula source text of the step, including its arrow.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathStep::Key(key) => write!(f, "->\"{}\"", key.replace('"', "\"\"")),
+            PathStep::Index(index) => write!(f, "->{}", index),
+        }
+    }
+}
+
+/// A variable followed by its JSON path, e.g. `g->"specs"->0`.
+pub fn path_text(variable: &str, path: &[PathStep]) -> String {
+    path.iter().fold(variable.to_string(), |text, step| format!("{}{}", text, step))
+}
+
+/// One column of a `return` clause: a variable, a JSON path into one, or an aggregate of either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnItem {
+    pub variable: String,
+    /// Steps after `->`; empty for the variable itself.
+    pub path: Vec<PathStep>,
+    pub aggregate: Option<Aggregate>,
+    pub span: Span,
+}
+impl ReturnItem {
+    /// Column label, e.g. `n`, `g->"name"` or `count(distinct n)`.
+    pub fn column_name(&self) -> String {
+        let expression = path_text(&self.variable, &self.path);
+        match self.aggregate {
+            None => expression,
+            Some(Aggregate { function, distinct }) => format!("{}({}{})", function.name(), if distinct { "distinct " } else { "" }, expression),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aggregate {
+    pub function: AggregateFn,
+    pub distinct: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFn {
+    Count,
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+impl AggregateFn {
+    pub fn name(self) -> &'static str {
+        match self {
+            AggregateFn::Count => "count",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Avg => "avg",
+        }
+    }
+}
+
+/// `x desc` in an `order by` clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderItem {
+    pub item: ReturnItem,
+    pub descending: bool,
+    pub span: Span,
+}
+
+/// `check consistency [scope] [as of <time>];`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckConsistency {
+    /// Appearances of the scope `[{...}, *, *]`; empty for `[*, *, *]`.
+    pub scope: Option<Vec<SearchAppearance>>,
+    pub as_of: Option<Term>,
+    pub span: Span,
+}
+
+/// `explain [analyze] search ...;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explain {
+    /// Run the search and report its statistics rather than only its plan.
+    pub analyze: bool,
+    pub search: Search,
+    pub span: Span,
+}
+
+/// `begin;`, `commit;` or `rollback;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    pub action: TransactionAction,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionAction {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+/// A literal value or `$name` placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literal {
+    pub value: Value,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Unescaped contents of a string literal.
+    String(String),
+    Int(i64),
+    /// Decimal as written, e.g. `12.50`.
+    Decimal(String),
+    /// Certainty in percent as written, between -100 and 100.
+    Certainty(i16),
+    /// Time as written between the quotes.
+    Time(String),
+    /// Name of a time constant without `@`, e.g. `NOW`.
+    Constant(String),
+    /// `true` or `false`.
+    Bool(bool),
+    /// JSON object or array as written.
+    Json(String),
+    /// Name of a placeholder without `$`.
+    Param(String),
+}
+impl fmt::Display for Value {
+    /// Traqula source text of the value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Decimal(text) | Value::Json(text) => f.write_str(text),
+            Value::Certainty(percent) => write!(f, "{}%", percent),
+            Value::Time(text) => write!(f, "'{}'", text),
+            Value::Constant(name) => write!(f, "@{}", name),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Param(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+/// Parse a script, reporting the expected tokens and position on failure.
+pub fn parse(text: &str) -> Result<Script, BarecladError> {
+    let pairs = TraqulaParser::parse(Rule::traqula, text).map_err(|err| {
+        let (line, col) = match err.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let mut message = format!("{}", err);
+        if let ErrorVariant::ParsingError { positives, negatives: _ } = &err.variant && !positives.is_empty() {
+            let mut expected: Vec<&'static str> = positives.iter().map(|r| friendly_rule_name(*r)).collect();
+            expected.sort();
+            expected.dedup();
+            message.push_str(&format!("\nExpected one of: {}", expected.join(", ")));
+        }
+        BarecladError::Parse { message, line: Some(line), col: Some(col) }
+    })?;
+    let commands = pairs.filter(|p| p.as_rule() != Rule::EOI).map(command).collect::<Result<_, _>>()?;
+    Ok(Script { source: text.to_string(), commands })
+}
+
+/// The last line of a regex error, which states the problem without repeating the expression.
+pub(crate) fn regex_error(error: &regex::Error) -> String {
+    error.to_string().lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default().trim().trim_start_matches("error: ").to_string()
+}
+
+fn invalid(pair: &Pair<Rule>, what: &str) -> BarecladError {
+    let span = Span::of(pair);
+    BarecladError::Parse { message: format!("{}: {}", what, pair.as_str()), line: Some(span.line), col: Some(span.col) }
+}
+
+fn command(pair: Pair<Rule>) -> Result<Command, BarecladError> {
+    let span = Span::of(&pair);
+    Ok(match pair.as_rule() {
+        Rule::add_role => Command::AddRole(AddRole { roles: pair.into_inner().map(|r| r.as_str().trim().to_string()).collect(), span }),
+        Rule::add_posit => Command::AddPosit(AddPosit { posits: pair.into_inner().map(posit).collect::<Result<_, _>>()?, span }),
+        Rule::add_assertion => Command::AddAssertion(AddAssertion { assertions: pair.into_inner().map(assertion).collect::<Result<_, _>>()?, span }),
+        Rule::define_search => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            Command::DefineSearch(DefineSearch { name, query: search(inner.next().unwrap())?, span })
+        }
+        Rule::search => Command::Search(search(pair)?),
+        Rule::explain => {
+            let mut inner = pair.into_inner().peekable();
+            let analyze = inner.next_if(|p| p.as_rule() == Rule::analyze_kw).is_some();
+            Command::Explain(Explain { analyze, search: search(inner.next().unwrap())?, span })
+        }
+        Rule::transaction => {
+            let action = match pair.into_inner().next().unwrap().as_rule() {
+                Rule::begin_kw => TransactionAction::Begin,
+                Rule::commit_kw => TransactionAction::Commit,
+                _ => TransactionAction::Rollback,
+            };
+            Command::Transaction(Transaction { action, span })
+        }
+        Rule::load => Command::Load(load(pair)?),
+        _ => Command::CheckConsistency(check_consistency(pair)?),
+    })
+}
+
+/// Name of the variable of an `insert` or `recall`.
+fn name(pair: Pair<Rule>) -> String {
+    pair.into_inner().next().unwrap().as_str().to_string()
+}
+
+fn term(pair: Pair<Rule>) -> Result<Term, BarecladError> {
+    Ok(match pair.as_rule() {
+        Rule::wildcard => Term::Wildcard,
+        Rule::insert => Term::Insert(name(pair)),
+        Rule::recall => Term::Recall(name(pair)),
+        Rule::recall_union => Term::Union(pair.into_inner().map(name).collect()),
+        _ => Term::Literal(literal(pair)?),
+    })
+}
+
+/// The literal wrapped by a value or time position such as `appearing_value`.
+fn inner_literal(pair: Pair<Rule>) -> Result<Literal, BarecladError> {
+    literal(pair.into_inner().next().unwrap())
+}
+
+/// The text of a string literal, without its quotes.
+fn string(pair: &Pair<Rule>) -> String {
+    let text = pair.as_str().trim();
+    text[1..text.len() - 1].replace("\"\"", "\"")
+}
+
+fn literal(pair: Pair<Rule>) -> Result<Literal, BarecladError> {
+    let span = Span::of(&pair);
+    let text = pair.as_str().trim();
+    let value = match pair.as_rule() {
+        Rule::string => Value::String(string(&pair)),
+        Rule::int => Value::Int(text.parse().map_err(|_| invalid(&pair, "Invalid integer"))?),
+        Rule::decimal => Value::Decimal(text.to_string()),
+        Rule::certainty => match text.trim_end_matches('%').parse::<i16>() {
+            Ok(percent) if (-100..=100).contains(&percent) => Value::Certainty(percent),
+            _ => return Err(invalid(&pair, "Certainty must be between -100% and 100%")),
+        },
+        Rule::time => Value::Time(text[1..text.len() - 1].to_string()),
+        Rule::constant => Value::Constant(text[1..].trim().to_string()),
+        Rule::boolean => Value::Bool(text == "true"),
+        Rule::json => Value::Json(text.to_string()),
+        _ => Value::Param(text[1..].to_string()),
+    };
+    Ok(Literal { value, span })
+}
+
+fn posit(pair: Pair<Rule>) -> Result<Posit, BarecladError> {
+    let span = Span::of(&pair);
+    let mut variable = None;
+    let mut appearances = Vec::new();
+    let mut value = None;
+    let mut time = None;
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::insert => variable = Some(name(part)),
+            Rule::appearance_set => {
+                for appearance in part.into_inner() {
+                    let span = Span::of(&appearance);
+                    let mut inner = appearance.into_inner();
+                    let thing = term(inner.next().unwrap())?;
+                    appearances.push(Appearance { thing, role: inner.next().unwrap().as_str().trim().to_string(), span });
+                }
+            }
+            Rule::appearing_value => value = Some(inner_literal(part)?),
+            _ => time = Some(inner_literal(part)?),
+        }
+    }
+    Ok(Posit { variable, appearances, value: value.unwrap(), time: time.unwrap(), span })
+}
+
+fn load(pair: Pair<Rule>) -> Result<Load, BarecladError> {
+    let span = Span::of(&pair);
+    let mut posits = Vec::new();
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::posit => posits.push(posit(part)?),
+            Rule::load_columns => {
+                for column in part.into_inner() {
+                    let name = column.as_str()[1..].to_string();
+                    if columns.contains(&name) {
+                        return Err(invalid(&column, "Duplicate placeholder"));
+                    }
+                    columns.push(name);
+                }
+            }
+            Rule::load_row => {
+                if part.clone().into_inner().count() != columns.len() {
+                    let columns: Vec<String> = columns.iter().map(|column| format!("${}", column)).collect();
+                    return Err(invalid(&part, &format!("Row values do not match the placeholders ({})", columns.join(", "))));
+                }
+                rows.push(part.into_inner().map(inner_literal).collect::<Result<_, _>>()?);
+            }
+            _ => {}
+        }
+    }
+    Ok(Load { posits, columns, rows, span })
+}
+
+fn assertion(pair: Pair<Rule>) -> Result<Assertion, BarecladError> {
+    let span = Span::of(&pair);
+    let mut inner = pair.into_inner().peekable();
+    let variable = inner.next_if(|p| p.as_rule() == Rule::insert).map(name);
+    let positor = term(inner.next().unwrap().into_inner().next().unwrap())?;
+    let posit = name(inner.next().unwrap().into_inner().next().unwrap());
+    let certainty = literal(inner.next().unwrap())?;
+    let time = inner_literal(inner.next().unwrap())?;
+    Ok(Assertion { variable, positor, posit, certainty, time, span })
+}
+
+fn search(pair: Pair<Rule>) -> Result<Search, BarecladError> {
+    let mut query = Search { patterns: Vec::new(), condition: None, returns: Vec::new(), group_by: None, order_by: Vec::new(), limit: None, span: Span::of(&pair) };
+    for clause in pair.into_inner() {
+        match clause.as_rule() {
+            Rule::search_clause => query.patterns = clause.into_inner().map(pattern).collect::<Result<_, _>>()?,
+            Rule::where_clause => query.condition = Some(condition(clause.into_inner().next().unwrap())?),
+            Rule::return_clause => {
+                for item in clause.into_inner() {
+                    if item.as_rule() == Rule::group_by_clause {
+                        query.group_by = Some(item.into_inner().map(name).collect());
+                    } else {
+                        query.returns.push(return_item(item)?);
+                    }
+                }
+            }
+            Rule::order_by_clause => {
+                for item in clause.into_inner().filter(|p| p.as_rule() == Rule::order_item) {
+                    let span = Span::of(&item);
+                    let mut inner = item.into_inner();
+                    let item = return_item(inner.next().unwrap())?;
+                    let descending = inner.next().is_some_and(|d| d.as_str().eq_ignore_ascii_case("desc"));
+                    query.order_by.push(OrderItem { item, descending, span });
+                }
+            }
+            Rule::limit_clause => {
+                let count = clause.into_inner().next().unwrap();
+                query.limit = Some(count.as_str().parse().map_err(|_| invalid(&count, "Invalid limit"))?);
+            }
+            _ => {}
+        }
+    }
+    Ok(query)
+}
+
+fn return_item(pair: Pair<Rule>) -> Result<ReturnItem, BarecladError> {
+    let span = Span::of(&pair);
+    match pair.as_rule() {
+        Rule::recall => return Ok(ReturnItem { variable: name(pair), path: Vec::new(), aggregate: None, span }),
+        Rule::json_path => {
+            let (variable, path) = json_path(pair)?;
+            return Ok(ReturnItem { variable, path, aggregate: None, span });
+        }
+        _ => {}
+    }
+    let mut inner = pair.into_inner();
+    let function = match inner.next().unwrap().as_str().to_ascii_lowercase().as_str() {
+        "count" => AggregateFn::Count,
+        "min" => AggregateFn::Min,
+        "max" => AggregateFn::Max,
+        "sum" => AggregateFn::Sum,
+        _ => AggregateFn::Avg,
+    };
+    let mut distinct = false;
+    let mut variable = String::new();
+    let mut path = Vec::new();
+    for part in inner {
+        match part.as_rule() {
+            Rule::distinct_kw => distinct = true,
+            Rule::json_path => (variable, path) = json_path(part)?,
+            _ => variable = name(part),
+        }
+    }
+    Ok(ReturnItem { variable, path, aggregate: Some(Aggregate { function, distinct }), span })
+}
+
+fn json_path(pair: Pair<Rule>) -> Result<(String, Vec<PathStep>), BarecladError> {
+    let mut inner = pair.into_inner();
+    let variable = name(inner.next().unwrap());
+    let path = inner
+        .map(|step| match step.as_rule() {
+            Rule::path_index => step.as_str().parse().map(PathStep::Index).map_err(|_| invalid(&step, "Invalid array index")),
+            _ => Ok(PathStep::Key(string(&step))),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((variable, path))
+}
+
+fn pattern(pair: Pair<Rule>) -> Result<Pattern, BarecladError> {
+    let mut pattern = Pattern {
+        optional: false,
+        negated: false,
+        posit: None,
+        appearances: Vec::new(),
+        value: Term::Wildcard,
+        time: Term::Wildcard,
+        as_of: None,
+        certainty: None,
+        span: Span::of(&pair),
+    };
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::optional_kw => pattern.optional = true,
+            Rule::not_kw => pattern.negated = true,
+            Rule::insert | Rule::recall => pattern.posit = Some(term(part)?),
+            Rule::appearance_set_search => pattern.appearances = search_appearances(part)?,
+            Rule::appearing_value_search => pattern.value = term(part.into_inner().next().unwrap())?,
+            Rule::appearance_time_search => pattern.time = term(part.into_inner().next().unwrap())?,
+            Rule::as_of_clause => pattern.as_of = Some(term(part.into_inner().next().unwrap())?),
+            _ => pattern.certainty = Some(certainty_condition(part)?),
+        }
+    }
+    Ok(pattern)
+}
+
+fn search_appearances(pair: Pair<Rule>) -> Result<Vec<SearchAppearance>, BarecladError> {
+    pair.into_inner()
+        .filter(|p| p.as_rule() == Rule::appearance_search)
+        .map(|appearance| {
+            let span = Span::of(&appearance);
+            let mut inner = appearance.into_inner();
+            let thing = term(inner.next().unwrap())?;
+            let role = inner.next().filter(|r| r.as_rule() == Rule::role).map(|r| r.as_str().trim().to_string());
+            Ok(SearchAppearance { thing, role, span })
+        })
+        .collect()
+}
+
+fn certainty_condition(pair: Pair<Rule>) -> Result<CertaintyCondition, BarecladError> {
+    let span = Span::of(&pair);
+    let mut inner = pair.into_inner().skip(1);
+    let positor = inner.next().filter(|p| p.as_rule() == Rule::recall).map(name);
+    let comparator = comparator(inner.next().unwrap());
+    Ok(CertaintyCondition { positor, comparator, certainty: literal(inner.next().unwrap())?, span })
+}
+
+fn comparator(pair: Pair<Rule>) -> Comparator {
+    match pair.as_str() {
+        "<" => Comparator::Less,
+        "<=" => Comparator::LessOrEqual,
+        ">" => Comparator::Greater,
+        ">=" => Comparator::GreaterOrEqual,
+        "=" => Comparator::Equal,
+        _ => Comparator::DoubleEqual,
+    }
+}
+
+fn operand(pair: Pair<Rule>) -> Result<Operand, BarecladError> {
+    Ok(match pair.as_rule() {
+        Rule::recall => Operand::Variable(name(pair)),
+        _ => Operand::Literal(inner_literal(pair)?),
+    })
+}
+
+fn condition(pair: Pair<Rule>) -> Result<Condition, BarecladError> {
+    let span = Span::of(&pair);
+    let rule = pair.as_rule();
+    Ok(match rule {
+        Rule::disjunction | Rule::conjunction => {
+            let mut terms = pair.into_inner().filter(|p| !matches!(p.as_rule(), Rule::or_kw | Rule::and_kw)).map(condition).collect::<Result<Vec<_>, _>>()?;
+            match terms.len() {
+                1 => terms.pop().unwrap(),
+                _ if rule == Rule::disjunction => Condition::Or(terms),
+                _ => Condition::And(terms),
+            }
+        }
+        Rule::negation => {
+            let mut inner = pair.into_inner();
+            let first = inner.next().unwrap();
+            if first.as_rule() == Rule::not_kw {
+                Condition::Not(Box::new(condition(inner.next().unwrap())?))
+            } else {
+                condition(first)?
+            }
+        }
+        Rule::condition => {
+            let mut inner = pair.into_inner();
+            let variable = name(inner.next().unwrap());
+            let comparator = comparator(inner.next().unwrap());
+            Condition::Compare { variable, comparator, operand: operand(inner.next().unwrap())?, span }
+        }
+        Rule::matches_condition => {
+            let mut inner = pair.into_inner();
+            let variable = name(inner.next().unwrap());
+            let mut negated = false;
+            let mut text = None;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::not_kw => negated = true,
+                    Rule::matches_kw => {}
+                    _ => text = Some(literal(part)?),
+                }
+            }
+            Condition::Matches { variable, negated, text: text.unwrap(), span }
+        }
+        Rule::path_condition => {
+            let mut inner = pair.into_inner();
+            let (variable, path) = json_path(inner.next().unwrap())?;
+            let comparator = comparator(inner.next().unwrap());
+            Condition::JsonPath { variable, path, comparator, operand: inner_literal(inner.next().unwrap())?, span }
+        }
+        Rule::like_condition => {
+            let mut inner = pair.into_inner();
+            let variable = name(inner.next().unwrap());
+            let mut negated = false;
+            let mut pattern = None;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::not_kw => negated = true,
+                    Rule::like_kw => {}
+                    _ => pattern = Some(literal(part)?),
+                }
+            }
+            Condition::Like { variable, negated, pattern: pattern.unwrap(), span }
+        }
+        Rule::regex_condition => {
+            let mut inner = pair.into_inner();
+            let variable = name(inner.next().unwrap());
+            let part = inner.next().unwrap();
+            let pattern = literal(part.clone())?;
+            // Placeholders are checked when bound; written expressions are checked here, where they have a position.
+            if let Value::String(expression) = &pattern.value {
+                regex::Regex::new(expression).map_err(|e| invalid(&part, &format!("Invalid regular expression ({})", regex_error(&e))))?;
+            }
+            Condition::Regex { variable, pattern, span }
+        }
+        _ => {
+            let mut inner = pair.into_inner();
+            let variable = name(inner.next().unwrap());
+            let mut negated = false;
+            let mut operands = Vec::new();
+            let mut set = None;
+            for part in inner {
+                match part.as_rule() {
+                    Rule::not_kw => negated = true,
+                    Rule::recall | Rule::rhs_value => operands.push(operand(part)?),
+                    Rule::set_reference => {
+                        let target = part.into_inner().next().unwrap();
+                        set = Some(match target.as_rule() {
+                            Rule::subquery => SetReference::Subquery(Box::new(search(target)?)),
+                            _ => SetReference::Named(name(target)),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            match set {
+                Some(set) => Condition::InSet { variable, negated, set, span },
+                None if rule == Rule::between_condition => {
+                    let mut operands = operands.into_iter();
+                    Condition::Between { variable, negated, low: operands.next().unwrap(), high: operands.next().unwrap(), span }
+                }
+                None => Condition::In { variable, negated, operands, span },
+            }
+        }
+    })
+}
+
+fn check_consistency(pair: Pair<Rule>) -> Result<CheckConsistency, BarecladError> {
+    let mut check = CheckConsistency { scope: None, as_of: None, span: Span::of(&pair) };
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::consistency_scope => check.scope = Some(search_appearances(part.into_inner().next().unwrap())?),
+            _ => check.as_of = Some(term(part.into_inner().next().unwrap())?),
+        }
+    }
+    Ok(check)
+}
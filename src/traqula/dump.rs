@@ -0,0 +1,263 @@
+//! Dumping a database as a Traqula script, and restoring one from it.
+//!
+//! A dump adds the roles, then every posit in the order of their identities, one command per line:
+//! ```text
+//! add role callsign, rank;
+//! add posit +p7 [{(+t6, callsign)}, "Kestrel", '2025-06-18'];
+//! add posit +p8 [{(t6, rank)}, 3, '2025-06-18'];
+//! add assertion +p10 [+t9, p7, 100%, '2025-06-19'];
+//! ```
+//! Variables are named after the identities of things (`t6`) and posits (`p7`), so the same database
+//! always dumps the same way and dumps can be diffed. Assertions are written as `add assertion`, and
+//! meta-posits recall the posits they are about. Things that appear in no posit are left out.
+//!
+//! Any engine can run a dump, giving its things new identities. [`restore`] replays it keeping the
+//! identities named by the variables instead, so that they can still be referred to. Roles are
+//! restored by name.
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::ast::{self, Span, Term};
+use super::{TypedValue, time_of};
+use crate::construct::{AppearanceSet, Database, PositKeeper, Thing, MAX_RESTORED_THING};
+use crate::datatype::{Certainty, DataType, Decimal, JSON, Time};
+use crate::error::BarecladError;
+
+/// Write the roles and posits of a database as a Traqula script.
+pub fn dump(database: &Database) -> Result<String, BarecladError> {
+    let mut script = String::new();
+    let mut roles: Vec<_> = database.role_keeper().lock().unwrap().iter().filter(|role| !role.reserved()).cloned().collect();
+    roles.sort_by_key(|role| role.role());
+    if !roles.is_empty() {
+        let names: Vec<&str> = roles.iter().map(|role| role.name()).collect();
+        writeln!(script, "add role {};", names.join(", ")).unwrap();
+    }
+    let appearance_set_lookup = database.posit_thing_to_appearance_set_lookup();
+    let appearance_sets = appearance_set_lookup.lock().unwrap();
+    let time_lookup = database.posit_time_lookup();
+    let times = time_lookup.lock().unwrap();
+    let data_type_lookup = database.role_name_to_data_type_lookup();
+    let data_types = data_type_lookup.lock().unwrap();
+    let posit_keeper = database.posit_keeper();
+    let mut posit_keeper = posit_keeper.lock().unwrap();
+    let mut posits: Vec<Thing> = appearance_sets.keys().copied().collect();
+    posits.sort_unstable();
+    // Things are inserted where they first appear, and posits where they are added.
+    let mut inserted = HashSet::new();
+    let mut variable = |thing: Thing| {
+        if appearance_sets.contains_key(&thing) {
+            format!("p{thing}")
+        } else if inserted.insert(thing) {
+            format!("+t{thing}")
+        } else {
+            format!("t{thing}")
+        }
+    };
+    for posit in posits {
+        let missing = |what: &str| BarecladError::Invariant(format!("Posit {posit} has no {what}"));
+        let appearance_set = &appearance_sets[&posit];
+        let time = time_literal(times.get(&posit).ok_or_else(|| missing("time"))?);
+        let (value, data_type) = value_literal(&mut posit_keeper, data_types.lookup(&appearance_set.roles()), posit).ok_or_else(|| missing("value"))?;
+        let appearances = appearance_set.appearances();
+        let in_role = |name: &str| appearances.iter().find(|appearance| appearance.role().name() == name).map(|appearance| appearance.thing());
+        match (in_role("ascertains"), in_role("posit")) {
+            (Some(positor), Some(asserted)) if appearances.len() == 2 && data_type == Certainty::DATA_TYPE && appearance_sets.contains_key(&asserted) => {
+                writeln!(script, "add assertion +p{posit} [{}, p{asserted}, {value}, {time}];", variable(positor)).unwrap();
+            }
+            _ => {
+                let appearances: Vec<String> = appearances
+                    .iter()
+                    .map(|appearance| format!("({}, {})", variable(appearance.thing()), appearance.role().name()))
+                    .collect();
+                writeln!(script, "add posit +p{posit} [{{{}}}, {value}, {time}];", appearances.join(", ")).unwrap();
+            }
+        }
+    }
+    Ok(script)
+}
+
+/// The literal of a posit's appearing value, with the name of its data type.
+fn value_literal(posit_keeper: &mut PositKeeper, data_types: &HashSet<String>, posit: Thing) -> Option<(String, &'static str)> {
+    let names = [String::DATA_TYPE, i64::DATA_TYPE, Decimal::DATA_TYPE, Certainty::DATA_TYPE, Time::DATA_TYPE, JSON::DATA_TYPE];
+    names.into_iter().filter(|name| data_types.contains(*name)).find_map(|name| {
+        let literal = match name {
+            String::DATA_TYPE => posit_keeper.posit::<String>(posit).map(|p| format!("\"{}\"", p.value().replace('"', "\"\""))),
+            i64::DATA_TYPE => posit_keeper.posit::<i64>(posit).map(|p| p.value().to_string()),
+            Decimal::DATA_TYPE => posit_keeper.posit::<Decimal>(posit).map(|p| decimal_literal(p.value())),
+            Certainty::DATA_TYPE => posit_keeper.posit::<Certainty>(posit).map(|p| format!("{}%", p.value().percent())),
+            Time::DATA_TYPE => posit_keeper.posit::<Time>(posit).map(|p| time_literal(p.value())),
+            _ => posit_keeper.posit::<JSON>(posit).map(|p| p.value().to_string()),
+        };
+        literal.map(|literal| (literal, name))
+    })
+}
+
+/// Decimals are written with a fractional part, which tells them from integers.
+fn decimal_literal(decimal: &Decimal) -> String {
+    let text = decimal.to_plain_string();
+    if text.contains('.') { text } else { format!("{text}.0") }
+}
+
+/// Times are written as the constants `@BOT` and `@EOT` or quoted, with years of four digits and
+/// months of two so they read back at the same resolution.
+fn time_literal(time: &Time) -> String {
+    let text = time.to_string();
+    match text.as_str() {
+        "BOT" => "@BOT".into(),
+        "EOT" => "@EOT".into(),
+        _ => match text.parse::<i32>() {
+            Ok(year) => format!("'{year:04}'"),
+            Err(_) => match text.split_once('-') {
+                Some((year, month)) if !year.is_empty() && month.len() == 1 => format!("'{year}-0{month}'"),
+                _ => format!("'{text}'"),
+            },
+        },
+    }
+}
+
+/// Replay a dump, keeping the identities its variables are named after. Nothing is restored unless
+/// all of it is, and identities already in use in the database fail the restore.
+pub fn restore(database: &Database, script: &str) -> Result<(), BarecladError> {
+    let script = ast::parse(script)?;
+    // Roles added along the way get identities above those to restore.
+    let mut highest = 0;
+    for command in &script.commands {
+        let names: Vec<&str> = match command {
+            ast::Command::AddPosit(add) => add
+                .posits
+                .iter()
+                .flat_map(|posit| posit.variable.iter().map(String::as_str).chain(posit.appearances.iter().filter_map(|appearance| inserted(&appearance.thing))))
+                .collect(),
+            ast::Command::AddAssertion(add) => add
+                .assertions
+                .iter()
+                .flat_map(|assertion| assertion.variable.iter().map(String::as_str).chain(inserted(&assertion.positor)))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for name in names {
+            // Identities out of range are reported where they are restored.
+            if let Some(identity) = name.get(1..).and_then(|digits| digits.parse::<Thing>().ok()) {
+                highest = highest.max(identity.min(MAX_RESTORED_THING));
+            }
+        }
+    }
+    database.load(|database| {
+        database.thing_generator().lock().unwrap().reserve(highest);
+        let mut things: HashMap<&str, Thing> = HashMap::new();
+        for command in &script.commands {
+            match command {
+                ast::Command::AddRole(add) => {
+                    for role in &add.roles {
+                        database.create_role(role.clone(), false);
+                    }
+                }
+                ast::Command::AddPosit(add) => {
+                    for posit in &add.posits {
+                        let name = posit.variable.as_deref().unwrap_or_default();
+                        let posit_thing = identity(name, 'p', posit.span)?;
+                        let mut roles = Vec::new();
+                        for appearance in &posit.appearances {
+                            let thing = match &appearance.thing {
+                                Term::Insert(name) => {
+                                    let thing = database.restore_thing(identity(name, 't', appearance.span)?)?;
+                                    things.insert(name, thing);
+                                    thing
+                                }
+                                Term::Recall(name) => recall(&things, name, appearance.span)?,
+                                _ => return Err(error(appearance.span, "Expected a thing variable".into())),
+                            };
+                            roles.push((thing, appearance.role.as_str(), appearance.span));
+                        }
+                        let appearance_set = appearance_set(database, &roles)?;
+                        let time = time_of(&posit.time.value).ok_or_else(|| error(posit.time.span, "Expected a time".into()))?;
+                        restore_posit(database, posit_thing, appearance_set, &posit.value, time)?;
+                        things.insert(name, posit_thing);
+                    }
+                }
+                ast::Command::AddAssertion(add) => {
+                    for assertion in &add.assertions {
+                        let name = assertion.variable.as_deref().unwrap_or_default();
+                        let posit_thing = identity(name, 'p', assertion.span)?;
+                        let positor = match &assertion.positor {
+                            Term::Insert(name) => {
+                                let thing = database.restore_thing(identity(name, 't', assertion.span)?)?;
+                                things.insert(name, thing);
+                                thing
+                            }
+                            Term::Recall(name) => recall(&things, name, assertion.span)?,
+                            _ => return Err(error(assertion.span, "Expected a positor variable".into())),
+                        };
+                        let asserted = recall(&things, &assertion.posit, assertion.span)?;
+                        let appearance_set = appearance_set(database, &[(positor, "ascertains", assertion.span), (asserted, "posit", assertion.span)])?;
+                        let time = time_of(&assertion.time.value).ok_or_else(|| error(assertion.time.span, "Expected a time".into()))?;
+                        restore_posit(database, posit_thing, appearance_set, &assertion.certainty, time)?;
+                        things.insert(name, posit_thing);
+                    }
+                }
+                other => return Err(error(other.span(), "Only add role, add posit and add assertion commands can be restored".into())),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn inserted(term: &Term) -> Option<&str> {
+    match term {
+        Term::Insert(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn error(span: Span, message: String) -> BarecladError {
+    BarecladError::Parse { message, line: Some(span.line), col: Some(span.col) }
+}
+
+/// The identity a variable such as `t12` or `p12` is named after.
+fn identity(name: &str, prefix: char, span: Span) -> Result<Thing, BarecladError> {
+    let identity = name.strip_prefix(prefix)
+        .and_then(|digits| digits.parse::<Thing>().ok())
+        .filter(|identity| format!("{prefix}{identity}") == name)
+        .ok_or_else(|| error(span, format!("Expected a variable such as {prefix}12, named after an identity, found '{name}'")))?;
+    if identity > MAX_RESTORED_THING {
+        return Err(error(span, format!("Identity {identity} is above the highest that can be restored, {MAX_RESTORED_THING}")));
+    }
+    Ok(identity)
+}
+
+fn recall(things: &HashMap<&str, Thing>, name: &str, span: Span) -> Result<Thing, BarecladError> {
+    things.get(name).copied().ok_or_else(|| error(span, format!("Unknown variable: {name}")))
+}
+
+fn appearance_set(database: &Database, roles: &[(Thing, &str, Span)]) -> Result<Arc<AppearanceSet>, BarecladError> {
+    let mut appearances = Vec::new();
+    for (index, (thing, name, span)) in roles.iter().enumerate() {
+        if roles[..index].iter().any(|(_, other, _)| other == name) {
+            return Err(error(*span, format!("Role '{name}' appears twice in a posit")));
+        }
+        let role = database.role_keeper().lock().unwrap().find(name).ok_or_else(|| error(*span, format!("Unknown role: {name}")))?;
+        appearances.push(database.create_apperance(*thing, role).0);
+    }
+    Ok(database.create_appearance_set(appearances).0)
+}
+
+fn restore_posit(database: &Database, identity: Thing, appearance_set: Arc<AppearanceSet>, value: &ast::Literal, time: Time) -> Result<(), BarecladError> {
+    let TypedValue { json, string, time: value_time, decimal, int, certainty } = TypedValue::of(&value.value);
+    if let Some(value) = json {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else if let Some(value) = string {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else if let Some(value) = value_time {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else if let Some(value) = certainty {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else if let Some(value) = decimal {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else if let Some(value) = int {
+        database.restore_posit(identity, appearance_set, value, time)?;
+    } else {
+        return Err(error(value.span, format!("Expected a value, found {}", value.value)));
+    }
+    Ok(())
+}
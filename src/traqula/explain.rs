@@ -0,0 +1,106 @@
+//! Plans and execution statistics of searches, as returned by `explain` and `explain analyze`.
+//!
+//! `explain` lists the patterns of a search in the order the [planner](super::planner) matches
+//! them, with their estimated candidates and the `where` comparisons pushed down to them, followed
+//! by the comparisons of the `where` clause and the projection. `explain analyze` runs the search,
+//! discarding its rows, and adds what each step actually did.
+use std::time::{Duration, Instant};
+
+use super::ast;
+use super::planner::Plan;
+
+/// What matching one pattern did; `None` for patterns never reached.
+#[derive(Debug, Default)]
+pub(crate) struct PatternProfile {
+    /// Posits left after the filters of the pattern.
+    pub candidates: Option<u64>,
+    /// Bindings after the pattern was matched, or applied for `not` patterns.
+    pub bindings: Option<usize>,
+    /// Candidates dropped by pushed down comparisons, or bindings dropped by a `not` pattern.
+    pub filtered: u64,
+    pub elapsed: Duration,
+}
+
+/// What evaluating one comparison of the `where` clause did.
+#[derive(Debug)]
+pub(crate) struct PredicateProfile {
+    pub predicate: String,
+    /// Bindings the comparison was evaluated for.
+    pub evaluated: u64,
+    /// Bindings the `where` clause rejected and the comparison counted against: it did not hold, or
+    /// held under `not`.
+    pub filtered: u64,
+    pub elapsed: Duration,
+}
+
+/// Plan of a search and, when analyzing, the statistics of running it.
+#[derive(Debug, Default)]
+pub(crate) struct Profile {
+    /// Run the search rather than stop once it is planned.
+    pub analyze: bool,
+    pub plan: Option<Plan>,
+    /// Indexed by the position of the pattern in the search as written.
+    pub patterns: Vec<PatternProfile>,
+    pub predicates: Vec<PredicateProfile>,
+    /// Rows returned by the search.
+    pub rows: usize,
+    pub elapsed: Duration,
+    current: Option<(usize, Instant)>,
+}
+impl Profile {
+    pub fn new(analyze: bool) -> Self {
+        Profile { analyze, ..Default::default() }
+    }
+    /// Start timing the pattern at the given written position, finishing the one before.
+    pub fn start_pattern(&mut self, pattern: usize, bindings: usize) {
+        self.finish_pattern(bindings);
+        self.current = Some((pattern, Instant::now()));
+    }
+    /// Stop timing the current pattern, recording the bindings it left.
+    pub fn finish_pattern(&mut self, bindings: usize) {
+        if let Some((pattern, started)) = self.current.take() {
+            let profile = &mut self.patterns[pattern];
+            profile.bindings = Some(bindings);
+            profile.elapsed += started.elapsed();
+        }
+    }
+}
+
+/// Rows with the type of each of their cells.
+type TypedRows = Vec<(Vec<String>, Vec<String>)>;
+
+/// Columns and typed rows describing a profiled search.
+pub(crate) fn result_set(script: &ast::Script, search: &ast::Search, profile: &Profile) -> (Vec<String>, TypedRows) {
+    let mut columns = vec!["step", "operation", "source", "estimate", "pushdown"];
+    if profile.analyze {
+        columns.extend(["candidates", "bindings", "filtered", "time_us"]);
+    }
+    let mut rows = Vec::new();
+    let mut push = |operation: &str, source: String, estimate: Option<u64>, pushdown: String, analyzed: [Option<u64>; 4]| {
+        let mut cells = vec![Some((rows.len() + 1).to_string()), Some(operation.to_string()), Some(source), estimate.map(|e| e.to_string()), Some(pushdown)];
+        let mut types = vec!["i64", "String", "String", "i64", "String"];
+        if profile.analyze {
+            cells.extend(analyzed.map(|n| n.map(|n| n.to_string())));
+            types.extend(["i64"; 4]);
+        }
+        // Missing numbers are nulls, as for unmatched optional variables.
+        let types = cells.iter().zip(types).map(|(cell, t)| if cell.is_some() { t.to_string() } else { "null".to_string() }).collect();
+        rows.push((cells.into_iter().map(Option::unwrap_or_default).collect(), types));
+    };
+    for step in profile.plan.iter().flat_map(|plan| &plan.steps) {
+        let pattern = &search.patterns[step.pattern];
+        let operation = if pattern.negated { "exclude" } else if pattern.optional { "optional match" } else { "match" };
+        let pushdown = step.pushed.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(" and ");
+        let analyzed = profile.patterns.get(step.pattern).map_or([None; 4], |p| {
+            [p.candidates, p.bindings.map(|b| b as u64), Some(p.filtered), Some(p.elapsed.as_micros() as u64)]
+        });
+        push(operation, script.text(pattern.span).to_string(), step.estimate, pushdown, analyzed);
+    }
+    for predicate in &profile.predicates {
+        let analyzed = [Some(predicate.evaluated), None, Some(predicate.filtered), Some(predicate.elapsed.as_micros() as u64)];
+        push("where", predicate.predicate.clone(), None, String::new(), analyzed);
+    }
+    let returns = search.returns.iter().map(|r| r.column_name()).collect::<Vec<_>>().join(", ");
+    push("return", returns, None, String::new(), [None, Some(profile.rows as u64), None, Some(profile.elapsed.as_micros() as u64)]);
+    (columns.into_iter().map(String::from).collect(), rows)
+}
@@ -0,0 +1,160 @@
+//! Candidate posits from the ordered value and time indexes of the [`Database`].
+//!
+//! Literal values and times in patterns, and the `where` comparisons the [planner](super::planner)
+//! pushes down to a pattern, narrow its candidates through the indexes before they are checked one
+//! by one. The indexes only drop posits that cannot satisfy the comparison, so the per posit checks
+//! decide the results and report the errors as before:
+//!
+//! * Posits of types the index does not cover, such as strings compared with numbers, are kept.
+//! * Integers and decimals reading like certainties (between -1 and 1) are kept for ordering
+//!   comparisons with numbers, which reject them for lacking a percent sign.
+//! * String values are indexed by their [`words`]; a text matches the posits containing all of its
+//!   words, or words starting with those followed by `*`.
+//! * Times are keyed by the first instant they cover. Times of different resolutions compare at the
+//!   coarser one, so ranges are widened to every time that may compare as requested.
+use std::ops::Bound;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use roaring::RoaringTreemap;
+
+use super::{Predicate, RhsValueKind, TypedValue};
+use crate::construct::{Database, words};
+use crate::datatype::{Decimal, Time};
+
+type Range<K> = (Bound<K>, Bound<K>);
+
+/// Integers beyond this magnitude lose precision in the numeric comparisons of the engine.
+const EXACT_INTEGER: u64 = 1 << 53;
+
+/// The keys comparing to `key` with the comparator.
+fn range_of<K: Clone>(op: &str, key: &K) -> Option<Range<K>> {
+    use Bound::*;
+    Some(match op {
+        "<" => (Unbounded, Excluded(key.clone())),
+        "<=" => (Unbounded, Included(key.clone())),
+        ">" => (Excluded(key.clone()), Unbounded),
+        ">=" => (Included(key.clone()), Unbounded),
+        "=" | "==" => (Included(key.clone()), Included(key.clone())),
+        _ => return None,
+    })
+}
+
+/// The integers comparing to a decimal with the comparator.
+fn integer_range_of(op: &str, decimal: &Decimal) -> Option<Range<i64>> {
+    use Bound::*;
+    use bigdecimal::{RoundingMode, ToPrimitive};
+    let floor = decimal.with_scale_round(0, RoundingMode::Floor).to_i64()?;
+    let ceiling = decimal.with_scale_round(0, RoundingMode::Ceiling).to_i64()?;
+    Some(match op {
+        "<" => (Unbounded, Excluded(ceiling)),
+        "<=" => (Unbounded, Included(floor)),
+        ">" => (Excluded(floor), Unbounded),
+        ">=" => (Included(ceiling), Unbounded),
+        // A fraction equals no integer.
+        "=" | "==" => (Included(ceiling), Included(floor)),
+        _ => return None,
+    })
+}
+
+/// The keys of the times that may compare to `time` with the comparator.
+fn time_range_of(op: &str, time: &Time) -> Option<Range<NaiveDateTime>> {
+    use Bound::*;
+    let (first, last) = time.span();
+    Some(match op {
+        "<" => (Unbounded, Excluded(first)),
+        "<=" => (Unbounded, Included(last)),
+        ">" => (Excluded(last), Unbounded),
+        // No time spans a new year, so a time overlapping `time` starts in its year at the earliest.
+        ">=" => (Included(NaiveDate::from_ymd_opt(first.year(), 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0)).unwrap_or(first)), Unbounded),
+        "=" | "==" => (Included(first), Included(first)),
+        _ => return None,
+    })
+}
+
+/// Narrow candidates to the posits that may satisfy a pushed down comparison, or `None` when no
+/// index applies to it.
+pub(crate) fn narrow(database: &Database, predicate: &Predicate, candidates: &RoaringTreemap) -> Option<RoaringTreemap> {
+    match predicate {
+        Predicate::Time(_, op, time) => {
+            let range = time_range_of(op, time)?;
+            Some(candidates & database.time_to_posit_thing_lookup().lock().unwrap().range(range))
+        }
+        Predicate::Value(_, op, rhs) => {
+            let (covered, matching) = value_range(database, op, rhs)?;
+            Some((candidates - &covered) | (candidates & &matching))
+        }
+        Predicate::Matches(_, text) => Some(candidates & matching(database, text)),
+        _ => None,
+    }
+}
+
+/// The posits of the types indexed for a value comparison, and those among them that may satisfy it.
+fn value_range(database: &Database, op: &str, rhs: &RhsValueKind) -> Option<(RoaringTreemap, RoaringTreemap)> {
+    let integers = database.i64_to_posit_thing_lookup();
+    let decimals = database.decimal_to_posit_thing_lookup();
+    let (integers, decimals) = (integers.lock().unwrap(), decimals.lock().unwrap());
+    let (mut matching, covered) = match rhs {
+        RhsValueKind::Int(int) if int.unsigned_abs() < EXACT_INTEGER => {
+            let decimal = Decimal::from_str(&int.to_string())?;
+            (integers.range(range_of(op, int)?) | decimals.range(range_of(op, &decimal)?), integers.all() | decimals.all())
+        }
+        RhsValueKind::Decimal(raw) => {
+            let decimal = Decimal::from_str(raw)?;
+            (integers.range(integer_range_of(op, &decimal)?) | decimals.range(range_of(op, &decimal)?), integers.all() | decimals.all())
+        }
+        RhsValueKind::Cert(percent) => {
+            let certainties = database.certainty_to_posit_thing_lookup();
+            let certainties = certainties.lock().unwrap();
+            return Some((certainties.all().clone(), certainties.range(range_of(op, percent)?)));
+        }
+        _ => return None,
+    };
+    if matches!(op, "<" | "<=" | ">" | ">=") {
+        matching |= integers.range(-1..=1);
+        matching |= decimals.range(Decimal::from_str("-1")?..=Decimal::from_str("1")?);
+    }
+    Some((covered, matching))
+}
+
+/// Narrow candidates to the posits that may have a literal value, or `None` when no index applies to it.
+pub(crate) fn narrow_to_value(database: &Database, value: &TypedValue, candidates: &RoaringTreemap) -> Option<RoaringTreemap> {
+    let matching = if let Some(int) = value.int {
+        database.i64_to_posit_thing_lookup().lock().unwrap().range(int..=int)
+    } else if let Some(decimal) = &value.decimal {
+        database.decimal_to_posit_thing_lookup().lock().unwrap().range(decimal.clone()..=decimal.clone())
+    } else if let Some(certainty) = &value.certainty {
+        let percent = certainty.percent();
+        database.certainty_to_posit_thing_lookup().lock().unwrap().range(percent..=percent)
+    } else if let Some(time) = &value.time {
+        let first = time.span().0;
+        database.time_value_to_posit_thing_lookup().lock().unwrap().range(first..=first)
+    } else {
+        return None;
+    };
+    Some(candidates & matching)
+}
+
+/// Narrow candidates to the posits that may have a literal time.
+pub(crate) fn narrow_to_time(database: &Database, time: &Time, candidates: &RoaringTreemap) -> RoaringTreemap {
+    let first = time.span().0;
+    candidates & database.time_to_posit_thing_lookup().lock().unwrap().range(first..=first)
+}
+
+/// The posits whose string values contain every word of a text, where a word followed by `*` is a
+/// prefix. A text without words matches no posits.
+pub(crate) fn matching(database: &Database, text: &str) -> RoaringTreemap {
+    let lookup = database.word_to_posit_thing_lookup();
+    let lookup = lookup.lock().unwrap();
+    let mut posits: Option<RoaringTreemap> = None;
+    for term in text.split_whitespace() {
+        let terms = words(term);
+        for (i, word) in terms.iter().enumerate() {
+            let found = if i + 1 == terms.len() && term.ends_with('*') { lookup.prefixed(word) } else { lookup.range(word.clone()..=word.clone()) };
+            posits = Some(match posits {
+                None => found,
+                Some(acc) => acc & found,
+            });
+        }
+    }
+    posits.unwrap_or_default()
+}
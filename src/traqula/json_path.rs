@@ -0,0 +1,118 @@
+//! Scalars extracted from JSON values by `->` paths, as in `g->"revision" > 2` or `return g->"name"`.
+//!
+//! A path walks the keys of objects and the indexes of arrays. Integers, decimals and strings at its
+//! end are typed like appearing values, so they compare and aggregate as `i64`, `Decimal` and
+//! `String` values do; booleans compare equal to `true` or `false`. A path leading nowhere, or to a
+//! JSON `null`, extracts null, which no comparison holds for.
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use jsondata::Json;
+
+use super::ast::PathStep;
+use super::{CellValue, RhsValueKind, cmp_bigdecimal, cmp_strings};
+use crate::error::BarecladError;
+
+/// The value at the end of a path.
+#[derive(Debug, Clone)]
+pub(crate) enum Scalar {
+    Int(i64),
+    Decimal(BigDecimal),
+    String(String),
+    Bool(bool),
+    /// An object or array, in compact JSON.
+    Json(String),
+    Null,
+}
+
+/// Follow a path into a JSON value.
+pub(crate) fn extract(json: &Json, path: &[PathStep]) -> Scalar {
+    let mut json = json;
+    for step in path {
+        let next = match (step, json) {
+            (PathStep::Key(key), Json::Object(properties)) => properties.iter().find(|p| p.as_key() == key).map(|p| p.as_value()),
+            (PathStep::Index(index), Json::Array(items)) => items.get(*index),
+            _ => None,
+        };
+        match next {
+            Some(next) => json = next,
+            None => return Scalar::Null,
+        }
+    }
+    match json {
+        Json::Bool(b) => Scalar::Bool(*b),
+        Json::String(s) => Scalar::String(s.clone()),
+        Json::Integer(_) => match json.to_integer().map(i64::try_from) {
+            Some(Ok(int)) => Scalar::Int(int),
+            _ => json.to_string().parse::<BigDecimal>().map(Scalar::Decimal).unwrap_or(Scalar::Null),
+        },
+        Json::Float(_) => json.to_string().parse::<BigDecimal>().map(Scalar::Decimal).unwrap_or(Scalar::Null),
+        Json::Array(_) | Json::Object(_) => Scalar::Json(json.to_string()),
+        _ => Scalar::Null,
+    }
+}
+
+impl Scalar {
+    /// The projected cell: display, type name and typed value.
+    pub(crate) fn cell(self) -> (String, String, CellValue) {
+        match self {
+            Scalar::Int(int) => (int.to_string(), "i64".into(), CellValue::Int(int)),
+            Scalar::Decimal(decimal) => (decimal.to_string(), "Decimal".into(), CellValue::Decimal(decimal)),
+            Scalar::String(text) => (text.clone(), "String".into(), CellValue::Text(text)),
+            Scalar::Bool(b) => (b.to_string(), "bool".into(), CellValue::Other),
+            Scalar::Json(text) => (text, "JSON".into(), CellValue::Other),
+            Scalar::Null => (String::new(), "null".into(), CellValue::Null),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Scalar::Int(_) => "i64",
+            Scalar::Decimal(_) => "Decimal",
+            Scalar::String(_) => "String",
+            Scalar::Bool(_) => "bool",
+            Scalar::Json(_) => "JSON",
+            Scalar::Null => "null",
+        }
+    }
+}
+
+/// Compare an extracted scalar with a literal from a `where` clause. As for value variables,
+/// ordering values of a type the literal cannot be ordered with is an error; equality is false.
+pub(crate) fn compare(scalar: &Scalar, lhs: &str, op: &str, rhs: &RhsValueKind) -> Result<bool, BarecladError> {
+    let ordering = matches!(op, "<" | "<=" | ">" | ">=");
+    let number = |n: &Scalar| match n {
+        Scalar::Int(int) => Some(BigDecimal::from(*int)),
+        Scalar::Decimal(decimal) => Some(decimal.clone()),
+        _ => None,
+    };
+    let held = match (scalar, rhs) {
+        (Scalar::Null, _) => false,
+        (_, RhsValueKind::Int(int)) => match number(scalar) {
+            Some(lhs) => cmp_bigdecimal(&lhs, &BigDecimal::from(*int), op),
+            None => return mismatch(scalar, lhs, op, rhs, ordering),
+        },
+        (_, RhsValueKind::Decimal(raw)) => match (number(scalar), BigDecimal::from_str(raw)) {
+            (Some(lhs), Ok(rhs)) => cmp_bigdecimal(&lhs, &rhs, op),
+            _ => return mismatch(scalar, lhs, op, rhs, ordering),
+        },
+        (Scalar::String(text), RhsValueKind::String(rhs)) => cmp_strings(text, rhs, op),
+        (Scalar::Bool(b), RhsValueKind::Bool(rhs)) if !ordering => b == rhs,
+        _ => return mismatch(scalar, lhs, op, rhs, ordering),
+    };
+    Ok(held)
+}
+
+fn mismatch(scalar: &Scalar, lhs: &str, op: &str, rhs: &RhsValueKind, ordering: bool) -> Result<bool, BarecladError> {
+    if ordering {
+        let rhs = match rhs {
+            RhsValueKind::Cert(percent) => format!("{}%", percent),
+            RhsValueKind::Int(int) => int.to_string(),
+            RhsValueKind::Decimal(text) | RhsValueKind::Const(text) => text.clone(),
+            RhsValueKind::String(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+            RhsValueKind::Bool(b) => b.to_string(),
+        };
+        return Err(BarecladError::Execution(format!("Ordering comparison not allowed: {}({}) {} {}", lhs, scalar.type_name(), op, rhs)));
+    }
+    Ok(false)
+}
@@ -0,0 +1,278 @@
+//! Evaluation order of the patterns of a search.
+//!
+//! Patterns are matched one after the other, each narrowing the bindings of those before it, so a
+//! selective pattern pays off most when it is matched first. The planner estimates the candidate
+//! posits of every pattern from the role and appearance set indexes and matches the patterns by
+//! ascending estimate, as far as their dependencies allow:
+//!
+//! * `optional` and `not` patterns stay where they are written, and no other pattern moves across them.
+//! * A pattern that needs a variable bound when it is matched, through `as of <variable>`,
+//!   `by <positor>` or a recalled posit identity, keeps its written order relative to the patterns
+//!   mentioning that variable.
+//!
+//! Comparisons and text tests of a value or time variable with a literal that the whole `where`
+//! clause depends on are also pushed down to the pattern binding the variable, where they filter its
+//! candidates before any bindings are built, through the ordered value and time indexes where they
+//! apply. They are still evaluated per binding, so results and errors stay the same.
+//!
+//! Before planning, [`check_negated_variables`] rejects searches using a variable outside the `not`
+//! pattern that introduces it, as such a variable is never bound.
+use std::sync::Arc;
+
+use roaring::RoaringTreemap;
+
+use super::{Condition, Predicate, Variables, ast, bound_things};
+use crate::construct::{Appearance, AppearanceSet, Database, Thing};
+use crate::error::BarecladError;
+
+/// Share of candidates assumed to remain after an equality with a literal.
+const EQUALITY_SELECTIVITY: u64 = 10;
+/// Share of candidates assumed to remain after a range comparison with a literal.
+const RANGE_SELECTIVITY: u64 = 3;
+
+/// A pattern in evaluation order.
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    /// Position of the pattern in the search as written.
+    pub pattern: usize,
+    /// Estimated number of candidate posits, or `None` for a pattern without roles.
+    pub estimate: Option<u64>,
+    /// Comparisons from the `where` clause that filter the candidates of the pattern.
+    pub pushed: Vec<Predicate>,
+}
+
+/// The patterns of a search in the order they are matched.
+#[derive(Debug, Clone)]
+pub(crate) struct Plan {
+    pub steps: Vec<Step>,
+}
+
+/// Plan a search given the condition tree of its `where` clause and the variables bound before it.
+pub(crate) fn plan(database: &Database, query: &ast::Search, condition: Option<&Condition>, variables: &Variables) -> Plan {
+    let patterns = &query.patterns;
+    let mut pushed: Vec<Vec<Predicate>> = vec![Vec::new(); patterns.len()];
+    let mut conjuncts = Vec::new();
+    if let Some(condition) = condition {
+        required_predicates(condition, &mut conjuncts);
+    }
+    for predicate in conjuncts {
+        let (variable, time) = match predicate {
+            Predicate::Time(variable, _, _) => (variable, true),
+            Predicate::Value(variable, _, _) | Predicate::Matches(variable, _) | Predicate::Like(variable, _, _) | Predicate::Regex(variable, _) => (variable, false),
+            _ => continue,
+        };
+        let mut mentioning = patterns.iter().enumerate().filter(|(_, p)| mentions(p).contains(&variable.as_str()) || requires(p).contains(&variable.as_str()));
+        let (Some((index, pattern)), None) = (mentioning.next(), mentioning.next()) else { continue };
+        let slot = if time { &pattern.time } else { &pattern.value };
+        if movable(pattern) && !matches!(pattern.as_of, Some(ast::Term::Recall(_))) && term_name(slot) == Some(variable.as_str()) {
+            pushed[index].push(predicate.clone());
+        }
+    }
+    let estimates: Vec<Option<u64>> = patterns.iter().zip(&pushed).map(|(p, pushed)| estimate(database, p, pushed, variables)).collect();
+    let mut order: Vec<usize> = Vec::with_capacity(patterns.len());
+    let mut segment_start = 0;
+    while segment_start < patterns.len() {
+        if !movable(&patterns[segment_start]) {
+            order.push(segment_start);
+            segment_start += 1;
+            continue;
+        }
+        let segment_end = (segment_start..patterns.len()).find(|&i| !movable(&patterns[i])).unwrap_or(patterns.len());
+        let mut remaining: Vec<usize> = (segment_start..segment_end).collect();
+        while !remaining.is_empty() {
+            // Among the patterns whose dependencies are matched, take the most selective; ties keep written order.
+            let ready = remaining
+                .iter()
+                .copied()
+                .filter(|&j| remaining.iter().all(|&i| i >= j || !depends(&patterns[i], &patterns[j])))
+                .min_by_key(|&j| (estimates[j].unwrap_or(u64::MAX), j))
+                .unwrap();
+            remaining.retain(|&j| j != ready);
+            order.push(ready);
+        }
+        segment_start = segment_end;
+    }
+    let steps = order.into_iter().map(|i| Step { pattern: i, estimate: estimates[i], pushed: std::mem::take(&mut pushed[i]) }).collect();
+    Plan { steps }
+}
+
+/// Fail if the `where`, `return`, `group by` or `order by` clause of a search uses a variable that
+/// only a `not` pattern introduces; the variables of a negated pattern stay local to it.
+pub(crate) fn check_negated_variables(query: &ast::Search, variables: &Variables) -> Result<(), BarecladError> {
+    let bound: Vec<&str> = query.patterns.iter().filter(|p| !p.negated).flat_map(|p| mentions(p).into_iter().chain(requires(p))).collect();
+    let local: Vec<&str> = query
+        .patterns
+        .iter()
+        .filter(|p| p.negated)
+        .flat_map(mentions)
+        .filter(|name| !bound.contains(name) && !variables.contains_key(*name))
+        .collect();
+    if local.is_empty() {
+        return Ok(());
+    }
+    let mut used: Vec<&str> = Vec::new();
+    if let Some(condition) = &query.condition {
+        condition_variables(condition, &mut used);
+    }
+    used.extend(query.returns.iter().map(|r| r.variable.as_str()));
+    used.extend(query.group_by.iter().flatten().map(String::as_str));
+    used.extend(query.order_by.iter().map(|o| o.item.variable.as_str()));
+    match used.into_iter().find(|name| local.contains(name)) {
+        Some(name) => Err(BarecladError::Execution(format!("Variable '{}' is only bound inside a 'not' pattern and cannot be used outside it", name))),
+        None => Ok(()),
+    }
+}
+
+/// Variables a `where` condition compares; subqueries after `in` have variables of their own.
+fn condition_variables<'a>(condition: &'a ast::Condition, out: &mut Vec<&'a str>) {
+    match condition {
+        ast::Condition::Or(terms) | ast::Condition::And(terms) => terms.iter().for_each(|t| condition_variables(t, out)),
+        ast::Condition::Not(inner) => condition_variables(inner, out),
+        ast::Condition::Compare { variable, operand, .. } => {
+            out.push(variable);
+            if let ast::Operand::Variable(other) = operand {
+                out.push(other);
+            }
+        }
+        ast::Condition::Between { variable, low, high, .. } => {
+            out.push(variable);
+            for operand in [low, high] {
+                if let ast::Operand::Variable(other) = operand {
+                    out.push(other);
+                }
+            }
+        }
+        ast::Condition::In { variable, operands, .. } => {
+            out.push(variable);
+            out.extend(operands.iter().filter_map(|o| match o {
+                ast::Operand::Variable(other) => Some(other.as_str()),
+                ast::Operand::Literal(_) => None,
+            }));
+        }
+        ast::Condition::InSet { variable, .. }
+        | ast::Condition::Matches { variable, .. }
+        | ast::Condition::Like { variable, .. }
+        | ast::Condition::Regex { variable, .. }
+        | ast::Condition::JsonPath { variable, .. } => out.push(variable),
+    }
+}
+
+/// Whether the pattern may change places with its neighbours.
+fn movable(pattern: &ast::Pattern) -> bool {
+    !pattern.optional && !pattern.negated
+}
+
+/// Whether the written order of two patterns matters because one needs a variable the other mentions.
+fn depends(a: &ast::Pattern, b: &ast::Pattern) -> bool {
+    let (a_mentions, b_mentions) = (mentions(a), mentions(b));
+    requires(a).iter().any(|v| b_mentions.contains(v)) || requires(b).iter().any(|v| a_mentions.contains(v))
+}
+
+/// The predicates every result must satisfy: the comparisons of the `where` clause joined by `and`.
+fn required_predicates<'a>(condition: &'a Condition, out: &mut Vec<&'a Predicate>) {
+    match condition {
+        Condition::Atom(predicate) => out.push(predicate),
+        Condition::And(terms) => terms.iter().for_each(|t| required_predicates(t, out)),
+        Condition::Or(_) | Condition::Not(_) => {}
+    }
+}
+
+fn term_name(term: &ast::Term) -> Option<&str> {
+    match term {
+        ast::Term::Insert(name) | ast::Term::Recall(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// Variables the pattern binds or joins on.
+fn mentions(pattern: &ast::Pattern) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    names.extend(pattern.posit.as_ref().and_then(term_name));
+    for appearance in &pattern.appearances {
+        match &appearance.thing {
+            ast::Term::Union(members) => names.extend(members.iter().map(String::as_str)),
+            thing => names.extend(term_name(thing)),
+        }
+    }
+    names.extend(term_name(&pattern.value));
+    names.extend(term_name(&pattern.time));
+    names
+}
+
+/// Variables that must be bound before the pattern is matched.
+fn requires(pattern: &ast::Pattern) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    if let Some(ast::Term::Recall(name)) = &pattern.posit {
+        names.push(name);
+    }
+    if let Some(ast::Term::Recall(name)) = &pattern.as_of {
+        names.push(name);
+    }
+    names.extend(pattern.certainty.as_ref().and_then(|c| c.positor.as_deref()));
+    names
+}
+
+/// Estimate the candidate posits of a pattern: the posits having all its roles, narrowed to the
+/// appearances of identities bound before the search, then scaled down for literals.
+fn estimate(database: &Database, pattern: &ast::Pattern, pushed: &[Predicate], variables: &Variables) -> Option<u64> {
+    let mut candidates: Option<RoaringTreemap> = None;
+    for appearance in &pattern.appearances {
+        let Some(role) = &appearance.role else { continue };
+        let Some(role) = database.role_keeper().lock().unwrap().find(role) else { return Some(0) };
+        let mut posits = database.role_to_posit_thing_lookup().lock().unwrap().get(&role.role()).cloned().unwrap_or_default();
+        let names: Vec<&str> = match &appearance.thing {
+            ast::Term::Recall(name) => vec![name],
+            ast::Term::Union(members) => members.iter().map(String::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let things: Vec<Thing> = names.iter().filter_map(|n| bound_things(variables, n)).flatten().collect();
+        if !things.is_empty() && (things.len() as u64) < posits.len() {
+            posits &= posits_of(database, &things, role.name());
+        }
+        candidates = Some(match candidates {
+            None => posits,
+            Some(acc) => acc & posits,
+        });
+    }
+    let mut candidates = candidates?;
+    if let Some(posits) = pattern.posit.as_ref().and_then(term_name).and_then(|n| bound_things(variables, n)) {
+        candidates &= posits.into_iter().collect::<RoaringTreemap>();
+    }
+    let mut estimate = candidates.len();
+    let literals = [&pattern.value, &pattern.time].into_iter().filter(|t| matches!(t, ast::Term::Literal(_))).count();
+    let mut shares = vec![EQUALITY_SELECTIVITY; literals];
+    shares.extend(pushed.iter().map(|p| match p {
+        Predicate::Time(_, op, _) | Predicate::Value(_, op, _) if op != "=" && op != "==" => RANGE_SELECTIVITY,
+        Predicate::Like(..) | Predicate::Regex(..) => RANGE_SELECTIVITY,
+        _ => EQUALITY_SELECTIVITY,
+    }));
+    for share in shares {
+        if estimate > 0 {
+            estimate = (estimate / share).max(1);
+        }
+    }
+    Some(estimate)
+}
+
+/// The posits in which one of the things appears in the named role.
+fn posits_of(database: &Database, things: &[Thing], role: &str) -> RoaringTreemap {
+    let appearances: Vec<Arc<Appearance>> = {
+        let lookup = database.thing_to_appearance_lookup();
+        let guard = lookup.lock().unwrap();
+        things.iter().filter_map(|t| guard.get(t)).flatten().filter(|a| a.role().name() == role).cloned().collect()
+    };
+    let appearance_sets: Vec<Arc<AppearanceSet>> = {
+        let lookup = database.appearance_to_appearance_set_lookup();
+        let guard = lookup.lock().unwrap();
+        appearances.iter().filter_map(|a| guard.get(a)).flatten().cloned().collect()
+    };
+    let lookup = database.appearance_set_to_posit_thing_lookup();
+    let guard = lookup.lock().unwrap();
+    let mut posits = RoaringTreemap::new();
+    for appearance_set in &appearance_sets {
+        if let Some(found) = guard.get(appearance_set) {
+            posits |= found;
+        }
+    }
+    posits
+}
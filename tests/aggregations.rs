@@ -0,0 +1,90 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database(
+        "add role employee, department, salary, bonus, rating;
+         add posit [{(+e1, department)}, \"sales\", '2019-03-01'], [{(e1, salary)}, 100, '2019-03-01'], [{(e1, bonus)}, 1.5, '2019-03-01'], [{(e1, rating)}, 60%, '2019-03-01'];
+         add posit [{(+e2, department)}, \"sales\", '2020-07-15'], [{(e2, salary)}, 200, '2020-07-15'], [{(e2, bonus)}, 2.25, '2020-07-15'], [{(e2, rating)}, 80%, '2020-07-15'];
+         add posit [{(+e3, department)}, \"support\", '2021-01-10'], [{(e3, salary)}, 150, '2021-01-10'], [{(e3, bonus)}, 0.5, '2021-01-10'], [{(e3, rating)}, 70%, '2021-01-10'];",
+    )
+}
+
+fn sorted_rows(engine: &Engine, script: &str) -> (Vec<String>, Vec<Vec<String>>, Vec<Vec<String>>) {
+    let res = engine.execute_collect(script).expect("query ok");
+    let mut pairs: Vec<(Vec<String>, Vec<String>)> = res.rows.into_iter().zip(res.row_types).collect();
+    pairs.sort();
+    let (rows, types) = pairs.into_iter().unzip();
+    (res.columns, rows, types)
+}
+
+#[test]
+fn implicit_single_group() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let (columns, rows, types) = sorted_rows(&engine, "search [{(*, salary)}, +s, +t] return count(s), min(s), max(s), sum(s), avg(s), min(t), max(t);");
+    assert_eq!(columns, vec!["count(s)", "min(s)", "max(s)", "sum(s)", "avg(s)", "min(t)", "max(t)"]);
+    assert_eq!(rows, vec![vec!["3", "100", "200", "450", "150", "2019-03-01", "2021-01-10"]]);
+    assert_eq!(types[0], vec!["i64", "i64", "i64", "i64", "Decimal", "Time", "Time"]);
+}
+
+#[test]
+fn implicit_group_by_plain_variables() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let (_, rows, _) = sorted_rows(&engine, "search [{(*, department)}, +d, *] return d, count(d);");
+    assert_eq!(rows, vec![vec!["sales", "2"], vec!["support", "1"]]);
+}
+
+#[test]
+fn explicit_group_by_across_patterns() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let (columns, rows, _) = sorted_rows(&engine, "search [{(+e, department)}, +d, *], [{(e, salary)}, +s, *] return d, sum(s), avg(s) group by d;");
+    assert_eq!(columns, vec!["d", "sum(s)", "avg(s)"]);
+    assert_eq!(rows, vec![vec!["sales", "300", "150"], vec!["support", "150", "150"]]);
+    // Group keys need not be returned.
+    let (_, rows, _) = sorted_rows(&engine, "search [{(+e, department)}, +d, *], [{(e, salary)}, +s, *] return max(s) group by d;");
+    assert_eq!(rows, vec![vec!["150"], vec!["200"]]);
+}
+
+#[test]
+fn decimal_and_certainty_semantics() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let (_, rows, types) = sorted_rows(&engine, "search [{(*, bonus)}, +b, *] return sum(b), avg(b), max(b);");
+    assert_eq!(rows, vec![vec!["4.25", "1.416667", "2.25"]]);
+    assert_eq!(types[0], vec!["Decimal", "Decimal", "Decimal"]);
+    let (_, rows, types) = sorted_rows(&engine, "search [{(*, rating)}, +r, *] return min(r), max(r), avg(r);");
+    assert_eq!(rows, vec![vec!["0.60", "0.80", "0.70"]]);
+    assert_eq!(types[0], vec!["Certainty", "Certainty", "Certainty"]);
+}
+
+#[test]
+fn count_distinct_and_limit() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let (columns, rows, _) = sorted_rows(&engine, "search [{(*, department)}, +d, *] return count(distinct d), count(d);");
+    assert_eq!(columns, vec!["count(distinct d)", "count(d)"]);
+    assert_eq!(rows, vec![vec!["2", "3"]]);
+    let res = engine.execute_collect("search [{(*, department)}, +d, *] return d, count(d) limit 1;").unwrap();
+    assert_eq!(res.rows.len(), 1);
+}
+
+#[test]
+fn type_errors_for_unsupported_aggregates() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, department)}, +d, *] return sum(d);").unwrap_err();
+    assert!(format!("{}", err).contains("sum() not supported for String"));
+    let err = engine.execute_collect("search [{(*, salary)}, +s, +t] return sum(t);").unwrap_err();
+    assert!(format!("{}", err).contains("sum() not supported for Time"));
+    let err = engine.execute_collect("search [{(*, rating)}, +r, *] return sum(r);").unwrap_err();
+    assert!(format!("{}", err).contains("sum() not supported for Certainty"));
+    let err = engine.execute_collect("search [{(+e, department)}, +d, *], [{(e, salary)}, +s, *] return d, s, count(s) group by d;").unwrap_err();
+    assert!(format!("{}", err).contains("must appear in group by"));
+}
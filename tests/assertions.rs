@@ -0,0 +1,69 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role name;
+         add posit +p [{(+c, name)}, \"Acme\", '2020-01-01'], +q [{(+d, name)}, \"Bolt\", '2020-01-01'];
+         add assertion [+archie, p, 80%, '2021-01-01'], [archie, q, 75%, '2021-01-01'], [archie, q, -100%, '2022-01-01'];",
+    )
+}
+
+#[test]
+fn assertions_join_with_the_posits_they_ascertain() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search +p [{(*, name)}, +n, *], [{(+a, ascertains), (p, posit)}, +c, +t] return n, c, t;");
+    assert_eq!(rows, vec![vec!["Acme", "0.80", "2021-01-01"], vec!["Bolt", "-1", "2022-01-01"], vec!["Bolt", "0.75", "2021-01-01"]]);
+}
+
+#[test]
+fn recalled_posit_variable_restricts_a_later_pattern() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+a, ascertains), (+p, posit)}, +c, *], p [{(*, name)}, +n, *] where c < 0% return n, c;");
+    assert_eq!(rows, vec![vec!["Bolt", "-1"]]);
+}
+
+#[test]
+fn assertions_can_target_posits_found_by_search() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "search +p [{(*, name)}, \"Acme\", *] return p;
+        add assertion +x [+bella, p, 60%, '2021-06-01'];
+        search x [{(+b, ascertains), (*, posit)}, +c, +t] return c, t;";
+    let rows = engine.execute_collect(script).unwrap().rows;
+    // Rows of both searches are collected; the second contributes the new assertion.
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1], vec!["0.60", "2021-06-01"]);
+}
+
+#[test]
+fn asserting_a_non_posit_is_an_error() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("add role person; add posit [{(+someone, person)}, \"Zed\", '2020-01-01']; add assertion [+archie, someone, 50%, '2021-01-01'];").unwrap_err();
+    assert!(format!("{}", err).contains("not a posit"));
+    let err = engine.execute_collect("add assertion [+archie, nobody, 50%, '2021-01-01'];").unwrap_err();
+    assert!(format!("{}", err).contains("Unknown variable in assertion: nobody"));
+}
+
+#[test]
+fn certainties_outside_a_hundred_percent_are_errors_not_clamped() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search +p [{(*, name)}, \"Acme\", *] return p; add assertion [+a, p, 150%, '2021'];").unwrap_err();
+    assert!(matches!(err, bareclad::error::BarecladError::Parse { line: Some(1), col: Some(68), .. }), "{}", err);
+    assert!(format!("{}", err).contains("Certainty must be between -100% and 100%: 150%"));
+    let rows = sorted_rows(&engine, "search [{(*, ascertains), (*, posit)}, +c, *] return c;");
+    assert_eq!(rows.len(), 3);
+    // Small certainties keep their percentage rather than reading as fractions.
+    let script = "search +p [{(*, name)}, \"Acme\", *] return p;
+        add assertion +x [+a, p, 1%, '2021-06-01'];
+        search x [{(*, ascertains), (*, posit)}, +c, *] where c = 1% return x;";
+    assert_eq!(engine.execute_collect(script).unwrap().rows.len(), 2);
+}
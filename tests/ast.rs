@@ -0,0 +1,94 @@
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::error::BarecladError;
+use bareclad::traqula::ast::{self, AggregateFn, Command, Comparator, Condition, Operand, SetReference, Term, Value};
+use bareclad::traqula::Engine;
+
+#[test]
+fn searches_parse_into_patterns_predicates_and_projections() {
+    let script = ast::parse(
+        "search +p [{(w|h, wife), (*, husband)}, +n, *] as of @NOW by * certainty >= 50%, optional [{(w, name)}, +m, +t]
+         where n > 3 and not t in (search [{(+x, name)}, *, *] return x)
+         return p, count(distinct m) group by p order by p desc limit 5;",
+    )
+    .unwrap();
+    let Command::Search(search) = &script.commands[0] else { panic!("expected a search") };
+    let [first, second] = search.patterns.as_slice() else { panic!("expected two patterns") };
+    assert_eq!(first.posit, Some(Term::Insert("p".into())));
+    assert_eq!(first.appearances[0].thing, Term::Union(vec!["w".into(), "h".into()]));
+    assert_eq!(first.appearances[1].thing, Term::Wildcard);
+    assert_eq!(first.appearances[1].role.as_deref(), Some("husband"));
+    assert!(matches!(&first.as_of, Some(Term::Literal(l)) if l.value == Value::Constant("NOW".into())));
+    let certainty = first.certainty.as_ref().unwrap();
+    assert_eq!((certainty.positor.as_deref(), certainty.comparator, &certainty.certainty.value), (None, Comparator::GreaterOrEqual, &Value::Certainty(50)));
+    assert!(second.optional && !second.negated);
+    assert_eq!(second.time, Term::Insert("t".into()));
+    let Some(Condition::And(terms)) = &search.condition else { panic!("expected a conjunction") };
+    assert!(matches!(&terms[0], Condition::Compare { variable, comparator: Comparator::Greater, operand: Operand::Literal(l), .. } if variable == "n" && l.value == Value::Int(3)));
+    assert!(matches!(&terms[1], Condition::Not(inner) if matches!(inner.as_ref(), Condition::InSet { set: SetReference::Subquery(_), .. })));
+    let returned: Vec<String> = search.returns.iter().map(|r| r.column_name()).collect();
+    assert_eq!(returned, vec!["p", "count(distinct m)"]);
+    assert_eq!(search.returns[1].aggregate.map(|a| a.function), Some(AggregateFn::Count));
+    assert_eq!(search.group_by, Some(vec!["p".to_string()]));
+    assert!(search.order_by[0].descending);
+    assert_eq!(search.limit, Some(5));
+}
+
+#[test]
+fn nodes_carry_the_span_of_their_source_text() {
+    let source = "add role name;\nadd posit [{(+c, name)}, \"Acme\", '2020-01-01'];\nsearch [{(c, name)}, +n, *]\n  where n = \"Acme\" return n;";
+    let script = ast::parse(source).unwrap();
+    assert_eq!(script.commands.len(), 3);
+    let texts: Vec<&str> = script.commands.iter().map(|c| script.text(c.span())).collect();
+    assert_eq!(texts[0], "add role name;");
+    assert!(texts[2].starts_with("search") && texts[2].ends_with("return n;"));
+    let Command::AddPosit(add) = &script.commands[1] else { panic!("expected add posit") };
+    let time = &add.posits[0].time;
+    assert_eq!(script.text(time.span), "'2020-01-01'");
+    assert_eq!((time.span.line, time.span.col), (2, 34));
+    let Command::Search(search) = &script.commands[2] else { panic!("expected a search") };
+    let Some(Condition::Compare { span, .. }) = &search.condition else { panic!("expected a comparison") };
+    assert_eq!(script.text(*span), "n = \"Acme\"");
+    assert_eq!((span.line, span.col), (4, 9));
+}
+
+#[test]
+fn literal_values_print_as_traqula_source() {
+    let script = ast::parse("add posit [{(+c, name)}, \"say \"\"hi\"\"\", @NOW], [{(c, name)}, -12, '2020'], [{(c, name)}, 75%, '2020'], [{(c, name)}, 1.50, '2020'];").unwrap();
+    let Command::AddPosit(add) = &script.commands[0] else { panic!("expected add posit") };
+    assert_eq!(add.posits[0].value.value, Value::String("say \"hi\"".into()));
+    let printed: Vec<String> = add.posits.iter().map(|p| p.value.value.to_string()).collect();
+    assert_eq!(printed, vec!["\"say \"\"hi\"\"\"", "-12", "75%", "1.50"]);
+    let times: Vec<String> = add.posits.iter().map(|p| p.time.value.to_string()).collect();
+    assert_eq!(times, vec!["@NOW", "'2020'", "'2020'", "'2020'"]);
+}
+
+#[test]
+fn parse_errors_report_their_position_and_the_engine_runs_the_tree() {
+    match ast::parse("add role name;\nsearch [{(+c, name)}, +n, *] retrun n;").unwrap_err() {
+        BarecladError::Parse { message, line, col } => {
+            assert!(message.contains("Expected one of"));
+            assert_eq!(line, Some(2));
+            assert!(col.is_some());
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let engine = Engine::new(&db);
+    let prepared = engine.prepare("add role name; add posit [{(+c, name)}, $name, '2020']; search [{(*, name)}, +n, *] return n;").unwrap();
+    let Command::AddPosit(add) = &prepared.script().commands[1] else { panic!("expected add posit") };
+    assert_eq!(add.posits[0].value.value, Value::Param("name".into()));
+    let params = [("name".to_string(), bareclad::traqula::Param::String("Acme".into()))].into_iter().collect();
+    assert_eq!(engine.execute_prepared(&prepared, &params).unwrap().rows, vec![vec!["Acme"]]);
+}
+
+#[test]
+fn posits_in_roles_never_declared_are_errors() {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("add role name; add posit [{(+c, name)}, \"Acme\", '2020'], [{(c, nickname)}, \"Ac\", '2020'];").unwrap_err();
+    assert!(err.to_string().contains("Unknown role: nickname"), "{}", err);
+    // The failed command adds nothing and leaves the engine usable.
+    assert!(engine.execute_collect("search [{(*, name)}, +n, *] return n;").unwrap().rows.is_empty());
+    engine.execute_collect("add role name; add posit [{(+c, name)}, \"Acme\", '2020'];").unwrap();
+    assert_eq!(engine.execute_collect("search [{(*, name)}, +n, *] return n;").unwrap().rows, vec![vec!["Acme"]]);
+}
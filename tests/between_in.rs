@@ -0,0 +1,74 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_first_column};
+
+fn setup() -> Database {
+    database("add role ticket, status, priority, estimate, confidence; add posit [{(+t1, status)}, \"open\", '2019-06-01'], [{(t1, priority)}, 10, '2019-06-01'], [{(t1, estimate)}, 1.5, '2019-06-01'], [{(t1, confidence)}, 25%, '2019-06-01']; add posit [{(+t2, status)}, \"pending\", '2020-06-01'], [{(t2, priority)}, 20, '2020-06-01'], [{(t2, estimate)}, 2.5, '2020-06-01'], [{(t2, confidence)}, 50%, '2020-06-01']; add posit [{(+t3, status)}, \"closed\", '2021-06-01'], [{(t3, priority)}, 30, '2021-06-01'], [{(t3, estimate)}, 3.5, '2021-06-01'], [{(t3, confidence)}, 75%, '2021-06-01'];")
+}
+
+#[test]
+fn time_between_is_inclusive() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, +t] where t between '2020' and '2021' return s;");
+    assert_eq!(values, vec!["closed", "pending"]);
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, +t] where t not between '2020-01-01' and '2020-12-31' return s;");
+    assert_eq!(values, vec!["closed", "open"]);
+}
+
+#[test]
+fn value_between_across_types() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, priority)}, +p, *] where p between 15 and 30 return p;"), vec!["20", "30"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, estimate)}, +e, *] where e between 2 and 3.0 return e;"), vec!["2.5"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, confidence)}, +c, *] where c between 50% and 100% return c;"), vec!["0.50", "0.75"]);
+}
+
+#[test]
+fn value_and_time_membership() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, status)}, +s, *] where s in (\"open\", \"closed\") return s;"), vec!["closed", "open"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, status)}, +s, *] where s not in (\"open\", \"closed\") return s;"), vec!["pending"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, priority)}, +p, *] where p in (10, 30, 40) return p;"), vec!["10", "30"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, confidence)}, +c, *] where c in (25%) return c;"), vec!["0.25"]);
+    assert_eq!(sorted_first_column(&engine, "search [{(*, status)}, +s, +t] where t in ('2019-06-01', '2021-06-01') return s;"), vec!["closed", "open"]);
+}
+
+#[test]
+fn between_and_combines_with_boolean_and() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let values = sorted_first_column(&engine, "search [{(*, priority)}, +p, +t] where p between 10 and 20 and t >= '2020' return p;");
+    assert_eq!(values, vec!["20"]);
+}
+
+#[test]
+fn strict_type_checks_apply() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, status)}, +s, *] where s between 1 and 5 return s;").unwrap_err();
+    assert!(format!("{}", err).contains("Ordering comparison not allowed"));
+    let err = engine.execute_collect("search [{(*, confidence)}, +c, *] where c between 10 and 90 return c;").unwrap_err();
+    assert!(format!("{}", err).contains("percent sign"));
+}
+
+#[test]
+fn operands_that_cannot_be_compared_are_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    for script in [
+        "search [{(*, status)}, +s, +t] where t between '2020-13-45' and '2030' return s;",
+        "search [{(*, status)}, +s, +t] where t not between '2019' and '2020-13-45' return s;",
+        "search [{(*, status)}, +s, +t] where t in ('2020-13-45') return s;",
+        "search [{(*, status)}, +s, +t] where not t in ('2021-06-01', '2020-13-45') return s;",
+    ] {
+        let err = engine.execute_collect(script).unwrap_err();
+        assert!(matches!(err, bareclad::error::BarecladError::Parse { line: Some(1), col: Some(_), .. }), "{}: {}", script, err);
+        assert!(format!("{}", err).contains("'2020-13-45'"), "{}", err);
+    }
+}
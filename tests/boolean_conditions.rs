@@ -0,0 +1,78 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_first_column};
+
+fn setup() -> Database {
+    database("add role ticket, status, priority; add posit [{(+t1, status)}, \"open\", '2020-01-01'], [{(t1, priority)}, 10, '2020-01-01']; add posit [{(+t2, status)}, \"pending\", '2021-01-01'], [{(t2, priority)}, 20, '2021-01-01']; add posit [{(+t3, status)}, \"closed\", '2022-01-01'], [{(t3, priority)}, 30, '2022-01-01'];")
+}
+
+#[test]
+fn or_matches_either_side() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, *] where s == \"open\" or s == \"pending\" return s;");
+    assert_eq!(values, vec!["open", "pending"]);
+}
+
+#[test]
+fn not_negates_condition() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, *] where not s == \"closed\" return s;");
+    assert_eq!(values, vec!["open", "pending"]);
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // (s == "open") or (s == "closed" and t >= '2022-01-01')
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, +t] where s == \"open\" or s == \"closed\" and t >= '2022-01-01' return s;");
+    assert_eq!(values, vec!["closed", "open"]);
+    // Parentheses override precedence: (s == "open" or s == "closed") and t >= '2022-01-01'
+    let values = sorted_first_column(&engine, "search [{(*, status)}, +s, +t] where (s == \"open\" or s == \"closed\") and t >= '2022-01-01' return s;");
+    assert_eq!(values, vec!["closed"]);
+}
+
+#[test]
+fn grouping_across_patterns() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "search [{(+x, status)}, +s, *], [{(x, priority)}, +p, *] where not (p > 10 and s == \"pending\") and (p < 20 or p >= 30) return s;";
+    let values = sorted_first_column(&engine, script);
+    assert_eq!(values, vec!["closed", "open"]);
+}
+
+#[test]
+fn keyword_prefixed_variables_still_parse() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let values = sorted_first_column(&engine, "search [{(*, priority)}, +order, *] where order >= 20 and order <= 20 return order;");
+    assert_eq!(values, vec!["20"]);
+}
+
+#[test]
+fn type_errors_surface_inside_or() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, status)}, +s, *] where s < 5 or s == \"open\" return s;").unwrap_err();
+    assert!(format!("{}", err).contains("Ordering comparison not allowed"));
+}
+
+#[test]
+fn invalid_literals_are_errors_under_or_and_not() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    for script in [
+        "search [{(*, status)}, +s, +t] where t < '2020-13-45' return s;",
+        "search [{(*, status)}, +s, +t] where s = \"open\" or t < '2020-13-45' return s;",
+        "search [{(*, status)}, +s, +t] where not t < '2020-13-45' return s;",
+    ] {
+        let err = engine.execute_collect(script).unwrap_err();
+        assert!(matches!(err, bareclad::error::BarecladError::Parse { line: Some(1), col: Some(_), .. }), "{}: {}", script, err);
+        assert!(format!("{}", err).contains("'2020-13-45'"), "{}", err);
+    }
+}
@@ -0,0 +1,145 @@
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::datatype::Time;
+use bareclad::error::BarecladError;
+use bareclad::traqula::{Engine, Param};
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database("add role callsign, rank, squadron; add posit [{(+s, squadron)}, \"Nightjar\", '2025-01-01'];")
+}
+
+#[test]
+fn load_adds_the_templates_once_per_row() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    engine
+        .execute_collect(
+            "search [{(+s, squadron)}, \"Nightjar\", *] return s;
+             load [{(+p, callsign), (s, squadron)}, $callsign, $since], [{(p, rank)}, $rank, '2025-06-18']
+             values ($callsign, $since, $rank)
+                 (\"Kestrel\", '2025-02-01', 3),
+                 (\"Osprey\", \"2025-03-01\", 1),
+                 (\"Harrier\", '2025-04-01', 2);",
+        )
+        .unwrap();
+    // Each row has a pilot of its own, in the squadron found before the load.
+    let rows = sorted_rows(&engine, "search [{(+p, callsign), (*, squadron)}, +n, +t], [{(p, rank)}, +r, *] return n, t, r;");
+    assert_eq!(rows, vec![vec!["Harrier", "2025-04-01", "2"], vec!["Kestrel", "2025-02-01", "3"], vec!["Osprey", "2025-03-01", "1"]]);
+    // Posit variables collect the posits of every row, while inserted identities stay local to their row.
+    let result = engine
+        .execute_collect(
+            "load +c [{(+p, callsign)}, $callsign, '2025-06-18'] values ($callsign) (\"Merlin\"), (\"Hobby\");
+             add assertion [+me, c, 100%, '2025-06-19'];
+             search [{(*, callsign)}, +n, *] where n in (\"Merlin\", \"Hobby\") return count(n);",
+        )
+        .unwrap();
+    assert_eq!(result.rows, vec![vec!["2"]]);
+    let err = engine.execute_collect("load [{(+p, callsign)}, $callsign, '2025-06-18'] values ($callsign) (\"Merlin\"); add posit [{(p, rank)}, 4, '2025-06-18'];").unwrap_err();
+    assert!(err.to_string().contains("Unknown variable in posit: p"), "{}", err);
+}
+
+#[test]
+fn a_failing_row_loads_nothing() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine
+        .execute_collect("load [{(+p, callsign)}, $callsign, $since]\nvalues ($callsign, $since) (\"Kestrel\", '2025-02-01'), (\"Osprey\", 3);")
+        .unwrap_err();
+    match err {
+        BarecladError::Parse { message, line, col } => {
+            assert!(message.contains("Row value for $since must be a time: 3"), "{}", message);
+            assert_eq!((line, col), (Some(2), Some(66)));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    assert!(sorted_rows(&engine, "search [{(*, callsign)}, +n, *] return n;").is_empty());
+    let err = engine.execute_collect("load [{(+p, callsign)}, $callsign, '2025-02-01'] values ($callsign) (\"Kestrel\", 3);").unwrap_err();
+    assert!(err.to_string().contains("Row values do not match the placeholders ($callsign)"), "{}", err);
+}
+
+#[test]
+fn placeholders_not_bound_by_rows_are_parameters() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("load [{(+p, callsign)}, $callsign, $since] values ($callsign) (\"Kestrel\");").unwrap_err();
+    assert!(err.to_string().contains("Missing parameter: $since"), "{}", err);
+    let params = [("since".to_string(), Param::String("2025-02-01".into()))].into_iter().collect();
+    engine.execute_with_params("load [{(+p, callsign)}, $callsign, $since] values ($callsign) (\"Kestrel\"), (\"Osprey\");", &params).unwrap();
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, +t] return n, t;");
+    assert_eq!(rows, vec![vec!["Kestrel", "2025-02-01"], vec!["Osprey", "2025-02-01"]]);
+}
+
+#[test]
+fn bulk_loads_keep_the_ledger_intact() {
+    let path = "test_bareclad_bulk_load.db".to_string();
+    let _ = std::fs::remove_file(&path);
+    {
+        let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+        let (role, _) = db.create_role("reading".to_string(), false);
+        let time = Time::new();
+        db.load(|db| {
+            for reading in 0..1000i64 {
+                let (appearance, _) = db.create_apperance(*db.create_thing(), role.clone());
+                let (appearance_set, _) = db.create_appearance_set(vec![appearance]);
+                if reading % 2 == 0 {
+                    db.create_posit(appearance_set, reading, time.clone());
+                } else {
+                    db.create_posit(appearance_set, format!("note {reading}"), time.clone());
+                }
+            }
+            Ok(())
+        })
+        .unwrap();
+        let engine = Engine::new(&db);
+        engine.execute_collect("load [{(+r, reading)}, $value, '2025-06-18'] values ($value) (1.5), ({\"unit\": \"mm\"}), (75%), ('2025-01-01');").unwrap();
+        let mut persistor = db.persistor.lock().unwrap();
+        assert!(persistor.verify_integrity().is_ok());
+        assert_eq!(persistor.current_superhash().map(|(_, count)| count), Some(1004));
+    }
+    // Reopening restores what was loaded, and later posits chain on from it.
+    let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+    assert_eq!(db.posit_keeper().lock().unwrap().len(), 1004);
+    Engine::new(&db).execute_collect("add posit [{(+r, reading)}, 42, '2025-06-18'];").unwrap();
+    let mut persistor = db.persistor.lock().unwrap();
+    assert!(persistor.verify_integrity().is_ok());
+    assert_eq!(persistor.current_superhash().map(|(_, count)| count), Some(1005));
+    drop(persistor);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn loads_build_the_ordered_and_word_indexes_as_they_commit() {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let (role, _) = db.create_role("reading".to_string(), false);
+    let time = Time::new();
+    db.load(|db| {
+        for reading in 10..20i64 {
+            let (appearance, _) = db.create_apperance(*db.create_thing(), role.clone());
+            let (appearance_set, _) = db.create_appearance_set(vec![appearance]);
+            db.create_posit(appearance_set.clone(), reading, time.clone());
+            db.create_posit(appearance_set, format!("note {reading}"), time.clone());
+        }
+        assert!(db.i64_to_posit_thing_lookup.lock().unwrap().all().is_empty());
+        assert!(db.word_to_posit_thing_lookup.lock().unwrap().all().is_empty());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(db.i64_to_posit_thing_lookup.lock().unwrap().all().len(), 10);
+    assert_eq!(db.word_to_posit_thing_lookup.lock().unwrap().all().len(), 10);
+    assert_eq!(db.time_to_posit_thing_lookup.lock().unwrap().all().len(), 20);
+    let engine = Engine::new(&db);
+    assert_eq!(sorted_rows(&engine, "search [{(*, reading)}, +r, *] where r >= 18 return r;"), vec![vec!["18"], vec!["19"]]);
+    assert_eq!(sorted_rows(&engine, "search [{(*, reading)}, +r, *] where r matches \"note 13\" return r;"), vec![vec!["note 13"]]);
+    // A load that fails indexes nothing.
+    let failed: Result<(), BarecladError> = db.load(|db| {
+        let (appearance, _) = db.create_apperance(*db.create_thing(), role.clone());
+        let (appearance_set, _) = db.create_appearance_set(vec![appearance]);
+        db.create_posit(appearance_set, 42i64, time.clone());
+        Err(BarecladError::Execution("abandoned".into()))
+    });
+    assert!(failed.is_err());
+    assert_eq!(db.i64_to_posit_thing_lookup.lock().unwrap().all().len(), 10);
+}
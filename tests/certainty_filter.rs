@@ -0,0 +1,73 @@
+use bareclad::construct::Database;
+use bareclad::error::BarecladError;
+use bareclad::traqula::{Engine, Param};
+
+mod common;
+
+use common::{database, params, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role name, milestone;
+         add posit +p [{(+c, name)}, \"Acme\", '2020-01-01'], +q [{(+d, name)}, \"Bolt\", '2020-01-01'];
+         add assertion [+archie, p, 80%, '2021-01-01'], [archie, q, 75%, '2021-01-01'], [archie, q, -100%, '2022-01-01'];
+         add assertion [+bella, q, 90%, '2023-01-01'];
+         add posit [{(archie, name)}, \"Archie\", '2020-01-01'], [{(bella, name)}, \"Bella\", '2020-01-01'];
+         add posit [{(+m, milestone)}, \"review\", '2021-06-01'];",
+    )
+}
+
+#[test]
+fn latest_assertion_of_the_positor_decides() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+a, name)}, \"Archie\", *], [{(*, name)}, +n, *] by a certainty >= 75% return n;");
+    assert_eq!(rows, vec![vec!["Acme"]]);
+    let rows = sorted_rows(&engine, "search [{(+a, name)}, \"Archie\", *], [{(*, name)}, +n, *] by a certainty < 0% return n;");
+    assert_eq!(rows, vec![vec!["Bolt"]]);
+}
+
+#[test]
+fn as_of_selects_an_earlier_opinion() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+a, name)}, \"Archie\", *], [{(*, name)}, +n, *] as of '2021-06-01' by a certainty >= 75% return n;");
+    assert_eq!(rows, vec![vec!["Acme"], vec!["Bolt"]]);
+    let rows = sorted_rows(&engine, "search [{(*, milestone)}, *, +mt], [{(+a, name)}, \"Archie\", *], [{(*, name)}, +n, *] as of mt by a certainty >= 75% return n;");
+    assert_eq!(rows, vec![vec!["Acme"], vec!["Bolt"]]);
+}
+
+#[test]
+fn wildcard_accepts_any_positor_and_bound_positors_pair_per_row() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(*, name)}, +n, *] by * certainty >= 80% return n;");
+    assert_eq!(rows, vec![vec!["Acme"], vec!["Bolt"]]);
+    // Positors without assertions (and their own name posits) never qualify.
+    let rows = sorted_rows(&engine, "search [{(+a, name)}, +who, *], [{(*, name)}, +n, *] by a certainty >= 75% return who, n;");
+    assert_eq!(rows, vec![vec!["Archie", "Acme"], vec!["Bella", "Bolt"]]);
+}
+
+#[test]
+fn unknown_positor_is_an_error() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, name)}, +n, *] by nobody certainty >= 50% return n;").unwrap_err();
+    assert!(format!("{}", err).contains("Unknown positor variable in certainty filter: nobody"));
+}
+
+#[test]
+fn certainties_outside_a_hundred_percent_are_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    for (script, col) in [("search [{(*, name)}, +n, *] by * certainty >= 150% return n;", 47), ("search [{(*, name)}, +n, *] by * certainty < -500% return n;", 46)] {
+        let err = engine.execute_collect(script).unwrap_err();
+        assert!(matches!(err, BarecladError::Parse { line: Some(1), col: Some(c), .. } if c == col), "{}: {}", script, err);
+        assert!(format!("{}", err).contains("Certainty must be between -100% and 100%"), "{}", err);
+    }
+    let script = "search [{(*, name)}, +n, *] by * certainty >= $c return n;";
+    for c in [Param::Certainty(120), Param::String("150%".into())] {
+        let err = engine.execute_with_params(script, &params(&[("c", c)])).unwrap_err();
+        assert!(matches!(err, BarecladError::Parse { line: Some(1), col: Some(47), .. }), "{}", err);
+    }
+}
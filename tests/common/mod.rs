@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::traqula::{Engine, Param};
+
+/// An in-memory database with `script` run against it.
+pub fn database(script: &str) -> Database {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    Engine::new(&db).execute_collect(script).expect("setup ok");
+    db
+}
+
+/// Rows of the last search in `script`, sorted so results compare independently of binding order.
+pub fn sorted_rows(engine: &Engine, script: &str) -> Vec<Vec<String>> {
+    let mut rows = engine.execute_collect(script).expect("query ok").rows;
+    rows.sort();
+    rows
+}
+
+/// First column of [`sorted_rows`].
+pub fn sorted_first_column(engine: &Engine, script: &str) -> Vec<String> {
+    sorted_rows(engine, script).into_iter().map(|mut row| row.remove(0)).collect()
+}
+
+/// Named parameters for `Engine::execute_with_params` and friends.
+pub fn params(pairs: &[(&str, Param)]) -> HashMap<String, Param> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+}
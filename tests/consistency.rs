@@ -0,0 +1,77 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database(
+        "add role name, nickname;
+         add posit +p1 [{(+c, name)}, \"Acme\", '2020-01-01'], +p2 [{(c, name)}, \"Acme Inc\", '2020-01-01'], +p3 [{(c, name)}, \"Acme\", '2019-01-01'];
+         add posit +p4 [{(+d, nickname)}, \"Bolt\", '2020-01-01'], +p5 [{(d, nickname)}, \"Bolt Ltd\", '2020-01-01'];
+         add assertion [+archie, p1, 75%, '2021-01-01'], [archie, p2, 75%, '2021-01-01'], [archie, p2, -100%, '2022-01-01'], [archie, p3, 90%, '2021-01-01'];
+         add assertion [+bella, p1, 60%, '2021-01-01'], [bella, p2, -80%, '2021-01-01'];
+         add assertion [+doris, p4, 80%, '2021-01-01'], [doris, p5, 80%, '2021-01-01'];",
+    )
+}
+
+/// (group, value, certainty) of each reported assertion.
+fn reported(engine: &Engine, script: &str) -> Vec<(String, String, String)> {
+    let res = engine.execute_collect(script).expect("check ok");
+    assert_eq!(res.columns, vec!["group", "positor", "posit", "value", "time", "certainty"]);
+    res.rows.into_iter().map(|r| (r[0].clone(), r[3].clone(), r[5].clone())).collect()
+}
+
+fn triple(group: &str, value: &str, certainty: &str) -> (String, String, String) {
+    (group.to_string(), value.to_string(), certainty.to_string())
+}
+
+#[test]
+fn reports_self_contradicting_positors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // Archie retracted 'Acme Inc' in 2022 and Bella's opinions are consistent; only Doris's opinions contradict each other.
+    let rows = reported(&engine, "check consistency;");
+    assert_eq!(rows, vec![triple("1", "Bolt", "0.80"), triple("1", "Bolt Ltd", "0.80")]);
+    let res = engine.execute_collect("check consistency;").unwrap();
+    assert_eq!(res.rows[0][1], res.rows[1][1]);
+    assert_eq!(res.rows[0][4], "2020-01-01");
+    assert_eq!(res.row_types[0], vec!["i64", "Thing", "Thing", "String", "Time", "Certainty"]);
+    // Each check is its own result set in multi-result execution.
+    let sets = engine.execute_collect_multi("check consistency; check consistency as of '2021-06-01';").unwrap();
+    assert_eq!(sets.iter().map(|s| s.row_count).collect::<Vec<_>>(), vec![2, 4]);
+}
+
+#[test]
+fn as_of_uses_the_opinions_held_at_that_time() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = reported(&engine, "check consistency as of '2021-06-01';");
+    assert_eq!(
+        rows,
+        vec![triple("1", "Acme", "0.75"), triple("1", "Acme Inc", "0.75"), triple("2", "Bolt", "0.80"), triple("2", "Bolt Ltd", "0.80")]
+    );
+}
+
+#[test]
+fn scope_restricts_roles_and_identities() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = reported(&engine, "check consistency [{(*, name)}, *, *] as of '2021-06-01';");
+    assert_eq!(rows, vec![triple("1", "Acme", "0.75"), triple("1", "Acme Inc", "0.75")]);
+    // A recalled identity bound by an earlier search in the script narrows the scope.
+    let res = engine.execute_collect("search [{(+x, nickname)}, \"Bolt\", *] return x; check consistency [{(x, nickname)}, *, *];").unwrap();
+    assert_eq!(res.rows.len(), 3);
+    assert_eq!(res.rows[1][3], "Bolt");
+}
+
+#[test]
+fn scope_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("check consistency [{(+x, name)}, *, *];").unwrap_err();
+    assert!(format!("{}", err).contains("cannot bind variables"));
+    let err = engine.execute_collect("check consistency [{(nobody, name)}, *, *];").unwrap_err();
+    assert!(format!("{}", err).contains("Unknown variable in check consistency: nobody"));
+}
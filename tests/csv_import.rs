@@ -0,0 +1,92 @@
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::import::{Mapping, RowError, import_csv};
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::sorted_rows;
+
+fn mapping(json: &str) -> Mapping {
+    serde_json::from_str(json).expect("mapping ok")
+}
+
+const PILOTS: &str = r#"{
+    "identities": { "pilot": ["pilot_id"], "squadron": ["squadron"] },
+    "posits": [
+        { "appearances": [{ "identity": "pilot", "role": "callsign" }], "value": "callsign", "type": "String", "time": { "column": "since" } },
+        { "appearances": [{ "identity": "pilot", "role": "rank" }], "value": "rank", "type": "i64", "time": { "constant": "2025-06-18" } },
+        { "appearances": [{ "identity": "pilot", "role": "pilot" }, { "identity": "squadron", "role": "squadron" }],
+          "value": "posted", "type": "Certainty", "time": { "column": "since" } }
+    ]
+}"#;
+
+#[test]
+fn rows_become_posits_about_shared_things() {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let csv = "pilot_id,callsign,rank,squadron,posted,since\n\
+               1,Kestrel,3,Nightjar,100%,2025-02-01\n\
+               2,\"Osprey, \"\"the elder\"\"\",1,Nightjar,75%,2025-03-01\n\
+               3,Harrier,,Swift,100%,2025-04-01\n";
+    let report = import_csv(&db, csv.as_bytes(), &mapping(PILOTS)).unwrap();
+    assert_eq!((report.rows, report.posits), (3, 8));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+p, callsign)}, +n, +t], [{(p, pilot), (+s, squadron)}, +c, *] return n, t, c;");
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Harrier", "2025-04-01", "1"],
+            vec!["Kestrel", "2025-02-01", "1"],
+            vec!["Osprey, \"the elder\"", "2025-03-01", "0.75"],
+        ]
+    );
+    // Rows with the same key values share a thing; empty values leave out their posit.
+    let rows = sorted_rows(&engine, "search [{(*, pilot), (+s, squadron)}, *, *] return count(s);");
+    assert_eq!(rows, vec![vec!["3"]]);
+    let rows = sorted_rows(&engine, "search [{(+p, callsign)}, \"Kestrel\", *], [{(p, pilot), (+s, squadron)}, *, *], [{(+q, pilot), (s, squadron)}, *, *] return count(q);");
+    assert_eq!(rows, vec![vec!["2"]]);
+    assert_eq!(sorted_rows(&engine, "search [{(*, rank)}, +r, *] return r;"), vec![vec!["1"], vec!["3"]]);
+}
+
+#[test]
+fn bad_rows_are_reported_while_the_others_are_imported() {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let csv = "pilot_id,callsign,rank,squadron,posted,since\n\
+               1,Kestrel,three,Nightjar,100%,2025-02-01\n\
+               2,Osprey,1,Nightjar,75%,yesterday\n\
+               3,\"Harrier\nof the North\",2,Swift,100%,2025-04-01\n\
+               ,Merlin,4,Swift,100%,2025-05-01\n\
+               5,Hobby,4\n\
+               6,Peregrine,5,Swift,150%,2025-06-01\n";
+    let report = import_csv(&db, csv.as_bytes(), &mapping(PILOTS)).unwrap();
+    assert_eq!((report.rows, report.posits), (6, 3));
+    assert_eq!(
+        report.errors,
+        vec![
+            RowError { line: 2, message: "Invalid i64 value 'three'".into() },
+            RowError { line: 3, message: "Invalid time 'yesterday'".into() },
+            RowError { line: 6, message: "No key for identity 'pilot'".into() },
+            RowError { line: 7, message: "Expected 6 fields, found 3".into() },
+            RowError { line: 8, message: "Invalid Certainty value '150%'".into() },
+        ]
+    );
+    // Nothing of a failing row is kept.
+    let engine = Engine::new(&db);
+    assert_eq!(sorted_rows(&engine, "search [{(*, callsign)}, +n, *] return n;"), vec![vec!["Harrier\nof the North"]]);
+}
+
+#[test]
+fn mappings_that_do_not_fit_the_header_import_nothing() {
+    let db = Database::new(PersistenceMode::InMemory).unwrap();
+    let csv = "pilot_id;callsign;since\n1;Kestrel;2025-02-01\n";
+    let err = import_csv(&db, csv.as_bytes(), &mapping(PILOTS)).unwrap_err();
+    assert!(err.to_string().contains("Column 'pilot_id' is not in the CSV header"), "{}", err);
+    let semicolons = mapping(
+        r#"{ "delimiter": ";", "identities": { "pilot": ["pilot_id"] },
+             "posits": [{ "appearances": [{ "identity": "pilot", "role": "callsign" }, { "identity": "pilot", "role": "callsign" }],
+                          "value": "callsign", "type": "String", "time": { "column": "since" } }] }"#,
+    );
+    let err = import_csv(&db, csv.as_bytes(), &semicolons).unwrap_err();
+    assert!(err.to_string().contains("Role 'callsign' appears twice in a posit"), "{}", err);
+    assert!(db.role_keeper().lock().unwrap().find("callsign").is_none());
+}
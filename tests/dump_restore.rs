@@ -0,0 +1,117 @@
+use bareclad::construct::{Database, PersistenceMode, MAX_RESTORED_THING};
+use bareclad::error::BarecladError;
+use bareclad::traqula::{Engine, dump, restore};
+
+mod common;
+
+use common::{database, sorted_rows};
+
+const SCRIPT: &str = r#"
+    add role callsign, rank, squadron, pilot, emblem, note;
+    add posit +k [{(+p, callsign)}, "Kestrel ""Kes""", '2025-06-18 21:05:00'], [{(p, rank)}, 3, '2025'], [{(+s, squadron)}, 2.0, '2025-02'];
+    add posit [{(p, pilot), (s, squadron)}, 75%, @BOT], [{(s, emblem)}, {"colours": ["red", "}"]}, '2025-06-18'], [{(s, note)}, '2025-06-01', @EOT];
+    add posit [{(k, posit), (+c, note)}, "Checked", '2025-06-19'];
+    add assertion [+me, k, 100%, '2025-06-19 10:00:00.5'];
+    add posit [{(+q, emblem)}, [1, 2, {"a": "]"}], '2025-06-18'];
+"#;
+
+const DUMP: &str = r#"add role callsign, rank, squadron, pilot, emblem, note;
+add posit +p12 [{(+t11, callsign)}, "Kestrel ""Kes""", '2025-06-18 21:05:00'];
+add posit +p13 [{(t11, rank)}, 3, '2025'];
+add posit +p15 [{(+t14, squadron)}, 2.0, '2025-02'];
+add posit +p16 [{(t11, pilot), (t14, squadron)}, 75%, @BOT];
+add posit +p17 [{(t14, emblem)}, {"colours":["red","}"]}, '2025-06-18'];
+add posit +p18 [{(t14, note)}, '2025-06-01', @EOT];
+add posit +p20 [{(+t19, note), (p12, posit)}, "Checked", '2025-06-19'];
+add assertion +p22 [+t21, p12, 100%, '2025-06-19 10:00:00.500'];
+add posit +p24 [{(+t23, emblem)}, [1,2,{"a":"]"}], '2025-06-18'];
+"#;
+
+const SEARCH: &str = "search +k [{(+p, callsign)}, +n, +t], [{(p, pilot), (+s, squadron)}, +c, *], [{(k, posit), (*, note)}, +m, *], [{(s, note)}, +d, *] return k, p, n, t, c, m, d;";
+
+#[test]
+fn dumps_name_variables_after_identities_and_restore_keeps_them() {
+    let db = database(SCRIPT);
+    let script = dump(&db).unwrap();
+    assert_eq!(script, DUMP);
+    let restored = Database::new(PersistenceMode::InMemory).unwrap();
+    restore(&restored, &script).unwrap();
+    assert_eq!(dump(&restored).unwrap(), script);
+    let rows = sorted_rows(&Engine::new(&restored), SEARCH);
+    assert_eq!(rows, sorted_rows(&Engine::new(&db), SEARCH));
+    assert_eq!(rows[0][..3], ["12", "11", "Kestrel \"Kes\""]);
+    // New things in the restored database do not reuse identities.
+    let result = Engine::new(&restored).execute_collect("add posit [{(+p, callsign)}, \"Osprey\", '2025-06-20']; search +k [{(+p, callsign)}, \"Osprey\", *] return k;").unwrap();
+    assert!(result.rows[0][0].parse::<u64>().unwrap() > 24);
+}
+
+#[test]
+fn dumps_replay_as_ordinary_scripts() {
+    let db = database("add posit [{(+x, thing)}, \"Filler\", @NOW];");
+    let engine = Engine::new(&db);
+    engine.execute_collect(DUMP).unwrap();
+    let rows = sorted_rows(&engine, "search [{(+p, callsign)}, +n, +t], [{(p, pilot), (*, squadron)}, +c, *] return n, t, c;");
+    assert_eq!(rows, vec![vec!["Kestrel \"Kes\"", "2025-06-18 21:05:00", "0.75"]]);
+}
+
+#[test]
+fn restores_that_do_not_fit_restore_nothing() {
+    let db = database(SCRIPT);
+    let posits = db.posit_keeper().lock().unwrap().len();
+    // The identities are taken already.
+    let err = restore(&db, DUMP).unwrap_err();
+    assert!(err.to_string().contains("Thing 11 is already in use"), "{}", err);
+    let other = database("add role callsign; add posit [{(+p, callsign)}, \"Osprey\", '2025-06-20'];");
+    let err = restore(&other, "add posit +p7 [{(+t9, callsign)}, \"Osprey\", '2025-06-20'];").unwrap_err();
+    assert!(err.to_string().contains("Thing 7 is already in use"), "{}", err);
+    let err = restore(&other, "add posit +p30 [{(+t29, callsign)}, \"Kestrel\", '2025-06-18'];\nadd posit +p31 [{(t29, callsign)}, \"Kestrel\", '2025-06-18'];").unwrap_err();
+    assert!(err.to_string().contains("Posit 31 is already kept as 30"), "{}", err);
+    // Only dumps can be restored.
+    match restore(&other, "add posit +p30 [{(+t29, callsign)}, \"Kestrel\", '2025-06-18'];\nadd posit [{(+pilot, callsign)}, \"Merlin\", '2025-06-18'];").unwrap_err() {
+        BarecladError::Parse { message, line, .. } => {
+            assert!(message.contains("Expected a variable such as p12, named after an identity, found ''"), "{}", message);
+            assert_eq!(line, Some(2));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    let err = restore(&other, "search [{(+p, callsign)}, +n, *] return n;").unwrap_err();
+    assert!(err.to_string().contains("Only add role, add posit and add assertion commands can be restored"), "{}", err);
+    assert_eq!(db.posit_keeper().lock().unwrap().len(), posits);
+    assert_eq!(sorted_rows(&Engine::new(&other), "search [{(*, callsign)}, +n, *] return n;"), vec![vec!["Osprey"]]);
+    assert!(other.thing_generator().lock().unwrap().check(29).is_none());
+}
+
+#[test]
+fn restored_databases_persist() {
+    let path = "test_bareclad_dump_restore.db".to_string();
+    let _ = std::fs::remove_file(&path);
+    {
+        let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+        restore(&db, DUMP).unwrap();
+        let mut persistor = db.persistor.lock().unwrap();
+        assert!(persistor.verify_integrity().is_ok());
+        assert_eq!(persistor.current_superhash().map(|(_, count)| count), Some(9));
+    }
+    let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+    assert_eq!(dump(&db).unwrap(), DUMP);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn identities_out_of_range_are_not_restored() {
+    let db = database("add role callsign;");
+    let huge = format!("add posit +p7 [{{(+t{}, callsign)}}, \"Kestrel\", '2025-06-18'];", u64::MAX);
+    match restore(&db, &huge).unwrap_err() {
+        BarecladError::Parse { message, line, .. } => {
+            assert!(message.contains(&format!("Identity {} is above the highest that can be restored", u64::MAX)), "{}", message);
+            assert_eq!(line, Some(1));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    let err = db.restore_thing(MAX_RESTORED_THING + 1).unwrap_err();
+    assert!(err.to_string().contains("above the highest identity that can be restored"), "{}", err);
+    // The highest identity restores, and things generated afterwards follow it.
+    restore(&db, &format!("add posit +p7 [{{(+t{}, callsign)}}, \"Kestrel\", '2025-06-18'];", MAX_RESTORED_THING)).unwrap();
+    let result = Engine::new(&db).execute_collect("add posit [{(+p, callsign)}, \"Osprey\", '2025-06-20']; search [{(+p, callsign)}, \"Osprey\", *] return p;").unwrap();
+    assert!(result.rows[0][0].parse::<u64>().unwrap() > MAX_RESTORED_THING);
+}
@@ -0,0 +1,97 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database(
+        "add role name, employees, headquarters;
+         add posit [{(+a, name)}, \"Acme\", '2020-01-01'], [{(a, employees)}, 120, '2020-01-01'], [{(a, headquarters)}, \"Oslo\", '2020-01-01'];
+         add posit [{(+b, name)}, \"Bolt\", '2020-01-01'], [{(b, employees)}, 8, '2021-01-01'];
+         add posit [{(+c, name)}, \"Cogs\", '2020-01-01'], [{(c, employees)}, 45, '2022-01-01'];
+         add posit [{(+d, name)}, \"Dyne\", '2020-01-01'];",
+    )
+}
+
+#[test]
+fn explain_lists_patterns_in_evaluation_order() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let result = engine
+        .execute_collect("explain search [{(+x, name)}, +n, *], [{(x, employees)}, +e, *], [{(x, headquarters)}, *, *] where e > 10 return n, e;")
+        .unwrap();
+    assert_eq!(result.columns, vec!["step", "operation", "source", "estimate", "pushdown"]);
+    let rows: Vec<Vec<&str>> = result.rows.iter().map(|r| r.iter().map(String::as_str).collect()).collect();
+    assert_eq!(
+        rows,
+        vec![
+            vec!["1", "match", "[{(x, employees)}, +e, *]", "1", "e > 10"],
+            vec!["2", "match", "[{(x, headquarters)}, *, *]", "1", ""],
+            vec!["3", "match", "[{(+x, name)}, +n, *]", "4", ""],
+            vec!["4", "where", "e > 10", "", ""],
+            vec!["5", "return", "n, e", "", ""],
+        ]
+    );
+    assert_eq!(result.row_types[3], vec!["i64", "String", "String", "null", "String"]);
+}
+
+#[test]
+fn explain_analyze_reports_what_each_step_did() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let result = engine.execute_collect("explain analyze search [{(+x, name)}, +n, *], [{(x, employees)}, +e, +t] where e > 10 and t > '2020-06-01' return n;").unwrap();
+    assert_eq!(&result.columns[5..], ["candidates", "bindings", "filtered", "time_us"]);
+    let cells = |operation: &str| -> Vec<Vec<String>> {
+        result.rows.iter().filter(|r| r[1] == operation).map(|r| [&r[2..3], &r[5..8]].concat()).collect()
+    };
+    // Pushed down comparisons drop 'Acme' (2020) and 'Bolt' (8 employees) before any bindings are built.
+    assert_eq!(cells("match"), vec![vec!["[{(x, employees)}, +e, +t]", "1", "1", "2"], vec!["[{(+x, name)}, +n, *]", "1", "1", "0"]]);
+    assert_eq!(cells("where"), vec![vec!["e > 10", "1", "", "0"], vec!["t > '2020-06-01'", "1", "", "0"]]);
+    assert_eq!(cells("return"), vec![vec!["n", "", "1", ""]]);
+    assert!(result.rows.iter().all(|r| r[8].parse::<u64>().is_ok() || r[1] == "where" && r[8].is_empty()));
+}
+
+#[test]
+fn explain_analyze_counts_rows_filtered_by_where_and_not() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let result = engine
+        .execute_collect("explain analyze search [{(+x, name)}, +n, *], optional [{(x, employees)}, +e, *], not [{(x, headquarters)}, *, *] where not n = \"Bolt\" or e > 100 return n;")
+        .unwrap();
+    let summary: Vec<(&str, &str, &str)> = result.rows.iter().map(|r| (r[1].as_str(), r[6].as_str(), r[7].as_str())).collect();
+    assert_eq!(
+        summary,
+        vec![("match", "4", "0"), ("optional match", "4", "0"), ("exclude", "3", "1"), ("where", "", "1"), ("where", "", "1"), ("return", "2", "")]
+    );
+}
+
+#[test]
+fn explain_is_a_result_set_of_its_own() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let sets = engine.execute_collect_multi("search [{(*, name)}, +n, *] return n; explain search [{(*, name)}, +n, *] return n;").unwrap();
+    assert_eq!(sets.len(), 2);
+    assert_eq!(sets[0].row_count, 4);
+    assert_eq!(sets[1].search.as_deref(), Some("explain search [{(*, name)}, +n, *] return n;"));
+    assert_eq!(sets[1].rows[0][2], "[{(*, name)}, +n, *]");
+    let prepared = engine.prepare("explain analyze search [{(*, employees)}, +e, *] where e > $min return e;").unwrap();
+    assert_eq!(prepared.result_sets(), 1);
+    let params = [("min".to_string(), bareclad::traqula::Param::Int(10))].into_iter().collect();
+    let result = engine.execute_prepared(&prepared, &params).unwrap();
+    assert_eq!(result.rows.last().unwrap()[6], "2");
+}
+
+#[test]
+fn explain_analyze_handles_roles_never_declared() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let result = engine
+        .execute_collect("explain analyze search [{(+x, name)}, +n, *], optional [{(x, motto)}, +m, *], not [{(x, founder)}, *, *] return n, m;")
+        .unwrap();
+    let summary: Vec<(&str, &str, &str)> = result.rows.iter().map(|r| (r[1].as_str(), r[5].as_str(), r[6].as_str())).collect();
+    assert_eq!(summary, vec![("match", "4", "4"), ("optional match", "0", "4"), ("exclude", "0", "4"), ("return", "", "4")]);
+    let err = engine.execute_collect("explain analyze search [{(+x, name)}, +n, *], [{(x, motto)}, +m, *] return n, m;").unwrap_err();
+    assert!(err.to_string().contains("Unknown role: motto"), "{}", err);
+}
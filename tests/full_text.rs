@@ -0,0 +1,54 @@
+use bareclad::construct::Database;
+use bareclad::traqula::{Engine, Param};
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role witness, sighting, confidence;
+         add posit [{(+guide, witness)}, \"Holo-Guide 42\", '2025-06-18'], [{(guide, sighting)}, \"Specter phase-shifted through east wing\", '2025-06-18 22:10'], [{(guide, confidence)}, 82%, '2025-06-18'];
+         add posit [{(+curator, witness)}, \"Curator Emeritus\", '2025-06-18'], [{(curator, sighting)}, \"Archivist carrying Chrono-Lotus\", '2025-06-18 22:22'], [{(curator, confidence)}, 97%, '2025-06-18'];
+         add posit [{(+sentinel, witness)}, \"Sentinel Drone S9\", '2025-06-18'], [{(sentinel, sighting)}, \"Extraction corridor sealed\", '2025-06-18 22:21'];",
+    )
+}
+
+#[test]
+fn matches_every_word_ignoring_case_and_punctuation() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+w, witness)}, +n, *], [{(w, sighting)}, +report, *] where report matches \"Phase shifted\" return n;");
+    assert_eq!(rows, vec![vec!["Holo-Guide 42"]]);
+    let rows = sorted_rows(&engine, "search [{(+w, witness)}, +n, *], [{(w, sighting)}, +report, *] where report matches \"phase sealed\" return n;");
+    assert!(rows.is_empty());
+    let rows = sorted_rows(&engine, "search [{(+w, witness)}, +n, *], [{(w, sighting)}, +report, *] where report not matches \"chrono-lotus\" return n;");
+    assert_eq!(rows, vec![vec!["Holo-Guide 42"], vec!["Sentinel Drone S9"]]);
+}
+
+#[test]
+fn trailing_star_matches_word_prefixes() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(*, sighting)}, +report, *] where report matches \"ch*\" or report matches \"corr*\" return report;");
+    assert_eq!(rows, vec![vec!["Archivist carrying Chrono-Lotus"], vec!["Extraction corridor sealed"]]);
+    let rows = sorted_rows(&engine, "search [{(*, witness)}, +n, *] where n matches \"s*\" return n;");
+    assert_eq!(rows, vec![vec!["Sentinel Drone S9"]]);
+}
+
+#[test]
+fn matches_binds_string_parameters_and_rejects_other_variables() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let params = [("text".to_string(), Param::String("EAST wing".into()))].into_iter().collect();
+    let result = engine.execute_with_params("search [{(+w, witness)}, +n, *], [{(w, sighting)}, +report, *] where report matches $text return n;", &params).unwrap();
+    assert_eq!(result.rows, vec![vec!["Holo-Guide 42"]]);
+    let params = [("text".to_string(), Param::Int(3))].into_iter().collect();
+    let err = engine.execute_with_params("search [{(*, sighting)}, +report, *] where report matches $text return report;", &params).unwrap_err();
+    assert!(err.to_string().contains("Parameter $text must be a string"));
+    // Non-string values never match.
+    let rows = sorted_rows(&engine, "search [{(*, confidence)}, +c, *] where c matches \"82\" return c;");
+    assert!(rows.is_empty());
+    let err = engine.execute_collect("search [{(+w, witness)}, *, *] where w matches \"guide\" return w;").unwrap_err();
+    assert!(err.to_string().contains("Non-value variable used in matches: w"));
+}
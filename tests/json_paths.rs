@@ -0,0 +1,68 @@
+use bareclad::construct::Database;
+use bareclad::traqula::{Engine, Param};
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role agent, gadget;
+         add posit [{(+nova, agent)}, \"Nova Meridian\", '2024-04-01'], [{(nova, gadget)}, { \"name\": \"Phase Cloak\", \"revision\": 3, \"weight\": 1.25, \"active\": true, \"parts\": [\"lens\", \"coil\"] }, '2025-06-18'];
+         add posit [{(+quill, agent)}, \"Ivo Quill\", '2024-06-12'], [{(quill, gadget)}, { \"name\": \"Chrono Scanner\", \"revision\": 1, \"weight\": 0.5, \"active\": false }, '2025-06-18'];
+         add posit [{(+rook, agent)}, \"Rook\", '2024-07-01'], [{(rook, gadget)}, \"improvised\", '2025-06-18'];",
+    )
+}
+
+#[test]
+fn where_compares_extracted_scalars_by_type() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"revision\" > 2 return n;");
+    assert_eq!(rows, vec![vec!["Nova Meridian"]]);
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"weight\" < 1 or g->\"name\" = \"Phase Cloak\" return n;");
+    assert_eq!(rows, vec![vec!["Ivo Quill"], vec!["Nova Meridian"]]);
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"active\" = false return n;");
+    assert_eq!(rows, vec![vec!["Ivo Quill"]]);
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"active\" == true return n;");
+    assert_eq!(rows, vec![vec!["Nova Meridian"]]);
+    // Booleans equal only booleans, and no appearing value is one.
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"name\" = true or n = true return n;");
+    assert!(rows.is_empty());
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where g->\"parts\"->1 = \"coil\" return n;");
+    assert_eq!(rows, vec![vec!["Nova Meridian"]]);
+    // Missing keys and values other than JSON extract null, which no comparison holds for.
+    let rows = sorted_rows(&engine, "search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] where not g->\"missing\" = 1 return n;");
+    assert_eq!(rows.len(), 3);
+    let params = [("min".to_string(), Param::Int(1))].into_iter().collect();
+    let result = engine.execute_with_params("search [{(*, gadget)}, +g, *] where g->\"revision\" >= $min return g->\"revision\" order by g->\"revision\";", &params).unwrap();
+    assert_eq!(result.rows, vec![vec!["1"], vec!["3"]]);
+}
+
+#[test]
+fn ordering_a_mistyped_scalar_is_an_error() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, gadget)}, +g, *] where g->\"name\" > 2 return g;").unwrap_err();
+    assert!(err.to_string().contains("Ordering comparison not allowed: g->\"name\"(String) > 2"), "{}", err);
+    let err = engine.execute_collect("search [{(*, gadget)}, +g, *] where g->\"active\" > false return g;").unwrap_err();
+    assert!(err.to_string().contains("Ordering comparison not allowed: g->\"active\"(bool) > false"), "{}", err);
+    let err = engine.execute_collect("search [{(*, gadget)}, +g, *] where g->\"name\" = '2020-13-45' return g;").unwrap_err();
+    assert!(matches!(err, bareclad::error::BarecladError::Parse { line: Some(1), col: Some(49), .. }), "{}", err);
+    let err = engine.execute_collect("search [{(+a, agent)}, *, *] where a->\"name\" = 1 return a;").unwrap_err();
+    assert!(err.to_string().contains("Non-value variable used in JSON path: a"), "{}", err);
+}
+
+#[test]
+fn return_projects_typed_scalars() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let result = engine.execute_collect("search [{(+a, agent)}, +n, *], [{(a, gadget)}, +g, *] return n, g->\"name\", g->\"weight\", g->\"active\", g->\"parts\" order by n;").unwrap();
+    assert_eq!(result.columns, vec!["n", "g->\"name\"", "g->\"weight\"", "g->\"active\"", "g->\"parts\""]);
+    assert_eq!(result.rows[0], vec!["Ivo Quill", "Chrono Scanner", "0.5", "false", ""]);
+    assert_eq!(result.row_types[0], vec!["String", "String", "Decimal", "bool", "null"]);
+    assert_eq!(result.rows[1], vec!["Nova Meridian", "Phase Cloak", "1.25", "true", "[\"lens\",\"coil\"]"]);
+    assert_eq!(result.row_types[1], vec!["String", "String", "Decimal", "bool", "JSON"]);
+    let result = engine.execute_collect("search [{(*, gadget)}, +g, *] return sum(g->\"revision\"), max(g->\"weight\"), count(g->\"name\");").unwrap();
+    assert_eq!(result.rows, vec![vec!["4", "1.25", "2"]]);
+}
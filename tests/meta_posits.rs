@@ -0,0 +1,67 @@
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+const SETUP: &str = "add role name, source, comment;
+    add posit +p [{(+c, name)}, \"Acme\", '2020-01-01'], [{(p, posit), (+src, source)}, \"scanned letter\", '2024'];
+    add posit [{(p, posit)}, \"check spelling\", '2024'];";
+
+#[test]
+fn posit_identities_join_meta_posits_in_both_directions() {
+    let db = database(SETUP);
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search +p [{(*, name)}, +n, *], [{(p, posit), (*, source)}, +origin, *] return n, origin;");
+    assert_eq!(rows, vec![vec!["Acme", "scanned letter"]]);
+    let rows = sorted_rows(&engine, "search [{(+p, posit), (*, source)}, +origin, *], p [{(*, name)}, +n, *] return n, origin;");
+    assert_eq!(rows, vec![vec!["Acme", "scanned letter"]]);
+    // A pattern with only the posit role also matches meta-posits with further roles.
+    let rows = sorted_rows(&engine, "search +p [{(*, name)}, *, *], [{(p, posit)}, +note, *] return note;");
+    assert_eq!(rows, vec![vec!["check spelling"], vec!["scanned letter"]]);
+}
+
+#[test]
+fn meta_posits_can_annotate_posits_found_by_search() {
+    let db = database(SETUP);
+    let engine = Engine::new(&db);
+    let script = "search +p [{(*, name)}, \"Acme\", *] return p;
+        add posit [{(p, posit), (+reviewer, comment)}, \"verified\", '2025'];
+        search +q [{(*, name)}, *, *], [{(q, posit), (*, comment)}, +note, *] return q, note;";
+    let rows = engine.execute_collect(script).unwrap().rows;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1], vec![rows[0][0].clone(), "verified".to_string()]);
+}
+
+#[test]
+fn unknown_posit_variable_is_an_error() {
+    let db = database(SETUP);
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("add posit [{(nothing, posit)}, \"orphan\", '2024'];").unwrap_err();
+    assert!(format!("{}", err).contains("Unknown variable in posit: nothing"));
+}
+
+#[test]
+fn meta_posits_round_trip_through_the_persistor() {
+    let path = std::env::temp_dir().join("bareclad_meta_posits_round_trip.db").to_string_lossy().to_string();
+    let _ = std::fs::remove_file(&path);
+    let query = "search +p [{(+c, name)}, +n, *], [{(p, posit), (+s, source)}, +origin, *] return p, c, s, n, origin;";
+    let before = {
+        let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+        let engine = Engine::new(&db);
+        engine.execute(SETUP);
+        engine.execute_collect(query).unwrap().rows
+    };
+    assert_eq!(before.len(), 1);
+    let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+    let engine = Engine::new(&db);
+    // Identities of the posit, the named thing and the source survive the restart.
+    assert_eq!(engine.execute_collect(query).unwrap().rows, before);
+    // Things created after the restore never reuse identities seen in restored appearances.
+    let fresh = engine.execute_collect("add posit [{(+z, comment)}, \"new\", '2025']; search [{(+z, comment)}, *, *] return z;").unwrap().rows;
+    assert!(!before[0][..3].contains(&fresh[0][0]));
+    drop(engine);
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+}
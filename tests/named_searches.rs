@@ -0,0 +1,76 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role agent name, status, lead;
+         add posit [{(+a, agent name)}, \"Ann\", '2020-01-01'], [{(a, status)}, \"active\", '2020-01-01'], [{(a, lead)}, \"team x\", '2020-01-01'];
+         add posit [{(+b, agent name)}, \"Bob\", '2020-01-01'], [{(b, status)}, \"retired\", '2020-01-01'], [{(b, lead)}, \"team y\", '2020-01-01'];
+         add posit [{(+c, agent name)}, \"Cid\", '2020-01-01'], [{(c, status)}, \"active\", '2020-01-01'];",
+    )
+}
+
+/// Named searches belong to the engine that defined them.
+fn engine(db: &Database) -> Engine<'_> {
+    let engine = Engine::new(db);
+    engine.execute("define search active_agents as search [{(+x, status)}, \"active\", *] return x;");
+    engine
+}
+
+#[test]
+fn named_searches_act_as_sets_in_patterns_and_predicates() {
+    let db = setup();
+    let engine = engine(&db);
+    let rows = sorted_rows(&engine, "search [{(active_agents, agent name)}, +n, *] return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Cid"]]);
+    let rows = sorted_rows(&engine, "search [{(+w, agent name)}, +n, *] where w in active_agents return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Cid"]]);
+    let rows = sorted_rows(&engine, "search [{(+w, agent name)}, +n, *] where w not in active_agents return n;");
+    assert_eq!(rows, vec![vec!["Bob"]]);
+}
+
+#[test]
+fn subqueries_are_evaluated_into_sets() {
+    let db = setup();
+    let engine = engine(&db);
+    let rows = sorted_rows(&engine, "search [{(+w, agent name)}, +n, *] where w in (search [{(+l, lead)}, *, *] return l) return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bob"]]);
+    // Subqueries and named searches nest.
+    let rows = sorted_rows(&engine, "search [{(+w, agent name)}, +n, *] where w in (search [{(+l, lead)}, *, *] where l in active_agents return l) return n;");
+    assert_eq!(rows, vec![vec!["Ann"]]);
+    let rows = sorted_rows(
+        &engine,
+        "define search leading as search [{(active_agents, lead)}, *, *] return active_agents;
+         search [{(leading, agent name)}, +n, *] return n;",
+    );
+    assert_eq!(rows, vec![vec!["Ann"]]);
+}
+
+#[test]
+fn empty_named_searches_match_nothing() {
+    let db = setup();
+    let engine = engine(&db);
+    engine.execute("define search nobody as search [{(+x, status)}, \"gone\", *] return x;");
+    assert!(sorted_rows(&engine, "search [{(nobody, agent name)}, +n, *] return n;").is_empty());
+    assert!(sorted_rows(&engine, "search [{(+w, agent name)}, +n, *] where w in nobody return n;").is_empty());
+}
+
+#[test]
+fn invalid_set_references_are_errors() {
+    let db = setup();
+    let engine = engine(&db);
+    let cases = [
+        ("search [{(+w, agent name)}, +n, *] where w in nothing return n;", "Unknown named search: nothing"),
+        ("search [{(+w, agent name)}, +n, *] where w in (search [{(*, lead)}, +t, *] return t) return n;", "Subquery must return a single identity column"),
+        ("search [{(+w, agent name)}, +n, *] where n in active_agents return n;", "requires an identity variable: n"),
+        ("define search again as search [{(again, agent name)}, *, *] return again;", "cannot refer to itself"),
+    ];
+    for (script, message) in cases {
+        let err = engine.execute_collect(script).unwrap_err();
+        assert!(format!("{}", err).contains(message), "{}: {}", script, err);
+    }
+}
@@ -0,0 +1,70 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role person name, death date, wife, husband;
+         add posit [{(+a, person name)}, \"Ann\", '2020-01-01'], [{(a, death date)}, '2021-01-01', '2021-01-02'];
+         add posit [{(+b, person name)}, \"Bob\", '2020-01-01'];
+         add posit [{(+c, person name)}, \"Cid\", '2020-01-01'];
+         add posit [{(a, wife), (b, husband)}, \"married\", '2010-01-01'];",
+    )
+}
+
+#[test]
+fn negated_patterns_exclude_matching_bindings() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+p, person name)}, +n, *], not [{(p, death date)}, *, *] return n;");
+    assert_eq!(rows, vec![vec!["Bob"], vec!["Cid"]]);
+    // Negations apply after all other patterns, wherever they are written.
+    let rows = sorted_rows(&engine, "search not [{(p, death date)}, *, *], [{(+p, person name)}, +n, *] return n;");
+    assert_eq!(rows, vec![vec!["Bob"], vec!["Cid"]]);
+    // Only posits passing the pattern's own filters exclude anything.
+    let rows = sorted_rows(&engine, "search [{(+p, person name)}, +n, *], not [{(p, death date)}, '1999-01-01', *] return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bob"], vec!["Cid"]]);
+}
+
+#[test]
+fn negated_patterns_join_on_several_variables_per_binding() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+w, person name)}, \"Ann\", *], [{(+h, person name)}, +n, *], not [{(w, wife), (h, husband)}, *, *] return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Cid"]]);
+}
+
+#[test]
+fn variables_introduced_by_a_negated_pattern_stay_local() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // '+h' matches any husband: people who are nobody's wife and nobody's husband.
+    let rows = sorted_rows(&engine, "search [{(+p, person name)}, +n, *], not [{(p, wife), (+h, husband)}, *, *], not [{(+w, wife), (p, husband)}, *, *] return n;");
+    assert_eq!(rows, vec![vec!["Cid"]]);
+    let err = engine.execute_collect("search [{(+p, person name)}, +n, *], not [{(p, death date)}, +d, *] where d = \"x\" return n;").unwrap_err();
+    assert!(format!("{}", err).contains("Variable 'd' is only bound inside a 'not' pattern"), "{}", err);
+    let err = engine.execute_collect("search [{(+p, person name)}, +n, *], not [{(p, wife), (+h, husband)}, *, *] return n, h;").unwrap_err();
+    assert!(format!("{}", err).contains("Variable 'h' is only bound inside a 'not' pattern"), "{}", err);
+}
+
+#[test]
+fn roles_never_declared_exclude_nothing() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+p, person name)}, +n, *], not [{(p, nickname)}, *, *] return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bob"], vec!["Cid"]]);
+}
+
+#[test]
+fn excluded_things_are_removed_from_the_variable() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "search [{(+p, person name)}, *, *], not [{(p, death date)}, *, *] return p;
+        search [{(p, person name)}, +n, *] return n;";
+    let mut rows = engine.execute_collect(script).unwrap().rows.split_off(2);
+    rows.sort();
+    assert_eq!(rows, vec![vec!["Bob"], vec!["Cid"]]);
+}
@@ -0,0 +1,90 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database(
+        "add role person name, email, age;
+         add posit [{(+a, person name)}, \"Ann\", '2020-01-01'], [{(a, email)}, \"ann@example.org\", '2020-01-01'], [{(a, age)}, 40, '2020-01-01'];
+         add posit [{(+b, person name)}, \"Bob\", '2020-01-01'], [{(b, age)}, 30, '2020-01-01'];
+         add posit [{(+c, person name)}, \"Cid\", '2020-01-01'];",
+    )
+}
+
+#[test]
+fn unmatched_optional_patterns_keep_the_binding_with_nulls() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let res = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, email)}, +e, *] return n, e;").unwrap();
+    let mut rows: Vec<(Vec<String>, Vec<String>)> = res.rows.into_iter().zip(res.row_types).collect();
+    rows.sort();
+    assert_eq!(rows[0], (vec!["Ann".to_string(), "ann@example.org".to_string()], vec!["String".to_string(), "String".to_string()]));
+    assert_eq!(rows[1], (vec!["Bob".to_string(), String::new()], vec!["String".to_string(), "null".to_string()]));
+    assert_eq!(rows[2].1, vec!["String", "null"]);
+    // Without 'optional' the pattern must match.
+    let rows = engine.execute_collect("search [{(+p, person name)}, +n, *], [{(p, email)}, +e, *] return n, e;").unwrap().rows;
+    assert_eq!(rows, vec![vec!["Ann", "ann@example.org"]]);
+}
+
+#[test]
+fn optional_posit_variables_and_order_by_nulls_last() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let res = engine
+        .execute_collect("search [{(+p, person name)}, +n, *], optional +q [{(p, email)}, *, *], optional [{(p, age)}, +g, *] return n, q, g order by g;")
+        .unwrap();
+    let names: Vec<&str> = res.rows.iter().map(|r| r[0].as_str()).collect();
+    assert_eq!(names, vec!["Bob", "Ann", "Cid"]);
+    assert_eq!(res.row_types[1], vec!["String", "Thing", "i64"]);
+    assert_eq!(res.row_types[2], vec!["String", "null", "null"]);
+}
+
+#[test]
+fn comparisons_with_null_do_not_hold() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, age)}, +g, *] where g < 35 return n;").unwrap().rows;
+    assert_eq!(rows, vec![vec!["Bob"]]);
+    let mut rows = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, age)}, +g, *] where not g < 35 return n;").unwrap().rows;
+    rows.sort();
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Cid"]]);
+}
+
+#[test]
+fn aggregates_skip_nulls() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let res = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, age)}, +g, *] return count(n), count(g), sum(g), max(g);").unwrap();
+    assert_eq!(res.rows, vec![vec!["3", "2", "70", "40"]]);
+    let res = engine.execute_collect("search [{(+p, person name)}, \"Cid\", *], optional [{(p, age)}, +g, *] return count(g), max(g);").unwrap();
+    assert_eq!(res.rows, vec![vec!["0", ""]]);
+    assert_eq!(res.row_types, vec![vec!["i64", "null"]]);
+}
+
+#[test]
+fn roles_never_declared_match_nothing_in_optional_patterns() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let res = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, nickname)}, +k, *] return n, k;").unwrap();
+    assert_eq!(res.rows.len(), 3);
+    assert!(res.row_types.iter().all(|types| types[1] == "null"));
+    // Other patterns must match, so an unknown role is an error.
+    let err = engine.execute_collect("search [{(+p, person name)}, +n, *], [{(p, nickname)}, +k, *] return n, k;").unwrap_err();
+    assert!(err.to_string().contains("Unknown role: nickname"), "{}", err);
+    // The failed search leaves the engine usable.
+    assert_eq!(engine.execute_collect("search [{(*, person name)}, +n, *] return count(n);").unwrap().rows, vec![vec!["3"]]);
+}
+
+#[test]
+fn roles_no_posit_appears_in_yet_match_nothing() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    engine.execute("add role nickname;");
+    let res = engine.execute_collect("search [{(+p, person name)}, +n, *], optional [{(p, nickname)}, +k, *] return n, k;").unwrap();
+    assert_eq!(res.rows.len(), 3);
+    assert!(res.row_types.iter().all(|types| types[1] == "null"));
+    assert!(engine.execute_collect("search [{(*, nickname)}, +k, *] return k;").unwrap().rows.is_empty());
+}
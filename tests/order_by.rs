@@ -0,0 +1,74 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database(
+        "add role ticket, status, estimate;
+         add posit [{(+t1, status)}, \"open\", '2019'], [{(t1, estimate)}, 3, '2019'];
+         add posit [{(+t2, status)}, \"pending\", '2022-01-01 10:00:00'], [{(t2, estimate)}, 2.5, '2022-01-01 10:00:00'];
+         add posit [{(+t3, status)}, \"closed\", '2020-06'], [{(t3, estimate)}, 10, '2020-06'];
+         add posit [{(+t4, status)}, \"open\", '2021-03-04'], [{(t4, estimate)}, 0.5, '2021-03-04'];",
+    )
+}
+
+fn rows(engine: &Engine, script: &str) -> Vec<Vec<String>> {
+    engine.execute_collect(script).expect("query ok").rows
+}
+
+#[test]
+fn orders_times_across_resolutions() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let r = rows(&engine, "search [{(*, status)}, +s, +t] return s, t order by t;");
+    let times: Vec<&str> = r.iter().map(|row| row[1].as_str()).collect();
+    assert_eq!(times, vec!["2019", "2020-6", "2021-03-04", "2022-01-01 10:00:00"]);
+}
+
+#[test]
+fn limit_applies_after_ordering() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let res = engine.execute_collect("search [{(*, status)}, +s, +t] return s order by t desc limit 2;").unwrap();
+    assert_eq!(res.rows, vec![vec!["pending"], vec!["open"]]);
+    assert!(res.limited);
+}
+
+#[test]
+fn orders_mixed_integers_and_decimals_numerically() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let r = rows(&engine, "search [{(*, estimate)}, +e, *] return e order by e desc;");
+    assert_eq!(r, vec![vec!["10"], vec!["3"], vec!["2.5"], vec!["0.5"]]);
+}
+
+#[test]
+fn multiple_keys_and_unreturned_variables() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let r = rows(&engine, "search [{(*, status)}, +s, +t] return s order by s asc, t desc;");
+    assert_eq!(r, vec![vec!["closed"], vec!["open"], vec!["open"], vec!["pending"]]);
+    let r = rows(&engine, "search [{(+x, status)}, +s, *], [{(x, estimate)}, +e, *] return s order by e;");
+    assert_eq!(r, vec![vec!["open"], vec!["pending"], vec!["open"], vec!["closed"]]);
+}
+
+#[test]
+fn orders_aggregated_rows() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let r = rows(&engine, "search [{(*, status)}, +s, *] return s, count(s) order by count(s) desc, s;");
+    assert_eq!(r, vec![vec!["open", "2"], vec!["closed", "1"], vec!["pending", "1"]]);
+}
+
+#[test]
+fn order_by_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, status)}, +s, *] return s order by missing;").unwrap_err();
+    assert!(format!("{}", err).contains("Unknown variable in order by: missing"));
+    let err = engine.execute_collect("search [{(*, status)}, +s, *] return s, count(s) order by max(s);").unwrap_err();
+    assert!(format!("{}", err).contains("must be a returned column or group key"));
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use bareclad::construct::Database;
+use bareclad::error::BarecladError;
+use bareclad::traqula::{parse_time, Engine, Param};
+
+mod common;
+
+use common::{database, params};
+
+fn setup() -> Database {
+    database(
+        "add role name, rating, settings;
+         add posit +p [{(+c, name)}, \"Acme\", '2020-01-01'], +q [{(+d, name)}, \"Bolt\", '2021-06-01'];
+         add assertion [+archie, p, 80%, '2021-01-01'], [archie, q, 40%, '2021-01-01'];",
+    )
+}
+
+#[test]
+fn values_bind_as_literals_of_their_type() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let tricky = "Zed \"the\" Co\"; add role hacked; search [{(*, name)}, +n, *] return n; \"";
+    let script = "add posit [{(+z, name)}, $name, '2022'], [{(z, rating)}, $stars, '2022'], [{(z, rating)}, $score, '2023'], [{(z, settings)}, $settings, '2022'];
+        search [{(+z, name)}, $name, *], [{(z, rating)}, +r, *] return r order by r;";
+    let bound = params(&[
+        ("name", Param::String(tricky.to_string())),
+        ("stars", Param::Int(4)),
+        ("score", Param::Decimal("4.25".to_string())),
+        ("settings", Param::Json("{\"theme\": \"dark\"}".to_string())),
+    ]);
+    let res = engine.execute_with_params(script, &bound).unwrap();
+    assert_eq!(res.rows, vec![vec!["4"], vec!["4.25"]]);
+    assert_eq!(res.row_types, vec![vec!["i64"], vec!["Decimal"]]);
+    // The quoted string was stored as written and compares equal in a where clause; nothing was injected.
+    let res = engine.execute_with_params("search [{(+z, name)}, +n, *] where n = $name return n;", &bound).unwrap();
+    assert_eq!(res.rows, vec![vec![tricky]]);
+    let names = engine.execute_collect("search [{(*, name)}, +n, *] return n;").unwrap();
+    assert_eq!(names.row_count, 3);
+}
+
+#[test]
+fn time_and_certainty_placeholders_accept_typed_values_and_strings() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "search [{(*, name)}, +n, +t] where t between $from and $to return n;";
+    let res = engine.execute_with_params(script, &params(&[("from", Param::String("2021".into())), ("to", Param::Time(parse_time("2021-12-31").unwrap()))])).unwrap();
+    assert_eq!(res.rows, vec![vec!["Bolt"]]);
+    let script = "search [{(*, name)}, +n, *] as of $when by * certainty >= $min return n;";
+    let res = engine.execute_with_params(script, &params(&[("when", Param::String("2021-06-01".into())), ("min", Param::Certainty(75))])).unwrap();
+    assert_eq!(res.rows, vec![vec!["Acme"]]);
+    let script = "search +q [{(*, name)}, \"Bolt\", *] return q; add assertion [+bella, q, $c, $t];";
+    let when = parse_time("2022-03-04 05:06:07.5").unwrap();
+    let bound = params(&[("c", Param::String("90%".into())), ("t", Param::Time(when))]);
+    engine.execute_with_params(script, &bound).unwrap();
+    let res = engine.execute_with_params("search [{(*, name)}, +n, *] by * certainty >= $c return n;", &bound).unwrap();
+    assert_eq!(res.rows, vec![vec!["Bolt"]]);
+    let res = engine.execute_collect("search [{(+a, ascertains), (*, posit)}, 90%, +t] return t;").unwrap();
+    assert_eq!(res.rows, vec![vec!["2022-03-04 05:06:07.500"]]);
+}
+
+#[test]
+fn missing_and_mistyped_parameters_are_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "search [{(*, name)}, +n, +t]\n  where t > $since return n;";
+    match engine.execute_with_params(script, &HashMap::new()).unwrap_err() {
+        BarecladError::Parse { message, line, col } => {
+            assert_eq!(message, "Missing parameter: $since");
+            assert_eq!((line, col), (Some(2), Some(13)));
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+    // Placeholders must be bound; plain execution reports them as missing.
+    let err = engine.execute_collect(script).unwrap_err();
+    assert!(format!("{err}").contains("Missing parameter: $since"));
+    let err = engine.execute_with_params(script, &params(&[("since", Param::Int(2020))])).unwrap_err();
+    assert!(format!("{err}").contains("Parameter $since must be a time"));
+    let err = engine.execute_with_params("add assertion [archie, p, $c, '2024'];", &params(&[("c", Param::Certainty(120))])).unwrap_err();
+    assert!(format!("{err}").contains("Parameter $c must be a certainty"));
+    let err = engine.execute_with_params("add posit [{(+x, rating)}, $v, '2024'];", &params(&[("v", Param::Decimal("1e3".into()))])).unwrap_err();
+    assert!(format!("{err}").contains("Parameter $v must be a decimal"));
+}
+
+#[test]
+fn placeholders_inside_literals_are_text() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let bound = params(&[("x", Param::String("ignored".into()))]);
+    engine.execute_with_params("add posit [{(+z, name)}, \"costs $x\", '2022'];", &bound).unwrap();
+    let res = engine.execute_collect("search [{(*, name)}, \"costs $x\", +t] return t;").unwrap();
+    assert_eq!(res.rows, vec![vec!["2022"]]);
+}
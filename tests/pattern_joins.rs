@@ -0,0 +1,13 @@
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+#[test]
+fn recalled_identities_join_every_matching_posit() {
+    let db = database("add role ticket, status, priority; add posit [{(+t1, status)}, \"open\", '2020-01-01'], [{(t1, priority)}, 10, '2020-01-01']; add posit [{(+t2, status)}, \"pending\", '2021-01-01'], [{(t2, priority)}, 20, '2021-01-01']; add posit [{(+t3, status)}, \"closed\", '2022-01-01'];");
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+x, status)}, +s, *], [{(x, priority)}, +p, *] return s, p;");
+    assert_eq!(rows, vec![vec!["open", "10"], vec!["pending", "20"]]);
+}
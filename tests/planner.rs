@@ -0,0 +1,68 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role name, employees, headquarters, milestone;
+         add posit [{(+a, name)}, \"Acme\", '2020-01-01'], +ae [{(a, employees)}, 120, '2020-01-01'], [{(a, headquarters)}, \"Oslo\", '2020-01-01'];
+         add posit [{(+b, name)}, \"Bolt\", '2020-01-01'], +be [{(b, employees)}, 8, '2021-01-01'];
+         add posit [{(+c, name)}, \"Cogs\", '2020-01-01'], [{(c, employees)}, 45, '2022-01-01'];
+         add posit [{(+d, name)}, \"Dyne\", '2020-01-01'], [{(+e, name)}, \"Edda\", '2020-01-01'], [{(+f, name)}, \"Fjord\", '2020-01-01'];
+         add assertion [+archie, ae, 80%, '2021-01-01'], [archie, be, 75%, '2021-01-01'];
+         add posit [{(archie, name)}, \"Archie\", '2020-01-01'];
+         add posit [{(+m, milestone)}, \"review\", '2021-06-01'];",
+    )
+}
+
+#[test]
+fn written_order_does_not_change_results() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let forward = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, employees)}, +e, *], [{(x, headquarters)}, +h, *] return n, e, h;");
+    let backward = sorted_rows(&engine, "search [{(x, headquarters)}, +h, *], [{(x, employees)}, +e, *], [{(+x, name)}, +n, *] return n, e, h;");
+    assert_eq!(forward, vec![vec!["Acme", "120", "Oslo"]]);
+    assert_eq!(forward, backward);
+    // Value variables join the same way whichever pattern binds them first.
+    let rows = sorted_rows(&engine, "search [{(+y, name)}, +n, *], [{(+x, headquarters)}, *, *], [{(x, name)}, n, *] return n;");
+    assert_eq!(rows, vec![vec!["Acme"]]);
+}
+
+#[test]
+fn patterns_needing_a_bound_variable_wait_for_it() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // The positor and the 'as of' time are bound by less selective patterns written before.
+    let rows = sorted_rows(&engine, "search [{(+p, name)}, +who, *], [{(*, employees)}, +e, *] by p certainty >= 75% return who, e;");
+    assert_eq!(rows, vec![vec!["Archie", "120"], vec!["Archie", "8"]]);
+    let rows = sorted_rows(&engine, "search [{(*, milestone)}, *, +mt], [{(*, employees)}, +e, +t] as of mt return e, t;");
+    assert_eq!(rows, vec![vec!["120", "2020-01-01"], vec!["8", "2021-01-01"]]);
+    let rows = sorted_rows(&engine, "search +p [{(+x, employees)}, *, *], p [{(x, employees)}, 45, *], [{(x, name)}, +n, *] return n;");
+    assert_eq!(rows, vec![vec!["Cogs"]]);
+}
+
+#[test]
+fn literal_comparisons_are_pushed_down_without_changing_results_or_errors() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, employees)}, +e, +t] where e > 10 and t < '2022-01-01' return n;");
+    assert_eq!(rows, vec![vec!["Acme"]]);
+    // Only comparisons every result depends on narrow the candidates.
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, employees)}, +e, *] where e > 100 or n = \"Bolt\" return n;");
+    assert_eq!(rows, vec![vec!["Acme"], vec!["Bolt"]]);
+    let err = engine.execute_collect("search [{(+x, name)}, +n, *] where n > 3 return n;").unwrap_err();
+    assert!(err.to_string().contains("Ordering comparison not allowed: variable 'n' of non-numeric type used with '>'"));
+}
+
+#[test]
+fn optional_and_negated_patterns_stay_in_place() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], optional [{(x, employees)}, +e, *], [{(x, headquarters)}, *, *] return n, e;");
+    assert_eq!(rows, vec![vec!["Acme", "120"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], not [{(x, employees)}, *, *], [{(+y, headquarters)}, *, *] return n;");
+    assert_eq!(rows, vec![vec!["Archie"], vec!["Dyne"], vec!["Edda"], vec!["Fjord"]]);
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use bareclad::construct::Database;
+use bareclad::error::BarecladError;
+use bareclad::import::{IngestedPosit, PositSpec, ingest_posits};
+use bareclad::interface::QueryInterface;
+use bareclad::server::router;
+use bareclad::traqula::Engine;
+use tower::ServiceExt;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database("add role callsign, rank, squadron, designation, pilot, posit, ascertains;")
+}
+
+fn specs(json: &str) -> Vec<PositSpec> {
+    serde_json::from_str(json).expect("posits ok")
+}
+
+#[test]
+fn structured_posits_map_onto_appearance_sets_values_and_times() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let ingested = ingest_posits(
+        &db,
+        &specs(
+            r#"[
+                { "appearances": [{ "ref": "p", "role": "callsign" }], "value": "Kestrel", "time": "2025-02-01" },
+                { "appearances": [{ "ref": "p", "role": "rank" }], "value": 3, "time": "2025-02-01" },
+                { "appearances": [{ "ref": "s", "role": "designation" }], "value": { "name": "Nightjar" }, "time": "@BOT" },
+                { "appearances": [{ "ref": "p", "role": "pilot" }, { "ref": "s", "role": "squadron" }], "value": "75%", "type": "Certainty", "time": "2025-02-01" },
+                { "appearances": [{ "ref": "p", "role": "callsign" }], "value": "Kestrel", "time": "2025-02-01" }
+            ]"#,
+        ),
+    )
+    .unwrap();
+    assert_eq!(ingested.len(), 5);
+    assert!(ingested[..4].iter().all(|posit| !posit.existed));
+    // Sending the same posit again finds the one already kept.
+    assert_eq!(ingested[4], IngestedPosit { posit: ingested[0].posit, existed: true });
+    let rows = sorted_rows(&engine, "search [{(+p, callsign)}, +n, +t], [{(p, rank)}, +r, *], [{(p, pilot), (+s, squadron)}, +c, *], [{(s, designation)}, +v, *] return n, t, r, c, v;");
+    assert_eq!(rows, vec![vec!["Kestrel", "2025-02-01", "3", "0.75", "{\"name\":\"Nightjar\"}"]]);
+}
+
+#[test]
+fn kept_things_and_posits_can_appear_by_identity() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let first = ingest_posits(&db, &specs(r#"[{ "ref": "fact", "appearances": [{ "ref": "p", "role": "callsign" }], "value": "Kestrel", "time": "2025-02-01" },
+                                              { "appearances": [{ "ref": "me", "role": "ascertains" }, { "ref": "fact", "role": "posit" }], "value": "100%", "type": "Certainty", "time": "2025-02-02" }]"#))
+        .unwrap();
+    let rows = sorted_rows(&engine, "search [{(+p, callsign)}, +n, *], [{(*, ascertains), (p, posit)}, +c, *] return n, c;");
+    assert!(rows.is_empty());
+    let rows = sorted_rows(&engine, "search +f [{(*, callsign)}, +n, *], [{(*, ascertains), (f, posit)}, +c, +t] return n, c, t;");
+    assert_eq!(rows, vec![vec!["Kestrel", "1", "2025-02-02"]]);
+    // A later request refers to the pilot by the identity it was given.
+    let pilot = engine.execute_collect("search [{(+p, callsign)}, \"Kestrel\", *] return p;").unwrap().rows[0][0].clone();
+    let json = format!(r#"{{ "appearances": [{{ "thing": {pilot}, "role": "rank" }}], "value": 2.5, "time": "2025-03-01" }}"#);
+    let spec: PositSpec = serde_json::from_str(&json).unwrap();
+    let second = ingest_posits(&db, &[spec]).unwrap();
+    assert!(!second[0].existed && second[0].posit > first[1].posit);
+    assert_eq!(sorted_rows(&engine, "search [{(+p, callsign)}, \"Kestrel\", *], [{(p, rank)}, +r, *] return r;"), vec![vec!["2.5"]]);
+}
+
+#[test]
+fn an_invalid_posit_ingests_nothing() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let cases = [
+        (r#"{ "appearances": [{ "ref": "p", "role": "nickname" }], "value": "Kes", "time": "2025-02-01" }"#, "Unknown role: nickname"),
+        (r#"{ "appearances": [{ "thing": 999, "role": "callsign" }], "value": "Kes", "time": "2025-02-01" }"#, "Unknown thing: 999"),
+        (r#"{ "appearances": [{ "ref": "p", "role": "rank" }], "value": "three", "type": "i64", "time": "2025-02-01" }"#, "Invalid i64 value 'three'"),
+        (r#"{ "appearances": [{ "ref": "p", "role": "rank" }], "value": true, "time": "2025-02-01" }"#, "Unsupported value: true"),
+        (r#"{ "appearances": [{ "ref": "p", "role": "rank" }], "value": 3, "time": "soon" }"#, "Invalid time 'soon'"),
+        (r#"{ "appearances": [{ "role": "rank" }], "value": 3, "time": "2025-02-01" }"#, "An appearance needs either a thing or a ref"),
+        (r#"{ "ref": "p", "appearances": [{ "ref": "p", "role": "rank" }], "value": 3, "time": "2025-02-01" }"#, "The ref 'p' is already in use"),
+    ];
+    for (spec, expected) in cases {
+        let json = format!(r#"[{{ "appearances": [{{ "ref": "p", "role": "callsign" }}], "value": "Kestrel", "time": "2025-02-01" }}, {spec}]"#);
+        match ingest_posits(&db, &specs(&json)).unwrap_err() {
+            BarecladError::Parse { message, line, .. } => {
+                assert!(message.contains(expected), "{} does not contain {}", message, expected);
+                assert_eq!(line, Some(2));
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(sorted_rows(&engine, "search [{(*, callsign)}, +n, *] return n;").is_empty());
+    }
+}
+
+#[tokio::test]
+async fn requests_that_cannot_be_carried_out_are_client_errors() {
+    let app = router(Arc::new(QueryInterface::new(Arc::new(setup()))));
+    let post = |uri: &str, body: &str| Request::post(uri).header("content-type", "application/json").body(Body::from(body.to_string())).unwrap();
+    let unknown_role = r#"{ "appearances": [{ "ref": "p", "role": "nickname" }], "value": "Maverick", "time": "1986-05-16" }"#;
+    let response = app.clone().oneshot(post("/v1/posits", unknown_role)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let unknown_thing = r#"{ "appearances": [{ "thing": 999999, "role": "callsign" }], "value": "Maverick", "time": "1986-05-16" }"#;
+    let response = app.clone().oneshot(post("/v1/posits", unknown_thing)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app.clone().oneshot(post("/v1/query", r#"{ "script": "search [{(*, nickname)}, +n, *] return n;" }"#)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = app.oneshot(post("/v1/query", r#"{ "script": "search [{(*, callsign)}, +n, *] return n;" }"#)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
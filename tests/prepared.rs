@@ -0,0 +1,94 @@
+use bareclad::construct::Database;
+use bareclad::error::BarecladError;
+use bareclad::traqula::{Engine, Param, RowSink, SinkFlow};
+
+mod common;
+
+use common::{database, params};
+
+fn setup() -> Database {
+    database(
+        "add role name, age;
+         add posit [{(+a, name)}, \"Archie\", '2020-01-01'], [{(a, age)}, 52, '2020-01-01'];
+         add posit [{(+b, name)}, \"Bella\", '2020-01-01'], [{(b, age)}, 51, '2020-01-01'];
+         add posit [{(+c, name)}, \"Cleo\", '2021-01-01'], [{(c, age)}, 19, '2021-01-01'];",
+    )
+}
+
+#[test]
+fn prepared_scripts_describe_their_parameters() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let prepared = engine
+        .prepare("  search [{(+p, name)}, +n, +t], [{(p, age)}, +y, *] where y >= $min and t <= $until return n;\n search [{(*, age)}, $min, *] return count(*);  ")
+        .unwrap_err();
+    assert!(matches!(prepared, BarecladError::Parse { .. }));
+    let prepared = engine
+        .prepare("  search [{(+p, name)}, +n, +t], [{(p, age)}, +y, *] where y >= $min and t <= $until return n;\n search [{(+q, age)}, $min, *] return q;  ")
+        .unwrap();
+    assert_eq!(prepared.params(), vec!["min", "until"]);
+    assert_eq!(prepared.result_sets(), 2);
+    assert!(prepared.text().starts_with("search") && prepared.text().ends_with("return q;"));
+}
+
+#[test]
+fn a_prepared_script_runs_with_different_parameters() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let prepared = engine.prepare("search [{(+p, name)}, +n, *], [{(p, age)}, +y, *] where y >= $min return n order by n;").unwrap();
+    let rows = |min: i64| engine.execute_prepared(&prepared, &params(&[("min", Param::Int(min))])).unwrap().rows;
+    assert_eq!(rows(50), vec![vec!["Archie"], vec!["Bella"]]);
+    assert_eq!(rows(52), vec![vec!["Archie"]]);
+    assert_eq!(rows(18), vec![vec!["Archie"], vec!["Bella"], vec!["Cleo"]]);
+    assert!(rows(60).is_empty());
+}
+
+#[test]
+fn prepared_scripts_with_several_searches_return_one_result_set_each() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let prepared = engine
+        .prepare("search [{(*, name)}, +n, +t] where t >= $since return n; search [{(*, age)}, +y, +t] where t >= $since return y;")
+        .unwrap();
+    let sets = engine.execute_prepared_multi(&prepared, &params(&[("since", Param::String("2021".into()))])).unwrap();
+    assert_eq!(sets.len(), 2);
+    assert_eq!(sets[0].rows, vec![vec!["Cleo"]]);
+    assert_eq!(sets[1].rows, vec![vec!["19"]]);
+}
+
+#[test]
+fn executing_a_prepared_script_checks_its_parameters() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let prepared = engine.prepare("add posit [{(+d, name)}, $name, $when];").unwrap();
+    let err = engine.execute_prepared(&prepared, &params(&[("name", Param::String("Dora".into()))])).unwrap_err();
+    assert!(format!("{err}").contains("Missing parameter: $when"));
+    let err = engine.execute_prepared(&prepared, &params(&[("name", Param::String("Dora".into())), ("when", Param::Int(2022))])).unwrap_err();
+    assert!(format!("{err}").contains("Parameter $when must be a time"));
+    // Failed bindings change nothing; a complete binding adds the posit.
+    assert_eq!(engine.execute_collect("search [{(*, name)}, +n, *] return count(n);").unwrap().rows, vec![vec!["3"]]);
+    engine.execute_prepared(&prepared, &params(&[("name", Param::String("Dora".into())), ("when", Param::String("2022-05-01".into()))])).unwrap();
+    let rows = engine.execute_collect("search [{(*, name)}, \"Dora\", +t] return t;").unwrap().rows;
+    assert_eq!(rows, vec![vec!["2022-05-01"]]);
+}
+
+#[test]
+fn bound_values_never_count_as_searches() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let prepared = engine.prepare("add posit [{(+d, name)}, $name, '2022-01-01']; search [{(*, name)}, +n, *] where n = $name return n;").unwrap();
+    assert_eq!(prepared.result_sets(), 1);
+    let name = params(&[("name", Param::String("search notes; search".into()))]);
+    struct Rows(Vec<Vec<String>>);
+    impl RowSink for Rows {
+        fn push(&mut self, row: Vec<String>, _types: Vec<String>) -> SinkFlow {
+            self.0.push(row);
+            SinkFlow::Continue
+        }
+    }
+    let mut rows = Rows(Vec::new());
+    let (columns, limited, count) = engine.execute_prepared_stream_single(&prepared, &name, &mut rows).unwrap();
+    assert_eq!((columns, limited, count), (vec!["n".to_string()], false, 1));
+    assert_eq!(rows.0, vec![vec!["search notes; search"]]);
+    assert_eq!(engine.execute_prepared(&prepared, &name).unwrap().rows, vec![vec!["search notes; search"], vec!["search notes; search"]]);
+}
@@ -0,0 +1,78 @@
+use bareclad::construct::Database;
+use bareclad::error::BarecladError;
+use bareclad::traqula::{Engine, Param};
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role callsign, rank;
+         add posit [{(+a, callsign)}, \"Cassini-7\", '2025-06-18'], [{(a, rank)}, 3, '2025-06-18'];
+         add posit [{(+b, callsign)}, \"Calypso\", '2025-06-18'], [{(b, rank)}, 1, '2025-06-18'];
+         add posit [{(+c, callsign)}, \"cartographer 100%\", '2025-06-18'], [{(c, rank)}, 2, '2025-06-18'];
+         add posit [{(+d, callsign)}, \"Zeta\", '2025-06-18'];",
+    )
+}
+
+#[test]
+fn like_matches_whole_values_with_wildcards() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n like \"Ca%\" return n;");
+    assert_eq!(rows, vec![vec!["Calypso"], vec!["Cassini-7"]]);
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n like \"Cassini-_\" or n like \"%100%\" return n;");
+    assert_eq!(rows, vec![vec!["Cassini-7"], vec!["cartographer 100%"]]);
+    // Without wildcards the whole value must match, and other characters are not special.
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n like \"Ca\" or n like \"Cassini.7\" return n;");
+    assert!(rows.is_empty());
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n not like \"%a%\" return n;");
+    assert!(rows.is_empty());
+    // Values of other types never match.
+    let rows = sorted_rows(&engine, "search [{(*, rank)}, +r, *] where r like \"%\" return r;");
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn regex_finds_matches_anywhere_in_values() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n ~ \"^[Cc]a\" and not n ~ \"\\d\" return n;");
+    assert_eq!(rows, vec![vec!["Calypso"]]);
+    let rows = sorted_rows(&engine, "search [{(+s, callsign)}, +n, *], [{(s, rank)}, +r, *] where n ~ \"(?i)CASS|zeta\" return n, r;");
+    assert_eq!(rows, vec![vec!["Cassini-7", "3"]]);
+}
+
+#[test]
+fn invalid_regex_is_a_positioned_parse_error() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("search [{(*, callsign)}, +n, *]\nwhere n ~ \"Ca(\" return n;").unwrap_err();
+    match err {
+        BarecladError::Parse { message, line, col } => {
+            assert!(message.contains("Invalid regular expression"), "{}", message);
+            assert_eq!((line, col), (Some(2), Some(11)));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    let params = [("re".to_string(), Param::String("[z-a]".into()))].into_iter().collect();
+    let err = engine.execute_with_params("search [{(*, callsign)}, +n, *] where n ~ $re return n;", &params).unwrap_err();
+    assert!(err.to_string().contains("Parameter $re must be a valid regular expression"), "{}", err);
+    let params = [("re".to_string(), Param::String("^Z".into())), ("like".to_string(), Param::String("_eta".into()))].into_iter().collect();
+    let result = engine.execute_with_params("search [{(*, callsign)}, +n, *] where n ~ $re and n like $like return n;", &params).unwrap();
+    assert_eq!(result.rows, vec![vec!["Zeta"]]);
+}
+
+#[test]
+fn strings_order_by_code_points() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n >= \"Ca\" and n < \"Z\" return n;");
+    assert_eq!(rows, vec![vec!["Calypso"], vec!["Cassini-7"]]);
+    let rows = sorted_rows(&engine, "search [{(*, callsign)}, +n, *] where n between \"Zeta\" and \"cartographer\" return n;");
+    assert_eq!(rows, vec![vec!["Zeta"]]);
+    // Numbers still cannot be ordered against strings.
+    let err = engine.execute_collect("search [{(*, rank)}, +r, *] where r < \"2\" return r;").unwrap_err();
+    assert!(err.to_string().contains("Ordering comparison not allowed"));
+}
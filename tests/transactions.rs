@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::Request;
+use bareclad::construct::{Database, PersistenceMode};
+use bareclad::interface::QueryInterface;
+use bareclad::server::router;
+use bareclad::traqula::Engine;
+use tower::ServiceExt;
+
+mod common;
+
+use common::database;
+
+fn setup() -> Database {
+    database("add role codename;")
+}
+
+fn codenames(engine: &Engine) -> Vec<Vec<String>> {
+    let mut rows = engine.execute_collect("search [{(*, codename)}, +n, *] return n;").expect("query ok").rows;
+    rows.sort();
+    rows
+}
+
+#[test]
+fn commit_keeps_and_rollback_forgets() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    engine.execute_collect("begin; add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18']; commit;").unwrap();
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"]]);
+    let result = engine.execute_collect("begin; add role alias; add posit [{(+a, codename)}, \"Osprey\", '2025-06-18'], [{(a, alias)}, \"Bird\", '2025-06-18']; rollback;").unwrap();
+    assert!(result.rows.is_empty());
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"]]);
+    assert!(db.role_keeper().lock().unwrap().find("alias").is_none());
+    // Searches within a transaction see its own changes.
+    let result = engine.execute_collect("begin; add posit [{(+a, codename)}, \"Osprey\", '2025-06-18']; search [{(*, codename)}, +n, *] where n = \"Osprey\" return n; commit;").unwrap();
+    assert_eq!(result.rows, vec![vec!["Osprey"]]);
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"], vec!["Osprey"]]);
+}
+
+#[test]
+fn a_failing_or_unfinished_transaction_is_rolled_back() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let err = engine.execute_collect("begin; add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18']; add posit [{(b, codename)}, \"Osprey\", '2025-06-18']; commit;").unwrap_err();
+    assert!(err.to_string().contains("Unknown variable in posit: b"), "{}", err);
+    assert!(codenames(&engine).is_empty());
+    let err = engine.execute_collect("begin; add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18'];").unwrap_err();
+    assert!(err.to_string().contains("neither committed nor rolled back"), "{}", err);
+    assert!(codenames(&engine).is_empty());
+    // Printing execution abandons the rest of a failed transaction too.
+    engine.execute("begin; add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18']; add posit [{(b, codename)}, \"Osprey\", '2025-06-18']; commit;");
+    assert!(codenames(&engine).is_empty());
+    let err = engine.execute_collect("commit;").unwrap_err();
+    assert!(err.to_string().contains("No transaction is open"), "{}", err);
+    let err = engine.execute_collect("begin; begin;").unwrap_err();
+    assert!(err.to_string().contains("A transaction is already open"), "{}", err);
+}
+
+#[test]
+fn atomic_engines_run_whole_scripts_as_transactions() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let script = "add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18']; search [{(*, codename)}, +n, *] where n < 3 return n;";
+    // Outside transactions every command takes effect on its own.
+    assert!(engine.execute_collect(script).is_err());
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"]]);
+    let atomic = Engine::new(&db).atomic();
+    let script = "add posit [{(+a, codename)}, \"Osprey\", '2025-06-18']; search [{(*, codename)}, +n, *] where n < 3 return n;";
+    assert!(atomic.execute_collect(script).is_err());
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"]]);
+    atomic.execute_collect("add posit [{(+a, codename)}, \"Osprey\", '2025-06-18'];").unwrap();
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"], vec!["Osprey"]]);
+}
+
+#[test]
+fn transactions_of_other_threads_wait_their_turn() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    std::thread::scope(|scope| {
+        for agent in 0..4 {
+            let db = &db;
+            scope.spawn(move || {
+                let engine = Engine::new(db);
+                for mission in 0..10 {
+                    let script = format!("begin; add posit [{{(+a, codename)}}, \"Agent {agent}-{mission}\", '2025-06-18']; add posit [{{(a, codename)}}, \"Spare\", '2025-06-19']; rollback; add posit [{{(+a, codename)}}, \"Agent {agent}-{mission}\", '2025-06-18'];");
+                    engine.execute_collect(&script).unwrap();
+                }
+            });
+        }
+    });
+    let rows = codenames(&engine);
+    assert_eq!(rows.len(), 40);
+    assert!(!rows.contains(&vec!["Spare".to_string()]));
+}
+
+#[test]
+fn searches_on_other_threads_read_uncommitted_changes() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    db.begin().unwrap();
+    engine.execute_collect("add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18'];").unwrap();
+    std::thread::scope(|scope| {
+        let seen = scope.spawn(|| codenames(&Engine::new(&db))).join().unwrap();
+        assert_eq!(seen, vec![vec!["Kestrel"]]);
+    });
+    db.rollback().unwrap();
+    assert!(codenames(&engine).is_empty());
+}
+
+#[test]
+fn rollback_forgets_the_identities_taken_however_far_apart() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let identity = |name: &str| {
+        let search = format!("search [{{(+a, codename)}}, \"{name}\", *] return a;");
+        engine.execute_collect(&search).unwrap().rows[0][0].parse::<u64>().unwrap()
+    };
+    engine.execute_collect("add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18'];").unwrap();
+    db.begin().unwrap();
+    db.thing_generator().lock().unwrap().reserve(1 << 60);
+    engine.execute_collect("add posit [{(+a, codename)}, \"Osprey\", '2025-06-18'];").unwrap();
+    assert!(identity("Osprey") > 1 << 60);
+    // Only the identities taken are forgotten, rather than every one below the raised bound.
+    db.rollback().unwrap();
+    engine.execute_collect("add posit [{(+a, codename)}, \"Merlin\", '2025-06-18'];").unwrap();
+    assert!(identity("Merlin") > identity("Kestrel") && identity("Merlin") < 1 << 60);
+}
+
+#[test]
+fn a_failed_commit_leaves_neither_memory_nor_file_changed() {
+    let path = "test_bareclad_transactions.db".to_string();
+    let _ = std::fs::remove_file(&path);
+    let db = Database::new(PersistenceMode::File(path.clone())).unwrap();
+    let engine = Engine::new(&db);
+    engine.execute_collect("add role codename; add posit [{(+a, codename)}, \"Kestrel\", '2025-06-18'];").unwrap();
+    let head = db.persistor.lock().unwrap().current_superhash();
+    // Make the database refuse one of the posits, after the others of the transaction were written.
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.execute_batch("create trigger refuse before insert on Posit when new.AppearingValue = 'Harrier' begin select raise(abort, 'refused'); end;").unwrap();
+    let err = engine.execute_collect("begin; add posit [{(+a, codename)}, \"Osprey\", '2025-06-18']; add posit [{(+a, codename)}, \"Harrier\", '2025-06-18']; commit;").unwrap_err();
+    assert!(err.to_string().contains("refused"), "{}", err);
+    assert_eq!(codenames(&engine), vec![vec!["Kestrel"]]);
+    assert_eq!(db.persistor.lock().unwrap().current_superhash(), head);
+    let posits: i64 = conn.query_row("select count(1) from Posit", [], |r| r.get(0)).unwrap();
+    assert_eq!(posits, 1);
+    conn.execute_batch("drop trigger refuse;").unwrap();
+    engine.execute_collect("begin; add posit [{(+a, codename)}, \"Osprey\", '2025-06-18']; commit;").unwrap();
+    // The ledger chains on from the posits actually stored.
+    let mut persistor = db.persistor.lock().unwrap();
+    assert!(persistor.verify_integrity().is_ok());
+    assert_eq!(persistor.current_superhash().map(|(_, count)| count), Some(2));
+    drop(persistor);
+    drop(conn);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn streamed_scripts_that_write_keep_no_writer_waiting_on_the_client() {
+    let rows: Vec<String> = (0..200).map(|n| format!("(\"agent {n}\")")).collect();
+    let db = Arc::new(database(&format!("add role codename; load [{{(+a, codename)}}, $n, '2025-01-01'] values ($n) {};", rows.join(", "))));
+    let kept = db.posit_keeper().lock().unwrap().len();
+    let app = router(Arc::new(QueryInterface::new(Arc::clone(&db))));
+    let script = "add posit [{(+a, codename)}, \"Kestrel\", '2025-01-02']; search [{(*, codename)}, +n, *] return n;";
+    let body = serde_json::json!({ "script": script, "stream": true }).to_string();
+    let request = Request::post("/v1/query").header("content-type", "application/json").body(Body::from(body)).unwrap();
+    // The client reads nothing until another writer has had its turn.
+    let response = app.oneshot(request).await.unwrap();
+    while db.posit_keeper().lock().unwrap().len() == kept {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let writer = Arc::clone(&db);
+    let (done, written) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        Engine::new(&writer).execute_collect("add posit [{(+a, codename)}, \"Osprey\", '2025-01-03'];").unwrap();
+        done.send(()).unwrap();
+    });
+    assert!(written.recv_timeout(Duration::from_secs(10)).is_ok(), "the other writer is still waiting");
+    let events = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(String::from_utf8(events.to_vec()).unwrap().matches("\"event\":\"row\"").count(), 201);
+}
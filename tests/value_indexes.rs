@@ -0,0 +1,80 @@
+use bareclad::construct::Database;
+use bareclad::traqula::Engine;
+
+mod common;
+
+use common::{database, sorted_rows};
+
+fn setup() -> Database {
+    database(
+        "add role name, age, height, confidence, founded;
+         add posit [{(+a, name)}, \"Ann\", '2020'], [{(a, age)}, 31, '2020-03'], [{(a, height)}, 1.72, '2020-03-15'];
+         add posit [{(+b, name)}, \"Bo\", '2021'], [{(b, age)}, 25, '2021-06-01 12:00:00'], [{(b, height)}, 1.80, '2021'];
+         add posit [{(+c, name)}, \"Cy\", '2022'], [{(c, age)}, 19, '2022-01-01'], [{(c, height)}, 0.5, '2022-01-01'];
+         add posit [{(a, confidence)}, 30%, '2020'], [{(b, confidence)}, 75%, '2020'];
+         add posit [{(a, founded)}, '1999-05', '2020'], [{(b, founded)}, '2001', '2020'];",
+    )
+}
+
+#[test]
+fn range_and_equality_on_numbers_and_decimals() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, +g, *] where g > 25 return n;");
+    assert_eq!(rows, vec![vec!["Ann"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, +g, *] where g >= 25 and g <= 31 return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bo"]]);
+    // Integers and decimals compare with each other through both indexes.
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, +g, *] where g < 25.5 and g > 2 return n;");
+    assert_eq!(rows, vec![vec!["Bo"], vec!["Cy"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, height)}, +h, *] where h == 1.8 return n;");
+    assert_eq!(rows, vec![vec!["Bo"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, 25, *] return n;");
+    assert_eq!(rows, vec![vec!["Bo"]]);
+    // Values reading like certainties are still rejected rather than dropped by the index.
+    let err = engine.execute_collect("search [{(+x, name)}, +n, *], [{(x, height)}, +h, *] where h > 1 return n;").unwrap_err();
+    assert!(err.to_string().contains("requires a percent sign"));
+}
+
+#[test]
+fn certainties_compare_by_percentage() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, +c, *] where c >= 30% return n, c;");
+    assert_eq!(rows, vec![vec!["Ann", "0.30"], vec!["Bo", "0.75"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, 75%, *] return n;");
+    assert_eq!(rows, vec![vec!["Bo"]]);
+}
+
+#[test]
+fn certainties_keep_the_percentage_written() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // These percentages are not exact binary fractions and used to be truncated to 28% and 56%.
+    engine.execute("add posit [{(+d, name)}, \"Di\", '2022'], [{(d, confidence)}, 29%, '2022']; add posit [{(+e, name)}, \"Ed\", '2022'], [{(e, confidence)}, 57%, '2022'];");
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, +v, *] return n, v;");
+    assert_eq!(rows, vec![vec!["Ann", "0.30"], vec!["Bo", "0.75"], vec!["Di", "0.29"], vec!["Ed", "0.57"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, +v, *] where v = 29% return n;");
+    assert_eq!(rows, vec![vec!["Di"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, 29%, *] return n;");
+    assert_eq!(rows, vec![vec!["Di"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, confidence)}, +v, *] where v < 30% return n;");
+    assert_eq!(rows, vec![vec!["Di"]]);
+}
+
+#[test]
+fn times_of_different_resolutions_compare_at_the_coarser() {
+    let db = setup();
+    let engine = Engine::new(&db);
+    // '2021' equals every time within the year, so only strictly earlier years are before it.
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, *, +t] where t < '2021' return n;");
+    assert_eq!(rows, vec![vec!["Ann"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, age)}, *, +t] where t >= '2021-06' return n;");
+    assert_eq!(rows, vec![vec!["Bo"], vec!["Cy"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, height)}, *, +t] where t <= '2021-03-01' return n;");
+    assert_eq!(rows, vec![vec!["Ann"], vec!["Bo"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, height)}, *, '2021'] return n;");
+    assert_eq!(rows, vec![vec!["Bo"]]);
+    let rows = sorted_rows(&engine, "search [{(+x, name)}, +n, *], [{(x, founded)}, '2001', *] return n;");
+    assert_eq!(rows, vec![vec!["Bo"]]);
+}
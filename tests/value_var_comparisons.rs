@@ -51,11 +51,21 @@ fn certainty_mixed_ordering_error() {
 }
 
 #[test]
-fn string_ordering_error() {
+fn string_ordering_uses_code_points() {
     let db = Database::new(PersistenceMode::InMemory).unwrap();
     let engine = Engine::new(Box::leak(Box::new(db)));
-    engine.execute("add role label; add posit [{(+l1, label)}, \"alpha\", @NOW]; add posit [{(+l2, label)}, \"beta\", @NOW];");
-    let script = "search [{(*, label)}, +l1, *], [{(*, label)}, +l2, *] where l1 < l2 return l1, l2;";
+    engine.execute("add role label; add posit [{(+l1, label)}, \"alpha\", @NOW]; add posit [{(+l2, label)}, \"beta\", @NOW]; add posit [{(+l3, label)}, \"Beta\", @NOW];");
+    let script = "search [{(*, label)}, +l1, *], [{(*, label)}, +l2, *] where l1 < l2 and l1 = \"alpha\" return l1, l2;";
+    let res = engine.execute_collect(script).expect("query ok");
+    // Upper case letters precede lower case ones.
+    assert_eq!(res.rows, vec![vec!["alpha".to_string(), "beta".to_string()]]);
+}
+
+#[test]
+fn string_and_number_ordering_error() {
+    let engine = setup();
+    engine.execute("add role label; add posit [{(+l1, label)}, \"alpha\", @NOW];");
+    let script = "search [{(*, label)}, +l, *], [{(*, number)}, +n, *] where l < n return l, n;";
     let err = engine.execute_collect(script).unwrap_err();
     assert!(format!("{}", err).contains("Ordering comparison not allowed"));
 }